@@ -3,10 +3,25 @@ use std::marker::PhantomData;
 use crate::ReservoirValue;
 
 pub mod biased_activation_function;
+pub mod standard_activation_function;
 pub use biased_activation_function::BiasedActivationFunction;
+pub use standard_activation_function::{Relu, Tanh};
 
 pub trait ActiviationFunction<T: ReservoirValue> {
     fn invoke(&self, index: usize, value: T) -> T;
+
+    // Applies the function in place to the nodes start_index..start_index + values.len().
+    // Functions with a vectorized kernel can override this.
+    fn invoke_slice(&self, start_index: usize, values: &mut [T]) {
+        for (offset, value) in values.iter_mut().enumerate() {
+            *value = self.invoke(start_index + offset, *value);
+        }
+    }
+
+    // Derivative at `value`, None if the function has no closed form for it.
+    fn derivative(&self, _index: usize, _value: T) -> Option<T> {
+        None
+    }
 }
 
 impl<T: ReservoirValue, A: ActiviationFunction<T>> ActiviationFunction<T> for Box<A> {
@@ -14,12 +29,29 @@ impl<T: ReservoirValue, A: ActiviationFunction<T>> ActiviationFunction<T> for Bo
     fn invoke(&self, index: usize, value: T) -> T {
         (**self).invoke(index, value)
     }
+
+    #[inline]
+    fn invoke_slice(&self, start_index: usize, values: &mut [T]) {
+        (**self).invoke_slice(start_index, values)
+    }
+
+    fn derivative(&self, index: usize, value: T) -> Option<T> {
+        (**self).derivative(index, value)
+    }
 }
 
 impl<T: ReservoirValue> ActiviationFunction<T> for Box<dyn ActiviationFunction<T>> {
     fn invoke(&self, index: usize, value: T) -> T {
         (**self).invoke(index, value)
     }
+
+    fn invoke_slice(&self, start_index: usize, values: &mut [T]) {
+        (**self).invoke_slice(start_index, values)
+    }
+
+    fn derivative(&self, index: usize, value: T) -> Option<T> {
+        (**self).derivative(index, value)
+    }
 }
 
 pub struct ActivationFunctionWrapper<T: ReservoirValue, F: Fn(usize, T) -> T> {
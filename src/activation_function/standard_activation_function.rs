@@ -0,0 +1,68 @@
+use crate::ReservoirValue;
+
+use super::ActiviationFunction;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tanh;
+
+impl<T: ReservoirValue> ActiviationFunction<T> for Tanh {
+    #[inline]
+    fn invoke(&self, _index: usize, value: T) -> T {
+        num_traits::Float::tanh(value)
+    }
+
+    fn derivative(&self, _index: usize, value: T) -> Option<T> {
+        let tanh = num_traits::Float::tanh(value);
+        Some(T::one() - tanh * tanh)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Relu;
+
+impl<T: ReservoirValue> ActiviationFunction<T> for Relu {
+    #[inline]
+    fn invoke(&self, _index: usize, value: T) -> T {
+        num_traits::Float::max(value, T::zero())
+    }
+
+    fn derivative(&self, _index: usize, value: T) -> Option<T> {
+        Some(if value > T::zero() {
+            T::one()
+        } else {
+            T::zero()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Relu, Tanh};
+    use crate::activation_function::{ActivationFunctionWrapper, ActiviationFunction};
+
+    #[test]
+    fn invoke_slice_matches_invoke() {
+        let values: Vec<f64> = (0..37).map(|i| (i as f64 - 18.) * 0.2).collect();
+        let wrapper = ActivationFunctionWrapper::new(|i, v: f64| v * i as f64);
+
+        let mut sliced = values.clone();
+        wrapper.invoke_slice(3, &mut sliced);
+        for (index, (sliced, value)) in sliced.iter().zip(values.iter()).enumerate() {
+            assert_eq!(*sliced, wrapper.invoke(index + 3, *value));
+        }
+
+        let mut sliced = values.clone();
+        Tanh.invoke_slice(0, &mut sliced);
+        assert!(sliced
+            .iter()
+            .zip(values.iter())
+            .all(|(s, v)| *s == v.tanh()));
+
+        let mut sliced = values.clone();
+        Relu.invoke_slice(0, &mut sliced);
+        assert!(sliced
+            .iter()
+            .zip(values.iter())
+            .all(|(s, v)| *s == v.max(0.)));
+    }
+}
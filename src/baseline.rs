@@ -0,0 +1,192 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+
+use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+use crate::ReservoirValue;
+
+// Trivial forecasts a reservoir computer has to beat. Every baseline gets the observed
+// `history`, columns are time steps with the newest one last, and forecasts the following
+// `steps` columns.
+
+// Repeats the last observation.
+pub fn persistence_forecast<T: ReservoirValue>(
+    history: DMatrixSlice<T>,
+    steps: usize,
+) -> DMatrix<T> {
+    assert!(history.ncols() > 0);
+    let last = history.column(history.ncols() - 1);
+    DMatrix::from_fn(history.nrows(), steps, |row, _| last[row])
+}
+
+// Repeats the mean of the history.
+pub fn climatological_forecast<T: ReservoirValue>(
+    history: DMatrixSlice<T>,
+    steps: usize,
+) -> DMatrix<T> {
+    assert!(history.ncols() > 0);
+    let mean = history.column_mean();
+    DMatrix::from_fn(history.nrows(), steps, |row, _| mean[row])
+}
+
+// Linear autoregressive model x_t = c + sum_k A_k x_{t-k} of order p, fitted via ridge
+// regression and iterated in closed loop.
+#[derive(Clone, Debug)]
+pub struct AutoregressiveModel<T: ReservoirValue> {
+    order: usize,
+    dimension: usize,
+    projection: LinearStateProjection<T>,
+}
+
+impl<T: ReservoirValue> AutoregressiveModel<T> {
+    pub fn fit(data: DMatrixSlice<T>, order: usize, beta: T) -> Self {
+        assert!(order > 0);
+        assert!(
+            data.ncols() > order,
+            "Fitting an AR({order}) model needs more than {order} columns."
+        );
+        let dimension = data.nrows();
+        let samples = data.ncols() - order;
+        let mut features = DMatrix::zeros(order * dimension + 1, samples);
+        for sample in 0..samples {
+            Self::lagged_features(
+                data.columns(sample, order),
+                features.column_mut(sample).as_mut_slice(),
+            );
+        }
+        let projection = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features,
+            data.columns(order, samples),
+        );
+        Self {
+            order,
+            dimension,
+            projection,
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    // Needs at least `order` columns of history, only the newest ones are used.
+    pub fn forecast(&self, history: DMatrixSlice<T>, steps: usize) -> DMatrix<T> {
+        assert_eq!(history.nrows(), self.dimension);
+        assert!(history.ncols() >= self.order);
+        let mut window = history
+            .columns(history.ncols() - self.order, self.order)
+            .clone_owned();
+        let mut features = DVector::zeros(self.order * self.dimension + 1);
+        let mut predictions = DMatrix::zeros(self.dimension, steps);
+        for step in 0..steps {
+            Self::lagged_features(window.columns(0, self.order), features.as_mut_slice());
+            self.projection
+                .project_into(&features, predictions.column_mut(step));
+            for column in 1..self.order {
+                window.swap_columns(column - 1, column);
+            }
+            window
+                .column_mut(self.order - 1)
+                .copy_from(&predictions.column(step));
+        }
+        predictions
+    }
+
+    // Newest lag first, the constant one for the intercept last.
+    fn lagged_features(window: DMatrixSlice<T>, features: &mut [T]) {
+        let dimension = window.nrows();
+        let order = window.ncols();
+        for lag in 0..order {
+            let column = window.column(order - 1 - lag);
+            for (row, value) in column.iter().enumerate() {
+                features[lag * dimension + row] = *value;
+            }
+        }
+        features[features.len() - 1] = T::one();
+    }
+}
+
+// 1 - MSE(prediction) / MSE(baseline). One is a perfect forecast, zero no improvement over
+// the baseline and negative values a forecast worse than the baseline.
+pub fn skill_score<T: ReservoirValue>(
+    prediction: DMatrixSlice<T>,
+    baseline: DMatrixSlice<T>,
+    truth: DMatrixSlice<T>,
+) -> T {
+    assert_eq!(prediction.shape(), truth.shape());
+    assert_eq!(baseline.shape(), truth.shape());
+    let baseline_error = (baseline - truth).norm_squared();
+    assert!(
+        baseline_error > T::zero(),
+        "The baseline forecast is perfect, the skill score is undefined."
+    );
+    T::one() - (prediction - truth).norm_squared() / baseline_error
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaselineSkill<T: ReservoirValue> {
+    pub persistence: T,
+    pub climatology: T,
+    pub autoregressive: T,
+}
+
+impl<T: ReservoirValue> BaselineSkill<T> {
+    // Skill of `prediction` against all baselines, each forecasting `truth` from `history`.
+    // The AR model of order `ar_order` is fitted on the history.
+    pub fn compare(
+        prediction: DMatrixSlice<T>,
+        history: DMatrixSlice<T>,
+        truth: DMatrixSlice<T>,
+        ar_order: usize,
+    ) -> Self {
+        let steps = truth.ncols();
+        let beta = T::from_f64(1e-8).unwrap();
+        let persistence = persistence_forecast(history, steps);
+        let climatology = climatological_forecast(history, steps);
+        let autoregressive =
+            AutoregressiveModel::fit(history, ar_order, beta).forecast(history, steps);
+        Self {
+            persistence: skill_score(prediction, persistence.columns(0, steps), truth),
+            climatology: skill_score(prediction, climatology.columns(0, steps), truth),
+            autoregressive: skill_score(prediction, autoregressive.columns(0, steps), truth),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{persistence_forecast, skill_score, AutoregressiveModel};
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn autoregressive_model_recovers_linear_recurrence() {
+        // x_t = 1.6 x_{t-1} - 0.8 x_{t-2} + 0.1
+        let mut values = vec![1., 0.5];
+        for t in 2..200 {
+            values.push(1.6 * values[t - 1] - 0.8 * values[t - 2] + 0.1);
+        }
+        let data = DMatrix::from_vec(1, 200, values);
+        let model = AutoregressiveModel::fit(data.columns(0, 150), 2, 1e-10);
+        let forecast = model.forecast(data.columns(0, 150), 50);
+        assert!((forecast - data.columns(150, 50)).amax() < 1e-6);
+    }
+
+    #[test]
+    fn skill_score_against_persistence() {
+        let history = DMatrix::from_vec(1, 2, vec![0., 1.]);
+        let truth = DMatrix::from_vec(1, 2, vec![2., 3.]);
+        let persistence = persistence_forecast(history.columns(0, 2), 2);
+        assert_eq!(persistence, DMatrix::from_vec(1, 2, vec![1., 1.]));
+        let skill = skill_score(
+            truth.columns(0, 2),
+            persistence.columns(0, 2),
+            truth.columns(0, 2),
+        );
+        assert_eq!(skill, 1.);
+        let skill = skill_score(
+            persistence.columns(0, 2),
+            persistence.columns(0, 2),
+            truth.columns(0, 2),
+        );
+        assert_eq!(skill, 0.);
+    }
+}
@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use nalgebra::{DMatrix, DMatrixSlice};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::metrics::normalized_root_mean_squared_error;
+use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::{Reservoir, ReservoirError, ReservoirValue};
+
+// Standard driven benchmark tasks. The reservoir is driven by `inputs` in open loop and a linear
+// readout of the states has to reproduce `targets`, column t of the targets belongs to the state
+// after input column t.
+#[derive(Clone, Debug)]
+pub struct BenchmarkTask<T: ReservoirValue> {
+    inputs: DMatrix<T>,
+    targets: DMatrix<T>,
+    washout: usize,
+    binary: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchmarkResult<T: ReservoirValue> {
+    pub train_nrmse: T,
+    pub test_nrmse: T,
+    // Fraction of correctly thresholded test outputs, binary tasks only.
+    pub test_accuracy: Option<T>,
+}
+
+impl<T: ReservoirValue> BenchmarkTask<T> {
+    pub fn new(inputs: DMatrix<T>, targets: DMatrix<T>, washout: usize) -> Self {
+        assert_eq!(inputs.ncols(), targets.ncols());
+        assert!(washout < inputs.ncols());
+        Self {
+            inputs,
+            targets,
+            washout,
+            binary: false,
+        }
+    }
+
+    // NARMA of the given order driven by u ~ U[0, 0.5]:
+    // y(t+1) = a y(t) + b y(t) sum_{i<order} y(t-i) + c u(t-order+1) u(t) + d
+    // The usual coefficients of NARMA-10 and NARMA-30 are in `narma10` and `narma30`.
+    pub fn narma(order: usize, coefficients: [f64; 4], length: usize, seed: u64) -> Self {
+        assert!(order > 0);
+        let [a, b, c, d] = coefficients;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let inputs: Vec<f64> = (0..length).map(|_| rng.gen_range(0.0..0.5)).collect();
+        let mut outputs = vec![0.; length + 1];
+        for t in 0..length {
+            let history: f64 = outputs[(t + 1).saturating_sub(order)..=t].iter().sum();
+            let delayed_input = if t + 1 >= order {
+                inputs[t + 1 - order]
+            } else {
+                0.
+            };
+            outputs[t + 1] =
+                a * outputs[t] + b * outputs[t] * history + c * delayed_input * inputs[t] + d;
+        }
+        Self::new(
+            Self::row(inputs.into_iter()),
+            Self::row(outputs.into_iter().skip(1)),
+            (2 * order).max(100).min(length / 2),
+        )
+    }
+
+    pub fn narma10(length: usize, seed: u64) -> Self {
+        Self::narma(10, [0.3, 0.05, 1.5, 0.1], length, seed)
+    }
+
+    pub fn narma30(length: usize, seed: u64) -> Self {
+        Self::narma(30, [0.2, 0.004, 1.5, 0.001], length, seed)
+    }
+
+    // Random bits as input, the target is the XOR of the bits `delay` and `delay + 1` steps back.
+    pub fn delayed_xor(delay: usize, length: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let bits: Vec<bool> = (0..length).map(|_| rng.gen()).collect();
+        let targets = (0..length).map(|t| {
+            if t > delay {
+                f64::from(u8::from(bits[t - delay] ^ bits[t - delay - 1]))
+            } else {
+                0.
+            }
+        });
+        let mut task = Self::new(
+            Self::row(bits.iter().map(|bit| f64::from(u8::from(*bit)))),
+            Self::row(targets),
+            (delay + 1).max(100).min(length / 2),
+        );
+        task.binary = true;
+        task
+    }
+
+    // One step ahead prediction of the Santa Fe laser intensities (data set A), scaled to [0, 1]
+    // by the largest intensity.
+    pub fn santa_fe_laser(series: DMatrixSlice<T>) -> Self {
+        assert_eq!(series.nrows(), 1);
+        assert!(series.ncols() > 1);
+        let scale = series.max();
+        assert!(scale > T::zero());
+        let scaled = series / scale;
+        let length = series.ncols() - 1;
+        Self::new(
+            scaled.columns(0, length).clone_owned(),
+            scaled.columns(1, length).clone_owned(),
+            100.min(length / 2),
+        )
+    }
+
+    pub fn inputs(&self) -> &DMatrix<T> {
+        &self.inputs
+    }
+
+    pub fn targets(&self) -> &DMatrix<T> {
+        &self.targets
+    }
+
+    pub fn washout(&self) -> usize {
+        self.washout
+    }
+
+    pub fn with_washout(mut self, washout: usize) -> Self {
+        assert!(washout < self.inputs.ncols());
+        self.washout = washout;
+        self
+    }
+
+    // The usual protocol: drive the reservoir through the whole input, drop the washout, fit a
+    // ridge readout on the first `train_fraction` of the remaining states and report the NRMSE on
+    // the training part and on the held-out rest.
+    pub fn evaluate<I, E, M>(
+        &self,
+        reservoir: &mut Reservoir<T, I, E>,
+        measurement: &M,
+        beta: T,
+        train_fraction: T,
+    ) -> BenchmarkResult<T>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert!(train_fraction > T::zero() && train_fraction < T::one());
+        let washout = self
+            .washout
+            .max(reservoir.input_projection().required_input_columns());
+        let states = reservoir.record_states(self.inputs.columns(0, self.inputs.ncols()), washout);
+        let features = measurement.measure_many(states.columns(0, states.ncols()));
+        let targets = self.targets.columns(washout, states.ncols());
+
+        let samples = features.ncols();
+        let train_samples = (T::from_usize(samples).unwrap() * train_fraction)
+            .to_usize()
+            .unwrap();
+        assert!(
+            train_samples > 0 && train_samples < samples,
+            "The task is too short to split {samples} samples."
+        );
+        let test_samples = samples - train_samples;
+        let readout = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features.columns(0, train_samples).clone_owned(),
+            targets.columns(0, train_samples),
+        );
+        let predictions = readout.project_many(features.columns(0, samples));
+
+        let train_nrmse = normalized_root_mean_squared_error(
+            predictions.columns(0, train_samples),
+            targets.columns(0, train_samples),
+        );
+        let test_nrmse = normalized_root_mean_squared_error(
+            predictions.columns(train_samples, test_samples),
+            targets.columns(train_samples, test_samples),
+        );
+        let test_accuracy = self.binary.then(|| {
+            let half = T::from_f64(0.5).unwrap();
+            let correct = predictions
+                .columns(train_samples, test_samples)
+                .iter()
+                .zip(targets.columns(train_samples, test_samples).iter())
+                .filter(|(prediction, target)| (**prediction > half) == (**target > half))
+                .count();
+            T::from_usize(correct).unwrap() / T::from_usize(test_samples).unwrap()
+        });
+        BenchmarkResult {
+            train_nrmse,
+            test_nrmse,
+            test_accuracy,
+        }
+    }
+
+    fn row(values: impl Iterator<Item = f64>) -> DMatrix<T> {
+        let values: Vec<T> = values.map(|value| T::from_f64(value).unwrap()).collect();
+        DMatrix::from_vec(1, values.len(), values)
+    }
+}
+
+// Reads a whitespace separated series such as the Santa Fe laser data `A.dat`, one row.
+pub fn load_series<T: ReservoirValue>(
+    path: impl AsRef<Path>,
+) -> Result<DMatrix<T>, ReservoirError> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|error| ReservoirError::InvalidData(format!("{}: {error}", path.display())))?;
+    let values = contents
+        .split_whitespace()
+        .map(|token| {
+            token
+                .parse::<f64>()
+                .ok()
+                .and_then(T::from_f64)
+                .ok_or_else(|| ReservoirError::InvalidData(format!("{token} is not a number")))
+        })
+        .collect::<Result<Vec<T>, _>>()?;
+    Ok(DMatrix::from_vec(1, values.len(), values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BenchmarkTask;
+
+    #[test]
+    fn narma10_recurrence() {
+        let task = BenchmarkTask::<f64>::narma10(200, 1);
+        let u = task.inputs();
+        let y = task.targets();
+        assert!(u.iter().all(|value| (0. ..0.5).contains(value)));
+        // Target t is y(t+1), target t - 1 is y(t).
+        let t = 50;
+        let history: f64 = (0..10).map(|i| y[t - 1 - i]).sum();
+        let expected = 0.3 * y[t - 1] + 0.05 * y[t - 1] * history + 1.5 * u[t - 9] * u[t] + 0.1;
+        assert!((y[t] - expected).abs() < 1e-12);
+
+        let xor = BenchmarkTask::<f64>::delayed_xor(2, 50, 1);
+        let bits = xor.inputs();
+        assert_eq!(xor.targets()[10], ((bits[8] + bits[7]) % 2.));
+    }
+}
@@ -0,0 +1,387 @@
+use nalgebra::{DMatrixSlice, DVectorSlice};
+
+use crate::{
+    input_projection::ReservoirInputProjection, output_projection::ReservoirStateProjection,
+    state_measurement::ReservoirStateMeasurement, time_evolution::ReservoirTimeEvolution,
+    ReservoirComputer, ReservoirValue,
+};
+
+// Online detection of regime changes in a stream of prediction residuals. Only the magnitude
+// of every residual is monitored, time steps count the observations since the last reset.
+pub trait ChangePointDetector {
+    // Returns the time step at which a newly detected regime began.
+    fn observe(&mut self, magnitude: f64) -> Option<usize>;
+
+    // All detected change points in increasing order.
+    fn change_points(&self) -> &[usize];
+
+    fn reset(&mut self);
+
+    fn update<T: ReservoirValue>(&mut self, residual: DVectorSlice<T>) -> Option<usize> {
+        self.observe(residual.norm().to_f64().unwrap())
+    }
+}
+
+// Two sided CUSUM on the standardized residual magnitude. Mean and standard deviation come from
+// the first `calibration_steps` magnitudes of every regime, `drift` and `threshold` are in units
+// of that standard deviation. The change point is the last step at which the alarming sum was
+// zero; the detector recalibrates afterwards.
+#[derive(Clone, Debug)]
+pub struct CusumDetector {
+    calibration_steps: usize,
+    drift: f64,
+    threshold: f64,
+    steps: usize,
+    calibrated: usize,
+    mean: f64,
+    squared_deviations: f64,
+    upper: f64,
+    lower: f64,
+    upper_start: usize,
+    lower_start: usize,
+    change_points: Vec<usize>,
+}
+
+impl CusumDetector {
+    pub fn new(calibration_steps: usize, drift: f64, threshold: f64) -> Self {
+        assert!(
+            calibration_steps > 1,
+            "At least two calibration steps are needed."
+        );
+        assert!(drift >= 0. && threshold > 0.);
+        Self {
+            calibration_steps,
+            drift,
+            threshold,
+            steps: 0,
+            calibrated: 0,
+            mean: 0.,
+            squared_deviations: 0.,
+            upper: 0.,
+            lower: 0.,
+            upper_start: 0,
+            lower_start: 0,
+            change_points: vec![],
+        }
+    }
+
+    // Current values of the increasing and decreasing sums.
+    pub fn sums(&self) -> (f64, f64) {
+        (self.upper, self.lower)
+    }
+
+    fn restart(&mut self) {
+        self.calibrated = 0;
+        self.mean = 0.;
+        self.squared_deviations = 0.;
+        self.upper = 0.;
+        self.lower = 0.;
+    }
+}
+
+impl ChangePointDetector for CusumDetector {
+    fn observe(&mut self, magnitude: f64) -> Option<usize> {
+        let step = self.steps;
+        self.steps += 1;
+        if self.calibrated < self.calibration_steps {
+            // Welford
+            self.calibrated += 1;
+            let delta = magnitude - self.mean;
+            self.mean += delta / self.calibrated as f64;
+            self.squared_deviations += delta * (magnitude - self.mean);
+            self.upper_start = self.steps;
+            self.lower_start = self.steps;
+            return None;
+        }
+        let deviation = (self.squared_deviations / (self.calibrated - 1) as f64)
+            .sqrt()
+            .max(f64::MIN_POSITIVE);
+        let standardized = (magnitude - self.mean) / deviation;
+        if self.upper == 0. {
+            self.upper_start = step;
+        }
+        if self.lower == 0. {
+            self.lower_start = step;
+        }
+        self.upper = (self.upper + standardized - self.drift).max(0.);
+        self.lower = (self.lower - standardized - self.drift).max(0.);
+        let start = if self.upper > self.threshold {
+            self.upper_start
+        } else if self.lower > self.threshold {
+            self.lower_start
+        } else {
+            return None;
+        };
+        self.change_points.push(start);
+        self.restart();
+        Some(start)
+    }
+
+    fn change_points(&self) -> &[usize] {
+        &self.change_points
+    }
+
+    fn reset(&mut self) {
+        self.restart();
+        self.steps = 0;
+        self.change_points.clear();
+    }
+}
+
+// Bayesian online change point detection (Adams and MacKay) with a normal model of unknown mean
+// and variance per regime, a normal gamma prior and a constant hazard of 1 / expected run
+// length. The run length distribution is truncated at `max_run_length`. A change point is
+// reported once the most probable run has lasted `confirmation_steps`, which keeps single
+// outliers from being reported.
+#[derive(Clone, Debug)]
+pub struct BayesianChangePointDetector {
+    hazard: f64,
+    max_run_length: usize,
+    confirmation_steps: usize,
+    // mean, kappa, alpha, beta
+    prior: (f64, f64, f64, f64),
+    run_length_probabilities: Vec<f64>,
+    statistics: Vec<(f64, f64, f64, f64)>,
+    steps: usize,
+    current_start: usize,
+    change_points: Vec<usize>,
+}
+
+impl BayesianChangePointDetector {
+    pub fn new(expected_run_length: f64, max_run_length: usize) -> Self {
+        assert!(expected_run_length > 1.);
+        assert!(max_run_length > 1);
+        let mut detector = Self {
+            hazard: 1. / expected_run_length,
+            max_run_length,
+            confirmation_steps: 5,
+            prior: (0., 1., 1., 1.),
+            run_length_probabilities: vec![],
+            statistics: vec![],
+            steps: 0,
+            current_start: 0,
+            change_points: vec![],
+        };
+        detector.reset();
+        detector
+    }
+
+    // Normal gamma prior of the magnitudes of a regime, the default (0, 1, 1, 1) suits
+    // magnitudes of order one.
+    pub fn with_prior(mut self, mean: f64, kappa: f64, alpha: f64, beta: f64) -> Self {
+        assert!(kappa > 0. && alpha > 0. && beta > 0.);
+        self.prior = (mean, kappa, alpha, beta);
+        self.reset();
+        self
+    }
+
+    pub fn with_confirmation_steps(mut self, confirmation_steps: usize) -> Self {
+        assert!(confirmation_steps < self.max_run_length);
+        self.confirmation_steps = confirmation_steps;
+        self
+    }
+
+    // Posterior over the run length, index r is the probability that the current regime began
+    // r steps ago.
+    pub fn run_length_probabilities(&self) -> &[f64] {
+        &self.run_length_probabilities
+    }
+
+    pub fn most_probable_run_length(&self) -> usize {
+        (0..self.run_length_probabilities.len())
+            .max_by(|a, b| {
+                self.run_length_probabilities[*a].total_cmp(&self.run_length_probabilities[*b])
+            })
+            .unwrap()
+    }
+}
+
+impl ChangePointDetector for BayesianChangePointDetector {
+    fn observe(&mut self, magnitude: f64) -> Option<usize> {
+        self.steps += 1;
+        let log_predictive: Vec<_> = self
+            .statistics
+            .iter()
+            .map(|(mean, kappa, alpha, beta)| {
+                let scale = (beta * (kappa + 1.) / (alpha * kappa)).sqrt();
+                student_t_log_density(magnitude, *mean, scale, 2. * alpha)
+            })
+            .collect();
+        let max_log = log_predictive
+            .iter()
+            .fold(f64::NEG_INFINITY, |max, value| max.max(*value));
+        let weighted: Vec<_> = self
+            .run_length_probabilities
+            .iter()
+            .zip(&log_predictive)
+            .map(|(probability, log)| probability * (log - max_log).exp())
+            .collect();
+
+        let mut probabilities = Vec::with_capacity(weighted.len() + 1);
+        probabilities.push(weighted.iter().sum::<f64>() * self.hazard);
+        probabilities.extend(weighted.iter().map(|weight| weight * (1. - self.hazard)));
+        let mut statistics = Vec::with_capacity(weighted.len() + 1);
+        statistics.push(self.prior);
+        statistics.extend(self.statistics.iter().map(|(mean, kappa, alpha, beta)| {
+            (
+                (kappa * mean + magnitude) / (kappa + 1.),
+                kappa + 1.,
+                alpha + 0.5,
+                beta + kappa * (magnitude - mean).powi(2) / (2. * (kappa + 1.)),
+            )
+        }));
+        probabilities.truncate(self.max_run_length);
+        statistics.truncate(self.max_run_length);
+        let total: f64 = probabilities.iter().sum();
+        probabilities
+            .iter_mut()
+            .for_each(|probability| *probability /= total);
+        self.run_length_probabilities = probabilities;
+        self.statistics = statistics;
+
+        // A saturated run length says nothing about where the run began.
+        let run_length = self.most_probable_run_length();
+        if run_length + 1 >= self.max_run_length || run_length < self.confirmation_steps {
+            return None;
+        }
+        // Run length r holds the last r magnitudes.
+        let start = self.steps - run_length;
+        if start <= self.current_start {
+            return None;
+        }
+        self.current_start = start;
+        self.change_points.push(start);
+        Some(start)
+    }
+
+    fn change_points(&self) -> &[usize] {
+        &self.change_points
+    }
+
+    fn reset(&mut self) {
+        self.run_length_probabilities = vec![1.];
+        self.statistics = vec![self.prior];
+        self.steps = 0;
+        self.current_start = 0;
+        self.change_points.clear();
+    }
+}
+
+fn student_t_log_density(value: f64, mean: f64, scale: f64, degrees_of_freedom: f64) -> f64 {
+    let z = (value - mean) / scale;
+    ln_gamma((degrees_of_freedom + 1.) / 2.)
+        - ln_gamma(degrees_of_freedom / 2.)
+        - 0.5 * (degrees_of_freedom * std::f64::consts::PI).ln()
+        - scale.ln()
+        - (degrees_of_freedom + 1.) / 2. * (z * z / degrees_of_freedom).ln_1p()
+}
+
+// Lanczos approximation, g = 7, for positive arguments.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // Reflection
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1. - x);
+    }
+    let x = x - 1.;
+    let mut sum = COEFFICIENTS[0];
+    for (index, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + index as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2. * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+impl<T, I, E, M, P> ReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    // Runs `detector`, after a reset, on the one step prediction residuals of `data` and returns
+    // the detected change points as column indices of `data`.
+    pub fn detect_change_points<D: ChangePointDetector>(
+        &mut self,
+        data: DMatrixSlice<T>,
+        sync_steps: usize,
+        detector: &mut D,
+    ) -> Vec<usize> {
+        let predictions = self.one_step_predictions(data, sync_steps);
+        let residuals = predictions - data.columns(sync_steps + 1, data.ncols() - sync_steps - 1);
+        detector.reset();
+        residuals
+            .column_iter()
+            .filter_map(|residual| detector.update(residual))
+            .map(|step| step + sync_steps + 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{ln_gamma, BayesianChangePointDetector, ChangePointDetector, CusumDetector};
+
+    // Magnitudes around 0.1 that jump to around 0.4 at step 300.
+    fn magnitudes() -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(4);
+        (0..600)
+            .map(|step| {
+                let level = if step < 300 { 0.1 } else { 0.4 };
+                level + rng.gen_range(-0.05..0.05)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ln_gamma_matches_factorials() {
+        assert!(ln_gamma(1.).abs() < 1e-12);
+        assert!((ln_gamma(5.) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cusum_finds_the_jump() {
+        let mut detector = CusumDetector::new(50, 0.5, 8.);
+        for magnitude in magnitudes() {
+            detector.observe(magnitude);
+        }
+        assert_eq!(
+            detector.change_points().len(),
+            1,
+            "{:?}",
+            detector.change_points()
+        );
+        assert!((299..=302).contains(&detector.change_points()[0]));
+        detector.reset();
+        assert!(detector.change_points().is_empty());
+    }
+
+    #[test]
+    fn bayesian_detector_finds_the_jump() {
+        let mut detector =
+            BayesianChangePointDetector::new(1000., 500).with_prior(0., 1., 1., 0.01);
+        let detected: Vec<_> = magnitudes()
+            .into_iter()
+            .filter_map(|magnitude| detector.observe(magnitude))
+            .collect();
+        assert_eq!(detected, detector.change_points());
+        assert_eq!(detected.len(), 1, "{detected:?}");
+        assert!((299..=302).contains(&detected[0]));
+        assert!(detector.most_probable_run_length() > 250);
+    }
+}
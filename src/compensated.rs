@@ -0,0 +1,134 @@
+use nalgebra::{DMatrix, DMatrixSlice};
+
+use crate::{state_matrix::StateMatrix, ReservoirValue};
+
+// Helpers that keep f32 readouts and regressions close to their f64 counterparts: compensated
+// (Neumaier) sums for dot products and Gram matrices accumulated in f64.
+
+// Columns converted to f64 at once, bounds the extra memory of the f64 accumulation.
+const ACCUMULATION_CHUNK: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CompensatedSum<T: ReservoirValue> {
+    sum: T,
+    compensation: T,
+}
+
+impl<T: ReservoirValue> Default for CompensatedSum<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ReservoirValue> CompensatedSum<T> {
+    pub fn new() -> Self {
+        Self {
+            sum: T::zero(),
+            compensation: T::zero(),
+        }
+    }
+
+    pub fn add(&mut self, value: T) {
+        let sum = self.sum + value;
+        // The smaller summand loses its low order bits, they are collected separately.
+        if num_traits::Float::abs(self.sum) >= num_traits::Float::abs(value) {
+            self.compensation += (self.sum - sum) + value;
+        } else {
+            self.compensation += (value - sum) + self.sum;
+        }
+        self.sum = sum;
+    }
+
+    pub fn value(&self) -> T {
+        self.sum + self.compensation
+    }
+}
+
+pub fn compensated_dot<T: ReservoirValue>(
+    a: impl IntoIterator<Item = T>,
+    b: impl IntoIterator<Item = T>,
+) -> T {
+    let mut sum = CompensatedSum::new();
+    for (a, b) in a.into_iter().zip(b) {
+        sum.add(a * b);
+    }
+    sum.value()
+}
+
+// True for types with a coarser machine epsilon than f64, e.g. f32.
+pub fn is_reduced_precision<T: ReservoirValue>() -> bool {
+    <T as num_traits::Float>::epsilon().to_f64().unwrap() > f64::EPSILON
+}
+
+// X Xᵀ accumulated in f64, one column of X per sample.
+pub fn gram_f64<T: ReservoirValue, S: StateMatrix<T> + ?Sized>(states: &S) -> DMatrix<f64> {
+    let mut gram = DMatrix::zeros(states.nrows(), states.nrows());
+    states.for_each_chunk(&mut |_, chunk| {
+        for start in (0..chunk.ncols()).step_by(ACCUMULATION_CHUNK) {
+            let columns = ACCUMULATION_CHUNK.min(chunk.ncols() - start);
+            let states = chunk.columns(start, columns).map(to_f64);
+            gram.gemm(1., &states, &states.transpose(), 1.);
+        }
+    });
+    gram
+}
+
+// X Yᵀ accumulated in f64.
+pub fn cross_f64<T: ReservoirValue, S: StateMatrix<T> + ?Sized>(
+    states: &S,
+    targets: DMatrixSlice<T>,
+) -> DMatrix<f64> {
+    assert_eq!(states.ncols(), targets.ncols());
+    let mut cross = DMatrix::zeros(states.nrows(), targets.nrows());
+    states.for_each_chunk(&mut |offset, chunk| {
+        for start in (0..chunk.ncols()).step_by(ACCUMULATION_CHUNK) {
+            let columns = ACCUMULATION_CHUNK.min(chunk.ncols() - start);
+            let states = chunk.columns(start, columns).map(to_f64);
+            let targets = targets.columns(offset + start, columns).map(to_f64);
+            cross.gemm(1., &states, &targets.transpose(), 1.);
+        }
+    });
+    cross
+}
+
+fn to_f64<T: ReservoirValue>(value: T) -> f64 {
+    value.to_f64().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::{compensated_dot, gram_f64, is_reduced_precision, CompensatedSum};
+
+    #[test]
+    fn compensated_sums_keep_small_summands() {
+        let mut sum = CompensatedSum::<f32>::new();
+        let mut naive = 0f32;
+        sum.add(1e8);
+        naive += 1e8;
+        for _ in 0..10_000 {
+            sum.add(1.);
+            naive += 1.;
+        }
+        sum.add(-1e8);
+        naive -= 1e8;
+        assert_eq!(sum.value(), 10_000.);
+        assert_eq!(naive, 0.);
+
+        let a = [1e7f32, 1., -1e7, 0.5];
+        let b = [1f32, 1., 1., 1.];
+        assert_eq!(compensated_dot(a, b), 1.5);
+        assert!(is_reduced_precision::<f32>());
+        assert!(!is_reduced_precision::<f64>());
+    }
+
+    #[test]
+    fn f64_gram_of_f32_states() {
+        let states = DMatrix::<f32>::from_fn(3, 600, |i, j| 100. + ((i * 7 + j) as f32).sin());
+        let reference = states.map(f64::from);
+        let expected = &reference * reference.transpose();
+        let gram = gram_f64(&states);
+        assert!((&gram - &expected).amax() / expected.amax() < 1e-14);
+    }
+}
@@ -16,9 +16,9 @@ where
     M: ReservoirStateMeasurement<T>,
     P: ReservoirStateProjection<T>,
 {
-    reservoir: ControlledReservoir<T, I, C, E>,
-    reservoir_state_measurement: M,
-    reservoir_state_projection: P,
+    pub(crate) reservoir: ControlledReservoir<T, I, C, E>,
+    pub(crate) reservoir_state_measurement: M,
+    pub(crate) reservoir_state_projection: P,
 }
 
 impl<T, I, C, E, M, P> ControlledReservoirComputer<T, I, C, E, M, P>
@@ -1,10 +1,14 @@
 use std::marker::PhantomData;
 
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
+
 use crate::{
     controlled_time_evolution::ControlledReservoirTimeEvolution,
     input_projection::ReservoirInputProjection, ReservoirValue,
 };
 
+use super::ControlledReservoir;
+
 #[derive(Debug)]
 pub struct ControlledReservoirDynamics<T, I, C, E>
 where
@@ -19,6 +23,25 @@ where
     _phantom: PhantomData<T>,
 }
 
+impl<T, I, C, E> Clone for ControlledReservoirDynamics<T, I, C, E>
+where
+    T: ReservoirValue + Clone,
+    I: ReservoirInputProjection<T> + Clone,
+    C: ReservoirInputProjection<T> + Clone,
+    E: ControlledReservoirTimeEvolution<T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reservoir_input_projection: self.reservoir_input_projection.clone(),
+            reservoir_controlled_input_projection: self
+                .reservoir_controlled_input_projection
+                .clone(),
+            reservoir_time_evolution: self.reservoir_time_evolution.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
 impl<T, I, C, E> ControlledReservoirDynamics<T, I, C, E>
 where
     T: ReservoirValue,
@@ -26,6 +49,34 @@ where
     I: ReservoirInputProjection<T>,
     C: ReservoirInputProjection<T>,
 {
+    pub fn new(
+        reservoir_input_projection: I,
+        reservoir_controlled_input_projection: C,
+        reservoir_time_evolution: E,
+    ) -> Self {
+        Self {
+            reservoir_input_projection,
+            reservoir_controlled_input_projection,
+            reservoir_time_evolution,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn into_parts(self) -> (I, C, E) {
+        (
+            self.reservoir_input_projection,
+            self.reservoir_controlled_input_projection,
+            self.reservoir_time_evolution,
+        )
+    }
+
+    pub fn into_reservoir(self, state: DVector<T>) -> ControlledReservoir<T, I, C, E> {
+        ControlledReservoir {
+            reservoir_state: state,
+            reservoir_dynamics: self,
+        }
+    }
+
     pub fn input_projection(&self) -> &I {
         &self.reservoir_input_projection
     }
@@ -37,4 +88,95 @@ where
     pub fn time_evolution(&self) -> &E {
         &self.reservoir_time_evolution
     }
+
+    // Feeds the input and control windows that end at column `last_column`.
+    fn evolve(
+        &mut self,
+        state: &mut DVector<T>,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+        last_column: usize,
+    ) {
+        let input_columns = self.reservoir_input_projection.required_input_columns();
+        let control_columns = self
+            .reservoir_controlled_input_projection
+            .required_input_columns();
+
+        let input_vector = self
+            .reservoir_input_projection
+            .project(input.columns(last_column + 1 - input_columns, input_columns));
+        let control_vector = self
+            .reservoir_controlled_input_projection
+            .project(control.columns(last_column + 1 - control_columns, control_columns));
+        self.reservoir_time_evolution.controlled_time_evolution(
+            state,
+            input_vector.column(0),
+            control_vector.column(0),
+        );
+    }
+
+    fn required_columns(&self) -> usize {
+        usize::max(
+            self.reservoir_input_projection.required_input_columns(),
+            self.reservoir_controlled_input_projection
+                .required_input_columns(),
+        )
+    }
+
+    pub fn synchronize_state(
+        &mut self,
+        state: &mut DVector<T>,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+    ) {
+        assert_eq!(input.nrows(), self.input_projection().input_dimension());
+        assert_eq!(
+            control.nrows(),
+            self.controlled_input_projection().input_dimension()
+        );
+        assert_eq!(input.ncols(), control.ncols());
+
+        for last_column in (self.required_columns() - 1)..input.ncols() {
+            self.evolve(state, input, control, last_column);
+        }
+    }
+
+    pub fn record_states(
+        &mut self,
+        state: &mut DVector<T>,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+        sync_steps: usize,
+    ) -> DMatrix<T> {
+        let mut states = DMatrix::zeros(
+            self.time_evolution().output_dimension(),
+            input.ncols() - sync_steps,
+        );
+        let slice = states.columns_mut(0, states.ncols());
+        self.record_states_into(state, input, control, sync_steps, slice);
+        states
+    }
+
+    pub fn record_states_into(
+        &mut self,
+        state: &mut DVector<T>,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+        sync_steps: usize,
+        mut result: DMatrixSliceMut<T>,
+    ) {
+        assert_eq!(input.ncols(), control.ncols());
+        assert!(sync_steps + 1 >= self.required_columns());
+        assert_eq!(result.ncols(), input.ncols() - sync_steps);
+
+        self.synchronize_state(
+            state,
+            input.columns(0, sync_steps),
+            control.columns(0, sync_steps),
+        );
+        for (index, last_column) in (sync_steps..input.ncols()).enumerate() {
+            self.evolve(state, input, control, last_column);
+            result.column_mut(index).copy_from(state);
+        }
+    }
 }
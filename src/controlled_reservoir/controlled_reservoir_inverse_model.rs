@@ -0,0 +1,100 @@
+use nalgebra::{DMatrixSlice, DVector, DVectorSlice};
+
+use crate::{
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    input_projection::ReservoirInputProjection, output_projection::ReservoirStateProjection,
+    state_measurement::ReservoirStateMeasurement, ReservoirValue,
+};
+
+use super::ControlledReservoir;
+
+// Maps the measured reservoir state together with a desired next output onto the control
+// that has to be applied. The reservoir state is driven by the current input and the
+// previously applied control.
+#[derive(Debug)]
+pub struct ControlledReservoirInverseModel<T, I, C, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    C: ReservoirInputProjection<T>,
+    E: ControlledReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub(crate) reservoir: ControlledReservoir<T, I, C, E>,
+    pub(crate) reservoir_state_measurement: M,
+    pub(crate) reservoir_state_projection: P,
+    pub(crate) features: DVector<T>,
+}
+
+impl<T, I, C, E, M, P> ControlledReservoirInverseModel<T, I, C, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    C: ReservoirInputProjection<T>,
+    E: ControlledReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn controlled_reservoir(&self) -> &ControlledReservoir<T, I, C, E> {
+        &self.reservoir
+    }
+
+    pub fn state_measurement(&self) -> &M {
+        &self.reservoir_state_measurement
+    }
+
+    pub fn state_projection(&self) -> &P {
+        &self.reservoir_state_projection
+    }
+
+    pub fn into_parts(self) -> (ControlledReservoir<T, I, C, E>, M, P) {
+        (
+            self.reservoir,
+            self.reservoir_state_measurement,
+            self.reservoir_state_projection,
+        )
+    }
+
+    pub fn synchronize_state(&mut self, input: DMatrixSlice<T>, previous_control: DMatrixSlice<T>) {
+        self.reservoir.synchronize_state(input, previous_control);
+    }
+
+    pub fn control(&mut self, desired_output: DVectorSlice<T>) -> &DVector<T> {
+        let measurement_dimension = self.reservoir_state_measurement.output_dimension();
+        assert_eq!(
+            measurement_dimension + desired_output.nrows(),
+            self.features.nrows()
+        );
+
+        let state_measurement = self
+            .reservoir_state_measurement
+            .measure(&self.reservoir.reservoir_state);
+        self.features
+            .rows_mut(0, measurement_dimension)
+            .copy_from(state_measurement);
+        self.features
+            .rows_mut(measurement_dimension, desired_output.nrows())
+            .copy_from(&desired_output);
+        self.reservoir_state_projection.project(&self.features)
+    }
+}
+
+impl<T, I, C, E, M, P> Clone for ControlledReservoirInverseModel<T, I, C, E, M, P>
+where
+    T: ReservoirValue + Clone,
+    I: ReservoirInputProjection<T> + Clone,
+    C: ReservoirInputProjection<T> + Clone,
+    E: ControlledReservoirTimeEvolution<T> + Clone,
+    M: ReservoirStateMeasurement<T> + Clone,
+    P: ReservoirStateProjection<T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reservoir: self.reservoir.clone(),
+            reservoir_state_measurement: self.reservoir_state_measurement.clone(),
+            reservoir_state_projection: self.reservoir_state_projection.clone(),
+            features: self.features.clone(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use nalgebra::DVector;
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
 
 use crate::{
     controlled_time_evolution::ControlledReservoirTimeEvolution,
@@ -15,8 +15,8 @@ where
     I: ReservoirInputProjection<T>,
     C: ReservoirInputProjection<T>,
 {
-    reservoir_state: DVector<T>,
-    reservoir_dynamics: ControlledReservoirDynamics<T, I, C, E>,
+    pub(crate) reservoir_state: DVector<T>,
+    pub(crate) reservoir_dynamics: ControlledReservoirDynamics<T, I, C, E>,
 }
 
 impl<T, I, C, E> ControlledReservoir<T, I, C, E>
@@ -26,6 +26,32 @@ where
     I: ReservoirInputProjection<T>,
     C: ReservoirInputProjection<T>,
 {
+    pub fn new(
+        reservoir_input_projection: I,
+        reservoir_controlled_input_projection: C,
+        reservoir_time_evolution: E,
+    ) -> Self {
+        let reservoir_dimension = reservoir_time_evolution.input_dimension();
+        let reservoir_dynamics = ControlledReservoirDynamics::new(
+            reservoir_input_projection,
+            reservoir_controlled_input_projection,
+            reservoir_time_evolution,
+        );
+        Self {
+            reservoir_dynamics,
+            reservoir_state: DVector::zeros(reservoir_dimension),
+        }
+    }
+
+    pub fn into_parts(self) -> (DVector<T>, I, C, E) {
+        let (i, c, e) = self.reservoir_dynamics.into_parts();
+        (self.reservoir_state, i, c, e)
+    }
+
+    pub fn split_reservoir_dynamics(self) -> (DVector<T>, ControlledReservoirDynamics<T, I, C, E>) {
+        (self.reservoir_state, self.reservoir_dynamics)
+    }
+
     pub fn reservoir_state(&self) -> &DVector<T> {
         &self.reservoir_state
     }
@@ -33,4 +59,50 @@ where
     pub fn reservoir_dynamics(&self) -> &ControlledReservoirDynamics<T, I, C, E> {
         &self.reservoir_dynamics
     }
+
+    pub fn synchronize_state(&mut self, input: DMatrixSlice<T>, control: DMatrixSlice<T>) {
+        self.reservoir_dynamics
+            .synchronize_state(&mut self.reservoir_state, input, control);
+    }
+
+    pub fn record_states(
+        &mut self,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+        sync_steps: usize,
+    ) -> DMatrix<T> {
+        self.reservoir_dynamics
+            .record_states(&mut self.reservoir_state, input, control, sync_steps)
+    }
+
+    pub fn record_states_into(
+        &mut self,
+        input: DMatrixSlice<T>,
+        control: DMatrixSlice<T>,
+        sync_steps: usize,
+        result: DMatrixSliceMut<T>,
+    ) {
+        self.reservoir_dynamics.record_states_into(
+            &mut self.reservoir_state,
+            input,
+            control,
+            sync_steps,
+            result,
+        )
+    }
+}
+
+impl<T, I, C, E> Clone for ControlledReservoir<T, I, C, E>
+where
+    T: ReservoirValue + Clone,
+    I: ReservoirInputProjection<T> + Clone,
+    C: ReservoirInputProjection<T> + Clone,
+    E: ControlledReservoirTimeEvolution<T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reservoir_state: self.reservoir_state.clone(),
+            reservoir_dynamics: self.reservoir_dynamics.clone(),
+        }
+    }
 }
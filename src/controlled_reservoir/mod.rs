@@ -1,9 +1,12 @@
 pub mod controlled_reservoir_computer;
 pub mod controlled_reservoir_computer_dynamics;
 pub mod controlled_reservoir_dynamics;
+pub mod controlled_reservoir_inverse_model;
 pub mod core_controlled_reservoir;
+pub mod training;
 
 pub use controlled_reservoir_computer::ControlledReservoirComputer;
 pub use controlled_reservoir_computer_dynamics::ControlledReservoirComputerDynamics;
 pub use controlled_reservoir_dynamics::ControlledReservoirDynamics;
+pub use controlled_reservoir_inverse_model::ControlledReservoirInverseModel;
 pub use core_controlled_reservoir::ControlledReservoir;
@@ -0,0 +1,91 @@
+use nalgebra::{ClosedAdd, ClosedMul, ComplexField, DMatrix, DVector};
+
+use crate::{
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    input_projection::ReservoirInputProjection, output_projection::LinearStateProjection,
+    state_measurement::ReservoirStateMeasurement, ReservoirValue,
+};
+
+use super::{ControlledReservoir, ControlledReservoirInverseModel};
+
+pub struct ControlledReservoirTraining<T>
+where
+    T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul,
+{
+    data: Vec<(DMatrix<T>, DMatrix<T>)>,
+    train_sync_steps: usize,
+    train_steps: usize,
+}
+
+impl<T> ControlledReservoirTraining<T>
+where
+    T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul,
+{
+    pub fn new(train_sync_steps: usize, train_steps: usize) -> Self {
+        Self {
+            data: vec![],
+            train_sync_steps,
+            train_steps,
+        }
+    }
+
+    pub fn add_data(&mut self, input: DMatrix<T>, control: DMatrix<T>) -> &mut Self {
+        assert_eq!(input.ncols(), control.ncols());
+        assert!(input.ncols() > self.train_sync_steps + self.train_steps);
+        self.data.push((input, control));
+        self
+    }
+
+    // Column t of the control matrix is the control applied together with input column t,
+    // i.e. it drives the system from input column t to input column t + 1.
+    pub fn train_inverse_model_via_ridge_regression<I, C, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: ControlledReservoir<T, I, C, E>,
+        measurement: M,
+    ) -> ControlledReservoirInverseModel<T, I, C, E, M, LinearStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        C: ReservoirInputProjection<T>,
+        E: ControlledReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert_eq!(
+            self.data.len(),
+            1,
+            "Multifunctional learning scenarios are not yet supported."
+        );
+        let (input, control) = &self.data[0];
+        let sync_train_steps = self.train_sync_steps + self.train_steps;
+
+        // The reservoir sees input t together with the previous control t - 1.
+        let recorded_states = reservoir.record_states(
+            input.columns(1, sync_train_steps - 1),
+            control.columns(0, sync_train_steps - 1),
+            self.train_sync_steps,
+        );
+        let recorded_states =
+            measurement.measure_many(recorded_states.columns(0, recorded_states.ncols()));
+
+        let measurement_dimension = recorded_states.nrows();
+        let samples = recorded_states.ncols();
+        let mut features = DMatrix::zeros(measurement_dimension + input.nrows(), samples);
+        features
+            .rows_mut(0, measurement_dimension)
+            .copy_from(&recorded_states);
+        features
+            .rows_mut(measurement_dimension, input.nrows())
+            .copy_from(&input.columns(self.train_sync_steps + 2, samples));
+        let target_controls = control.columns(self.train_sync_steps + 1, samples);
+
+        let linear_fit =
+            LinearStateProjection::via_ridge_regression_nalgebra(beta, &features, target_controls);
+
+        ControlledReservoirInverseModel {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+            features: DVector::zeros(features.nrows()),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use nalgebra::DMatrix;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::ReservoirValue;
+
+// Ordinary differential equation driven by a scalar control signal.
+pub trait ForcedSystem {
+    fn dimension(&self) -> usize;
+
+    fn derivative(&self, state: &[f64], control: f64, result: &mut [f64]);
+}
+
+// x'' - mu (1 - x^2) x' + x = u, state (x, x').
+#[derive(Clone, Copy, Debug)]
+pub struct VanDerPol {
+    pub mu: f64,
+}
+
+// x'' + delta x' + alpha x + beta x^3 = u, state (x, x').
+#[derive(Clone, Copy, Debug)]
+pub struct Duffing {
+    pub delta: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+// Two damped linear oscillators coupled by a spring, only the first one is forced:
+// x1'' = -omega1^2 x1 - damping x1' + coupling (x2 - x1) + u
+// x2'' = -omega2^2 x2 - damping x2' + coupling (x1 - x2)
+// state (x1, x1', x2, x2').
+#[derive(Clone, Copy, Debug)]
+pub struct CoupledOscillators {
+    pub omega1: f64,
+    pub omega2: f64,
+    pub damping: f64,
+    pub coupling: f64,
+}
+
+impl Default for VanDerPol {
+    fn default() -> Self {
+        Self { mu: 1. }
+    }
+}
+
+impl Default for Duffing {
+    // The double well of the classic chaotic forced Duffing oscillator.
+    fn default() -> Self {
+        Self {
+            delta: 0.3,
+            alpha: -1.,
+            beta: 1.,
+        }
+    }
+}
+
+impl Default for CoupledOscillators {
+    fn default() -> Self {
+        Self {
+            omega1: 1.,
+            omega2: 1.5,
+            damping: 0.1,
+            coupling: 0.5,
+        }
+    }
+}
+
+impl ForcedSystem for VanDerPol {
+    fn dimension(&self) -> usize {
+        2
+    }
+
+    fn derivative(&self, state: &[f64], control: f64, result: &mut [f64]) {
+        let (x, v) = (state[0], state[1]);
+        result[0] = v;
+        result[1] = self.mu * (1. - x * x) * v - x + control;
+    }
+}
+
+impl ForcedSystem for Duffing {
+    fn dimension(&self) -> usize {
+        2
+    }
+
+    fn derivative(&self, state: &[f64], control: f64, result: &mut [f64]) {
+        let (x, v) = (state[0], state[1]);
+        result[0] = v;
+        result[1] = -self.delta * v - self.alpha * x - self.beta * x * x * x + control;
+    }
+}
+
+impl ForcedSystem for CoupledOscillators {
+    fn dimension(&self) -> usize {
+        4
+    }
+
+    fn derivative(&self, state: &[f64], control: f64, result: &mut [f64]) {
+        let (x1, v1, x2, v2) = (state[0], state[1], state[2], state[3]);
+        result[0] = v1;
+        result[1] = -self.omega1 * self.omega1 * x1 - self.damping * v1
+            + self.coupling * (x2 - x1)
+            + control;
+        result[2] = v2;
+        result[3] = -self.omega2 * self.omega2 * x2 - self.damping * v2 + self.coupling * (x1 - x2);
+    }
+}
+
+// The control signal, sampled once per step and held constant during the step.
+#[derive(Clone, Copy, Debug)]
+pub enum Forcing {
+    // amplitude * cos(angular_frequency * t)
+    Sinusoidal {
+        amplitude: f64,
+        angular_frequency: f64,
+    },
+    // Uniform random values in [-amplitude, amplitude], each held for `hold_steps` steps.
+    RandomSteps {
+        amplitude: f64,
+        hold_steps: usize,
+        seed: u64,
+    },
+}
+
+impl Forcing {
+    pub fn sample(&self, dt: f64, steps: usize) -> Vec<f64> {
+        match *self {
+            Forcing::Sinusoidal {
+                amplitude,
+                angular_frequency,
+            } => (0..steps)
+                .map(|step| amplitude * (angular_frequency * step as f64 * dt).cos())
+                .collect(),
+            Forcing::RandomSteps {
+                amplitude,
+                hold_steps,
+                seed,
+            } => {
+                assert!(hold_steps > 0);
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut value = 0.;
+                (0..steps)
+                    .map(|step| {
+                        if step % hold_steps == 0 {
+                            value = rng.gen_range(-amplitude..=amplitude);
+                        }
+                        value
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// Integrates `system` from `initial` with RK4, `substeps` steps per sample of length `dt`.
+// Returns (states, control) in the layout of `ControlledReservoirTraining::add_data`: control
+// column t is applied during the step from state column t to state column t + 1, so the known
+// ground truth response to every control value is part of the data.
+pub fn simulate_forced<S: ForcedSystem, T: ReservoirValue>(
+    system: &S,
+    initial: &[f64],
+    forcing: &Forcing,
+    dt: f64,
+    steps: usize,
+    substeps: usize,
+) -> (DMatrix<T>, DMatrix<T>) {
+    let dimension = system.dimension();
+    assert_eq!(initial.len(), dimension);
+    assert!(dt > 0. && substeps > 0);
+    let controls = forcing.sample(dt, steps);
+    let h = dt / substeps as f64;
+
+    let mut state = initial.to_vec();
+    let mut k = vec![vec![0.; dimension]; 4];
+    let mut stage = vec![0.; dimension];
+    let mut states = DMatrix::zeros(dimension, steps);
+    for (step, control) in controls.iter().enumerate() {
+        for (target, value) in states.column_mut(step).iter_mut().zip(state.iter()) {
+            *target = T::from_f64(*value).unwrap();
+        }
+        for _ in 0..substeps {
+            system.derivative(&state, *control, &mut k[0]);
+            for (index, factor) in [(1, 0.5), (2, 0.5), (3, 1.)] {
+                for i in 0..dimension {
+                    stage[i] = state[i] + factor * h * k[index - 1][i];
+                }
+                system.derivative(&stage, *control, &mut k[index]);
+            }
+            for i in 0..dimension {
+                state[i] += h / 6. * (k[0][i] + 2. * k[1][i] + 2. * k[2][i] + k[3][i]);
+            }
+        }
+    }
+    let control = DMatrix::from_iterator(
+        1,
+        steps,
+        controls
+            .into_iter()
+            .map(|value| T::from_f64(value).unwrap()),
+    );
+    (states, control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{simulate_forced, CoupledOscillators, Forcing, VanDerPol};
+
+    #[test]
+    fn van_der_pol_reaches_its_limit_cycle() {
+        let forcing = Forcing::Sinusoidal {
+            amplitude: 0.,
+            angular_frequency: 1.,
+        };
+        let (states, control) =
+            simulate_forced::<_, f64>(&VanDerPol::default(), &[0.1, 0.], &forcing, 0.05, 2000, 4);
+        assert_eq!(states.shape(), (2, 2000));
+        assert!(control.iter().all(|value| *value == 0.));
+        // The limit cycle of mu = 1 has an amplitude of about 2.
+        let amplitude = states.row(0).columns(1000, 1000).amax();
+        assert!((amplitude - 2.).abs() < 0.05);
+    }
+
+    #[test]
+    fn coupled_oscillators_respond_to_held_control() {
+        let forcing = Forcing::RandomSteps {
+            amplitude: 1.,
+            hold_steps: 5,
+            seed: 3,
+        };
+        let (states, control) = simulate_forced::<_, f64>(
+            &CoupledOscillators::default(),
+            &[0.; 4],
+            &forcing,
+            0.1,
+            100,
+            2,
+        );
+        assert_eq!(control[0], control[4]);
+        assert_ne!(control[4], control[5]);
+        // Starting at rest only the velocity of the forced oscillator reacts in the first step.
+        assert!((states[(1, 1)] - 0.1 * control[0]).abs() < 0.01);
+        assert!(states[(3, 1)].abs() < 1e-3);
+    }
+}
@@ -0,0 +1,273 @@
+use nalgebra::{Complex, ComplexField, DMatrix, DVector};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::fft::fft;
+use crate::ReservoirValue;
+
+// Kuramoto-Sivashinsky equation u_t = -u u_x - u_xx - u_xxxx on the periodic domain
+// [0, domain_length), integrated with the ETDRK4 scheme of Kassam and Trefethen on
+// `resolution` grid points. Columns of the generated fields are time steps, rows grid points.
+#[derive(Clone, Debug)]
+pub struct KuramotoSivashinsky {
+    domain_length: f64,
+    resolution: usize,
+    dt: f64,
+    // Coefficients per Fourier mode.
+    e: Vec<f64>,
+    e2: Vec<f64>,
+    q: Vec<f64>,
+    f1: Vec<f64>,
+    f2: Vec<f64>,
+    f3: Vec<f64>,
+    g: Vec<Complex<f64>>,
+}
+
+impl Default for KuramotoSivashinsky {
+    // The common parallel reservoir setup, L = 22 with 64 grid points and dt = 0.25.
+    fn default() -> Self {
+        Self::new(22., 64, 0.25)
+    }
+}
+
+impl KuramotoSivashinsky {
+    pub fn new(domain_length: f64, resolution: usize, dt: f64) -> Self {
+        assert!(domain_length > 0.);
+        assert!(resolution >= 4 && resolution.is_multiple_of(2));
+        assert!(dt > 0.);
+        let wavenumbers: Vec<f64> = (0..resolution)
+            .map(|index| {
+                let mode = if index < resolution / 2 {
+                    index as f64
+                } else if index == resolution / 2 {
+                    0.
+                } else {
+                    index as f64 - resolution as f64
+                };
+                mode * std::f64::consts::TAU / domain_length
+            })
+            .collect();
+
+        // The phi functions are evaluated as means over a circle in the complex plane around
+        // every h L, which avoids the cancellation for small arguments.
+        const CONTOUR_POINTS: usize = 16;
+        let roots: Vec<Complex<f64>> = (0..CONTOUR_POINTS)
+            .map(|m| {
+                let angle = std::f64::consts::PI * (m as f64 + 0.5) / CONTOUR_POINTS as f64;
+                Complex::new(angle.cos(), angle.sin())
+            })
+            .collect();
+        let contour_mean = |linear: f64, phi: &dyn Fn(Complex<f64>) -> Complex<f64>| {
+            let sum: Complex<f64> = roots.iter().map(|root| phi(root + dt * linear)).sum();
+            dt * (sum / CONTOUR_POINTS as f64).re
+        };
+
+        let mut generator = Self {
+            domain_length,
+            resolution,
+            dt,
+            e: Vec::with_capacity(resolution),
+            e2: Vec::with_capacity(resolution),
+            q: Vec::with_capacity(resolution),
+            f1: Vec::with_capacity(resolution),
+            f2: Vec::with_capacity(resolution),
+            f3: Vec::with_capacity(resolution),
+            g: Vec::with_capacity(resolution),
+        };
+        for k in wavenumbers {
+            let linear = k * k - k * k * k * k;
+            generator.e.push((dt * linear).exp());
+            generator.e2.push((dt * linear / 2.).exp());
+            generator
+                .q
+                .push(contour_mean(linear, &|z| ((z / 2.).exp() - 1.) / z));
+            generator.f1.push(contour_mean(linear, &|z| {
+                (-4. - z + z.exp() * (4. - 3. * z + z * z)) / (z * z * z)
+            }));
+            generator.f2.push(contour_mean(linear, &|z| {
+                (2. + z + z.exp() * (z - 2.)) / (z * z * z)
+            }));
+            generator.f3.push(contour_mean(linear, &|z| {
+                (-4. - 3. * z - z * z + z.exp() * (4. - z)) / (z * z * z)
+            }));
+            generator.g.push(Complex::new(0., -0.5 * k));
+        }
+        generator
+    }
+
+    pub fn domain_length(&self) -> f64 {
+        self.domain_length
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    pub fn dt(&self) -> f64 {
+        self.dt
+    }
+
+    // Superposition of the four longest modes with seeded random amplitudes in [-0.5, 0.5] and
+    // phases, the spatial mean is zero.
+    pub fn random_initial_condition(&self, seed: u64) -> DVector<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let modes: Vec<(f64, f64)> = (0..4)
+            .map(|_| {
+                (
+                    rng.gen_range(-0.5..0.5),
+                    rng.gen_range(0.0..std::f64::consts::TAU),
+                )
+            })
+            .collect();
+        DVector::from_fn(self.resolution, |index, _| {
+            let x = std::f64::consts::TAU * index as f64 / self.resolution as f64;
+            modes
+                .iter()
+                .enumerate()
+                .map(|(mode, (amplitude, phase))| amplitude * ((mode + 1) as f64 * x + phase).cos())
+                .sum()
+        })
+    }
+
+    // Integrates `transient + steps` steps from `initial` and keeps the last `steps` fields.
+    pub fn simulate<T: ReservoirValue>(
+        &self,
+        initial: &DVector<f64>,
+        transient: usize,
+        steps: usize,
+    ) -> DMatrix<T> {
+        assert_eq!(initial.nrows(), self.resolution);
+        let mut spectrum: Vec<Complex<f64>> = initial
+            .iter()
+            .map(|value| Complex::new(*value, 0.))
+            .collect();
+        fft(&mut spectrum, false);
+
+        let mut buffers = StepBuffers::new(self.resolution);
+        for _ in 0..transient {
+            self.step(&mut spectrum, &mut buffers);
+        }
+        let mut fields = DMatrix::zeros(self.resolution, steps);
+        let mut field = vec![Complex::new(0., 0.); self.resolution];
+        for mut column in fields.column_iter_mut() {
+            self.step(&mut spectrum, &mut buffers);
+            field.copy_from_slice(&spectrum);
+            fft(&mut field, true);
+            for (target, value) in column.iter_mut().zip(field.iter()) {
+                *target = T::from_f64(value.re).unwrap();
+            }
+        }
+        fields
+    }
+
+    fn step(&self, v: &mut [Complex<f64>], buffers: &mut StepBuffers) {
+        let StepBuffers {
+            a,
+            b,
+            c,
+            nv,
+            na,
+            nb,
+            nc,
+            scratch,
+        } = buffers;
+        self.nonlinear(v, nv, scratch);
+        for mode in 0..self.resolution {
+            a[mode] = v[mode] * self.e2[mode] + nv[mode] * self.q[mode];
+        }
+        self.nonlinear(a, na, scratch);
+        for mode in 0..self.resolution {
+            b[mode] = v[mode] * self.e2[mode] + na[mode] * self.q[mode];
+        }
+        self.nonlinear(b, nb, scratch);
+        for mode in 0..self.resolution {
+            c[mode] = a[mode] * self.e2[mode] + (nb[mode] * 2. - nv[mode]) * self.q[mode];
+        }
+        self.nonlinear(c, nc, scratch);
+        for mode in 0..self.resolution {
+            v[mode] = v[mode] * self.e[mode]
+                + nv[mode] * self.f1[mode]
+                + (na[mode] + nb[mode]) * (2. * self.f2[mode])
+                + nc[mode] * self.f3[mode];
+        }
+    }
+
+    // -u u_x = -0.5 (u^2)_x in Fourier space.
+    fn nonlinear(
+        &self,
+        spectrum: &[Complex<f64>],
+        result: &mut [Complex<f64>],
+        scratch: &mut [Complex<f64>],
+    ) {
+        scratch.copy_from_slice(spectrum);
+        fft(scratch, true);
+        for value in scratch.iter_mut() {
+            *value = Complex::new(value.re * value.re, 0.);
+        }
+        fft(scratch, false);
+        for mode in 0..self.resolution {
+            result[mode] = self.g[mode] * scratch[mode];
+        }
+    }
+}
+
+struct StepBuffers {
+    a: Vec<Complex<f64>>,
+    b: Vec<Complex<f64>>,
+    c: Vec<Complex<f64>>,
+    nv: Vec<Complex<f64>>,
+    na: Vec<Complex<f64>>,
+    nb: Vec<Complex<f64>>,
+    nc: Vec<Complex<f64>>,
+    scratch: Vec<Complex<f64>>,
+}
+
+impl StepBuffers {
+    fn new(resolution: usize) -> Self {
+        let zeros = vec![Complex::new(0., 0.); resolution];
+        Self {
+            a: zeros.clone(),
+            b: zeros.clone(),
+            c: zeros.clone(),
+            nv: zeros.clone(),
+            na: zeros.clone(),
+            nb: zeros.clone(),
+            nc: zeros.clone(),
+            scratch: zeros,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KuramotoSivashinsky;
+    use nalgebra::DVector;
+
+    #[test]
+    fn kuramoto_sivashinsky_becomes_chaotic_and_keeps_the_mean() {
+        let generator = KuramotoSivashinsky::default();
+        let initial = generator.random_initial_condition(1).add_scalar(0.1);
+        let fields = generator.simulate::<f64>(&initial, 400, 400);
+        assert_eq!(fields.shape(), (64, 400));
+        for column in fields.column_iter() {
+            assert!((column.mean() - 0.1).abs() < 1e-8);
+        }
+        // The attractor has an amplitude of about 2 to 3.
+        assert!(fields.amax() > 1. && fields.amax() < 5.);
+
+        // Continuing from the last field gives the same trajectory.
+        let split = fields.column(199).clone_owned();
+        let continued = generator.simulate::<f64>(&DVector::from(split), 0, 10);
+        assert!((continued - fields.columns(200, 10)).amax() < 1e-8);
+    }
+
+    #[test]
+    fn kuramoto_sivashinsky_converges_with_dt() {
+        let coarse = KuramotoSivashinsky::new(22., 64, 0.1);
+        let fine = KuramotoSivashinsky::new(22., 64, 0.05);
+        let initial = coarse.random_initial_condition(2);
+        let coarse_field = coarse.simulate::<f64>(&initial, 0, 20);
+        let fine_field = fine.simulate::<f64>(&initial, 0, 40);
+        let difference = (coarse_field.column(19) - fine_field.column(39)).amax();
+        assert!(difference < 1e-5, "{difference}");
+    }
+}
@@ -0,0 +1,10 @@
+// Generators of the standard benchmark data.
+pub mod forced_oscillators;
+pub mod kuramoto_sivashinsky;
+pub mod stochastic_processes;
+
+pub use forced_oscillators::{
+    simulate_forced, CoupledOscillators, Duffing, ForcedSystem, Forcing, VanDerPol,
+};
+pub use kuramoto_sivashinsky::KuramotoSivashinsky;
+pub use stochastic_processes::{Arma, OrnsteinUhlenbeck};
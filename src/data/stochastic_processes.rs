@@ -0,0 +1,128 @@
+use nalgebra::DMatrix;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::random::standard_normal;
+use crate::ReservoirValue;
+
+// dx = theta (mu - x) dt + sigma dW, sampled with the exact transition so the statistics do
+// not depend on dt.
+#[derive(Clone, Copy, Debug)]
+pub struct OrnsteinUhlenbeck {
+    pub theta: f64,
+    pub mu: f64,
+    pub sigma: f64,
+}
+
+impl OrnsteinUhlenbeck {
+    pub fn stationary_standard_deviation(&self) -> f64 {
+        self.sigma / (2. * self.theta).sqrt()
+    }
+
+    // One row, the first column is `initial`.
+    pub fn simulate<T: ReservoirValue>(
+        &self,
+        initial: f64,
+        dt: f64,
+        steps: usize,
+        seed: u64,
+    ) -> DMatrix<T> {
+        assert!(self.theta > 0. && self.sigma >= 0. && dt > 0.);
+        let decay = (-self.theta * dt).exp();
+        let noise = self.stationary_standard_deviation() * (1. - decay * decay).sqrt();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut x = initial;
+        DMatrix::from_fn(1, steps, |_, step| {
+            if step > 0 {
+                x = self.mu + (x - self.mu) * decay + noise * standard_normal(&mut rng);
+            }
+            T::from_f64(x).unwrap()
+        })
+    }
+}
+
+// x_t = constant + sum_i ar[i] x_{t-1-i} + e_t + sum_j ma[j] e_{t-1-j}, e_t ~ N(0, noise^2).
+#[derive(Clone, Debug)]
+pub struct Arma {
+    pub constant: f64,
+    pub ar: Vec<f64>,
+    pub ma: Vec<f64>,
+    pub noise_standard_deviation: f64,
+}
+
+impl Arma {
+    // Mean of the stationary process.
+    pub fn mean(&self) -> f64 {
+        self.constant / (1. - self.ar.iter().sum::<f64>())
+    }
+
+    // The process starts at its mean with zero past noise, the first `burn_in` values are
+    // dropped.
+    pub fn simulate<T: ReservoirValue>(
+        &self,
+        burn_in: usize,
+        steps: usize,
+        seed: u64,
+    ) -> DMatrix<T> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let total = burn_in + steps;
+        let mean = self.mean();
+        let mut values = vec![mean; self.ar.len()];
+        let mut noise = vec![0.; self.ma.len()];
+        values.reserve(total);
+        noise.reserve(total);
+        for _ in 0..total {
+            let innovation = self.noise_standard_deviation * standard_normal(&mut rng);
+            let autoregressive: f64 = self
+                .ar
+                .iter()
+                .zip(values.iter().rev())
+                .map(|(coefficient, value)| coefficient * value)
+                .sum();
+            let moving_average: f64 = self
+                .ma
+                .iter()
+                .zip(noise.iter().rev())
+                .map(|(coefficient, value)| coefficient * value)
+                .sum();
+            values.push(self.constant + autoregressive + innovation + moving_average);
+            noise.push(innovation);
+        }
+        DMatrix::from_iterator(
+            1,
+            steps,
+            values[values.len() - steps..]
+                .iter()
+                .map(|value| T::from_f64(*value).unwrap()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arma, OrnsteinUhlenbeck};
+
+    #[test]
+    fn stationary_statistics() {
+        let process = OrnsteinUhlenbeck {
+            theta: 2.,
+            mu: 1.,
+            sigma: 0.5,
+        };
+        let samples = process.simulate::<f64>(1., 0.5, 20000, 1);
+        assert!((samples.mean() - 1.).abs() < 0.02);
+        assert!((samples.variance().sqrt() - process.stationary_standard_deviation()).abs() < 0.01);
+
+        // AR(1) with MA(1): variance (1 + 2 phi theta + theta^2) / (1 - phi^2) sigma^2
+        let arma = Arma {
+            constant: 0.5,
+            ar: vec![0.5],
+            ma: vec![0.4],
+            noise_standard_deviation: 1.,
+        };
+        let samples = arma.simulate::<f64>(100, 40000, 2);
+        assert!((samples.mean() - 1.).abs() < 0.05);
+        let expected_variance = (1. + 2. * 0.5 * 0.4 + 0.16) / (1. - 0.25);
+        assert!((samples.variance() - expected_variance).abs() < 0.1);
+        assert_eq!(samples, arma.simulate::<f64>(100, 40000, 2));
+    }
+}
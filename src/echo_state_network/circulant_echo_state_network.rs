@@ -0,0 +1,312 @@
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+
+use nalgebra::{Complex, ComplexField, DMatrix, DVector, DVectorSlice, Schur};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    thread_rng, Rng, SeedableRng,
+};
+
+use crate::{
+    activation_function::ActiviationFunction,
+    fft::fft,
+    hyperparameter::SpectralRadius,
+    profile::{ProfileComponent, ProfileTimer},
+    time_evolution::ReservoirTimeEvolution,
+    ReservoirValue,
+};
+
+// Echo state network whose coupling is a blocks x blocks grid of circulant blocks. Every block
+// is stored as the spectrum of its first column, so the coupling costs one transform per block
+// column and row plus blocks² pointwise products, O(n log n) for power of two block sizes
+// instead of the O(n²) of a dense matrix with the same number of links. Other block sizes
+// fall back to the direct transform.
+#[derive(Clone)]
+pub struct CirculantEchoStateNetwork<T: ReservoirValue, A: ActiviationFunction<T>> {
+    // Row major over the blocks, spectra[row * blocks + column].
+    spectra: Arc<Vec<Vec<Complex<f64>>>>,
+    blocks: usize,
+    block_size: usize,
+    activation_function: A,
+    _phantom: PhantomData<T>,
+}
+
+#[derive(Clone, Debug)]
+pub struct CirculantEchoStateNetworkBuilder<T: ReservoirValue> {
+    spectra: Vec<Vec<Complex<f64>>>,
+    blocks: usize,
+    block_size: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: ReservoirValue> CirculantEchoStateNetworkBuilder<T> {
+    pub fn random(size: usize, blocks: usize, average_degree: usize) -> Self {
+        Self::random_seeded(size, blocks, average_degree, thread_rng().gen())
+    }
+
+    // Every entry of the first block columns is a link with probability
+    // average_degree / (size - 1), weights are uniform in [-1, 1]. Nodes never couple to
+    // themselves, like in `EchoStateNetworkBuilder::random`.
+    pub fn random_seeded(size: usize, blocks: usize, average_degree: usize, seed: u64) -> Self {
+        assert!(
+            blocks > 0 && size.is_multiple_of(blocks),
+            "The size has to be a multiple of the number of blocks."
+        );
+        let block_size = size / blocks;
+        let link_probability = average_degree as f64 / (size - 1) as f64;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let zero_one = Uniform::new_inclusive(0.0, 1.0);
+        let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+
+        let first_columns = (0..blocks * blocks)
+            .map(|block| {
+                let diagonal_block = block / blocks == block % blocks;
+                DVector::from_fn(block_size, |offset, _| {
+                    if (diagonal_block && offset == 0)
+                        || zero_one.sample(&mut rng) > link_probability
+                    {
+                        T::zero()
+                    } else {
+                        T::from_f64(plus_minus_one.sample(&mut rng)).unwrap()
+                    }
+                })
+            })
+            .collect();
+        Self::from_first_columns(blocks, first_columns)
+    }
+
+    // First columns of the circulant blocks, row major over the blocks.
+    pub fn from_first_columns(blocks: usize, first_columns: Vec<DVector<T>>) -> Self {
+        assert_eq!(first_columns.len(), blocks * blocks);
+        let block_size = first_columns[0].nrows();
+        let spectra = first_columns
+            .iter()
+            .map(|column| {
+                assert_eq!(column.nrows(), block_size);
+                let mut spectrum: Vec<_> = column
+                    .iter()
+                    .map(|value| Complex::new(value.to_f64().unwrap(), 0.))
+                    .collect();
+                fft(&mut spectrum, false);
+                spectrum
+            })
+            .collect();
+        Self {
+            spectra,
+            blocks,
+            block_size,
+            _phantom: PhantomData,
+        }
+    }
+
+    // Exact, the eigenvalues are the ones of the blocks x blocks matrices formed by the block
+    // spectra at every frequency.
+    pub fn current_spectral_radius(&self) -> T {
+        T::from_f64(spectral_radius(&self.spectra, self.blocks, self.block_size)).unwrap()
+    }
+
+    pub fn spectral_radius(&mut self, radius: SpectralRadius<T>) -> &mut Self {
+        let radius = radius.get();
+        let scale =
+            radius.to_f64().unwrap() / spectral_radius(&self.spectra, self.blocks, self.block_size);
+        for value in self.spectra.iter_mut().flatten() {
+            *value *= scale;
+        }
+        self
+    }
+
+    pub fn build<A: ActiviationFunction<T>>(self, a: A) -> CirculantEchoStateNetwork<T, A> {
+        CirculantEchoStateNetwork {
+            spectra: Arc::new(self.spectra),
+            blocks: self.blocks,
+            block_size: self.block_size,
+            activation_function: a,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+fn spectral_radius(spectra: &[Vec<Complex<f64>>], blocks: usize, block_size: usize) -> f64 {
+    (0..block_size)
+        .map(|frequency| {
+            let block_matrix = DMatrix::from_fn(blocks, blocks, |row, column| {
+                spectra[row * blocks + column][frequency]
+            });
+            Schur::new(block_matrix)
+                .eigenvalues()
+                .unwrap()
+                .iter()
+                .map(|eigenvalue| eigenvalue.modulus())
+                .fold(0., f64::max)
+        })
+        .fold(0., f64::max)
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug for CirculantEchoStateNetwork<T, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CirculantEchoStateNetwork{{ blocks: {}, block_size: {} }}",
+            self.blocks, self.block_size
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> CirculantEchoStateNetwork<T, A> {
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn spectral_radius(&self) -> T {
+        T::from_f64(spectral_radius(&self.spectra, self.blocks, self.block_size)).unwrap()
+    }
+
+    // The coupling as a dense matrix, only meant for inspection of small networks.
+    pub fn coupling_matrix(&self) -> DMatrix<T> {
+        let size = self.blocks * self.block_size;
+        let mut matrix = DMatrix::zeros(size, size);
+        let mut unit = DVector::zeros(size);
+        for column in 0..size {
+            unit[column] = T::one();
+            matrix.column_mut(column).copy_from(&self.couple(&unit));
+            unit[column] = T::zero();
+        }
+        matrix
+    }
+
+    fn couple(&self, state: &DVector<T>) -> DVector<T> {
+        let block_size = self.block_size;
+        let transformed: Vec<Vec<Complex<f64>>> = (0..self.blocks)
+            .map(|block| {
+                let mut values: Vec<_> = state
+                    .rows(block * block_size, block_size)
+                    .iter()
+                    .map(|value| Complex::new(value.to_f64().unwrap(), 0.))
+                    .collect();
+                fft(&mut values, false);
+                values
+            })
+            .collect();
+
+        let mut coupled = DVector::zeros(state.nrows());
+        let mut accumulated = vec![Complex::new(0., 0.); block_size];
+        for row in 0..self.blocks {
+            accumulated.fill(Complex::new(0., 0.));
+            for (column, values) in transformed.iter().enumerate() {
+                let spectrum = &self.spectra[row * self.blocks + column];
+                for (sum, (weight, value)) in accumulated
+                    .iter_mut()
+                    .zip(spectrum.iter().zip(values.iter()))
+                {
+                    *sum += weight * value;
+                }
+            }
+            fft(&mut accumulated, true);
+            for (target, value) in coupled
+                .rows_mut(row * block_size, block_size)
+                .iter_mut()
+                .zip(accumulated.iter())
+            {
+                *target = T::from_f64(value.re).unwrap();
+            }
+        }
+        coupled
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for CirculantEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.blocks * self.block_size
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.blocks * self.block_size
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let timer = ProfileTimer::start();
+        let combined_state = self.couple(state) + input;
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        state.copy_from(&combined_state);
+        self.activation_function
+            .invoke_slice(0, state.as_mut_slice());
+        timer.stop(ProfileComponent::Activation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{ComplexField, DMatrix, DVector};
+
+    use super::CirculantEchoStateNetworkBuilder;
+    use crate::{
+        activation_function::ActivationFunctionWrapper, hyperparameter::SpectralRadius,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn block_circulant_coupling_and_spectral_radius() {
+        for (blocks, block_size) in [(1, 16), (2, 8), (3, 6)] {
+            let first_columns: Vec<_> = (0..blocks * blocks)
+                .map(|block| {
+                    DVector::from_fn(block_size, |offset, _| {
+                        ((block * 7 + offset * 3) as f64).sin()
+                    })
+                })
+                .collect();
+            let mut dense = DMatrix::zeros(blocks * block_size, blocks * block_size);
+            for row in 0..blocks {
+                for column in 0..blocks {
+                    let first_column = &first_columns[row * blocks + column];
+                    for i in 0..block_size {
+                        for j in 0..block_size {
+                            dense[(row * block_size + i, column * block_size + j)] =
+                                first_column[(i + block_size - j) % block_size];
+                        }
+                    }
+                }
+            }
+
+            let mut builder =
+                CirculantEchoStateNetworkBuilder::from_first_columns(blocks, first_columns);
+            let dense_radius = dense
+                .complex_eigenvalues()
+                .iter()
+                .map(|eigenvalue| eigenvalue.modulus())
+                .fold(0., f64::max);
+            assert!((builder.current_spectral_radius() - dense_radius).abs() < 1e-9);
+
+            builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+            let network = builder.build(ActivationFunctionWrapper::new(|_, v: f64| v));
+            let dense = dense * (0.9 / dense_radius);
+            assert!((network.spectral_radius() - 0.9).abs() < 1e-9);
+            assert!((network.coupling_matrix() - &dense).amax() < 1e-12);
+
+            let size = blocks * block_size;
+            let input = DVector::from_fn(size, |i, _| (i as f64 * 0.4).cos());
+            let mut state = DVector::from_fn(size, |i, _| (i as f64 * 0.9).sin());
+            let expected = &dense * &state + &input;
+            network.time_evolution(&mut state, input.column(0));
+            assert!((state - expected).amax() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn random_network_has_no_self_loops() {
+        let mut builder = CirculantEchoStateNetworkBuilder::<f64>::random_seeded(64, 4, 6, 3);
+        builder.spectral_radius(SpectralRadius::new(0.8).unwrap());
+        let network = builder.build(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+        let coupling = network.coupling_matrix();
+        assert!(coupling.diagonal().amax() < 1e-12);
+        assert!((network.spectral_radius() - 0.8).abs() < 1e-9);
+        assert_eq!((network.blocks(), network.block_size()), (4, 16));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fmt::Debug, sync::Arc};
+
+use nalgebra::{DVector, DVectorSlice};
+use nalgebra_sparse::CsrMatrix;
+
+use super::{checked_state_bounds, clamp_state, csr_multiply_add};
+use crate::{
+    activation_function::ActiviationFunction,
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    profile::{ProfileComponent, ProfileTimer},
+    time_evolution::{AllocationFreeTimeEvolution, ReservoirTimeEvolution},
+    ReservoirValue,
+};
+
+// Distribution the per node time constants are drawn from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeConstantDistribution<T: ReservoirValue> {
+    Constant(T),
+    Uniform { min: T, max: T },
+    // Uniform in log space, spreads the nodes evenly over several orders of magnitude.
+    LogUniform { min: T, max: T },
+}
+
+// Continuous time reservoir tau_i dx_i/dt = -x_i + f(A x + u)_i, integrated with one explicit
+// Euler step of `time_step` per call. Every node has its own time constant, so the effective
+// step dt / tau_i differs between the nodes.
+#[derive(Clone)]
+pub struct SparseContinuousEchoStateNetwork<T: ReservoirValue, A: ActiviationFunction<T>> {
+    pub(super) adjacency_matrix: Arc<CsrMatrix<T>>,
+    pub(super) activation_function: A,
+    pub(super) state_bounds: Option<(T, T)>,
+    pub(super) time_step: T,
+    pub(super) time_constants: DVector<T>,
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug
+    for SparseContinuousEchoStateNetwork<T, A>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ContinuousEchoStateNetwork{{ {}, {:?} }}",
+            self.time_step, self.adjacency_matrix
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> SparseContinuousEchoStateNetwork<T, A> {
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<T> {
+        &self.adjacency_matrix
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    pub fn state_bounds(&self) -> Option<(T, T)> {
+        self.state_bounds
+    }
+
+    pub fn time_step(&self) -> T {
+        self.time_step
+    }
+
+    pub fn time_constants(&self) -> &DVector<T> {
+        &self.time_constants
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for SparseContinuousEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let mut combined_state = DVector::zeros(state.nrows());
+        self.time_evolution_with_buffer(state, input, &mut combined_state);
+    }
+
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        combined_state: &mut DVector<T>,
+    ) {
+        let timer = ProfileTimer::start();
+        combined_state.copy_from(&input);
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            state.as_slice(),
+            combined_state.as_mut_slice(),
+            |weight| weight,
+        );
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        self.activation_function
+            .invoke_slice(0, combined_state.as_mut_slice());
+        for ((s, target), tau) in state
+            .iter_mut()
+            .zip(combined_state.iter())
+            .zip(self.time_constants.iter())
+        {
+            *s += self.time_step / *tau * (*target - *s);
+        }
+        clamp_state(state, self.state_bounds);
+        timer.stop(ProfileComponent::Activation);
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> AllocationFreeTimeEvolution<T>
+    for SparseContinuousEchoStateNetwork<T, A>
+{
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ControlledReservoirTimeEvolution<T>
+    for SparseContinuousEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn control_input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn controlled_time_evolution(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        control: DVectorSlice<T>,
+    ) {
+        let combined_input = input + control;
+        self.time_evolution(state, combined_input.column(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::TimeConstantDistribution;
+    use crate::{
+        activation_function::ActivationFunctionWrapper,
+        echo_state_network::EchoStateNetworkBuilder, time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn continuous_network_time_constants() {
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        let builder = EchoStateNetworkBuilder::<f64>::random_seeded(40, 4, 19);
+        let discrete = builder.clone().build_sparse_discrete_network(tanh());
+        // tau = dt relaxes to the target in a single step, i.e. the discrete network.
+        let continuous = builder.clone().build_continuous_network(
+            tanh(),
+            0.1,
+            TimeConstantDistribution::Constant(0.1),
+        );
+
+        let input = DVector::from_fn(40, |i, _| (i as f64 * 0.3).cos());
+        let mut discrete_state = DVector::from_element(40, 0.2);
+        let mut continuous_state = discrete_state.clone();
+        for _ in 0..5 {
+            discrete.time_evolution(&mut discrete_state, input.column(0));
+            continuous.time_evolution(&mut continuous_state, input.column(0));
+        }
+        assert!((discrete_state - continuous_state).amax() < 1e-14);
+
+        let continuous = builder.build_continuous_network(
+            tanh(),
+            0.01,
+            TimeConstantDistribution::LogUniform { min: 0.1, max: 10. },
+        );
+        let time_constants = continuous.time_constants();
+        assert!(time_constants.iter().all(|tau| (0.1..=10.).contains(tau)));
+        assert!(time_constants.min() < 1. && time_constants.max() > 1.);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{ops::Range, sync::Arc};
+
+use nalgebra::{DVector, DVectorSlice};
+use nalgebra_sparse::CsrMatrix;
+
+use super::csr_multiply_add;
+use crate::{time_evolution::ReservoirTimeEvolution, ReservoirValue};
+
+pub type SharedTimeEvolution<T> = Arc<dyn ReservoirTimeEvolution<T> + Send + Sync>;
+
+// Modular reservoir of child evolutions, possibly of different kinds, whose states are stacked
+// into blocks. A sparse coupling from block `from` to block `to` adds coupling * state[from] to
+// the input of `to`; all children step at once from the previous states, so any graph of
+// modules, cycles included, is evaluated in one step. The projected input drives the input
+// blocks, by default all of them.
+#[derive(Clone, Debug)]
+pub struct CoupledNetwork<T: ReservoirValue> {
+    children: Vec<SharedTimeEvolution<T>>,
+    offsets: Vec<usize>,
+    // (to, from, coupling)
+    couplings: Vec<(usize, usize, Arc<CsrMatrix<T>>)>,
+    input_blocks: Vec<bool>,
+}
+
+impl<T: ReservoirValue> CoupledNetwork<T> {
+    pub fn new(children: Vec<SharedTimeEvolution<T>>) -> Self {
+        assert!(!children.is_empty());
+        let mut offsets = vec![0];
+        for child in &children {
+            assert_eq!(child.input_dimension(), child.output_dimension());
+            offsets.push(offsets.last().unwrap() + child.output_dimension());
+        }
+        Self {
+            input_blocks: vec![true; children.len()],
+            children,
+            offsets,
+            couplings: vec![],
+        }
+    }
+
+    // Couplings between the same pair of blocks add up.
+    pub fn with_coupling(mut self, to: usize, from: usize, coupling: CsrMatrix<T>) -> Self {
+        assert!(to < self.children.len() && from < self.children.len());
+        assert_eq!(
+            (coupling.nrows(), coupling.ncols()),
+            (self.block_size(to), self.block_size(from))
+        );
+        self.couplings.push((to, from, Arc::new(coupling)));
+        self
+    }
+
+    // Only `blocks` receive the projected input, the rows of the others are ignored.
+    pub fn with_input_blocks(mut self, blocks: &[usize]) -> Self {
+        self.input_blocks = vec![false; self.children.len()];
+        for block in blocks {
+            self.input_blocks[*block] = true;
+        }
+        self
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn block_size(&self, block: usize) -> usize {
+        self.offsets[block + 1] - self.offsets[block]
+    }
+
+    // Rows of `block` in the state.
+    pub fn block_range(&self, block: usize) -> Range<usize> {
+        self.offsets[block]..self.offsets[block + 1]
+    }
+
+    pub fn child(&self, block: usize) -> &SharedTimeEvolution<T> {
+        &self.children[block]
+    }
+}
+
+impl<T: ReservoirValue> ReservoirTimeEvolution<T> for CoupledNetwork<T> {
+    fn input_dimension(&self) -> usize {
+        self.output_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let mut block_inputs = DVector::zeros(state.nrows());
+        for (block, driven) in self.input_blocks.iter().enumerate() {
+            if *driven {
+                let range = self.block_range(block);
+                block_inputs
+                    .rows_mut(range.start, range.len())
+                    .copy_from(&input.rows(range.start, range.len()));
+            }
+        }
+        for (to, from, coupling) in &self.couplings {
+            csr_multiply_add(
+                coupling,
+                &state.as_slice()[self.block_range(*from)],
+                &mut block_inputs.as_mut_slice()[self.block_range(*to)],
+                |weight| weight,
+            );
+        }
+        for (block, child) in self.children.iter().enumerate() {
+            let range = self.block_range(block);
+            let mut block_state = state.rows(range.start, range.len()).clone_owned();
+            child.time_evolution(
+                &mut block_state,
+                block_inputs.rows(range.start, range.len()),
+            );
+            state
+                .rows_mut(range.start, range.len())
+                .copy_from(&block_state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nalgebra::{DMatrix, DVector};
+    use nalgebra_sparse::CsrMatrix;
+
+    use super::CoupledNetwork;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn coupled_networks_match_the_block_network() {
+        let first = EchoStateNetworkBuilder::<f64>::random_seeded(20, 4, 1);
+        let second = EchoStateNetworkBuilder::<f64>::random_seeded(10, 4, 2);
+        let forward = DMatrix::from_fn(10, 20, |i, j| if (i + j) % 7 == 0 { 0.3 } else { 0. });
+        let backward = DMatrix::from_fn(20, 10, |i, j| if i == 2 * j { -0.2 } else { 0. });
+
+        let mut block_adjacency = DMatrix::zeros(30, 30);
+        block_adjacency
+            .slice_mut((0, 0), (20, 20))
+            .copy_from(&DMatrix::from(&first.adjacency_matrix));
+        block_adjacency
+            .slice_mut((20, 20), (10, 10))
+            .copy_from(&DMatrix::from(&second.adjacency_matrix));
+        block_adjacency
+            .slice_mut((20, 0), (10, 20))
+            .copy_from(&forward);
+        block_adjacency
+            .slice_mut((0, 20), (20, 10))
+            .copy_from(&backward);
+        let mut block_network = EchoStateNetworkBuilder::<f64>::random_seeded(30, 4, 3);
+        block_network.adjacency_matrix = CsrMatrix::from(&block_adjacency);
+        let block_network = block_network.build_sparse_discrete_network(Tanh);
+
+        let network = CoupledNetwork::new(vec![
+            Arc::new(first.build_sparse_discrete_network(Tanh)),
+            Arc::new(second.build_sparse_discrete_network(Tanh)),
+        ])
+        .with_coupling(1, 0, CsrMatrix::from(&forward))
+        .with_coupling(0, 1, CsrMatrix::from(&backward));
+        assert_eq!(network.output_dimension(), 30);
+        assert_eq!(network.block_range(1), 20..30);
+
+        let mut state = DVector::zeros(30);
+        let mut expected_state = DVector::zeros(30);
+        for step in 0..20 {
+            let input = DVector::from_fn(30, |i, _| ((i + step) as f64 * 0.3).sin());
+            network.time_evolution(&mut state, input.column(0));
+            block_network.time_evolution(&mut expected_state, input.column(0));
+        }
+        assert!((&state - &expected_state).amax() < 1e-12);
+
+        // Without input the second block only follows the first.
+        let network = network.with_input_blocks(&[0]);
+        let mut state = DVector::zeros(30);
+        let input = DVector::from_fn(30, |i, _| if i < 20 { 0. } else { 1. });
+        network.time_evolution(&mut state, input.column(0));
+        assert_eq!(state.amax(), 0.);
+    }
+}
@@ -0,0 +1,130 @@
+use nalgebra::{DVector, DVectorSlice};
+
+use crate::{time_evolution::ReservoirTimeEvolution, ReservoirValue};
+
+// Emulates a fixed point implementation of the wrapped network: the projected input and the
+// state after every step are rounded to multiples of 2^-fractional_bits and saturated at the
+// largest signed value with `integer_bits` integer bits. Together with ternary or binary
+// weights this models the quantization of FPGA or memristor reservoirs in software.
+#[derive(Clone, Debug)]
+pub struct FixedPointTimeEvolution<T: ReservoirValue, E: ReservoirTimeEvolution<T>> {
+    network: E,
+    integer_bits: u32,
+    fractional_bits: u32,
+    resolution: T,
+    max_value: T,
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> FixedPointTimeEvolution<T, E> {
+    pub fn new(network: E, integer_bits: u32, fractional_bits: u32) -> Self {
+        assert!(
+            integer_bits + fractional_bits < 64,
+            "At most 63 value bits are supported."
+        );
+        let resolution = T::from_f64(0.5f64.powi(fractional_bits as i32)).unwrap();
+        let max_value = T::from_f64(2f64.powi(integer_bits as i32)).unwrap() - resolution;
+        Self {
+            network,
+            integer_bits,
+            fractional_bits,
+            resolution,
+            max_value,
+        }
+    }
+
+    pub fn network(&self) -> &E {
+        &self.network
+    }
+
+    pub fn integer_bits(&self) -> u32 {
+        self.integer_bits
+    }
+
+    pub fn fractional_bits(&self) -> u32 {
+        self.fractional_bits
+    }
+
+    // Smallest representable step.
+    pub fn resolution(&self) -> T {
+        self.resolution
+    }
+
+    pub fn quantize(&self, value: T) -> T {
+        let rounded = num_traits::Float::round(value / self.resolution) * self.resolution;
+        num_traits::Float::min(
+            num_traits::Float::max(rounded, -self.max_value),
+            self.max_value,
+        )
+    }
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> ReservoirTimeEvolution<T>
+    for FixedPointTimeEvolution<T, E>
+{
+    fn input_dimension(&self) -> usize {
+        self.network.input_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.network.output_dimension()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let quantized_input = input.map(|value| self.quantize(value));
+        self.network
+            .time_evolution(state, quantized_input.column(0));
+        state.apply(|value| *value = self.quantize(*value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::FixedPointTimeEvolution;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        input_projection::DefaultInputProjection, time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn ternary_fixed_point_reservoir() {
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(50, 5, 3);
+        builder.ternarize(0.125);
+        assert!(builder
+            .adjacency_matrix
+            .values()
+            .iter()
+            .all(|value| value.abs() == 0.125));
+        let projection =
+            DefaultInputProjection::<f64>::new_random_ternary_seeded(2, 50, 0.5, 0.3, 4);
+        let nonzero = projection
+            .w_in()
+            .iter()
+            .filter(|value| **value != 0.)
+            .count();
+        assert!(projection
+            .w_in()
+            .iter()
+            .all(|value| [0., 0.5, -0.5].contains(value)));
+        assert!((15..=45).contains(&nonzero), "{nonzero}");
+
+        let network = builder.build_sparse_discrete_network(Tanh);
+        let coarse = FixedPointTimeEvolution::new(network.clone(), 1, 6);
+        let fine = FixedPointTimeEvolution::new(network.clone(), 1, 30);
+        assert_eq!(coarse.quantize(5.), 2. - 1. / 64.);
+        assert_eq!(coarse.quantize(0.3), 19. / 64.);
+
+        let (mut coarse_state, mut fine_state, mut float_state) =
+            (DVector::zeros(50), DVector::zeros(50), DVector::zeros(50));
+        for step in 0..20 {
+            let input = projection.w_in() * DVector::from_vec(vec![(step as f64).sin(), 0.3]);
+            coarse.time_evolution(&mut coarse_state, input.column(0));
+            fine.time_evolution(&mut fine_state, input.column(0));
+            network.time_evolution(&mut float_state, input.column(0));
+        }
+        assert!(coarse_state.iter().all(|value| (value * 64.).fract() == 0.));
+        assert!((&fine_state - &float_state).amax() < 1e-7);
+        assert!((&coarse_state - &float_state).amax() > 1e-4);
+    }
+}
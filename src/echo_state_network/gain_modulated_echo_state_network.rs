@@ -0,0 +1,171 @@
+use std::{fmt::Debug, sync::Arc};
+
+use nalgebra::{DMatrix, DVector, DVectorSlice};
+use nalgebra_sparse::CsrMatrix;
+use rand::distributions::uniform::SampleUniform;
+
+use super::{checked_state_bounds, clamp_state, csr_multiply_add};
+use crate::{
+    activation_function::ActiviationFunction, input_projection::DefaultInputProjection,
+    time_evolution::ReservoirTimeEvolution, ReservoirValue,
+};
+
+// Echo state network whose recurrent gains are modulated by the input,
+// r <- f((A r) ⊙ g(W_g u) + W_in u), e.g. for systems whose parameters are part of the input.
+// The state is [r; g(W_g u)] and the projected input [W_in u; W_g u], see
+// `stacked_input_projection`; the gains are kept in the state so that the readout sees the
+// context as well.
+#[derive(Clone)]
+pub struct SparseGainModulatedEchoStateNetwork<T, A, G>
+where
+    T: ReservoirValue,
+    A: ActiviationFunction<T>,
+    G: ActiviationFunction<T>,
+{
+    pub(super) adjacency_matrix: Arc<CsrMatrix<T>>,
+    pub(super) activation_function: A,
+    pub(super) gain_function: G,
+    pub(super) state_bounds: Option<(T, T)>,
+}
+
+impl<T, A, G> Debug for SparseGainModulatedEchoStateNetwork<T, A, G>
+where
+    T: ReservoirValue,
+    A: ActiviationFunction<T>,
+    G: ActiviationFunction<T>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GainModulatedEchoStateNetwork{{ {:?} }}",
+            self.adjacency_matrix
+        )
+    }
+}
+
+impl<T, A, G> SparseGainModulatedEchoStateNetwork<T, A, G>
+where
+    T: ReservoirValue,
+    A: ActiviationFunction<T>,
+    G: ActiviationFunction<T>,
+{
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<T> {
+        &self.adjacency_matrix
+    }
+
+    pub fn network_size(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    // Input projection onto [W_in u; W_g u]. Zero columns of `w_gain` keep channels from
+    // modulating, zero columns of `w_in` keep them from driving.
+    pub fn stacked_input_projection(
+        w_in: DMatrix<T>,
+        w_gain: DMatrix<T>,
+    ) -> DefaultInputProjection<T>
+    where
+        T: SampleUniform,
+    {
+        assert_eq!(w_in.shape(), w_gain.shape());
+        let size = w_in.nrows();
+        let mut stacked = DMatrix::zeros(2 * size, w_in.ncols());
+        stacked.rows_mut(0, size).copy_from(&w_in);
+        stacked.rows_mut(size, size).copy_from(&w_gain);
+        DefaultInputProjection::new_with_matrix(stacked)
+    }
+}
+
+impl<T, A, G> ReservoirTimeEvolution<T> for SparseGainModulatedEchoStateNetwork<T, A, G>
+where
+    T: ReservoirValue,
+    A: ActiviationFunction<T>,
+    G: ActiviationFunction<T>,
+{
+    fn input_dimension(&self) -> usize {
+        2 * self.network_size()
+    }
+
+    fn output_dimension(&self) -> usize {
+        2 * self.network_size()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let size = self.network_size();
+        let mut combined_state = DVector::zeros(size);
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            &state.as_slice()[..size],
+            combined_state.as_mut_slice(),
+            |weight| weight,
+        );
+
+        let mut gains = input.rows(size, size).clone_owned();
+        self.gain_function.invoke_slice(0, gains.as_mut_slice());
+        combined_state.component_mul_assign(&gains);
+        combined_state += input.rows(0, size);
+        self.activation_function
+            .invoke_slice(0, combined_state.as_mut_slice());
+        clamp_state(&mut combined_state, self.state_bounds);
+
+        state.rows_mut(0, size).copy_from(&combined_state);
+        state.rows_mut(size, size).copy_from(&gains);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::SparseGainModulatedEchoStateNetwork;
+    use crate::{
+        activation_function::{ActivationFunctionWrapper, Tanh},
+        echo_state_network::EchoStateNetworkBuilder,
+        input_projection::ReservoirInputProjection,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn unit_gains_match_the_plain_network() {
+        let builder = EchoStateNetworkBuilder::<f64>::random_seeded(20, 4, 1);
+        let plain = builder.clone().build_sparse_discrete_network(Tanh);
+        // 2 sigmoid(x), one at zero.
+        let gain = ActivationFunctionWrapper::new(|_, x: f64| 2. / (1. + (-x).exp()));
+        let modulated = builder.build_gain_modulated_network(Tanh, gain);
+        assert_eq!(modulated.output_dimension(), 40);
+
+        // The first channel drives, the second only modulates.
+        let w_in = DMatrix::from_fn(20, 2, |i, j| if j == 0 { (i as f64).sin() } else { 0. });
+        let w_gain = DMatrix::from_fn(20, 2, |i, j| if j == 1 { (i as f64).cos() } else { 0. });
+        let mut projection =
+            SparseGainModulatedEchoStateNetwork::<f64, Tanh, Tanh>::stacked_input_projection(
+                w_in.clone(),
+                w_gain,
+            );
+
+        let mut run = |context: f64| {
+            let mut state = DVector::zeros(40);
+            for step in 0..30 {
+                let input = DMatrix::from_column_slice(2, 1, &[(step as f64 * 0.4).sin(), context]);
+                let projected = projection.project(input.columns(0, 1)).clone_owned();
+                modulated.time_evolution(&mut state, projected.column(0));
+            }
+            state
+        };
+        let without_context = run(0.);
+        let with_context = run(1.);
+
+        let mut expected = DVector::zeros(20);
+        for step in 0..30 {
+            let input = &w_in * DVector::from_vec(vec![(step as f64 * 0.4).sin(), 0.]);
+            plain.time_evolution(&mut expected, input.column(0));
+        }
+        assert!((without_context.rows(0, 20) - &expected).amax() < 1e-12);
+        assert!((without_context.rows(20, 20).add_scalar(-1.)).amax() < 1e-12);
+        assert!((with_context.rows(0, 20) - &expected).amax() > 1e-3);
+    }
+}
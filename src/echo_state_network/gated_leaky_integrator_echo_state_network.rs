@@ -0,0 +1,148 @@
+use std::{fmt::Debug, sync::Arc};
+
+use nalgebra::{DVector, DVectorSlice};
+use nalgebra_sparse::CsrMatrix;
+
+use super::{checked_state_bounds, clamp_state, csr_multiply_add};
+use crate::{
+    activation_function::ActiviationFunction, time_evolution::ReservoirTimeEvolution,
+    ReservoirValue,
+};
+
+// Leaky integrator whose leak rate per node depends on the pre-activation e = A r + input,
+// alpha_i = sigmoid(slope_i e_i + bias_i), r <- (1 - alpha) ⊙ r + alpha ⊙ f(e). Strongly
+// driven nodes update quickly while quiet ones hold their state, like the update gate of a
+// GRU; the gate parameters are fixed, so only the readout is trained. Slopes of zero give
+// the constant leak rate sigmoid(bias).
+#[derive(Clone)]
+pub struct SparseGatedLeakyIntegratorEchoStateNetwork<T, A>
+where
+    T: ReservoirValue,
+    A: ActiviationFunction<T>,
+{
+    pub(super) adjacency_matrix: Arc<CsrMatrix<T>>,
+    pub(super) activation_function: A,
+    pub(super) gate_slopes: Arc<DVector<T>>,
+    pub(super) gate_biases: Arc<DVector<T>>,
+    pub(super) state_bounds: Option<(T, T)>,
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug
+    for SparseGatedLeakyIntegratorEchoStateNetwork<T, A>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "GatedEchoStateNetwork{{ {:?}, {:?} }}",
+            self.adjacency_matrix, self.gate_slopes
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>>
+    SparseGatedLeakyIntegratorEchoStateNetwork<T, A>
+{
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<T> {
+        &self.adjacency_matrix
+    }
+
+    pub fn gate_slopes(&self) -> &DVector<T> {
+        &self.gate_slopes
+    }
+
+    pub fn gate_biases(&self) -> &DVector<T> {
+        &self.gate_biases
+    }
+
+    // Per node gate parameters, e.g. drawn at random to spread the time constants.
+    pub fn with_gate_parameters(mut self, slopes: DVector<T>, biases: DVector<T>) -> Self {
+        assert_eq!(slopes.nrows(), self.adjacency_matrix.nrows());
+        assert_eq!(biases.nrows(), self.adjacency_matrix.nrows());
+        self.gate_slopes = Arc::new(slopes);
+        self.gate_biases = Arc::new(biases);
+        self
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    // Leak rates for the pre-activation `combined_state`.
+    pub fn leak_rates(&self, combined_state: &DVector<T>) -> DVector<T> {
+        combined_state.zip_zip_map(
+            &self.gate_slopes,
+            &self.gate_biases,
+            |value, slope, bias| {
+                T::one() / (T::one() + num_traits::Float::exp(-(slope * value + bias)))
+            },
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for SparseGatedLeakyIntegratorEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let mut combined_state = DVector::zeros(state.nrows());
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            state.as_slice(),
+            combined_state.as_mut_slice(),
+            |weight| weight,
+        );
+        combined_state += input;
+        let leak_rates = self.leak_rates(&combined_state);
+        self.activation_function
+            .invoke_slice(0, combined_state.as_mut_slice());
+        // r + alpha (f(e) - r)
+        combined_state -= &*state;
+        combined_state.component_mul_assign(&leak_rates);
+        *state += combined_state;
+        clamp_state(state, self.state_bounds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn constant_gates_interpolate_the_plain_step() {
+        let builder = EchoStateNetworkBuilder::<f64>::random_seeded(30, 4, 1);
+        let bias: f64 = -1.;
+        let leak_rate = 1. / (1. + (-bias).exp());
+        let plain = builder.clone().build_sparse_discrete_network(Tanh);
+        let gated = builder.build_gated_leaky_integrator_network(Tanh, 0., bias);
+
+        let mut state = DVector::zeros(30);
+        let mut expected = DVector::zeros(30);
+        for step in 0..20 {
+            let input = DVector::from_fn(30, |i, _| ((i + step) as f64 * 0.3).sin());
+            gated.time_evolution(&mut state, input.column(0));
+            let mut plain_step = expected.clone();
+            plain.time_evolution(&mut plain_step, input.column(0));
+            expected = expected * (1. - leak_rate) + plain_step * leak_rate;
+        }
+        assert!((&state - &expected).amax() < 1e-12);
+
+        // With a positive slope strongly driven nodes leak faster.
+        let gated = gated.with_gate_parameters(DVector::from_element(30, 4.), DVector::zeros(30));
+        let rates = gated.leak_rates(&DVector::from_element(30, 1.));
+        let quiet_rates = gated.leak_rates(&DVector::zeros(30));
+        assert!(rates[0] > 0.95 && (quiet_rates[0] - 0.5).abs() < 1e-12);
+    }
+}
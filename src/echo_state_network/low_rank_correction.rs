@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DMatrixSlice, DVector, DVectorSlice};
+
+use crate::{
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    time_evolution::ReservoirTimeEvolution, ReservoirValue,
+};
+
+// Echo state network with the coupling A + U Vᵀ. The correction is added to the input of the
+// wrapped network, which for networks of the form f(A state + input) is the same as changing
+// the coupling, without densifying A. U Vᵀ state costs O(n rank), so a small correction adapts
+// a large reservoir at almost no cost per step. The factors are shared between clones.
+#[derive(Clone, Debug)]
+pub struct LowRankCorrection<T: ReservoirValue, E: ReservoirTimeEvolution<T>> {
+    network: E,
+    u: Arc<DMatrix<T>>,
+    v: Arc<DMatrix<T>>,
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> LowRankCorrection<T, E> {
+    pub fn new(network: E, u: DMatrix<T>, v: DMatrix<T>) -> Self {
+        assert_eq!(u.shape(), v.shape());
+        assert_eq!(u.nrows(), network.output_dimension());
+        assert_eq!(network.input_dimension(), network.output_dimension());
+        Self {
+            network,
+            u: Arc::new(u),
+            v: Arc::new(v),
+        }
+    }
+
+    // Starts without correction, the factors are set with `set_factors`, e.g. after training.
+    pub fn zero(network: E, rank: usize) -> Self {
+        let size = network.output_dimension();
+        Self::new(
+            network,
+            DMatrix::zeros(size, rank),
+            DMatrix::zeros(size, rank),
+        )
+    }
+
+    pub fn set_factors(&mut self, u: DMatrix<T>, v: DMatrix<T>) {
+        assert_eq!(u.shape(), self.u.shape());
+        assert_eq!(v.shape(), self.v.shape());
+        self.u = Arc::new(u);
+        self.v = Arc::new(v);
+    }
+
+    pub fn rank(&self) -> usize {
+        self.u.ncols()
+    }
+
+    // U Vᵀ as a dense matrix, only meant for inspection of small networks.
+    pub fn correction(&self) -> DMatrix<T> {
+        self.u.as_ref() * self.v.transpose()
+    }
+
+    pub fn into_network(self) -> E {
+        self.network
+    }
+
+    pub fn network(&self) -> &E {
+        &self.network
+    }
+
+    pub fn u(&self) -> &DMatrix<T> {
+        &self.u
+    }
+
+    pub fn v(&self) -> &DMatrix<T> {
+        &self.v
+    }
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> ReservoirTimeEvolution<T>
+    for LowRankCorrection<T, E>
+{
+    fn input_dimension(&self) -> usize {
+        self.network.input_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.network.output_dimension()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let corrected_input = input + self.u.as_ref() * (self.v.tr_mul(state));
+        self.network
+            .time_evolution(state, corrected_input.column(0));
+    }
+
+    fn time_evolution_many(&self, states: &mut DMatrix<T>, inputs: DMatrixSlice<T>) {
+        assert_eq!(states.ncols(), inputs.ncols());
+        let corrected_inputs = inputs + self.u.as_ref() * self.v.tr_mul(states);
+        self.network
+            .time_evolution_many(states, corrected_inputs.columns(0, inputs.ncols()));
+    }
+
+    // The input derivative of the network is the one of its pre activation, so the correction
+    // adds (d next state / d input) U Vᵀ to the state derivative.
+    fn jacobians(&self, state: &DVector<T>, input: DVectorSlice<T>) -> (DMatrix<T>, DMatrix<T>) {
+        let corrected_input = input + self.u.as_ref() * self.v.tr_mul(state);
+        let (state_jacobian, input_jacobian) =
+            self.network.jacobians(state, corrected_input.column(0));
+        (
+            state_jacobian + (&input_jacobian * self.u.as_ref()) * self.v.transpose(),
+            input_jacobian,
+        )
+    }
+}
+
+impl<T, E> ControlledReservoirTimeEvolution<T> for LowRankCorrection<T, E>
+where
+    T: ReservoirValue,
+    E: ReservoirTimeEvolution<T> + ControlledReservoirTimeEvolution<T>,
+{
+    fn input_dimension(&self) -> usize {
+        ControlledReservoirTimeEvolution::input_dimension(&self.network)
+    }
+
+    fn control_input_dimension(&self) -> usize {
+        self.network.control_input_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        ControlledReservoirTimeEvolution::output_dimension(&self.network)
+    }
+
+    fn controlled_time_evolution(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        control: DVectorSlice<T>,
+    ) {
+        let corrected_input = input + self.u.as_ref() * self.v.tr_mul(state);
+        self.network
+            .controlled_time_evolution(state, corrected_input.column(0), control);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+    use nalgebra_sparse::CsrMatrix;
+
+    use super::LowRankCorrection;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn matches_the_densified_coupling() {
+        let builder = EchoStateNetworkBuilder::<f64>::random_seeded(30, 4, 2);
+        let u = DMatrix::from_fn(30, 2, |i, j| ((i * 3 + j) as f64).sin() * 0.1);
+        let v = DMatrix::from_fn(30, 2, |i, j| ((i + 5 * j) as f64).cos() * 0.1);
+        let mut densified = builder.clone();
+        densified.adjacency_matrix =
+            CsrMatrix::from(&(DMatrix::from(&builder.adjacency_matrix) + &u * v.transpose()));
+        let densified = densified.build_sparse_discrete_network(Tanh);
+        let mut corrected = LowRankCorrection::zero(builder.build_sparse_discrete_network(Tanh), 2);
+        corrected.set_factors(u.clone(), v.clone());
+        assert_eq!(corrected.rank(), 2);
+        assert!((corrected.correction() - &u * v.transpose()).amax() < 1e-15);
+
+        let inputs = DMatrix::from_fn(30, 3, |i, j| ((i + j) as f64 * 0.3).sin());
+        let mut states = DMatrix::from_fn(30, 3, |i, j| ((i * j) as f64 * 0.2).cos() * 0.4);
+        let mut expected_states = states.clone();
+        corrected.time_evolution_many(&mut states, inputs.columns(0, 3));
+        densified.time_evolution_many(&mut expected_states, inputs.columns(0, 3));
+        assert!((&states - &expected_states).amax() < 1e-12);
+
+        let mut state: DVector<f64> = states.column(0).clone_owned();
+        let mut expected_state = state.clone();
+        corrected.time_evolution(&mut state, inputs.column(1));
+        densified.time_evolution(&mut expected_state, inputs.column(1));
+        assert!((&state - &expected_state).amax() < 1e-12);
+
+        let (state_jacobian, input_jacobian) = corrected.jacobians(&state, inputs.column(2));
+        let (expected_state_jacobian, expected_input_jacobian) =
+            densified.jacobians(&state, inputs.column(2));
+        assert!((state_jacobian - expected_state_jacobian).amax() < 1e-12);
+        assert!((input_jacobian - expected_input_jacobian).amax() < 1e-12);
+    }
+}
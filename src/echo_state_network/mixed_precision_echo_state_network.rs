@@ -0,0 +1,194 @@
+use std::{fmt::Debug, sync::Arc};
+
+use nalgebra::{DVector, DVectorSlice};
+use nalgebra_sparse::CsrMatrix;
+
+use super::{checked_state_bounds, clamp_values, csr_multiply_add_with};
+use crate::{
+    activation_function::ActiviationFunction,
+    profile::{ProfileComponent, ProfileTimer},
+    time_evolution::{AllocationFreeTimeEvolution, ReservoirTimeEvolution},
+    ReservoirValue,
+};
+
+// Keeps the adjacency matrix and the state in f32 to halve their memory, the matrix vector
+// product and the activation are computed in `T`. Callers that own a large state keep it as
+// `DVector<f32>` and step it with `time_evolution_f32`; through `ReservoirTimeEvolution` the
+// state is rounded to f32 on entry and on exit of every step.
+#[derive(Clone)]
+pub struct SparseMixedPrecisionEchoStateNetwork<T: ReservoirValue, A: ActiviationFunction<T>> {
+    pub(super) adjacency_matrix: Arc<CsrMatrix<f32>>,
+    pub(super) activation_function: A,
+    pub(super) state_bounds: Option<(T, T)>,
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug
+    for SparseMixedPrecisionEchoStateNetwork<T, A>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MixedPrecisionEchoStateNetwork{{ {:?} }}",
+            self.adjacency_matrix
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> SparseMixedPrecisionEchoStateNetwork<T, A> {
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<f32> {
+        &self.adjacency_matrix
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    pub fn state_bounds(&self) -> Option<(T, T)> {
+        self.state_bounds
+    }
+
+    // One step of a state stored in f32, `accumulator` of the state dimension is scratch space.
+    pub fn time_evolution_f32(
+        &self,
+        state: &mut DVector<f32>,
+        input: DVectorSlice<T>,
+        accumulator: &mut DVector<T>,
+    ) {
+        self.step_into(
+            state.as_slice(),
+            |value| T::from_f32(value).unwrap(),
+            input,
+            accumulator,
+        );
+        for (state, value) in state.iter_mut().zip(accumulator.iter()) {
+            *state = value.to_f32().unwrap();
+        }
+    }
+
+    // Next state in `T` from a state of f32 precision stored as `V`.
+    fn step_into<V: Copy + Send + Sync>(
+        &self,
+        state: &[V],
+        to_value: impl Fn(V) -> T + Send + Sync,
+        input: DVectorSlice<T>,
+        accumulator: &mut DVector<T>,
+    ) {
+        let timer = ProfileTimer::start();
+        accumulator.copy_from(&input);
+        csr_multiply_add_with(
+            &self.adjacency_matrix,
+            state,
+            accumulator.as_mut_slice(),
+            |weight, value| T::from_f32(weight).unwrap() * to_value(value),
+        );
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        self.activation_function
+            .invoke_slice(0, accumulator.as_mut_slice());
+        clamp_values(accumulator.as_mut_slice(), self.state_bounds);
+        timer.stop(ProfileComponent::Activation);
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for SparseMixedPrecisionEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let mut accumulator = DVector::zeros(state.nrows());
+        self.time_evolution_with_buffer(state, input, &mut accumulator);
+    }
+
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        buffer: &mut DVector<T>,
+    ) {
+        for value in state.iter_mut() {
+            *value = T::from_f32(value.to_f32().unwrap()).unwrap();
+        }
+        self.step_into(state.as_slice(), |value| value, input, buffer);
+        for (value, next) in state.iter_mut().zip(buffer.iter()) {
+            *value = T::from_f32(next.to_f32().unwrap()).unwrap();
+        }
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> AllocationFreeTimeEvolution<T>
+    for SparseMixedPrecisionEchoStateNetwork<T, A>
+{
+}
+
+pub fn csr_to_f32<T: ReservoirValue>(matrix: &CsrMatrix<T>) -> CsrMatrix<f32> {
+    CsrMatrix::try_from_pattern_and_values(
+        matrix.pattern().clone(),
+        matrix
+            .values()
+            .iter()
+            .map(|value| value.to_f32().unwrap())
+            .collect(),
+    )
+    .unwrap()
+}
+
+pub fn csr_from_f32<T: ReservoirValue>(matrix: &CsrMatrix<f32>) -> CsrMatrix<T> {
+    CsrMatrix::try_from_pattern_and_values(
+        matrix.pattern().clone(),
+        matrix
+            .values()
+            .iter()
+            .map(|value| T::from_f32(*value).unwrap())
+            .collect(),
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use crate::{
+        activation_function::ActivationFunctionWrapper,
+        echo_state_network::EchoStateNetworkBuilder, time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn mixed_precision_matches_full_precision() {
+        let builder = EchoStateNetworkBuilder::<f64>::random(50, 5);
+        let full = builder
+            .clone()
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+        let mixed = builder
+            .build_mixed_precision_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+        let input = DVector::from_fn(50, |i, _| (i as f64 * 0.1).sin());
+        let mut full_state = DVector::from_element(50, 0.1);
+        let mut mixed_state = full_state.clone();
+        for _ in 0..10 {
+            full.time_evolution(&mut full_state, input.column(0));
+            mixed.time_evolution(&mut mixed_state, input.column(0));
+        }
+        assert!((&full_state - &mixed_state).amax() < 1e-5);
+
+        // The state is stored in f32 between steps.
+        assert!(mixed_state
+            .iter()
+            .all(|value| *value == *value as f32 as f64));
+        let mut f32_state = DVector::from_element(50, 0.1f32);
+        let mut accumulator = DVector::zeros(50);
+        for _ in 0..10 {
+            mixed.time_evolution_f32(&mut f32_state, input.column(0), &mut accumulator);
+        }
+        assert_eq!(f32_state.map(|value| value as f64), mixed_state);
+    }
+}
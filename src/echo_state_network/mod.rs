@@ -1,38 +1,85 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use nalgebra::{DMatrix, DVector, RealField};
-use nalgebra_sparse::CsrMatrix;
+use nalgebra::{DMatrix, DVector};
+use nalgebra_sparse::{CooMatrix, CsrMatrix};
+use num_traits::Float;
 use rand::{
-    distributions::{uniform::SampleUniform, Distribution, Uniform},
-    thread_rng,
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    thread_rng, Rng, SeedableRng,
 };
 
-use crate::{activation_function::ActiviationFunction, ReservoirValue};
+use crate::{
+    activation_function::ActiviationFunction,
+    generation_recipe::{AdjacencyTransform, GenerationRecipe, NetworkGenerator},
+    hyperparameter::{LeakRate, SpectralRadius},
+    ReservoirError, ReservoirValue,
+};
 
+pub mod block_sparse_echo_state_network;
+pub mod circulant_echo_state_network;
+pub mod continuous_echo_state_network;
+pub mod coupled_network;
+pub mod fixed_point_time_evolution;
+pub mod gain_modulated_echo_state_network;
+pub mod gated_leaky_integrator_echo_state_network;
+pub mod low_rank_correction;
+pub mod mixed_precision_echo_state_network;
+pub mod packed_boolean_network;
 pub mod sparse_discrete_echo_state_network;
 pub mod sparse_leaky_integrator_echo_state_network;
+pub mod two_timescale_network;
 
+pub use block_sparse_echo_state_network::{BlockSparseEchoStateNetwork, BlockSparseMatrix};
+pub use circulant_echo_state_network::{
+    CirculantEchoStateNetwork, CirculantEchoStateNetworkBuilder,
+};
+pub use continuous_echo_state_network::{
+    SparseContinuousEchoStateNetwork, TimeConstantDistribution,
+};
+pub use coupled_network::{CoupledNetwork, SharedTimeEvolution};
+pub use fixed_point_time_evolution::FixedPointTimeEvolution;
+pub use gain_modulated_echo_state_network::SparseGainModulatedEchoStateNetwork;
+pub use gated_leaky_integrator_echo_state_network::SparseGatedLeakyIntegratorEchoStateNetwork;
+pub use low_rank_correction::LowRankCorrection;
+pub use mixed_precision_echo_state_network::{
+    csr_from_f32, csr_to_f32, SparseMixedPrecisionEchoStateNetwork,
+};
+pub use packed_boolean_network::{PackedBooleanNetwork, PackedBooleanState};
 pub use sparse_discrete_echo_state_network::SparseDiscreteEchoStateNetwork;
 pub use sparse_leaky_integrator_echo_state_network::SparseLeakyIntegratorEchoStateNetwork;
+pub use two_timescale_network::TwoTimescaleNetwork;
 
 #[derive(Clone, Debug)]
-pub struct EchoStateNetworkBuilder<T: ReservoirValue + From<f32> + RealField + SampleUniform> {
+pub struct EchoStateNetworkBuilder<T: ReservoirValue> {
     spectral_radius: Option<T>,
     adjacency_matrix: CsrMatrix<T>,
+    size: usize,
+    average_degree: usize,
+    seed: u64,
+    generator: NetworkGenerator,
+    transforms: Vec<AdjacencyTransform>,
+    // Continues after the generation so that the power iterations are reproducible as well.
+    rng: StdRng,
 }
 
-impl<T: ReservoirValue + From<f32> + RealField + SampleUniform> EchoStateNetworkBuilder<T> {
+impl<T: ReservoirValue> EchoStateNetworkBuilder<T> {
     pub fn random(size: usize, average_degree: usize) -> Self {
-        let link_probability: T = (average_degree as f32 / (size - 1) as f32).into();
-        let mut rng = thread_rng();
-        let zero_one = Uniform::new_inclusive(T::zero(), T::one());
-        let plus_minus_one = Uniform::new_inclusive(-T::one(), T::one());
+        Self::random_seeded(size, average_degree, thread_rng().gen())
+    }
+
+    pub fn random_seeded(size: usize, average_degree: usize, seed: u64) -> Self {
+        let link_probability = average_degree as f64 / (size - 1) as f64;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let zero_one = Uniform::new_inclusive(0.0, 1.0);
+        let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
 
         let mut adjacency_matrix = DMatrix::zeros(size, size);
         for i in 0..adjacency_matrix.nrows() {
             for j in 0..adjacency_matrix.ncols() {
                 if i != j && zero_one.sample(&mut rng) <= link_probability {
-                    adjacency_matrix[(i, j)] = plus_minus_one.sample(&mut rng);
+                    adjacency_matrix[(i, j)] =
+                        T::from_f64(plus_minus_one.sample(&mut rng)).unwrap();
                 }
             }
         }
@@ -41,28 +88,268 @@ impl<T: ReservoirValue + From<f32> + RealField + SampleUniform> EchoStateNetwork
         Self {
             adjacency_matrix,
             spectral_radius: None,
+            size,
+            average_degree,
+            seed,
+            generator: NetworkGenerator::Bernoulli,
+            transforms: vec![],
+            rng,
+        }
+    }
+
+    // Every row draws from its own stream keyed by (seed, row), so the rows can be generated
+    // in parallel (with the `parallel` feature) and the matrix only depends on the seed. The
+    // links are sampled by geometric skipping straight into CSR form, which keeps large
+    // networks cheap. Same link distribution as `random`, but a different matrix for a seed.
+    pub fn random_parallel(size: usize, average_degree: usize, seed: u64) -> Self {
+        let link_probability = average_degree as f64 / (size - 1) as f64;
+        let rows = generate_rows(size, |row| bernoulli_row(size, link_probability, seed, row));
+        Self::from_rows(rows, average_degree, seed, NetworkGenerator::RowStreams)
+    }
+
+    // Every node receives exactly `in_degree` links instead of a binomially distributed
+    // number, which removes much of the run to run variance of small networks. Uses the same
+    // per row streams as `random_parallel`.
+    pub fn random_fixed_in_degree(size: usize, in_degree: usize, seed: u64) -> Self {
+        assert!(
+            in_degree < size,
+            "At most size - 1 links per node are possible."
+        );
+        let rows = generate_rows(size, |row| fixed_in_degree_row(size, in_degree, seed, row));
+        Self::from_rows(rows, in_degree, seed, NetworkGenerator::FixedInDegree)
+    }
+
+    fn from_rows(
+        rows: Vec<GeneratedRow>,
+        average_degree: usize,
+        seed: u64,
+        generator: NetworkGenerator,
+    ) -> Self {
+        let size = rows.len();
+        let mut row_offsets = Vec::with_capacity(size + 1);
+        row_offsets.push(0);
+        let mut col_indices = Vec::new();
+        let mut values = Vec::new();
+        for (columns, row_values) in rows {
+            col_indices.extend(columns);
+            values.extend(row_values.into_iter().map(|v| T::from_f64(v).unwrap()));
+            row_offsets.push(col_indices.len());
+        }
+        let adjacency_matrix =
+            CsrMatrix::try_from_csr_data(size, size, row_offsets, col_indices, values).unwrap();
+
+        Self {
+            adjacency_matrix,
+            spectral_radius: None,
+            size,
+            average_degree,
+            seed,
+            generator,
+            transforms: vec![],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn from_recipe(recipe: &GenerationRecipe) -> Result<Self, ReservoirError> {
+        match recipe {
+            GenerationRecipe::EchoStateNetwork {
+                size,
+                average_degree,
+                seed,
+                generator,
+                transforms,
+            } => {
+                let mut builder = match generator {
+                    NetworkGenerator::Bernoulli => {
+                        Self::random_seeded(*size, *average_degree, *seed)
+                    }
+                    NetworkGenerator::RowStreams => {
+                        Self::random_parallel(*size, *average_degree, *seed)
+                    }
+                    NetworkGenerator::FixedInDegree => {
+                        Self::random_fixed_in_degree(*size, *average_degree, *seed)
+                    }
+                };
+                for transform in transforms {
+                    match transform {
+                        AdjacencyTransform::SpectralRadius(radius) => {
+                            builder.spectral_radius(SpectralRadius::try_from(*radius)?)
+                        }
+                        AdjacencyTransform::LargestSingularValue(value) => {
+                            builder.largest_singular_value(T::from_f64(*value).unwrap())
+                        }
+                        AdjacencyTransform::SelfLoops {
+                            probability,
+                            weight,
+                        } => builder.self_loops(*probability, T::from_f64(*weight).unwrap()),
+                        AdjacencyTransform::Diagonal(value) => {
+                            builder.diagonal(T::from_f64(*value).unwrap())
+                        }
+                        AdjacencyTransform::Symmetrize => builder.symmetrize(),
+                        AdjacencyTransform::Antisymmetrize => builder.antisymmetrize(),
+                        AdjacencyTransform::Ternarize(weight) => {
+                            builder.ternarize(T::from_f64(*weight).unwrap())
+                        }
+                        AdjacencyTransform::Binarize => builder.binarize(),
+                    };
+                }
+                Ok(builder)
+            }
+            _ => Err(ReservoirError::InvalidRecipe(format!(
+                "not an echo state network recipe: {recipe}"
+            ))),
         }
     }
 
-    pub fn spectral_radius(&mut self, radius: T) -> &mut Self {
-        let mut rng = thread_rng();
-        let plus_minus_one = Uniform::new_inclusive(-T::one(), T::one());
+    pub fn recipe(&self) -> GenerationRecipe {
+        GenerationRecipe::EchoStateNetwork {
+            size: self.size,
+            average_degree: self.average_degree,
+            seed: self.seed,
+            generator: self.generator,
+            transforms: self.transforms.clone(),
+        }
+    }
+
+    pub fn spectral_radius(&mut self, radius: SpectralRadius<T>) -> &mut Self {
+        let radius = radius.get();
+        let spectral_radius = self.estimate_spectral_radius();
+
+        self.adjacency_matrix *= radius / spectral_radius;
+        self.spectral_radius = Some(spectral_radius);
+        self.transforms
+            .push(AdjacencyTransform::SpectralRadius(radius.to_f64().unwrap()));
+        self
+    }
+
+    // Scales the matrix to the given largest singular value, i.e. to the operator norm. A
+    // value below one is sufficient for the echo state property with tanh like activations,
+    // the spectral radius only gives a necessary condition.
+    pub fn largest_singular_value(&mut self, value: T) -> &mut Self {
+        let largest_singular_value = self.estimate_largest_singular_value();
+
+        self.adjacency_matrix *= value / largest_singular_value;
+        self.transforms
+            .push(AdjacencyTransform::LargestSingularValue(
+                value.to_f64().unwrap(),
+            ));
+        self
+    }
+
+    fn estimate_largest_singular_value(&mut self) -> T {
+        let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+        let transpose = self.adjacency_matrix.transpose();
+
+        // Power iteration on A^T A, its largest eigenvalue is the squared singular value.
+        let mut random_vector = DVector::zeros(self.adjacency_matrix.ncols());
+        for i in 0..random_vector.nrows() {
+            random_vector[i] = T::from_f64(plus_minus_one.sample(&mut self.rng)).unwrap();
+        }
+
+        for _ in 0..100 {
+            random_vector.normalize_mut();
+            random_vector = &transpose * (&self.adjacency_matrix * random_vector);
+        }
+        Float::sqrt(random_vector.norm())
+    }
+
+    // Each node gets a self-coupling of `weight` with the given probability, `random` itself
+    // never links a node to itself.
+    pub fn self_loops(&mut self, probability: f64, weight: T) -> &mut Self {
+        assert!((0.0..=1.0).contains(&probability));
+        let nodes: Vec<_> = (0..self.adjacency_matrix.nrows())
+            .filter(|_| self.rng.gen_bool(probability))
+            .collect();
+        self.replace_diagonal(nodes.into_iter().map(|node| (node, weight)));
+        self.transforms.push(AdjacencyTransform::SelfLoops {
+            probability,
+            weight: weight.to_f64().unwrap(),
+        });
+        self
+    }
+
+    // Sets every diagonal entry, e.g. a uniform ring of self-couplings.
+    pub fn diagonal(&mut self, value: T) -> &mut Self {
+        self.replace_diagonal((0..self.adjacency_matrix.nrows()).map(|node| (node, value)));
+        self.transforms
+            .push(AdjacencyTransform::Diagonal(value.to_f64().unwrap()));
+        self
+    }
+
+    fn replace_diagonal(&mut self, entries: impl Iterator<Item = (usize, T)>) {
+        let size = self.adjacency_matrix.nrows();
+        let mut diagonal: Vec<Option<T>> = vec![None; size];
+        for (node, value) in entries {
+            diagonal[node] = Some(value);
+        }
+
+        let mut coo = CooMatrix::new(size, size);
+        for (row, column, value) in self.adjacency_matrix.triplet_iter() {
+            if row != column || diagonal[row].is_none() {
+                coo.push(row, column, *value);
+            }
+        }
+        for (node, value) in diagonal.into_iter().enumerate() {
+            if let Some(value) = value {
+                coo.push(node, node, value);
+            }
+        }
+        self.adjacency_matrix = CsrMatrix::from(&coo);
+    }
+
+    // A = (A + A^T) / 2, real spectrum.
+    pub fn symmetrize(&mut self) -> &mut Self {
+        self.adjacency_matrix = self.symmetric_part(T::one());
+        self.transforms.push(AdjacencyTransform::Symmetrize);
+        self
+    }
+
+    // A = (A - A^T) / 2, purely imaginary spectrum.
+    pub fn antisymmetrize(&mut self) -> &mut Self {
+        self.adjacency_matrix = self.symmetric_part(-T::one());
+        self.transforms.push(AdjacencyTransform::Antisymmetrize);
+        self
+    }
+
+    // Every link gets the weight +-`weight` by its sign, e.g. to emulate hardware with ternary
+    // couplings {-w, 0, w}. The density is the one of the generator, i.e. the average degree.
+    pub fn ternarize(&mut self, weight: T) -> &mut Self {
+        assert!(weight > T::zero());
+        for value in self.adjacency_matrix.values_mut() {
+            *value = if *value < T::zero() { -weight } else { weight };
+        }
+        self.transforms
+            .push(AdjacencyTransform::Ternarize(weight.to_f64().unwrap()));
+        self
+    }
+
+    // Every link gets the weight one, binary couplings {0, 1}.
+    pub fn binarize(&mut self) -> &mut Self {
+        self.adjacency_matrix.values_mut().fill(T::one());
+        self.transforms.push(AdjacencyTransform::Binarize);
+        self
+    }
+
+    fn symmetric_part(&self, sign: T) -> CsrMatrix<T> {
+        let half = T::from_f64(0.5).unwrap();
+        let transpose = self.adjacency_matrix.transpose() * sign;
+        (&self.adjacency_matrix + &transpose) * half
+    }
+
+    fn estimate_spectral_radius(&mut self) -> T {
+        let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
 
         // Power iteration:
         let mut random_vector = DVector::zeros(self.adjacency_matrix.nrows());
         for i in 0..random_vector.nrows() {
-            random_vector[i] = plus_minus_one.sample(&mut rng);
+            random_vector[i] = T::from_f64(plus_minus_one.sample(&mut self.rng)).unwrap();
         }
 
         for _ in 0..50 {
             random_vector.normalize_mut();
             random_vector = &self.adjacency_matrix * random_vector;
         }
-        let spectral_radius = random_vector.norm();
-
-        self.adjacency_matrix *= radius / spectral_radius;
-        self.spectral_radius = Some(spectral_radius);
-        self
+        random_vector.norm()
     }
 
     pub fn build_sparse_discrete_network<A: ActiviationFunction<T>>(
@@ -70,8 +357,471 @@ impl<T: ReservoirValue + From<f32> + RealField + SampleUniform> EchoStateNetwork
         a: A,
     ) -> SparseDiscreteEchoStateNetwork<T, A> {
         SparseDiscreteEchoStateNetwork {
-            adjacency_matrix: self.adjacency_matrix,
+            adjacency_matrix: Arc::new(self.adjacency_matrix),
+            activation_function: a,
+            state_bounds: None,
+            spectral_radius_scale: T::one(),
+        }
+    }
+
+    // Stores the adjacency matrix in blocks, without a block size the cheapest of 1 (CSR like),
+    // 2, 4, 8, 16 and 32 that divides the size is selected.
+    pub fn build_block_sparse_network<A: ActiviationFunction<T>>(
+        self,
+        a: A,
+        block_size: Option<usize>,
+    ) -> BlockSparseEchoStateNetwork<T, A> {
+        let block_size = block_size.unwrap_or_else(|| {
+            BlockSparseMatrix::select_block_size(&self.adjacency_matrix, &[2, 4, 8, 16, 32])
+        });
+        BlockSparseEchoStateNetwork {
+            adjacency_matrix: Arc::new(BlockSparseMatrix::from_csr(
+                &self.adjacency_matrix,
+                block_size,
+            )),
             activation_function: a,
+            state_bounds: None,
         }
     }
+
+    pub fn build_sparse_leaky_integrator_network<A: ActiviationFunction<T>>(
+        self,
+        a: A,
+        leak_rate: LeakRate<T>,
+    ) -> SparseLeakyIntegratorEchoStateNetwork<T, A> {
+        SparseLeakyIntegratorEchoStateNetwork {
+            leaky_alpha: leak_rate.get(),
+            adjacency_matrix: Arc::new(self.adjacency_matrix),
+            activation_function: a,
+            state_bounds: None,
+        }
+    }
+
+    // Leak rates sigmoid(gate_slope e + gate_bias) of the pre-activation e for every node, see
+    // `SparseGatedLeakyIntegratorEchoStateNetwork`.
+    pub fn build_gated_leaky_integrator_network<A: ActiviationFunction<T>>(
+        self,
+        a: A,
+        gate_slope: T,
+        gate_bias: T,
+    ) -> SparseGatedLeakyIntegratorEchoStateNetwork<T, A> {
+        let size = self.adjacency_matrix.nrows();
+        SparseGatedLeakyIntegratorEchoStateNetwork {
+            adjacency_matrix: Arc::new(self.adjacency_matrix),
+            activation_function: a,
+            gate_slopes: Arc::new(DVector::from_element(size, gate_slope)),
+            gate_biases: Arc::new(DVector::from_element(size, gate_bias)),
+            state_bounds: None,
+        }
+    }
+
+    // r <- f((A r) ⊙ g(W_g u) + W_in u), see `SparseGainModulatedEchoStateNetwork`.
+    pub fn build_gain_modulated_network<A, G>(
+        self,
+        a: A,
+        g: G,
+    ) -> SparseGainModulatedEchoStateNetwork<T, A, G>
+    where
+        A: ActiviationFunction<T>,
+        G: ActiviationFunction<T>,
+    {
+        SparseGainModulatedEchoStateNetwork {
+            adjacency_matrix: Arc::new(self.adjacency_matrix),
+            activation_function: a,
+            gain_function: g,
+            state_bounds: None,
+        }
+    }
+
+    // One network per radius, all sharing a single copy of the adjacency matrix. The
+    // spectral radius is estimated once and applied as a scale factor at matvec time.
+    pub fn build_spectral_radius_sweep<A: ActiviationFunction<T> + Clone>(
+        mut self,
+        a: A,
+        radii: &[SpectralRadius<T>],
+    ) -> Vec<SparseDiscreteEchoStateNetwork<T, A>> {
+        let spectral_radius = self.estimate_spectral_radius();
+        let network = self.build_sparse_discrete_network(a);
+        radii
+            .iter()
+            .map(|radius| network.with_spectral_radius_scale(radius.get() / spectral_radius))
+            .collect()
+    }
+
+    // The time constants are drawn from the builder stream, so they replay with the recipe.
+    pub fn build_continuous_network<A: ActiviationFunction<T>>(
+        mut self,
+        a: A,
+        time_step: T,
+        time_constants: TimeConstantDistribution<T>,
+    ) -> SparseContinuousEchoStateNetwork<T, A> {
+        let size = self.adjacency_matrix.nrows();
+        let time_constants = match time_constants {
+            TimeConstantDistribution::Constant(tau) => DVector::from_element(size, tau),
+            TimeConstantDistribution::Uniform { min, max } => {
+                let distribution =
+                    Uniform::new_inclusive(min.to_f64().unwrap(), max.to_f64().unwrap());
+                DVector::from_fn(size, |_, _| {
+                    T::from_f64(distribution.sample(&mut self.rng)).unwrap()
+                })
+            }
+            TimeConstantDistribution::LogUniform { min, max } => {
+                let distribution =
+                    Uniform::new_inclusive(min.to_f64().unwrap().ln(), max.to_f64().unwrap().ln());
+                // exp(ln(max)) can round above max.
+                DVector::from_fn(size, |_, _| {
+                    let tau = T::from_f64(distribution.sample(&mut self.rng).exp()).unwrap();
+                    Float::min(Float::max(tau, min), max)
+                })
+            }
+        };
+        assert!(
+            time_constants.iter().all(|tau| *tau > T::zero()),
+            "Time constants have to be positive."
+        );
+
+        SparseContinuousEchoStateNetwork {
+            adjacency_matrix: Arc::new(self.adjacency_matrix),
+            activation_function: a,
+            state_bounds: None,
+            time_step,
+            time_constants,
+        }
+    }
+
+    pub fn build_mixed_precision_network<A: ActiviationFunction<T>>(
+        self,
+        a: A,
+    ) -> SparseMixedPrecisionEchoStateNetwork<T, A> {
+        SparseMixedPrecisionEchoStateNetwork {
+            adjacency_matrix: Arc::new(csr_to_f32(&self.adjacency_matrix)),
+            activation_function: a,
+            state_bounds: None,
+        }
+    }
+}
+
+// Bounds set by `with_state_bounds` of the networks. Every state component is clamped to
+// [lower, upper] after each step, which guards exploratory runs (ReLU like activations, spectral
+// radii above one) against blowing up.
+pub(super) fn checked_state_bounds<T: ReservoirValue>(lower: T, upper: T) -> Option<(T, T)> {
+    assert!(
+        lower <= upper,
+        "The lower state bound exceeds the upper one."
+    );
+    Some((lower, upper))
+}
+
+pub(super) fn clamp_state<T: ReservoirValue>(state: &mut DVector<T>, bounds: Option<(T, T)>) {
+    clamp_values(state.as_mut_slice(), bounds);
+}
+
+pub(super) fn clamp_values<T: ReservoirValue>(values: &mut [T], bounds: Option<(T, T)>) {
+    if let Some((lower, upper)) = bounds {
+        for value in values.iter_mut() {
+            *value = Float::min(Float::max(*value, lower), upper);
+        }
+    }
+}
+
+// target = matrix * vector + target, the weights are converted by `weight`. With the
+// `parallel` feature matrices with at least PARALLEL_MATVEC_MIN_SIZE rows are split into row
+// blocks for rayon, below that the threading overhead outweighs the product.
+pub(super) fn csr_multiply_add<W, T>(
+    matrix: &CsrMatrix<W>,
+    vector: &[T],
+    target: &mut [T],
+    weight: impl Fn(W) -> T + Send + Sync,
+) where
+    W: nalgebra::Scalar + Copy + Send + Sync,
+    T: ReservoirValue,
+{
+    csr_multiply_add_with(matrix, vector, target, |matrix_value, vector_value| {
+        weight(matrix_value) * vector_value
+    });
+}
+
+// Same with the product of a weight and a vector entry computed by `product`, for vectors
+// stored in another precision than the target.
+pub(super) fn csr_multiply_add_with<W, V, T>(
+    matrix: &CsrMatrix<W>,
+    vector: &[V],
+    target: &mut [T],
+    product: impl Fn(W, V) -> T + Send + Sync,
+) where
+    W: nalgebra::Scalar + Copy + Send + Sync,
+    V: Copy + Send + Sync,
+    T: ReservoirValue,
+{
+    assert_eq!(matrix.ncols(), vector.len());
+    assert_eq!(matrix.nrows(), target.len());
+    #[cfg(feature = "parallel")]
+    if matrix.nrows() >= PARALLEL_MATVEC_MIN_SIZE {
+        use rayon::prelude::*;
+
+        target
+            .par_chunks_mut(MATVEC_ROW_BLOCK)
+            .enumerate()
+            .for_each(|(block, target)| {
+                csr_rows_multiply_add(matrix, block * MATVEC_ROW_BLOCK, vector, target, &product)
+            });
+        return;
+    }
+    csr_rows_multiply_add(matrix, 0, vector, target, &product);
+}
+
+fn csr_rows_multiply_add<W, V, T>(
+    matrix: &CsrMatrix<W>,
+    first_row: usize,
+    vector: &[V],
+    target: &mut [T],
+    product: &impl Fn(W, V) -> T,
+) where
+    W: nalgebra::Scalar + Copy,
+    V: Copy,
+    T: ReservoirValue,
+{
+    for (offset, target) in target.iter_mut().enumerate() {
+        let row = matrix.row(first_row + offset);
+        for (column, value) in row.col_indices().iter().zip(row.values()) {
+            *target += product(*value, vector[*column]);
+        }
+    }
+}
+
+#[cfg(feature = "parallel")]
+pub const PARALLEL_MATVEC_MIN_SIZE: usize = 10_000;
+
+#[cfg(feature = "parallel")]
+const MATVEC_ROW_BLOCK: usize = 1024;
+
+#[cfg(feature = "parallel")]
+const GENERATION_ROW_BLOCK: usize = 256;
+
+fn row_rng(seed: u64, row: usize) -> StdRng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&(row as u64).to_le_bytes());
+    StdRng::from_seed(key)
+}
+
+// Off diagonal links of one row with values in [-1, 1].
+fn bernoulli_row(size: usize, link_probability: f64, seed: u64, row: usize) -> GeneratedRow {
+    let mut rng = row_rng(seed, row);
+    let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+    let mut columns = Vec::new();
+    let mut values = Vec::new();
+    if link_probability <= 0.0 {
+        return (columns, values);
+    }
+
+    // Position k enumerates the size - 1 off diagonal entries of the row.
+    let log_miss = (1.0 - link_probability.min(1.0)).ln();
+    let mut k = 0;
+    loop {
+        if log_miss < 0.0 {
+            let u: f64 = rng.gen();
+            k += ((1.0 - u).ln() / log_miss).floor() as usize;
+        }
+        if k >= size - 1 {
+            break;
+        }
+        columns.push(if k < row { k } else { k + 1 });
+        values.push(plus_minus_one.sample(&mut rng));
+        k += 1;
+    }
+    (columns, values)
+}
+
+// Exactly `in_degree` distinct off diagonal links, sampled without replacement.
+fn fixed_in_degree_row(size: usize, in_degree: usize, seed: u64, row: usize) -> GeneratedRow {
+    let mut rng = row_rng(seed, row);
+    let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+    let mut columns: Vec<_> = rand::seq::index::sample(&mut rng, size - 1, in_degree)
+        .into_iter()
+        .map(|k| if k < row { k } else { k + 1 })
+        .collect();
+    columns.sort_unstable();
+    let values = columns
+        .iter()
+        .map(|_| plus_minus_one.sample(&mut rng))
+        .collect();
+    (columns, values)
+}
+
+type GeneratedRow = (Vec<usize>, Vec<f64>);
+
+#[cfg(not(feature = "parallel"))]
+fn generate_rows(
+    size: usize,
+    row: impl Fn(usize) -> GeneratedRow + Send + Sync,
+) -> Vec<GeneratedRow> {
+    (0..size).map(row).collect()
+}
+
+#[cfg(feature = "parallel")]
+fn generate_rows(
+    size: usize,
+    row: impl Fn(usize) -> GeneratedRow + Send + Sync,
+) -> Vec<GeneratedRow> {
+    use rayon::prelude::*;
+
+    (0..size)
+        .into_par_iter()
+        .with_min_len(GENERATION_ROW_BLOCK)
+        .map(row)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bernoulli_row, generate_rows, EchoStateNetworkBuilder};
+    use crate::activation_function::{ActivationFunctionWrapper, Tanh};
+    use crate::hyperparameter::{LeakRate, SpectralRadius};
+
+    #[test]
+    fn random_parallel_is_deterministic() {
+        let size = 2000;
+        let link_probability = 6.0 / (size - 1) as f64;
+        let rows = generate_rows(size, |row| bernoulli_row(size, link_probability, 5, row));
+        for (row, generated) in rows.iter().enumerate().step_by(97) {
+            assert_eq!(*generated, bernoulli_row(size, link_probability, 5, row));
+            assert!(!generated.0.contains(&row));
+            assert!(generated.0.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        let network = EchoStateNetworkBuilder::<f64>::random_parallel(size, 6, 5)
+            .build_sparse_discrete_network(tanh());
+        let replayed = EchoStateNetworkBuilder::<f64>::from_recipe(
+            &EchoStateNetworkBuilder::<f64>::random_parallel(size, 6, 5).recipe(),
+        )
+        .unwrap()
+        .build_sparse_discrete_network(tanh());
+        assert_eq!(network.adjacency_matrix(), replayed.adjacency_matrix());
+
+        let average_degree = network.adjacency_matrix().nnz() as f64 / size as f64;
+        assert!((average_degree - 6.).abs() < 0.3);
+    }
+
+    #[test]
+    fn symmetrized_and_antisymmetrized_networks() {
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(80, 5, 3);
+        builder
+            .symmetrize()
+            .spectral_radius(SpectralRadius::new(0.8).unwrap());
+        let replayed = EchoStateNetworkBuilder::<f64>::from_recipe(&builder.recipe()).unwrap();
+        let symmetric = builder.build_sparse_discrete_network(tanh());
+        let matrix = symmetric.adjacency_matrix();
+        assert_eq!(*matrix, matrix.transpose());
+        assert_eq!(
+            matrix,
+            replayed
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix()
+        );
+
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(80, 5, 3);
+        builder.antisymmetrize();
+        let antisymmetric = builder.build_sparse_discrete_network(tanh());
+        let matrix = antisymmetric.adjacency_matrix();
+        assert_eq!(*matrix, matrix.transpose() * -1.0);
+    }
+
+    #[test]
+    fn largest_singular_value_scaling() {
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(60, 5, 11);
+        builder.largest_singular_value(0.7);
+        let network = builder
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+        let dense = nalgebra::DMatrix::from(network.adjacency_matrix());
+        let largest = dense.singular_values().max();
+        assert!((largest - 0.7).abs() < 1e-2);
+    }
+
+    #[test]
+    fn self_loops_and_fixed_diagonal() {
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(200, 5, 13);
+        builder.self_loops(0.5, 0.25);
+        let replayed = EchoStateNetworkBuilder::<f64>::from_recipe(&builder.recipe()).unwrap();
+        let network = builder.build_sparse_discrete_network(tanh());
+        let diagonal = nalgebra::DMatrix::from(network.adjacency_matrix()).diagonal();
+        let loops = diagonal.iter().filter(|v| **v == 0.25).count();
+        assert_eq!(loops + diagonal.iter().filter(|v| **v == 0.).count(), 200);
+        assert!(loops > 70 && loops < 130);
+        assert_eq!(
+            network.adjacency_matrix(),
+            replayed
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix()
+        );
+
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(200, 5, 13);
+        builder.self_loops(1.0, 0.25).diagonal(-0.5);
+        let network = builder.build_sparse_discrete_network(tanh());
+        let dense = nalgebra::DMatrix::from(network.adjacency_matrix());
+        assert!(dense.diagonal().iter().all(|v| *v == -0.5));
+        let mut original = nalgebra::DMatrix::from(
+            EchoStateNetworkBuilder::<f64>::random_seeded(200, 5, 13)
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix(),
+        );
+        original.fill_diagonal(-0.5);
+        assert_eq!(dense, original);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(miri, ignore)]
+    fn parallel_matvec_matches_sparse_product() {
+        let size = super::PARALLEL_MATVEC_MIN_SIZE + 500;
+        let builder = EchoStateNetworkBuilder::<f64>::random_parallel(size, 5, 23);
+        let state = nalgebra::DVector::from_fn(size, |i, _| (i as f64 * 0.01).sin());
+        let mut target = nalgebra::DVector::from_element(size, 0.5);
+        super::csr_multiply_add(
+            &builder.adjacency_matrix,
+            state.as_slice(),
+            target.as_mut_slice(),
+            |weight| weight,
+        );
+        let expected = &builder.adjacency_matrix * &state;
+        assert!((target.add_scalar(-0.5) - expected).amax() < 1e-12);
+    }
+
+    #[test]
+    fn fixed_in_degree_network() {
+        let builder = EchoStateNetworkBuilder::<f64>::random_fixed_in_degree(50, 7, 17);
+        let replayed = EchoStateNetworkBuilder::<f64>::from_recipe(&builder.recipe()).unwrap();
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        let network = builder.build_sparse_discrete_network(tanh());
+        let matrix = network.adjacency_matrix();
+        for (index, row) in matrix.row_iter().enumerate() {
+            assert_eq!(row.nnz(), 7);
+            assert!(!row.col_indices().contains(&index));
+        }
+        assert_eq!(
+            matrix,
+            replayed
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix()
+        );
+    }
+
+    #[test]
+    fn leaky_network_from_builder() {
+        let esn = EchoStateNetworkBuilder::<f64>::random_seeded(20, 3, 1)
+            .build_sparse_leaky_integrator_network(Tanh, LeakRate::new(0.3).unwrap());
+        assert_eq!(esn.leaky_alpha, 0.3);
+    }
+
+    #[test]
+    #[should_panic(expected = "lower state bound")]
+    fn inverted_state_bounds_are_rejected() {
+        EchoStateNetworkBuilder::<f64>::random_seeded(20, 3, 1)
+            .build_sparse_leaky_integrator_network(Tanh, LeakRate::new(0.3).unwrap())
+            .with_state_bounds(1., -1.);
+    }
 }
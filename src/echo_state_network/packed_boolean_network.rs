@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DVector, DVectorSlice};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+
+use crate::{time_evolution::ReservoirTimeEvolution, ReservoirValue};
+
+// Boolean state with one bit per node, 64 nodes per word.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedBooleanState {
+    words: Vec<u64>,
+    nodes: usize,
+}
+
+impl PackedBooleanState {
+    pub fn zeros(nodes: usize) -> Self {
+        Self {
+            words: vec![0; nodes.div_ceil(64)],
+            nodes,
+        }
+    }
+
+    pub fn from_bools(values: &[bool]) -> Self {
+        let mut state = Self::zeros(values.len());
+        for (node, value) in values.iter().enumerate() {
+            state.set(node, *value);
+        }
+        state
+    }
+
+    // Nodes with a value above one half are set.
+    pub fn pack<T: ReservoirValue>(values: DVectorSlice<T>) -> Self {
+        let half = T::from_f64(0.5).unwrap();
+        let mut state = Self::zeros(values.nrows());
+        for (node, value) in values.iter().enumerate() {
+            state.set(node, *value > half);
+        }
+        state
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn get(&self, node: usize) -> bool {
+        self.words[node / 64] >> (node % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, node: usize, value: bool) {
+        assert!(node < self.nodes);
+        let bit = 1 << (node % 64);
+        if value {
+            self.words[node / 64] |= bit;
+        } else {
+            self.words[node / 64] &= !bit;
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    // Unpacking measurement, set nodes are one and the others zero.
+    pub fn unpack<T: ReservoirValue>(&self) -> DVector<T> {
+        DVector::from_fn(self.nodes, |node, _| {
+            if self.get(node) {
+                T::one()
+            } else {
+                T::zero()
+            }
+        })
+    }
+
+    // One column per state, e.g. for training a readout on a recorded run.
+    pub fn unpack_many<T: ReservoirValue>(states: &[PackedBooleanState]) -> DMatrix<T> {
+        let nodes = states.first().map_or(0, |state| state.nodes);
+        let mut matrix = DMatrix::zeros(nodes, states.len());
+        for (mut column, state) in matrix.column_iter_mut().zip(states) {
+            assert_eq!(state.nodes, nodes);
+            column.copy_from(&state.unpack::<T>());
+        }
+        matrix
+    }
+}
+
+// Random threshold network in which every node reads `in_degree` <= 64 nodes, each either
+// excitatory or inhibitory. A step gathers the inputs of a node into one word, so its net input
+// is popcount(x & excitatory) - popcount(x & !excitatory) and the node fires if that plus its
+// drive reaches the threshold. States of 10^5 and more nodes take a few kilobytes and a step
+// costs a handful of word operations per node.
+#[derive(Clone, Debug)]
+pub struct PackedBooleanNetwork {
+    nodes: usize,
+    in_degree: usize,
+    // nodes x in_degree, row major
+    sources: Arc<Vec<u32>>,
+    excitatory: Arc<Vec<u64>>,
+    threshold: i32,
+}
+
+impl PackedBooleanNetwork {
+    pub fn new_random_seeded(
+        nodes: usize,
+        in_degree: usize,
+        excitatory_fraction: f64,
+        threshold: i32,
+        seed: u64,
+    ) -> Self {
+        assert!(
+            in_degree > 0 && in_degree <= 64,
+            "The in degree must be in 1..=64."
+        );
+        assert!(nodes <= u32::MAX as usize);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let node_distribution = Uniform::new(0, nodes as u32);
+        let sources = (0..nodes * in_degree)
+            .map(|_| node_distribution.sample(&mut rng))
+            .collect();
+        let excitatory = (0..nodes)
+            .map(|_| {
+                (0..in_degree).fold(0u64, |mask, input| {
+                    mask | (rng.gen_bool(excitatory_fraction) as u64) << input
+                })
+            })
+            .collect();
+        Self {
+            nodes,
+            in_degree,
+            sources: Arc::new(sources),
+            excitatory: Arc::new(excitatory),
+            threshold,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: i32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn in_degree(&self) -> usize {
+        self.in_degree
+    }
+
+    pub fn threshold(&self) -> i32 {
+        self.threshold
+    }
+
+    // Inputs of `node` as bits in the order of its sources.
+    fn gather(&self, state: &PackedBooleanState, node: usize) -> u64 {
+        let sources = &self.sources[node * self.in_degree..(node + 1) * self.in_degree];
+        sources.iter().enumerate().fold(0, |word, (input, source)| {
+            word | (state.get(*source as usize) as u64) << input
+        })
+    }
+
+    pub fn net_input(&self, state: &PackedBooleanState, node: usize) -> i32 {
+        let word = self.gather(state, node);
+        let excitatory = self.excitatory[node];
+        (word & excitatory).count_ones() as i32 - (word & !excitatory).count_ones() as i32
+    }
+
+    // Set bits of `drive` add one to the net input of their node.
+    pub fn step(
+        &self,
+        state: &PackedBooleanState,
+        drive: &PackedBooleanState,
+        next: &mut PackedBooleanState,
+    ) {
+        assert_eq!(state.nodes, self.nodes);
+        assert_eq!(drive.nodes, self.nodes);
+        assert_eq!(next.nodes, self.nodes);
+        for (index, word) in next.words.iter_mut().enumerate() {
+            let first = index * 64;
+            *word = (first..self.nodes.min(first + 64)).fold(0, |word, node| {
+                let net = self.net_input(state, node) + drive.get(node) as i32;
+                word | ((net >= self.threshold) as u64) << (node - first)
+            });
+        }
+    }
+
+    // Runs from `initial` with one drive per step and returns the visited states.
+    pub fn run(
+        &self,
+        initial: &PackedBooleanState,
+        drives: &[PackedBooleanState],
+    ) -> Vec<PackedBooleanState> {
+        let mut states = Vec::with_capacity(drives.len());
+        let mut state = initial.clone();
+        for drive in drives {
+            let mut next = PackedBooleanState::zeros(self.nodes);
+            self.step(&state, drive, &mut next);
+            states.push(next.clone());
+            state = next;
+        }
+        states
+    }
+}
+
+// On states of zeros and ones with a real valued projected input added to the net input, so
+// the network can be used with the usual training stack. Large networks should use `run` and
+// `PackedBooleanState::unpack_many` instead.
+impl<T: ReservoirValue> ReservoirTimeEvolution<T> for PackedBooleanNetwork {
+    fn input_dimension(&self) -> usize {
+        self.nodes
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.nodes
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let packed = PackedBooleanState::pack(state.column(0));
+        let threshold = T::from_i32(self.threshold).unwrap();
+        for (node, value) in state.iter_mut().enumerate() {
+            let net = T::from_i32(self.net_input(&packed, node)).unwrap() + input[node];
+            *value = if net >= threshold {
+                T::one()
+            } else {
+                T::zero()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::{PackedBooleanNetwork, PackedBooleanState};
+    use crate::time_evolution::ReservoirTimeEvolution;
+
+    #[test]
+    fn packed_steps_match_the_unpacked_evolution() {
+        let network = PackedBooleanNetwork::new_random_seeded(1000, 5, 0.6, 1, 7);
+        let initial =
+            PackedBooleanState::from_bools(&(0..1000).map(|i| i % 3 == 0).collect::<Vec<_>>());
+        let drives = (0..10)
+            .map(|step| {
+                PackedBooleanState::from_bools(
+                    &(0..1000)
+                        .map(|i| (i * 7 + step) % 5 == 0)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let states = network.run(&initial, &drives);
+
+        let mut state = initial.unpack::<f64>();
+        for (drive, packed) in drives.iter().zip(&states) {
+            network.time_evolution(&mut state, drive.unpack::<f64>().column(0));
+            assert_eq!(&PackedBooleanState::pack(state.column(0)), packed);
+        }
+        let active = states.last().unwrap().count_ones();
+        assert!(active > 0 && active < 1000, "{active}");
+
+        let unpacked = PackedBooleanState::unpack_many::<f64>(&states);
+        assert_eq!(unpacked.shape(), (1000, 10));
+        assert_eq!(unpacked.column(9).sum() as usize, active);
+        assert_eq!(DVector::from(unpacked.column(9)), states[9].unpack::<f64>());
+    }
+}
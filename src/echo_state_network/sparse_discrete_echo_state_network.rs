@@ -1,33 +1,71 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use nalgebra::{DVector, DVectorSlice, RealField};
+use nalgebra::{DMatrix, DMatrixSlice, DVector, DVectorSlice};
 use nalgebra_sparse::CsrMatrix;
-use rand::distributions::uniform::SampleUniform;
 
+use super::{checked_state_bounds, clamp_state, clamp_values, csr_multiply_add};
 use crate::{
-    activation_function::ActiviationFunction, time_evolution::ReservoirTimeEvolution,
+    activation_function::ActiviationFunction,
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    profile::{ProfileComponent, ProfileTimer},
+    time_evolution::{
+        finite_difference_jacobians, AllocationFreeTimeEvolution, ReservoirTimeEvolution,
+    },
     ReservoirValue,
 };
 
 #[derive(Clone)]
-pub struct SparseDiscreteEchoStateNetwork<
-    T: ReservoirValue + From<f32> + RealField + SampleUniform,
-    A: ActiviationFunction<T>,
-> {
-    pub(super) adjacency_matrix: CsrMatrix<T>,
+pub struct SparseDiscreteEchoStateNetwork<T: ReservoirValue, A: ActiviationFunction<T>> {
+    pub(super) adjacency_matrix: Arc<CsrMatrix<T>>,
     pub(super) activation_function: A,
+    pub(super) state_bounds: Option<(T, T)>,
+    // Applied at matvec time, lets several networks share one adjacency matrix.
+    pub(super) spectral_radius_scale: T,
 }
 
-impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFunction<T>> Debug
-    for SparseDiscreteEchoStateNetwork<T, A>
-{
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug for SparseDiscreteEchoStateNetwork<T, A> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "EchoStateNetwork{{ {:?} }}", self.adjacency_matrix)
     }
 }
 
-impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFunction<T>>
-    ReservoirTimeEvolution<T> for SparseDiscreteEchoStateNetwork<T, A>
+impl<T: ReservoirValue, A: ActiviationFunction<T>> SparseDiscreteEchoStateNetwork<T, A> {
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<T> {
+        &self.adjacency_matrix
+    }
+
+    pub fn activation_function(&self) -> &A {
+        &self.activation_function
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    pub fn state_bounds(&self) -> Option<(T, T)> {
+        self.state_bounds
+    }
+
+    pub fn spectral_radius_scale(&self) -> T {
+        self.spectral_radius_scale
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T> + Clone> SparseDiscreteEchoStateNetwork<T, A> {
+    // Shares the adjacency matrix, the effective matrix is `scale` times the stored one.
+    pub fn with_spectral_radius_scale(&self, scale: T) -> Self {
+        Self {
+            adjacency_matrix: self.adjacency_matrix.clone(),
+            activation_function: self.activation_function.clone(),
+            state_bounds: self.state_bounds,
+            spectral_radius_scale: scale,
+        }
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for SparseDiscreteEchoStateNetwork<T, A>
 {
     fn input_dimension(&self) -> usize {
         self.adjacency_matrix.nrows()
@@ -38,14 +76,221 @@ impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFu
     }
 
     fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
-        let combined_state = &self.adjacency_matrix * &(*state) + input;
-        for (index, (s, e)) in state
-            .as_mut_slice()
-            .iter_mut()
-            .zip(combined_state.as_slice().iter())
+        let timer = ProfileTimer::start();
+        let mut combined_state = DVector::zeros(state.nrows());
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            state.as_slice(),
+            combined_state.as_mut_slice(),
+            |weight| weight,
+        );
+        if self.spectral_radius_scale != T::one() {
+            combined_state *= self.spectral_radius_scale;
+        }
+        combined_state += input;
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        state.copy_from(&combined_state);
+        self.activation_function
+            .invoke_slice(0, state.as_mut_slice());
+        clamp_state(state, self.state_bounds);
+        timer.stop(ProfileComponent::Activation);
+    }
+
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        buffer: &mut DVector<T>,
+    ) {
+        let timer = ProfileTimer::start();
+        buffer.fill(T::zero());
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            state.as_slice(),
+            buffer.as_mut_slice(),
+            |weight| weight,
+        );
+        if self.spectral_radius_scale != T::one() {
+            *buffer *= self.spectral_radius_scale;
+        }
+        *buffer += input;
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        state.copy_from(buffer);
+        self.activation_function
+            .invoke_slice(0, state.as_mut_slice());
+        clamp_state(state, self.state_bounds);
+        timer.stop(ProfileComponent::Activation);
+    }
+
+    fn time_evolution_many(&self, states: &mut DMatrix<T>, inputs: DMatrixSlice<T>) {
+        assert_eq!(states.ncols(), inputs.ncols());
+        let timer = ProfileTimer::start();
+        let mut combined_states = self.adjacency_matrix.as_ref() * &(*states);
+        if self.spectral_radius_scale != T::one() {
+            combined_states *= self.spectral_radius_scale;
+        }
+        combined_states += inputs;
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        states.copy_from(&combined_states);
+        let size = states.nrows();
+        for state in states.as_mut_slice().chunks_mut(size) {
+            self.activation_function.invoke_slice(0, state);
+            clamp_values(state, self.state_bounds);
+        }
+        timer.stop(ProfileComponent::Activation);
+    }
+
+    // diag(f'(z)) W and diag(f'(z)) with z = W state + input, clamped nodes do not move.
+    fn jacobians(&self, state: &DVector<T>, input: DVectorSlice<T>) -> (DMatrix<T>, DMatrix<T>) {
+        let mut combined_state = self.adjacency_matrix.as_ref() * state;
+        combined_state *= self.spectral_radius_scale;
+        combined_state += input;
+        let slopes: Option<Vec<T>> = combined_state
+            .iter()
             .enumerate()
-        {
-            *s = self.activation_function.invoke(index, *e);
+            .map(|(index, value)| {
+                let slope = self.activation_function.derivative(index, *value)?;
+                let activated = self.activation_function.invoke(index, *value);
+                Some(match self.state_bounds {
+                    Some((lower, upper)) if activated < lower || activated > upper => T::zero(),
+                    _ => slope,
+                })
+            })
+            .collect();
+        let Some(slopes) = slopes else {
+            return finite_difference_jacobians(self, state, input);
+        };
+
+        let mut state_jacobian = DMatrix::zeros(state.nrows(), state.nrows());
+        for (row_index, row) in self.adjacency_matrix.row_iter().enumerate() {
+            let slope = slopes[row_index] * self.spectral_radius_scale;
+            for (column, weight) in row.col_indices().iter().zip(row.values()) {
+                state_jacobian[(row_index, *column)] = slope * *weight;
+            }
         }
+        (
+            state_jacobian,
+            DMatrix::from_diagonal(&DVector::from_vec(slopes)),
+        )
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> AllocationFreeTimeEvolution<T>
+    for SparseDiscreteEchoStateNetwork<T, A>
+{
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ControlledReservoirTimeEvolution<T>
+    for SparseDiscreteEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn control_input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn controlled_time_evolution(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        control: DVectorSlice<T>,
+    ) {
+        let combined_input = input + control;
+        self.time_evolution(state, combined_input.column(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use crate::{
+        activation_function::{ActivationFunctionWrapper, Tanh},
+        echo_state_network::EchoStateNetworkBuilder,
+        hyperparameter::SpectralRadius,
+        time_evolution::{finite_difference_jacobians, ReservoirTimeEvolution},
+    };
+
+    #[test]
+    fn spectral_radius_sweep_shares_topology() {
+        let builder = EchoStateNetworkBuilder::<f64>::random(50, 5);
+        let sweep = builder.clone().build_spectral_radius_sweep(
+            ActivationFunctionWrapper::new(|_, v: f64| v.tanh()),
+            &[0.3, 0.6, 0.9].map(|radius| SpectralRadius::new(radius).unwrap()),
+        );
+        let mut rescaled = builder;
+        rescaled.adjacency_matrix *= sweep[1].spectral_radius_scale();
+        let rescaled = rescaled
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+        assert_eq!(sweep.len(), 3);
+        assert!(std::ptr::eq(
+            sweep[0].adjacency_matrix(),
+            sweep[2].adjacency_matrix()
+        ));
+        assert!(
+            (sweep[2].spectral_radius_scale() / sweep[0].spectral_radius_scale() - 3.).abs()
+                < 1e-12
+        );
+
+        let input = DVector::from_fn(50, |i, _| (i as f64 * 0.1).sin());
+        let mut swept_state = DVector::from_element(50, 0.1);
+        let mut rescaled_state = swept_state.clone();
+        for _ in 0..10 {
+            sweep[1].time_evolution(&mut swept_state, input.column(0));
+            rescaled.time_evolution(&mut rescaled_state, input.column(0));
+        }
+        assert!((swept_state - rescaled_state).amax() < 1e-12);
+    }
+
+    #[test]
+    fn state_bounds_prevent_blow_up() {
+        let relu = || ActivationFunctionWrapper::new(|_, v: f64| v.max(0.));
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(50, 5, 23);
+        builder.spectral_radius(SpectralRadius::new(3.0).unwrap());
+        let unbounded = builder.clone().build_sparse_discrete_network(relu());
+        let bounded = builder
+            .build_sparse_discrete_network(relu())
+            .with_state_bounds(-2., 2.);
+
+        let input = DVector::from_element(50, 1.0);
+        let mut unbounded_state = DVector::from_element(50, 0.1);
+        let mut bounded_state = unbounded_state.clone();
+        for _ in 0..50 {
+            unbounded.time_evolution(&mut unbounded_state, input.column(0));
+            bounded.time_evolution(&mut bounded_state, input.column(0));
+        }
+        assert!(unbounded_state.amax() > 1e3);
+        assert!(bounded_state.iter().all(|v| (-2.0..=2.0).contains(v)));
+        assert_eq!(bounded.state_bounds(), Some((-2., 2.)));
+    }
+
+    #[test]
+    fn analytic_jacobians_match_finite_differences() {
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(30, 4, 5);
+        builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+        let esn = builder
+            .build_sparse_discrete_network(Tanh)
+            .with_spectral_radius_scale(1.3);
+        let state = DVector::from_fn(30, |i, _| (i as f64 * 0.7).sin() * 0.5);
+        let input = DVector::from_fn(30, |i, _| (i as f64 * 0.3).cos() * 0.2);
+
+        let (state_jacobian, input_jacobian) = esn.jacobians(&state, input.column(0));
+        let (expected_state, expected_input) =
+            finite_difference_jacobians(&esn, &state, input.column(0));
+        assert!((state_jacobian - expected_state).amax() < 1e-8);
+        assert!((input_jacobian - expected_input).amax() < 1e-8);
     }
 }
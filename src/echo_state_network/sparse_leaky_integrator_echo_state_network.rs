@@ -1,26 +1,30 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use nalgebra::{DVector, DVectorSlice, RealField};
+use nalgebra::{DVector, DVectorSlice};
 use nalgebra_sparse::CsrMatrix;
-use rand::distributions::uniform::SampleUniform;
 
+use super::{checked_state_bounds, clamp_state, csr_multiply_add};
 use crate::{
-    activation_function::ActiviationFunction, time_evolution::ReservoirTimeEvolution,
+    activation_function::ActiviationFunction,
+    controlled_time_evolution::ControlledReservoirTimeEvolution,
+    profile::{ProfileComponent, ProfileTimer},
+    time_evolution::{AllocationFreeTimeEvolution, ReservoirTimeEvolution},
     ReservoirValue,
 };
 
 #[derive(Clone)]
 pub struct SparseLeakyIntegratorEchoStateNetwork<T, A>
 where
-    T: ReservoirValue + From<f32> + RealField + SampleUniform,
+    T: ReservoirValue,
     A: ActiviationFunction<T>,
 {
     pub(super) leaky_alpha: T,
-    pub(super) adjacency_matrix: CsrMatrix<T>,
+    pub(super) adjacency_matrix: Arc<CsrMatrix<T>>,
     pub(super) activation_function: A,
+    pub(super) state_bounds: Option<(T, T)>,
 }
 
-impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFunction<T>> Debug
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Debug
     for SparseLeakyIntegratorEchoStateNetwork<T, A>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -32,8 +36,23 @@ impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFu
     }
 }
 
-impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFunction<T>>
-    ReservoirTimeEvolution<T> for SparseLeakyIntegratorEchoStateNetwork<T, A>
+impl<T: ReservoirValue, A: ActiviationFunction<T>> SparseLeakyIntegratorEchoStateNetwork<T, A> {
+    pub fn adjacency_matrix(&self) -> &CsrMatrix<T> {
+        &self.adjacency_matrix
+    }
+
+    pub fn with_state_bounds(mut self, lower: T, upper: T) -> Self {
+        self.state_bounds = checked_state_bounds(lower, upper);
+        self
+    }
+
+    pub fn state_bounds(&self) -> Option<(T, T)> {
+        self.state_bounds
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ReservoirTimeEvolution<T>
+    for SparseLeakyIntegratorEchoStateNetwork<T, A>
 {
     fn input_dimension(&self) -> usize {
         self.adjacency_matrix.nrows()
@@ -44,15 +63,65 @@ impl<T: ReservoirValue + From<f32> + RealField + SampleUniform, A: ActiviationFu
     }
 
     fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
-        let combined_state = &self.adjacency_matrix * &(*state) + input;
-        for (index, (s, e)) in state
-            .as_mut_slice()
-            .iter_mut()
-            .zip(combined_state.as_slice().iter())
-            .enumerate()
-        {
-            *s = (T::one() - self.leaky_alpha) * *e
-                + self.leaky_alpha * self.activation_function.invoke(index, *e);
-        }
+        let mut combined_state = DVector::zeros(state.nrows());
+        self.time_evolution_with_buffer(state, input, &mut combined_state);
+    }
+
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        combined_state: &mut DVector<T>,
+    ) {
+        let timer = ProfileTimer::start();
+        combined_state.copy_from(&input);
+        csr_multiply_add(
+            &self.adjacency_matrix,
+            state.as_slice(),
+            combined_state.as_mut_slice(),
+            |weight| weight,
+        );
+        timer.stop(ProfileComponent::SparseMatrixVector);
+
+        let timer = ProfileTimer::start();
+        // state = (1 - alpha) e + alpha f(e), f is applied in place on e.
+        state.copy_from(combined_state);
+        *state *= T::one() - self.leaky_alpha;
+        self.activation_function
+            .invoke_slice(0, combined_state.as_mut_slice());
+        state.axpy(self.leaky_alpha, combined_state, T::one());
+        clamp_state(state, self.state_bounds);
+        timer.stop(ProfileComponent::Activation);
+    }
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> AllocationFreeTimeEvolution<T>
+    for SparseLeakyIntegratorEchoStateNetwork<T, A>
+{
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> ControlledReservoirTimeEvolution<T>
+    for SparseLeakyIntegratorEchoStateNetwork<T, A>
+{
+    fn input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn control_input_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.adjacency_matrix.nrows()
+    }
+
+    fn controlled_time_evolution(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        control: DVectorSlice<T>,
+    ) {
+        let combined_input = input + control;
+        self.time_evolution(state, combined_input.column(0));
     }
 }
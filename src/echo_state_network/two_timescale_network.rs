@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DVector, DVectorSlice};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    SeedableRng,
+};
+
+use crate::{time_evolution::ReservoirTimeEvolution, ReservoirValue};
+
+// Hierarchy of a fast and a slow network for signals with separated time scales. The state is
+// [fast; filtered; slow]: the fast network is driven by the input and top down by the slow
+// state, filtered is an exponential moving average of the fast state with weight `smoothing`
+// per step, and the slow network is driven by the filtered state only, so it never sees the
+// fast fluctuations. Only the fast rows of the projected input are used. The couplings are
+// shared between clones.
+#[derive(Clone, Debug)]
+pub struct TwoTimescaleNetwork<T, F, S>
+where
+    T: ReservoirValue,
+    F: ReservoirTimeEvolution<T>,
+    S: ReservoirTimeEvolution<T>,
+{
+    fast: F,
+    slow: S,
+    smoothing: T,
+    // slow x fast
+    upward_coupling: Arc<DMatrix<T>>,
+    // fast x slow
+    downward_coupling: Arc<DMatrix<T>>,
+}
+
+impl<T, F, S> TwoTimescaleNetwork<T, F, S>
+where
+    T: ReservoirValue,
+    F: ReservoirTimeEvolution<T>,
+    S: ReservoirTimeEvolution<T>,
+{
+    pub fn new(
+        fast: F,
+        slow: S,
+        smoothing: T,
+        upward_coupling: DMatrix<T>,
+        downward_coupling: DMatrix<T>,
+    ) -> Self {
+        assert!(
+            smoothing > T::zero() && smoothing <= T::one(),
+            "The smoothing must be in (0, 1]."
+        );
+        let (fast_dimension, slow_dimension) = (fast.output_dimension(), slow.output_dimension());
+        assert_eq!(fast.input_dimension(), fast_dimension);
+        assert_eq!(slow.input_dimension(), slow_dimension);
+        assert_eq!(upward_coupling.shape(), (slow_dimension, fast_dimension));
+        assert_eq!(downward_coupling.shape(), (fast_dimension, slow_dimension));
+        Self {
+            fast,
+            slow,
+            smoothing,
+            upward_coupling: Arc::new(upward_coupling),
+            downward_coupling: Arc::new(downward_coupling),
+        }
+    }
+
+    // Dense couplings with weights uniform in [-strength, strength], a downward strength of
+    // zero gives a pure feed forward hierarchy.
+    pub fn new_random_seeded(
+        fast: F,
+        slow: S,
+        smoothing: T,
+        upward_strength: T,
+        downward_strength: T,
+        seed: u64,
+    ) -> Self {
+        let (fast_dimension, slow_dimension) = (fast.output_dimension(), slow.output_dimension());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+        let mut coupling = |rows, columns, strength: T| {
+            DMatrix::from_fn(rows, columns, |_, _| {
+                T::from_f64(plus_minus_one.sample(&mut rng)).unwrap() * strength
+            })
+        };
+        let upward_coupling = coupling(slow_dimension, fast_dimension, upward_strength);
+        let downward_coupling = coupling(fast_dimension, slow_dimension, downward_strength);
+        Self::new(fast, slow, smoothing, upward_coupling, downward_coupling)
+    }
+
+    pub fn fast(&self) -> &F {
+        &self.fast
+    }
+
+    pub fn slow(&self) -> &S {
+        &self.slow
+    }
+
+    pub fn smoothing(&self) -> T {
+        self.smoothing
+    }
+
+    pub fn upward_coupling(&self) -> &DMatrix<T> {
+        &self.upward_coupling
+    }
+
+    pub fn downward_coupling(&self) -> &DMatrix<T> {
+        &self.downward_coupling
+    }
+
+    pub fn fast_dimension(&self) -> usize {
+        self.fast.output_dimension()
+    }
+
+    pub fn slow_dimension(&self) -> usize {
+        self.slow.output_dimension()
+    }
+}
+
+impl<T, F, S> ReservoirTimeEvolution<T> for TwoTimescaleNetwork<T, F, S>
+where
+    T: ReservoirValue,
+    F: ReservoirTimeEvolution<T>,
+    S: ReservoirTimeEvolution<T>,
+{
+    fn input_dimension(&self) -> usize {
+        self.output_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        2 * self.fast_dimension() + self.slow_dimension()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let (fast_dimension, slow_dimension) = (self.fast_dimension(), self.slow_dimension());
+        let mut fast_state = state.rows(0, fast_dimension).clone_owned();
+        let mut slow_state = state.rows(2 * fast_dimension, slow_dimension).clone_owned();
+
+        let fast_input =
+            input.rows(0, fast_dimension) + self.downward_coupling.as_ref() * &slow_state;
+        self.fast
+            .time_evolution(&mut fast_state, fast_input.column(0));
+        let mut filtered = state.rows_mut(fast_dimension, fast_dimension);
+        filtered *= T::one() - self.smoothing;
+        filtered.axpy(self.smoothing, &fast_state, T::one());
+        let slow_input = self.upward_coupling.as_ref() * filtered;
+        self.slow
+            .time_evolution(&mut slow_state, slow_input.column(0));
+
+        state.rows_mut(0, fast_dimension).copy_from(&fast_state);
+        state
+            .rows_mut(2 * fast_dimension, slow_dimension)
+            .copy_from(&slow_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::TwoTimescaleNetwork;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn feed_forward_hierarchy_matches_its_parts() {
+        let fast = EchoStateNetworkBuilder::<f64>::random_seeded(20, 4, 1)
+            .build_sparse_discrete_network(Tanh);
+        let slow = EchoStateNetworkBuilder::<f64>::random_seeded(10, 4, 2)
+            .build_sparse_discrete_network(Tanh);
+        let network =
+            TwoTimescaleNetwork::new_random_seeded(fast.clone(), slow.clone(), 0.1, 0.5, 0., 3);
+        assert_eq!(network.output_dimension(), 50);
+        assert_eq!(network.input_dimension(), 50);
+
+        let mut state = DVector::zeros(50);
+        let mut fast_state = DVector::zeros(20);
+        let mut filtered = DVector::zeros(20);
+        let mut slow_state = DVector::zeros(10);
+        for step in 0..30 {
+            let input = DVector::from_fn(50, |i, _| ((i + step) as f64 * 0.7).sin());
+            network.time_evolution(&mut state, input.column(0));
+
+            fast.time_evolution(&mut fast_state, input.rows(0, 20));
+            filtered = filtered * 0.9 + &fast_state * 0.1;
+            let slow_input = network.upward_coupling() * &filtered;
+            slow.time_evolution(&mut slow_state, slow_input.column(0));
+        }
+        assert!((state.rows(0, 20) - &fast_state).amax() < 1e-12);
+        assert!((state.rows(20, 20) - &filtered).amax() < 1e-12);
+        assert!((state.rows(40, 10) - &slow_state).amax() < 1e-12);
+
+        // The filtered state changes less per step than the fast one.
+        let mut next = state.clone();
+        let input = DMatrix::from_fn(50, 1, |i, _| (i as f64).cos());
+        network.time_evolution(&mut next, input.column(0));
+        let change = next - &state;
+        assert!(change.rows(20, 20).amax() < change.rows(0, 20).amax());
+    }
+}
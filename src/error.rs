@@ -0,0 +1,57 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReservoirError {
+    DimensionMismatch {
+        component: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    InvalidRecipe(String),
+    InvalidHyperparameter(String),
+    Protocol(String),
+    InvalidData(String),
+    External(String),
+}
+
+impl Display for ReservoirError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReservoirError::DimensionMismatch {
+                component,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Dimension mismatch in {component}: expected {expected}, got {actual}."
+            ),
+            ReservoirError::InvalidRecipe(reason) => {
+                write!(f, "Invalid generation recipe: {reason}.")
+            }
+            ReservoirError::InvalidHyperparameter(reason) => {
+                write!(f, "Invalid hyperparameter: {reason}.")
+            }
+            ReservoirError::Protocol(reason) => write!(f, "Protocol error: {reason}."),
+            ReservoirError::InvalidData(reason) => write!(f, "Invalid data: {reason}."),
+            ReservoirError::External(reason) => write!(f, "External reservoir error: {reason}."),
+        }
+    }
+}
+
+impl std::error::Error for ReservoirError {}
+
+pub(crate) fn check_dimension(
+    component: &'static str,
+    expected: usize,
+    actual: usize,
+) -> Result<(), ReservoirError> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(ReservoirError::DimensionMismatch {
+            component,
+            expected,
+            actual,
+        })
+    }
+}
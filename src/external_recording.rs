@@ -0,0 +1,295 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use nalgebra::{DVector, DVectorSlice};
+
+use crate::{
+    error::check_dimension, time_evolution::ReservoirTimeEvolution, ReservoirError, ReservoirValue,
+};
+
+const MAGIC: &[u8; 4] = b"RCRR";
+const FORMAT_VERSION: u32 = 1;
+// Magic, format version, dimension and step count.
+const HEADER_LEN: usize = 24;
+
+// Steps of a reservoir as (state, input, next state), e.g. from a single session on lab
+// hardware, so that readout training and hyperparameter studies can run offline afterwards.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalRecording {
+    dimension: usize,
+    // state, input and next state per step
+    values: Vec<f64>,
+}
+
+impl ExternalRecording {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            values: vec![],
+        }
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn steps(&self) -> usize {
+        if self.dimension == 0 {
+            0
+        } else {
+            self.values.len() / (3 * self.dimension)
+        }
+    }
+
+    pub fn push<T: ReservoirValue>(
+        &mut self,
+        state: &DVector<T>,
+        input: DVectorSlice<T>,
+        next: &DVector<T>,
+    ) {
+        assert_eq!(state.nrows(), self.dimension);
+        assert_eq!(input.nrows(), self.dimension);
+        assert_eq!(next.nrows(), self.dimension);
+        let values = state.iter().chain(input.iter()).chain(next.iter());
+        self.values
+            .extend(values.map(|value| value.to_f64().unwrap()));
+    }
+
+    // (state, input, next state) of `step`.
+    pub fn step(&self, step: usize) -> (&[f64], &[f64], &[f64]) {
+        let values = &self.values[3 * self.dimension * step..3 * self.dimension * (step + 1)];
+        let (state, rest) = values.split_at(self.dimension);
+        let (input, next) = rest.split_at(self.dimension);
+        (state, input, next)
+    }
+
+    // Little endian: magic, format version, dimension, step count and then the values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 8 * self.values.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.dimension as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.steps() as u64).to_le_bytes());
+        for value in &self.values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReservoirError> {
+        let truncated = || ReservoirError::InvalidData("truncated recording".to_string());
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+            return Err(ReservoirError::InvalidData(
+                "not a reservoir recording".to_string(),
+            ));
+        }
+        let format_version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if format_version != FORMAT_VERSION {
+            return Err(ReservoirError::InvalidData(format!(
+                "recording format version {format_version} is not supported"
+            )));
+        }
+        let dimension = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let steps = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+        let value_count = dimension
+            .checked_mul(3)
+            .and_then(|count| count.checked_mul(steps))
+            .ok_or_else(truncated)?;
+        if Some(bytes.len() - HEADER_LEN) != value_count.checked_mul(8) {
+            return Err(truncated());
+        }
+        let values = bytes[HEADER_LEN..]
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        Ok(Self { dimension, values })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReservoirError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|error| ReservoirError::InvalidData(format!("{}: {error}", path.display())))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReservoirError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|error| ReservoirError::InvalidData(format!("{}: {error}", path.display())))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+// Passes steps through to `network`, typically an `ExternalTimeEvolution`, and records them.
+// Clones share the recording.
+#[derive(Clone, Debug)]
+pub struct RecordingTimeEvolution<T: ReservoirValue, E: ReservoirTimeEvolution<T>> {
+    network: E,
+    recording: Arc<Mutex<ExternalRecording>>,
+    phantom: std::marker::PhantomData<T>,
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> RecordingTimeEvolution<T, E> {
+    pub fn new(network: E) -> Self {
+        assert_eq!(network.input_dimension(), network.output_dimension());
+        let recording = ExternalRecording::new(network.output_dimension());
+        Self {
+            network,
+            recording: Arc::new(Mutex::new(recording)),
+            phantom: std::marker::PhantomData,
+        }
+    }
+
+    pub fn network(&self) -> &E {
+        &self.network
+    }
+
+    pub fn recording(&self) -> ExternalRecording {
+        self.recording.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReservoirError> {
+        self.recording.lock().unwrap().save(path)
+    }
+}
+
+impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> ReservoirTimeEvolution<T>
+    for RecordingTimeEvolution<T, E>
+{
+    fn input_dimension(&self) -> usize {
+        self.network.input_dimension()
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.network.output_dimension()
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        let previous = state.clone();
+        self.network.time_evolution(state, input);
+        self.recording.lock().unwrap().push(&previous, input, state);
+    }
+}
+
+// Serves the steps of a recording back: a step from a recorded state with a recorded input
+// yields the recorded next state, any other step fails. Rerunning the inputs of the session,
+// with the same synchronization, therefore reproduces its states exactly. Later recordings of
+// the same step win.
+#[derive(Clone, Debug)]
+pub struct ReplayTimeEvolution {
+    recording: Arc<ExternalRecording>,
+    steps: Arc<HashMap<Vec<u64>, usize>>,
+}
+
+impl ReplayTimeEvolution {
+    pub fn new(recording: ExternalRecording) -> Self {
+        let steps = (0..recording.steps())
+            .map(|step| {
+                let (state, input, _) = recording.step(step);
+                (replay_key(state.iter().chain(input).copied()), step)
+            })
+            .collect();
+        Self {
+            recording: Arc::new(recording),
+            steps: Arc::new(steps),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReservoirError> {
+        Ok(Self::new(ExternalRecording::load(path)?))
+    }
+
+    pub fn recording(&self) -> &ExternalRecording {
+        &self.recording
+    }
+
+    pub fn try_time_evolution<T: ReservoirValue>(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+    ) -> Result<(), ReservoirError> {
+        check_dimension("replayed state", self.recording.dimension, state.nrows())?;
+        check_dimension("replayed input", self.recording.dimension, input.nrows())?;
+        let key = replay_key(
+            state
+                .iter()
+                .chain(input.iter())
+                .map(|value| value.to_f64().unwrap()),
+        );
+        let step = self
+            .steps
+            .get(&key)
+            .ok_or_else(|| ReservoirError::External("the step was not recorded".to_string()))?;
+        let (_, _, next) = self.recording.step(*step);
+        for (value, recorded) in state.iter_mut().zip(next) {
+            *value = T::from_f64(*recorded).unwrap();
+        }
+        Ok(())
+    }
+}
+
+impl<T: ReservoirValue> ReservoirTimeEvolution<T> for ReplayTimeEvolution {
+    fn input_dimension(&self) -> usize {
+        self.recording.dimension
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.recording.dimension
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        if let Err(error) = self.try_time_evolution(state, input) {
+            panic!("{error}");
+        }
+    }
+}
+
+// Bit patterns, with both zeros mapped to the same key.
+fn replay_key(values: impl Iterator<Item = f64>) -> Vec<u64> {
+    values.map(|value| (value + 0.).to_bits()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::{ExternalRecording, RecordingTimeEvolution, ReplayTimeEvolution};
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    #[test]
+    fn replay_reproduces_the_recorded_session() {
+        let network = EchoStateNetworkBuilder::<f64>::random_seeded(8, 3, 1)
+            .build_sparse_discrete_network(Tanh);
+        let recorder = RecordingTimeEvolution::new(network);
+        let inputs: Vec<_> = (0..20)
+            .map(|step| DVector::from_fn(8, |i, _| ((i * step) as f64 * 0.1).sin()))
+            .collect();
+        let mut states = vec![];
+        let mut state = DVector::zeros(8);
+        for input in &inputs {
+            recorder.time_evolution(&mut state, input.column(0));
+            states.push(state.clone());
+        }
+        let recording = recorder.recording();
+        assert_eq!(recording.steps(), 20);
+        let bytes = recording.to_bytes();
+        assert_eq!(ExternalRecording::from_bytes(&bytes).unwrap(), recording);
+        assert!(ExternalRecording::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let replay = ReplayTimeEvolution::new(ExternalRecording::from_bytes(&bytes).unwrap());
+        let mut state = DVector::zeros(8);
+        for (input, expected) in inputs.iter().zip(&states) {
+            replay.time_evolution(&mut state, input.column(0));
+            assert_eq!(&state, expected);
+        }
+        let unknown = DVector::from_element(8, 0.5);
+        assert!(replay
+            .try_time_evolution(&mut state, unknown.column(0))
+            .is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use nalgebra::{DVector, DVectorSlice};
+
+use crate::{
+    error::check_dimension, time_evolution::ReservoirTimeEvolution, ReservoirError, ReservoirValue,
+};
+
+pub type ExternalCallback<T> =
+    Box<dyn FnMut(&DVector<T>, &DVector<T>) -> Result<DVector<T>, ReservoirError> + Send>;
+
+// One step requested from the external reservoir.
+#[derive(Clone, Debug)]
+pub struct ExternalRequest<T: ReservoirValue> {
+    pub state: DVector<T>,
+    pub input: DVector<T>,
+}
+
+// Side of the channel held by the driver of the hardware or the remote simulator, which answers
+// every request with the next state or an error.
+#[derive(Debug)]
+pub struct ExternalEndpoint<T: ReservoirValue> {
+    requests: Receiver<ExternalRequest<T>>,
+    responses: Sender<Result<DVector<T>, ReservoirError>>,
+}
+
+impl<T: ReservoirValue> ExternalEndpoint<T> {
+    // Blocks until the next request, None once the evolution is dropped.
+    pub fn next_request(&self) -> Option<ExternalRequest<T>> {
+        self.requests.recv().ok()
+    }
+
+    pub fn respond(&self, response: Result<DVector<T>, ReservoirError>) {
+        // A closed evolution no longer needs the answer.
+        let _ = self.responses.send(response);
+    }
+
+    // Answers requests with `step` until the evolution is dropped.
+    pub fn serve(
+        &self,
+        mut step: impl FnMut(ExternalRequest<T>) -> Result<DVector<T>, ReservoirError>,
+    ) {
+        while let Some(request) = self.next_request() {
+            self.respond(step(request));
+        }
+    }
+}
+
+enum Backend<T: ReservoirValue> {
+    Callback(ExternalCallback<T>),
+    Channel {
+        requests: Sender<ExternalRequest<T>>,
+        responses: Receiver<Result<DVector<T>, ReservoirError>>,
+        timeout: Duration,
+    },
+}
+
+// Physical or remote reservoir behind the time evolution interface, so that lab hardware can be
+// trained and read out like any other network. The state update is delegated to a callback or,
+// over a channel, to an `ExternalEndpoint`; steps are serialized and clones share the backend.
+// `try_time_evolution` reports failures, timeouts and responses of the wrong dimension, while
+// `time_evolution` panics on them since the training stack has no way to recover.
+#[derive(Clone)]
+pub struct ExternalTimeEvolution<T: ReservoirValue> {
+    dimension: usize,
+    backend: Arc<Mutex<Backend<T>>>,
+}
+
+impl<T: ReservoirValue> Debug for ExternalTimeEvolution<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ExternalTimeEvolution{{ {} }}", self.dimension)
+    }
+}
+
+impl<T: ReservoirValue> ExternalTimeEvolution<T> {
+    // `callback` maps the state and the projected input to the next state.
+    pub fn from_callback(
+        dimension: usize,
+        callback: impl FnMut(&DVector<T>, &DVector<T>) -> Result<DVector<T>, ReservoirError>
+            + Send
+            + 'static,
+    ) -> Self {
+        Self {
+            dimension,
+            backend: Arc::new(Mutex::new(Backend::Callback(Box::new(callback)))),
+        }
+    }
+
+    // Steps fail if the endpoint does not answer within `timeout`.
+    pub fn channel(dimension: usize, timeout: Duration) -> (Self, ExternalEndpoint<T>) {
+        let (request_sender, request_receiver) = channel();
+        let (response_sender, response_receiver) = channel();
+        let evolution = Self {
+            dimension,
+            backend: Arc::new(Mutex::new(Backend::Channel {
+                requests: request_sender,
+                responses: response_receiver,
+                timeout,
+            })),
+        };
+        let endpoint = ExternalEndpoint {
+            requests: request_receiver,
+            responses: response_sender,
+        };
+        (evolution, endpoint)
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub fn try_time_evolution(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+    ) -> Result<(), ReservoirError> {
+        check_dimension("external state", self.dimension, state.nrows())?;
+        check_dimension("external input", self.dimension, input.nrows())?;
+        let mut backend = self
+            .backend
+            .lock()
+            .map_err(|_| ReservoirError::External("a previous step panicked".to_string()))?;
+        let next = match &mut *backend {
+            Backend::Callback(callback) => callback(state, &input.clone_owned())?,
+            Backend::Channel {
+                requests,
+                responses,
+                timeout,
+            } => {
+                // Drop answers to requests that timed out earlier.
+                while responses.try_recv().is_ok() {}
+                let request = ExternalRequest {
+                    state: state.clone(),
+                    input: input.clone_owned(),
+                };
+                requests.send(request).map_err(|_| {
+                    ReservoirError::External("the endpoint is disconnected".to_string())
+                })?;
+                match responses.recv_timeout(*timeout) {
+                    Ok(response) => response?,
+                    Err(RecvTimeoutError::Timeout) => {
+                        return Err(ReservoirError::External(format!(
+                            "no response within {timeout:?}"
+                        )))
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        return Err(ReservoirError::External(
+                            "the endpoint is disconnected".to_string(),
+                        ))
+                    }
+                }
+            }
+        };
+        check_dimension("external response", self.dimension, next.nrows())?;
+        state.copy_from(&next);
+        Ok(())
+    }
+}
+
+impl<T: ReservoirValue> ReservoirTimeEvolution<T> for ExternalTimeEvolution<T> {
+    fn input_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn output_dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
+        if let Err(error) = self.try_time_evolution(state, input) {
+            panic!("{error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use nalgebra::DVector;
+
+    use super::ExternalTimeEvolution;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        time_evolution::ReservoirTimeEvolution, ReservoirError,
+    };
+
+    #[test]
+    fn external_steps_match_the_wrapped_network() {
+        let network = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, 1)
+            .build_sparse_discrete_network(Tanh);
+        let simulator = network.clone();
+        let callback = ExternalTimeEvolution::from_callback(10, move |state, input| {
+            let mut next = state.clone();
+            simulator.time_evolution(&mut next, input.column(0));
+            Ok(next)
+        });
+        let (remote, endpoint) = ExternalTimeEvolution::channel(10, Duration::from_secs(10));
+        let simulator = network.clone();
+        let server = thread::spawn(move || {
+            endpoint.serve(|request| {
+                let mut next = request.state;
+                simulator.time_evolution(&mut next, request.input.column(0));
+                Ok(next)
+            })
+        });
+
+        let (mut state, mut callback_state, mut remote_state) =
+            (DVector::zeros(10), DVector::zeros(10), DVector::zeros(10));
+        for step in 0..10 {
+            let input = DVector::from_fn(10, |i, _| ((i + step) as f64).sin());
+            network.time_evolution(&mut state, input.column(0));
+            callback.time_evolution(&mut callback_state, input.column(0));
+            remote.time_evolution(&mut remote_state, input.column(0));
+        }
+        assert_eq!(callback_state, state);
+        assert_eq!(remote_state, state);
+        drop(remote);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn external_failures_are_reported() {
+        let failing = ExternalTimeEvolution::<f64>::from_callback(2, |_, _| {
+            Err(ReservoirError::External("laser off".to_string()))
+        });
+        let mut state = DVector::zeros(2);
+        let input = DVector::zeros(2);
+        assert_eq!(
+            failing.try_time_evolution(&mut state, input.column(0)),
+            Err(ReservoirError::External("laser off".to_string()))
+        );
+
+        let wrong_size =
+            ExternalTimeEvolution::<f64>::from_callback(2, |_, _| Ok(DVector::zeros(3)));
+        assert!(matches!(
+            wrong_size.try_time_evolution(&mut state, input.column(0)),
+            Err(ReservoirError::DimensionMismatch { actual: 3, .. })
+        ));
+
+        let (silent, endpoint) =
+            ExternalTimeEvolution::<f64>::channel(2, Duration::from_millis(20));
+        let error = silent
+            .try_time_evolution(&mut state, input.column(0))
+            .unwrap_err();
+        assert!(error.to_string().contains("no response"), "{error}");
+        drop(endpoint);
+        assert!(silent
+            .try_time_evolution(&mut state, input.column(0))
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "laser off")]
+    fn failing_steps_panic_in_the_time_evolution() {
+        let failing = ExternalTimeEvolution::<f64>::from_callback(2, |_, _| {
+            Err(ReservoirError::External("laser off".to_string()))
+        });
+        failing.time_evolution(&mut DVector::zeros(2), DVector::zeros(2).column(0));
+    }
+}
@@ -0,0 +1,98 @@
+use nalgebra::Complex;
+
+// In place discrete Fourier transform, radix-2 for power of two lengths and the direct sum
+// otherwise. The inverse transform includes the 1 / n factor.
+pub(crate) fn fft(values: &mut [Complex<f64>], inverse: bool) {
+    let n = values.len();
+    if n <= 1 {
+        return;
+    }
+    if n.is_power_of_two() {
+        radix2(values, inverse);
+    } else {
+        direct(values, inverse);
+    }
+    if inverse {
+        let scale = 1. / n as f64;
+        for value in values.iter_mut() {
+            *value *= scale;
+        }
+    }
+}
+
+fn radix2(values: &mut [Complex<f64>], inverse: bool) {
+    let n = values.len();
+    let bits = n.trailing_zeros();
+    for index in 0..n {
+        let reversed = index.reverse_bits() >> (usize::BITS - bits);
+        if index < reversed {
+            values.swap(index, reversed);
+        }
+    }
+    let sign = if inverse { 1. } else { -1. };
+    let mut length = 2;
+    while length <= n {
+        let angle = sign * std::f64::consts::TAU / length as f64;
+        let root = Complex::new(angle.cos(), angle.sin());
+        for start in (0..n).step_by(length) {
+            let mut twiddle = Complex::new(1., 0.);
+            for offset in 0..length / 2 {
+                let even = values[start + offset];
+                let odd = values[start + offset + length / 2] * twiddle;
+                values[start + offset] = even + odd;
+                values[start + offset + length / 2] = even - odd;
+                twiddle *= root;
+            }
+        }
+        length *= 2;
+    }
+}
+
+fn direct(values: &mut [Complex<f64>], inverse: bool) {
+    let n = values.len();
+    let sign = if inverse { 1. } else { -1. };
+    let input = values.to_vec();
+    for (frequency, value) in values.iter_mut().enumerate() {
+        *value = input
+            .iter()
+            .enumerate()
+            .map(|(index, x)| {
+                let angle =
+                    sign * std::f64::consts::TAU * ((frequency * index) % n) as f64 / n as f64;
+                x * Complex::new(angle.cos(), angle.sin())
+            })
+            .sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fft;
+    use nalgebra::{Complex, ComplexField};
+
+    #[test]
+    fn fft_matches_direct_transform_and_inverts() {
+        for n in [8, 6] {
+            let signal: Vec<_> = (0..n)
+                .map(|i| Complex::new((i as f64 * 0.7).sin(), i as f64 * 0.1))
+                .collect();
+            let mut transformed = signal.clone();
+            fft(&mut transformed, false);
+            for (frequency, value) in transformed.iter().enumerate() {
+                let expected: Complex<f64> = signal
+                    .iter()
+                    .enumerate()
+                    .map(|(index, x)| {
+                        let angle = -std::f64::consts::TAU * (frequency * index) as f64 / n as f64;
+                        x * Complex::new(angle.cos(), angle.sin())
+                    })
+                    .sum();
+                assert!((value - expected).modulus() < 1e-12);
+            }
+            fft(&mut transformed, true);
+            for (value, original) in transformed.iter().zip(signal.iter()) {
+                assert!((value - original).modulus() < 1e-12);
+            }
+        }
+    }
+}
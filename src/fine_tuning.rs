@@ -0,0 +1,429 @@
+use nalgebra::{ClosedAdd, ClosedMul, DMatrix, DMatrixSlice, DVector};
+use nalgebra_sparse::CsrMatrix;
+use rand::{distributions::uniform::SampleUniform, rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    activation_function::ActiviationFunction,
+    echo_state_network::{LowRankCorrection, SparseDiscreteEchoStateNetwork},
+    input_projection::DefaultInputProjection,
+    output_projection::LinearStateProjection,
+    state_measurement::DefaultStateMeasurement,
+    Reservoir, ReservoirComputer, ReservoirValue,
+};
+
+// Gradient based fine tuning of a ridge trained echo state network: backpropagation through
+// time adjusts a scale of the input weights and a rank `rank` correction U Vᵀ of the coupling
+// while the readout stays fixed. The loss is the mean squared teacher forced one step error
+// after `sync_steps` steps from the zero state, the same pairing as the ridge training.
+
+pub type EchoStateNetworkComputer<T, A> = ReservoirComputer<
+    T,
+    DefaultInputProjection<T>,
+    SparseDiscreteEchoStateNetwork<T, A>,
+    DefaultStateMeasurement<T>,
+    LinearStateProjection<T>,
+>;
+
+pub type FineTunedComputer<T, A> = ReservoirComputer<
+    T,
+    DefaultInputProjection<T>,
+    LowRankCorrection<T, SparseDiscreteEchoStateNetwork<T, A>>,
+    DefaultStateMeasurement<T>,
+    LinearStateProjection<T>,
+>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FineTuningConfig {
+    pub rank: usize,
+    pub epochs: usize,
+    // Adam step size.
+    pub learning_rate: f64,
+    pub sync_steps: usize,
+    // Draws the initial V, U starts at zero so the tuning starts from the trained model.
+    pub seed: u64,
+}
+
+impl Default for FineTuningConfig {
+    fn default() -> Self {
+        Self {
+            rank: 4,
+            epochs: 50,
+            learning_rate: 1e-4,
+            sync_steps: 100,
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FineTuningReport {
+    // Loss before the first update and after every epoch.
+    losses: Vec<f64>,
+    best_epoch: usize,
+    input_scale: f64,
+}
+
+impl FineTuningReport {
+    pub fn losses(&self) -> &[f64] {
+        &self.losses
+    }
+
+    pub fn initial_loss(&self) -> f64 {
+        self.losses[0]
+    }
+
+    pub fn best_loss(&self) -> f64 {
+        self.losses[self.best_epoch]
+    }
+
+    // 0 if no update improved on the ridge trained model.
+    pub fn best_epoch(&self) -> usize {
+        self.best_epoch
+    }
+
+    pub fn input_scale(&self) -> f64 {
+        self.input_scale
+    }
+}
+
+// Returns the parameters with the lowest loss seen, with the state after driving it with
+// `data` so the last data column can serve as kickstarter.
+pub fn fine_tune<T, A>(
+    reservoir_computer: EchoStateNetworkComputer<T, A>,
+    data: DMatrixSlice<T>,
+    config: &FineTuningConfig,
+) -> (FineTunedComputer<T, A>, FineTuningReport)
+where
+    T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul,
+    A: ActiviationFunction<T>,
+{
+    assert!(
+        config.rank > 0,
+        "The correction needs a rank of at least one."
+    );
+    assert!(
+        data.ncols() > config.sync_steps + 1,
+        "Not enough data for {} synchronization steps.",
+        config.sync_steps
+    );
+    let (_, dynamics) = reservoir_computer.split_reservoir_computer_dynamics();
+    let (input_projection, network, measurement, projection) = dynamics.into_parts();
+    assert!(
+        network.state_bounds().is_none(),
+        "Clamped states can not be fine tuned."
+    );
+    assert!(
+        network
+            .activation_function()
+            .derivative(0, T::zero())
+            .is_some(),
+        "The activation function has no derivative."
+    );
+
+    let size = network.adjacency_matrix().nrows();
+    let steps = data.ncols() - 1;
+    let problem = Problem {
+        adjacency: network.adjacency_matrix(),
+        adjacency_transpose: network.adjacency_matrix().transpose(),
+        scale: network.spectral_radius_scale(),
+        activation: network.activation_function(),
+        projected_inputs: input_projection.w_in() * data.columns(0, steps),
+        targets: data.columns(1, steps),
+        w_out: projection.w_out(),
+        sync_steps: config.sync_steps,
+    };
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let spread = 1. / (size as f64).sqrt();
+    let mut parameters = DVector::zeros(1 + 2 * size * config.rank);
+    parameters[0] = T::one();
+    for value in parameters
+        .rows_mut(1 + size * config.rank, size * config.rank)
+        .iter_mut()
+    {
+        *value = T::from_f64(rng.gen_range(-spread..spread)).unwrap();
+    }
+
+    let mut optimizer = Adam::new(parameters.nrows(), config.learning_rate);
+    let (_, _, initial_loss) = problem.forward(&parameters, config.rank);
+    let mut losses = vec![initial_loss.to_f64().unwrap()];
+    let mut best = (0, parameters.clone());
+    for epoch in 1..=config.epochs {
+        let gradient = problem.gradient(&parameters, config.rank);
+        optimizer.step(&mut parameters, &gradient);
+        let (_, _, loss) = problem.forward(&parameters, config.rank);
+        let loss = loss.to_f64().unwrap();
+        if loss < losses[best.0] {
+            best = (epoch, parameters.clone());
+        }
+        losses.push(loss);
+    }
+
+    let (best_epoch, parameters) = best;
+    let (states, _, _) = problem.forward(&parameters, config.rank);
+    let (input_scale, u, v) = unpack(&parameters, size, config.rank);
+    let mut reservoir = Reservoir::new(
+        DefaultInputProjection::new_with_matrix(input_projection.w_in() * input_scale),
+        LowRankCorrection::new(network, u, v),
+    );
+    reservoir.reservoir_state = states.column(steps - 1).clone_owned();
+    let report = FineTuningReport {
+        losses,
+        best_epoch,
+        input_scale: input_scale.to_f64().unwrap(),
+    };
+    (
+        ReservoirComputer::new(reservoir, measurement, projection),
+        report,
+    )
+}
+
+struct Problem<'a, T: ReservoirValue, A: ActiviationFunction<T>> {
+    adjacency: &'a CsrMatrix<T>,
+    adjacency_transpose: CsrMatrix<T>,
+    scale: T,
+    activation: &'a A,
+    projected_inputs: DMatrix<T>,
+    targets: DMatrixSlice<'a, T>,
+    w_out: &'a DMatrix<T>,
+    sync_steps: usize,
+}
+
+impl<T: ReservoirValue, A: ActiviationFunction<T>> Problem<'_, T, A> {
+    fn loss_weight(&self) -> T {
+        T::one() / T::from_usize(self.targets.ncols() - self.sync_steps).unwrap()
+    }
+
+    // States, pre activations and loss of the teacher forced run from the zero state.
+    fn forward(&self, parameters: &DVector<T>, rank: usize) -> (DMatrix<T>, DMatrix<T>, T) {
+        let size = self.adjacency.nrows();
+        let steps = self.targets.ncols();
+        let (input_scale, u, v) = unpack(parameters, size, rank);
+        let mut states = DMatrix::zeros(size, steps);
+        let mut combined_states = DMatrix::zeros(size, steps);
+        let mut state = DVector::zeros(size);
+        let mut loss = T::zero();
+        for step in 0..steps {
+            let mut combined = self.adjacency * &state * self.scale + &u * v.tr_mul(&state);
+            combined.axpy(input_scale, &self.projected_inputs.column(step), T::one());
+            combined_states.column_mut(step).copy_from(&combined);
+            state.copy_from(&combined);
+            self.activation.invoke_slice(0, state.as_mut_slice());
+            states.column_mut(step).copy_from(&state);
+            if step >= self.sync_steps {
+                loss += (self.w_out * &state - self.targets.column(step)).norm_squared();
+            }
+        }
+        (states, combined_states, loss * self.loss_weight())
+    }
+
+    // Backpropagation through the whole run, packed like the parameters.
+    fn gradient(&self, parameters: &DVector<T>, rank: usize) -> DVector<T> {
+        let size = self.adjacency.nrows();
+        let (_, u, v) = unpack(parameters, size, rank);
+        let (states, combined_states, _) = self.forward(parameters, rank);
+        let error_weight = self.loss_weight() * T::from_f64(2.).unwrap();
+
+        let mut input_scale_gradient = T::zero();
+        let mut u_gradient = DMatrix::zeros(size, rank);
+        let mut v_gradient = DMatrix::zeros(size, rank);
+        // Derivative of the loss by the state of the current step.
+        let mut state_gradient = DVector::zeros(size);
+        let zero_state = DVector::zeros(size);
+        for step in (0..states.ncols()).rev() {
+            if step >= self.sync_steps {
+                let error = self.w_out * states.column(step) - self.targets.column(step);
+                state_gradient += self.w_out.tr_mul(&error) * error_weight;
+            }
+            let delta = DVector::from_fn(size, |node, _| {
+                state_gradient[node]
+                    * self
+                        .activation
+                        .derivative(node, combined_states[(node, step)])
+                        .unwrap()
+            });
+            let previous_state = if step > 0 {
+                states.column(step - 1).clone_owned()
+            } else {
+                zero_state.clone()
+            };
+            let u_delta = u.tr_mul(&delta);
+            input_scale_gradient += delta.dot(&self.projected_inputs.column(step));
+            u_gradient += &delta * v.tr_mul(&previous_state).transpose();
+            v_gradient += &previous_state * u_delta.transpose();
+            state_gradient = &self.adjacency_transpose * &delta * self.scale + &v * u_delta;
+        }
+
+        let mut gradient = DVector::zeros(parameters.nrows());
+        gradient[0] = input_scale_gradient;
+        gradient
+            .rows_mut(1, size * rank)
+            .copy_from_slice(u_gradient.as_slice());
+        gradient
+            .rows_mut(1 + size * rank, size * rank)
+            .copy_from_slice(v_gradient.as_slice());
+        gradient
+    }
+}
+
+// Input scale, U and V, both column major.
+fn unpack<T: ReservoirValue>(
+    parameters: &DVector<T>,
+    size: usize,
+    rank: usize,
+) -> (T, DMatrix<T>, DMatrix<T>) {
+    let values = parameters.as_slice();
+    (
+        values[0],
+        DMatrix::from_column_slice(size, rank, &values[1..1 + size * rank]),
+        DMatrix::from_column_slice(size, rank, &values[1 + size * rank..]),
+    )
+}
+
+struct Adam<T: ReservoirValue> {
+    learning_rate: T,
+    first_moment: DVector<T>,
+    second_moment: DVector<T>,
+    steps: i32,
+}
+
+impl<T: ReservoirValue> Adam<T> {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    fn new(dimension: usize, learning_rate: f64) -> Self {
+        Self {
+            learning_rate: T::from_f64(learning_rate).unwrap(),
+            first_moment: DVector::zeros(dimension),
+            second_moment: DVector::zeros(dimension),
+            steps: 0,
+        }
+    }
+
+    fn step(&mut self, parameters: &mut DVector<T>, gradient: &DVector<T>) {
+        let beta1 = T::from_f64(Self::BETA1).unwrap();
+        let beta2 = T::from_f64(Self::BETA2).unwrap();
+        let epsilon = T::from_f64(Self::EPSILON).unwrap();
+        self.steps += 1;
+        self.first_moment = &self.first_moment * beta1 + gradient * (T::one() - beta1);
+        self.second_moment =
+            &self.second_moment * beta2 + gradient.component_mul(gradient) * (T::one() - beta2);
+        let first_correction = T::one() - num_traits::Float::powi(beta1, self.steps);
+        let second_correction = T::one() - num_traits::Float::powi(beta2, self.steps);
+        for (parameter, (first, second)) in parameters
+            .iter_mut()
+            .zip(self.first_moment.iter().zip(self.second_moment.iter()))
+        {
+            let second = num_traits::Float::sqrt(*second / second_correction);
+            *parameter -= self.learning_rate * (*first / first_correction) / (second + epsilon);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::{fine_tune, unpack, FineTuningConfig, Problem};
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        hyperparameter::SpectralRadius, input_projection::DefaultInputProjection,
+        output_projection::LinearStateProjection, state_measurement::DefaultStateMeasurement,
+        Reservoir, ReservoirComputer,
+    };
+
+    fn data() -> DMatrix<f64> {
+        DMatrix::from_fn(2, 300, |i, j| {
+            let t = j as f64 * 0.1;
+            if i == 0 {
+                t.sin() + 0.3 * (2.3 * t).sin()
+            } else {
+                t.cos() * (0.7 * t).sin()
+            }
+        })
+    }
+
+    fn ridge_model(
+        data: &DMatrix<f64>,
+        sync_steps: usize,
+    ) -> super::EchoStateNetworkComputer<f64, Tanh> {
+        let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(40, 4, 8);
+        builder.spectral_radius(SpectralRadius::new(0.8).unwrap());
+        let mut reservoir = Reservoir::new(
+            DefaultInputProjection::new_random_seeded(2, 40, 0.5, 9),
+            builder.build_sparse_discrete_network(Tanh),
+        );
+        let steps = data.ncols() - 1;
+        let states = reservoir.record_states(data.columns(0, steps), sync_steps);
+        let projection = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-6,
+            &states,
+            data.columns(1 + sync_steps, steps - sync_steps),
+        );
+        ReservoirComputer::new(reservoir, DefaultStateMeasurement::new(40), projection)
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences() {
+        let data = data().columns(0, 60).clone_owned();
+        let model = ridge_model(&data, 10);
+        let (_, dynamics) = model.split_reservoir_computer_dynamics();
+        let (input_projection, network, _, projection) = dynamics.into_parts();
+        let problem = Problem {
+            adjacency: network.adjacency_matrix(),
+            adjacency_transpose: network.adjacency_matrix().transpose(),
+            scale: network.spectral_radius_scale(),
+            activation: &Tanh,
+            projected_inputs: input_projection.w_in() * data.columns(0, 59),
+            targets: data.columns(1, 59),
+            w_out: projection.w_out(),
+            sync_steps: 10,
+        };
+        let parameters = DVector::from_fn(1 + 2 * 40 * 2, |i, _| {
+            if i == 0 {
+                1.1
+            } else {
+                0.05 * (i as f64 * 0.37).sin()
+            }
+        });
+        let gradient = problem.gradient(&parameters, 2);
+        for index in [0, 1, 17, 80, 81, 120, 160] {
+            let mut shifted = parameters.clone();
+            shifted[index] += 1e-6;
+            let (_, _, forward) = problem.forward(&shifted, 2);
+            shifted[index] -= 2e-6;
+            let (_, _, backward) = problem.forward(&shifted, 2);
+            let expected = (forward - backward) / 2e-6;
+            assert!(
+                (gradient[index] - expected).abs() < 1e-6 * (1. + expected.abs()),
+                "{index}: {} vs {expected}",
+                gradient[index]
+            );
+        }
+        let (scale, u, v) = unpack(&parameters, 40, 2);
+        assert_eq!(
+            (scale, u[(0, 0)], v[(0, 0)]),
+            (1.1, parameters[1], parameters[81])
+        );
+    }
+
+    #[test]
+    fn fine_tuning_lowers_the_training_loss() {
+        let data = data();
+        let config = FineTuningConfig {
+            rank: 2,
+            epochs: 30,
+            sync_steps: 50,
+            ..FineTuningConfig::default()
+        };
+        let (mut model, report) = fine_tune(ridge_model(&data, 50), data.columns(0, 300), &config);
+        assert_eq!(report.losses().len(), 31);
+        assert!(report.best_epoch() > 0);
+        assert!(report.best_loss() < report.initial_loss());
+
+        let predictions = model.one_step_predictions(data.columns(0, 300), 50);
+        let loss = (predictions - data.columns(51, 249)).norm_squared() / 249.;
+        assert!((loss - report.best_loss()).abs() < 1e-9 * report.best_loss().max(1.));
+    }
+}
@@ -0,0 +1,94 @@
+use nalgebra::{ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice};
+use rand::{distributions::uniform::SampleUniform, thread_rng, Rng};
+
+use crate::{
+    activation_function::Tanh,
+    echo_state_network::EchoStateNetworkBuilder,
+    hyperparameter::{Regularization, SpectralRadius},
+    input_projection::DefaultInputProjection,
+    reservoir::training::ReservoirTraining,
+    state_measurement::DefaultStateMeasurement,
+    Reservoir, ReservoirValue,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FitPredictConfig {
+    pub reservoir_size: usize,
+    pub average_degree: usize,
+    pub spectral_radius: SpectralRadius<f64>,
+    pub input_strength: f64,
+    pub beta: Regularization<f64>,
+    pub train_sync_steps: usize,
+    // Draws a fresh reservoir on every call if not set.
+    pub seed: Option<u64>,
+}
+
+impl Default for FitPredictConfig {
+    fn default() -> Self {
+        Self {
+            reservoir_size: 300,
+            average_degree: 6,
+            spectral_radius: SpectralRadius::new(0.9).unwrap(),
+            input_strength: 1.0,
+            beta: Regularization::new(1e-6).unwrap(),
+            train_sync_steps: 100,
+            seed: None,
+        }
+    }
+}
+
+// Builds a tanh echo state network, trains a ridge readout on `train_data` (columns are time
+// steps) and predicts `horizon` steps in closed loop. The reservoir state continues from the
+// end of the training data, so the kickstarter is usually its last column.
+pub fn fit_predict<T>(
+    config: &FitPredictConfig,
+    train_data: DMatrix<T>,
+    kickstarter: DMatrixSlice<T>,
+    horizon: usize,
+) -> DMatrix<T>
+where
+    T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul + SampleUniform,
+{
+    assert!(
+        train_data.ncols() > config.train_sync_steps + 1,
+        "Not enough training data for {} synchronization steps.",
+        config.train_sync_steps
+    );
+    let dimension = train_data.nrows();
+    let seed = config.seed.unwrap_or_else(|| thread_rng().gen());
+
+    let mut esn_builder = EchoStateNetworkBuilder::<T>::random_seeded(
+        config.reservoir_size,
+        config.average_degree,
+        seed,
+    );
+    esn_builder.spectral_radius(
+        SpectralRadius::try_from(config.spectral_radius.get())
+            .unwrap_or_else(|error| panic!("{error}")),
+    );
+    let esn = esn_builder.build_sparse_discrete_network(Tanh);
+    let input_projection = DefaultInputProjection::new_random_seeded(
+        dimension,
+        config.reservoir_size,
+        T::from_f64(config.input_strength).unwrap(),
+        seed.wrapping_add(1),
+    );
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let mut training = ReservoirTraining::new(
+        config.train_sync_steps,
+        train_data.ncols() - config.train_sync_steps,
+        0,
+        0,
+    );
+    training.add_data(train_data);
+    let mut reservoir_computer = training.train_via_grouped_ridge_regression(
+        &[(
+            (0..dimension).collect(),
+            T::from_f64(config.beta.get()).unwrap(),
+        )],
+        reservoir,
+        DefaultStateMeasurement::new(config.reservoir_size),
+    );
+    reservoir_computer.predict_from_recent(kickstarter, horizon)
+}
@@ -0,0 +1,387 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::error::ReservoirError;
+
+// Construction parameters and seed of a randomly generated component. The text form
+// `kind key=value ...` can be stored and parsed again, replaying it with the matching
+// `from_recipe` regenerates identical weights as long as the rand version (and with it the
+// `StdRng` stream) stays the same.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerationRecipe {
+    EchoStateNetwork {
+        size: usize,
+        average_degree: usize,
+        seed: u64,
+        generator: NetworkGenerator,
+        transforms: Vec<AdjacencyTransform>,
+    },
+    DefaultInputProjection {
+        input_dimension: usize,
+        output_dimension: usize,
+        input_strength: f64,
+        seed: u64,
+    },
+    InputProjectionWithEmbedding {
+        system_dimension: usize,
+        output_dimension: usize,
+        column_offsets: Vec<usize>,
+        embedded_channels: Vec<usize>,
+        seed: u64,
+    },
+}
+
+// How the links of a random echo state network were drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkGenerator {
+    Bernoulli,
+    RowStreams,
+    FixedInDegree,
+}
+
+impl Display for NetworkGenerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkGenerator::Bernoulli => write!(f, "bernoulli"),
+            NetworkGenerator::RowStreams => write!(f, "row_streams"),
+            NetworkGenerator::FixedInDegree => write!(f, "fixed_in_degree"),
+        }
+    }
+}
+
+impl FromStr for NetworkGenerator {
+    type Err = ReservoirError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bernoulli" => Ok(NetworkGenerator::Bernoulli),
+            "row_streams" => Ok(NetworkGenerator::RowStreams),
+            "fixed_in_degree" => Ok(NetworkGenerator::FixedInDegree),
+            _ => Err(ReservoirError::InvalidRecipe(format!(
+                "unknown generator {s}"
+            ))),
+        }
+    }
+}
+
+// Post generation steps of the echo state network builder, replayed in order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdjacencyTransform {
+    SpectralRadius(f64),
+    LargestSingularValue(f64),
+    SelfLoops { probability: f64, weight: f64 },
+    Diagonal(f64),
+    Symmetrize,
+    Antisymmetrize,
+    Ternarize(f64),
+    Binarize,
+}
+
+impl Display for AdjacencyTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdjacencyTransform::SpectralRadius(radius) => write!(f, "spectral_radius:{radius}"),
+            AdjacencyTransform::LargestSingularValue(value) => {
+                write!(f, "largest_singular_value:{value}")
+            }
+            AdjacencyTransform::SelfLoops {
+                probability,
+                weight,
+            } => write!(f, "self_loops:{probability}:{weight}"),
+            AdjacencyTransform::Diagonal(value) => write!(f, "diagonal:{value}"),
+            AdjacencyTransform::Symmetrize => write!(f, "symmetrize"),
+            AdjacencyTransform::Antisymmetrize => write!(f, "antisymmetrize"),
+            AdjacencyTransform::Ternarize(weight) => write!(f, "ternarize:{weight}"),
+            AdjacencyTransform::Binarize => write!(f, "binarize"),
+        }
+    }
+}
+
+impl FromStr for AdjacencyTransform {
+    type Err = ReservoirError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ReservoirError::InvalidRecipe(format!("unknown transform {s}"));
+        let (kind, argument) = s.split_once(':').unwrap_or((s, ""));
+        match (kind, argument) {
+            ("spectral_radius", radius) => Ok(AdjacencyTransform::SpectralRadius(
+                radius.parse().map_err(|_| invalid())?,
+            )),
+            ("largest_singular_value", value) => Ok(AdjacencyTransform::LargestSingularValue(
+                value.parse().map_err(|_| invalid())?,
+            )),
+            ("self_loops", arguments) => {
+                let (probability, weight) = arguments.split_once(':').ok_or_else(invalid)?;
+                Ok(AdjacencyTransform::SelfLoops {
+                    probability: probability.parse().map_err(|_| invalid())?,
+                    weight: weight.parse().map_err(|_| invalid())?,
+                })
+            }
+            ("diagonal", value) => Ok(AdjacencyTransform::Diagonal(
+                value.parse().map_err(|_| invalid())?,
+            )),
+            ("symmetrize", "") => Ok(AdjacencyTransform::Symmetrize),
+            ("antisymmetrize", "") => Ok(AdjacencyTransform::Antisymmetrize),
+            ("ternarize", weight) => Ok(AdjacencyTransform::Ternarize(
+                weight.parse().map_err(|_| invalid())?,
+            )),
+            ("binarize", "") => Ok(AdjacencyTransform::Binarize),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+fn join<V: Display>(values: &[V]) -> String {
+    values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Display for GenerationRecipe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationRecipe::EchoStateNetwork {
+                size,
+                average_degree,
+                seed,
+                generator,
+                transforms,
+            } => write!(
+                f,
+                "echo_state_network size={size} average_degree={average_degree} seed={seed} generator={generator} transforms={}",
+                join(transforms)
+            ),
+            GenerationRecipe::DefaultInputProjection {
+                input_dimension,
+                output_dimension,
+                input_strength,
+                seed,
+            } => write!(
+                f,
+                "default_input_projection input_dimension={input_dimension} output_dimension={output_dimension} input_strength={input_strength} seed={seed}"
+            ),
+            GenerationRecipe::InputProjectionWithEmbedding {
+                system_dimension,
+                output_dimension,
+                column_offsets,
+                embedded_channels,
+                seed,
+            } => write!(
+                f,
+                "input_projection_with_embedding system_dimension={system_dimension} output_dimension={output_dimension} column_offsets={} embedded_channels={} seed={seed}",
+                join(column_offsets),
+                join(embedded_channels)
+            ),
+        }
+    }
+}
+
+struct RecipeFields<'a>(HashMap<&'a str, &'a str>);
+
+impl<'a> RecipeFields<'a> {
+    fn value<V: FromStr>(&self, key: &str) -> Result<V, ReservoirError> {
+        let value = self
+            .0
+            .get(key)
+            .ok_or_else(|| ReservoirError::InvalidRecipe(format!("missing field {key}")))?;
+        value
+            .parse()
+            .map_err(|_| ReservoirError::InvalidRecipe(format!("invalid value for {key}")))
+    }
+
+    fn list<V: FromStr>(&self, key: &str) -> Result<Vec<V>, ReservoirError> {
+        let values = self
+            .0
+            .get(key)
+            .ok_or_else(|| ReservoirError::InvalidRecipe(format!("missing field {key}")))?;
+        values
+            .split(',')
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| ReservoirError::InvalidRecipe(format!("invalid value for {key}")))
+            })
+            .collect()
+    }
+}
+
+impl FromStr for GenerationRecipe {
+    type Err = ReservoirError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = s.split_whitespace();
+        let kind = tokens
+            .next()
+            .ok_or_else(|| ReservoirError::InvalidRecipe("empty recipe".to_string()))?;
+        let fields = RecipeFields(
+            tokens
+                .map(|token| {
+                    token.split_once('=').ok_or_else(|| {
+                        ReservoirError::InvalidRecipe(format!("expected key=value, got {token}"))
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+        );
+
+        match kind {
+            "echo_state_network" => Ok(GenerationRecipe::EchoStateNetwork {
+                size: fields.value("size")?,
+                average_degree: fields.value("average_degree")?,
+                seed: fields.value("seed")?,
+                generator: fields.value("generator")?,
+                transforms: fields.list("transforms")?,
+            }),
+            "default_input_projection" => Ok(GenerationRecipe::DefaultInputProjection {
+                input_dimension: fields.value("input_dimension")?,
+                output_dimension: fields.value("output_dimension")?,
+                input_strength: fields.value("input_strength")?,
+                seed: fields.value("seed")?,
+            }),
+            "input_projection_with_embedding" => {
+                Ok(GenerationRecipe::InputProjectionWithEmbedding {
+                    system_dimension: fields.value("system_dimension")?,
+                    output_dimension: fields.value("output_dimension")?,
+                    column_offsets: fields.list("column_offsets")?,
+                    embedded_channels: fields.list("embedded_channels")?,
+                    seed: fields.value("seed")?,
+                })
+            }
+            _ => Err(ReservoirError::InvalidRecipe(format!(
+                "unknown component {kind}"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AdjacencyTransform, GenerationRecipe, NetworkGenerator};
+    use crate::{
+        activation_function::ActivationFunctionWrapper,
+        echo_state_network::EchoStateNetworkBuilder,
+        hyperparameter::SpectralRadius,
+        input_projection::{DefaultInputProjection, InputProjectionWithEmbedding},
+        ReservoirError,
+    };
+
+    #[test]
+    fn recipe_round_trip() {
+        let recipes = [
+            GenerationRecipe::EchoStateNetwork {
+                size: 100,
+                average_degree: 6,
+                seed: 42,
+                generator: NetworkGenerator::FixedInDegree,
+                transforms: vec![
+                    AdjacencyTransform::Symmetrize,
+                    AdjacencyTransform::SpectralRadius(0.1 + 0.2),
+                    AdjacencyTransform::Antisymmetrize,
+                    AdjacencyTransform::LargestSingularValue(0.95),
+                    AdjacencyTransform::SelfLoops {
+                        probability: 0.25,
+                        weight: -0.5,
+                    },
+                    AdjacencyTransform::Diagonal(0.1),
+                    AdjacencyTransform::Ternarize(0.25),
+                    AdjacencyTransform::Binarize,
+                ],
+            },
+            GenerationRecipe::DefaultInputProjection {
+                input_dimension: 3,
+                output_dimension: 100,
+                input_strength: 1.0 / 3.0,
+                seed: u64::MAX,
+            },
+            GenerationRecipe::InputProjectionWithEmbedding {
+                system_dimension: 3,
+                output_dimension: 100,
+                column_offsets: vec![0, 1, 3],
+                embedded_channels: vec![],
+                seed: 7,
+            },
+        ];
+        for recipe in recipes {
+            assert_eq!(recipe.to_string().parse::<GenerationRecipe>(), Ok(recipe));
+        }
+
+        assert!("echo_state_network size=100"
+            .parse::<GenerationRecipe>()
+            .is_err());
+        assert!("reservoir size=100".parse::<GenerationRecipe>().is_err());
+    }
+
+    #[test]
+    fn recipe_replay_regenerates_weights() {
+        let mut builder = EchoStateNetworkBuilder::<f64>::random(60, 4);
+        builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+        let replayed = EchoStateNetworkBuilder::<f64>::from_recipe(
+            &builder.recipe().to_string().parse().unwrap(),
+        )
+        .unwrap();
+        let tanh = || ActivationFunctionWrapper::new(|_, v: f64| v.tanh());
+        assert_eq!(
+            builder
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix(),
+            replayed
+                .build_sparse_discrete_network(tanh())
+                .adjacency_matrix()
+        );
+
+        let projection = DefaultInputProjection::<f64>::new_random(3, 60, 0.5);
+        let replayed = DefaultInputProjection::<f64>::from_recipe(
+            &projection.recipe().unwrap().to_string().parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(projection.w_in(), replayed.w_in());
+
+        let projection = InputProjectionWithEmbedding::<f64>::new_random_with_embedded_channels(
+            3,
+            60,
+            &[1, 4],
+            &[2],
+        );
+        let replayed = InputProjectionWithEmbedding::<f64>::from_recipe(
+            &projection.recipe().unwrap().to_string().parse().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(projection.w_in(), replayed.w_in());
+        assert_eq!(projection.column_offsets(), replayed.column_offsets());
+        assert_eq!(projection.embedded_channels(), replayed.embedded_channels());
+
+        let projection = DefaultInputProjection::<f64>::new_with_matrix(projection.w_in().clone());
+        assert!(projection.recipe().is_none());
+    }
+
+    #[test]
+    fn replaying_a_recipe_of_another_kind_is_an_error() {
+        let network_recipe = EchoStateNetworkBuilder::<f64>::random_seeded(20, 3, 1).recipe();
+        let projection_recipe = DefaultInputProjection::<f64>::new_random_seeded(2, 20, 0.5, 1)
+            .recipe()
+            .unwrap()
+            .clone();
+        assert!(matches!(
+            EchoStateNetworkBuilder::<f64>::from_recipe(&projection_recipe),
+            Err(ReservoirError::InvalidRecipe(_))
+        ));
+        assert!(matches!(
+            DefaultInputProjection::<f64>::from_recipe(&network_recipe),
+            Err(ReservoirError::InvalidRecipe(_))
+        ));
+        assert!(matches!(
+            InputProjectionWithEmbedding::<f64>::from_recipe(&projection_recipe),
+            Err(ReservoirError::InvalidRecipe(_))
+        ));
+        assert!(matches!(
+            EchoStateNetworkBuilder::<f64>::from_recipe(
+                &"echo_state_network size=20 average_degree=3 seed=1 generator=bernoulli \
+                  transforms=spectral_radius:-1"
+                    .parse()
+                    .unwrap()
+            ),
+            Err(ReservoirError::InvalidHyperparameter(_))
+        ));
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::Debug;
+
+use nalgebra::{DMatrix, DMatrixSlice, DVector, DVectorSlice};
+
+use crate::{
+    input_projection::ReservoirInputProjection, output_projection::ReservoirStateProjection,
+    state_measurement::ReservoirStateMeasurement, time_evolution::ReservoirTimeEvolution,
+    Reservoir, ReservoirValue,
+};
+
+use super::KnowledgeBasedModel;
+
+// The readout sees the measured reservoir state extended by the one-step prediction of the
+// knowledge-based model. If `feed_model_into_reservoir` is set the input projection receives
+// the input extended by the model prediction as well.
+#[derive(Debug)]
+pub struct HybridReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+    K: KnowledgeBasedModel<T>,
+{
+    pub(crate) reservoir: Reservoir<T, I, E>,
+    pub(crate) reservoir_state_measurement: M,
+    pub(crate) reservoir_state_projection: P,
+    pub(crate) knowledge_based_model: K,
+    pub(crate) feed_model_into_reservoir: bool,
+    pub(crate) reservoir_input: DVector<T>,
+    pub(crate) model_prediction: DVector<T>,
+    pub(crate) features: DVector<T>,
+}
+
+impl<T, I, E, M, P, K> HybridReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+    K: KnowledgeBasedModel<T>,
+{
+    pub fn new(
+        reservoir: Reservoir<T, I, E>,
+        measurement: M,
+        projection: P,
+        knowledge_based_model: K,
+        feed_model_into_reservoir: bool,
+    ) -> Self {
+        let model_dimension = knowledge_based_model.dimension();
+        let reservoir_input_dimension = reservoir.input_projection().input_dimension();
+        assert_eq!(reservoir.input_projection().required_input_columns(), 1);
+        if feed_model_into_reservoir {
+            assert_eq!(reservoir_input_dimension, 2 * model_dimension);
+        } else {
+            assert_eq!(reservoir_input_dimension, model_dimension);
+        }
+        assert_eq!(
+            projection.input_dimension(),
+            measurement.output_dimension() + model_dimension
+        );
+
+        Self {
+            reservoir_input: DVector::zeros(reservoir_input_dimension),
+            model_prediction: DVector::zeros(model_dimension),
+            features: DVector::zeros(measurement.output_dimension() + model_dimension),
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: projection,
+            knowledge_based_model,
+            feed_model_into_reservoir,
+        }
+    }
+
+    pub fn state(&self) -> &DVector<T> {
+        self.reservoir.state()
+    }
+
+    pub fn reservoir(&self) -> &Reservoir<T, I, E> {
+        &self.reservoir
+    }
+
+    pub fn state_measurement(&self) -> &M {
+        &self.reservoir_state_measurement
+    }
+
+    pub fn state_projection(&self) -> &P {
+        &self.reservoir_state_projection
+    }
+
+    pub fn knowledge_based_model(&self) -> &K {
+        &self.knowledge_based_model
+    }
+
+    pub fn feeds_model_into_reservoir(&self) -> bool {
+        self.feed_model_into_reservoir
+    }
+
+    fn drive(&mut self, input: DVectorSlice<T>) {
+        let model_dimension = self.model_prediction.nrows();
+        self.knowledge_based_model
+            .predict_into(input, self.model_prediction.column_mut(0));
+
+        self.reservoir_input
+            .rows_mut(0, model_dimension)
+            .copy_from(&input);
+        if self.feed_model_into_reservoir {
+            self.reservoir_input
+                .rows_mut(model_dimension, model_dimension)
+                .copy_from(&self.model_prediction);
+        }
+        self.reservoir
+            .synchronize_state(self.reservoir_input.columns(0, 1));
+    }
+
+    pub fn synchronize_state(&mut self, input: DMatrixSlice<T>) {
+        for column in input.column_iter() {
+            self.drive(column);
+        }
+    }
+
+    pub fn step(&mut self, input: DVectorSlice<T>) -> &DVector<T> {
+        self.drive(input);
+
+        let model_dimension = self.model_prediction.nrows();
+        let state_measurement = self
+            .reservoir_state_measurement
+            .measure(&self.reservoir.reservoir_state);
+        let measurement_dimension = state_measurement.nrows();
+        self.features
+            .rows_mut(0, measurement_dimension)
+            .copy_from(state_measurement);
+        self.features
+            .rows_mut(measurement_dimension, model_dimension)
+            .copy_from(&self.model_prediction);
+        self.reservoir_state_projection.project(&self.features)
+    }
+
+    pub fn predict(&mut self, kickstarter: DVectorSlice<T>, predict_steps: usize) -> DMatrix<T> {
+        let mut result = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            predict_steps,
+        );
+        if predict_steps == 0 {
+            return result;
+        }
+
+        let prediction = self.step(kickstarter);
+        result.column_mut(0).copy_from(prediction);
+        let mut current = result.column(0).clone_owned();
+        for step in 1..predict_steps {
+            let prediction = self.step(current.column(0));
+            result.column_mut(step).copy_from(prediction);
+            current.copy_from(&result.column(step));
+        }
+        result
+    }
+}
+
+impl<T, I, E, M, P, K> Clone for HybridReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue + Clone,
+    I: ReservoirInputProjection<T> + Clone,
+    E: ReservoirTimeEvolution<T> + Clone,
+    M: ReservoirStateMeasurement<T> + Clone,
+    P: ReservoirStateProjection<T> + Clone,
+    K: KnowledgeBasedModel<T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reservoir: self.reservoir.clone(),
+            reservoir_state_measurement: self.reservoir_state_measurement.clone(),
+            reservoir_state_projection: self.reservoir_state_projection.clone(),
+            knowledge_based_model: self.knowledge_based_model.clone(),
+            feed_model_into_reservoir: self.feed_model_into_reservoir,
+            reservoir_input: self.reservoir_input.clone(),
+            model_prediction: self.model_prediction.clone(),
+            features: self.features.clone(),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use nalgebra::{DVectorSlice, DVectorSliceMut};
+
+use crate::ReservoirValue;
+
+pub mod hybrid_reservoir_computer;
+pub mod residual_reservoir_computer;
+
+pub use hybrid_reservoir_computer::HybridReservoirComputer;
+pub use residual_reservoir_computer::ResidualReservoirComputer;
+
+pub trait KnowledgeBasedModel<T: ReservoirValue>: Debug {
+    fn dimension(&self) -> usize;
+
+    fn predict_into(&self, input: DVectorSlice<T>, target: DVectorSliceMut<T>);
+}
+
+impl<T: ReservoirValue, K: KnowledgeBasedModel<T>> KnowledgeBasedModel<T> for Box<K> {
+    fn dimension(&self) -> usize {
+        (**self).dimension()
+    }
+
+    fn predict_into(&self, input: DVectorSlice<T>, target: DVectorSliceMut<T>) {
+        (**self).predict_into(input, target);
+    }
+}
+
+impl<T: ReservoirValue> KnowledgeBasedModel<T> for Box<dyn KnowledgeBasedModel<T>> {
+    fn dimension(&self) -> usize {
+        (**self).dimension()
+    }
+
+    fn predict_into(&self, input: DVectorSlice<T>, target: DVectorSliceMut<T>) {
+        (**self).predict_into(input, target);
+    }
+}
+
+pub struct KnowledgeBasedModelWrapper<T: ReservoirValue, F: Fn(DVectorSlice<T>, DVectorSliceMut<T>)>
+{
+    dimension: usize,
+    func: F,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: ReservoirValue, F: Fn(DVectorSlice<T>, DVectorSliceMut<T>)>
+    KnowledgeBasedModelWrapper<T, F>
+{
+    pub fn new(dimension: usize, f: F) -> Self {
+        Self {
+            dimension,
+            func: f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(DVectorSlice<T>, DVectorSliceMut<T>)> Debug
+    for KnowledgeBasedModelWrapper<T, F>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "KnowledgeBasedModelWrapper{{ {} }}", self.dimension)
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(DVectorSlice<T>, DVectorSliceMut<T>)> KnowledgeBasedModel<T>
+    for KnowledgeBasedModelWrapper<T, F>
+{
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn predict_into(&self, input: DVectorSlice<T>, target: DVectorSliceMut<T>) {
+        (self.func)(input, target);
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(DVectorSlice<T>, DVectorSliceMut<T>) + Clone> Clone
+    for KnowledgeBasedModelWrapper<T, F>
+{
+    fn clone(&self) -> Self {
+        Self {
+            dimension: self.dimension,
+            func: self.func.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
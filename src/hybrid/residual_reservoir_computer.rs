@@ -0,0 +1,132 @@
+use std::fmt::Debug;
+
+use nalgebra::{DMatrix, DMatrixSlice, DVector, DVectorSlice};
+
+use crate::{
+    input_projection::ReservoirInputProjection, output_projection::ReservoirStateProjection,
+    state_measurement::ReservoirStateMeasurement, time_evolution::ReservoirTimeEvolution,
+    Reservoir, ReservoirValue,
+};
+
+use super::KnowledgeBasedModel;
+
+// The readout learns the error of the baseline predictor, predictions are the baseline
+// prediction plus the learned correction.
+#[derive(Debug)]
+pub struct ResidualReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+    K: KnowledgeBasedModel<T>,
+{
+    pub(crate) reservoir: Reservoir<T, I, E>,
+    pub(crate) reservoir_state_measurement: M,
+    pub(crate) reservoir_state_projection: P,
+    pub(crate) baseline: K,
+    pub(crate) prediction: DVector<T>,
+}
+
+impl<T, I, E, M, P, K> ResidualReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+    K: KnowledgeBasedModel<T>,
+{
+    pub fn new(reservoir: Reservoir<T, I, E>, measurement: M, projection: P, baseline: K) -> Self {
+        assert_eq!(reservoir.input_projection().required_input_columns(), 1);
+        assert_eq!(
+            reservoir.input_projection().input_dimension(),
+            baseline.dimension()
+        );
+        assert_eq!(projection.input_dimension(), measurement.output_dimension());
+        assert_eq!(projection.output_dimension(), baseline.dimension());
+
+        Self {
+            prediction: DVector::zeros(baseline.dimension()),
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: projection,
+            baseline,
+        }
+    }
+
+    pub fn state(&self) -> &DVector<T> {
+        self.reservoir.state()
+    }
+
+    pub fn reservoir(&self) -> &Reservoir<T, I, E> {
+        &self.reservoir
+    }
+
+    pub fn state_measurement(&self) -> &M {
+        &self.reservoir_state_measurement
+    }
+
+    pub fn state_projection(&self) -> &P {
+        &self.reservoir_state_projection
+    }
+
+    pub fn baseline(&self) -> &K {
+        &self.baseline
+    }
+
+    pub fn synchronize_state(&mut self, input: DMatrixSlice<T>) {
+        self.reservoir.synchronize_state(input);
+    }
+
+    pub fn step(&mut self, input: DVectorSlice<T>) -> &DVector<T> {
+        self.baseline
+            .predict_into(input, self.prediction.column_mut(0));
+        self.reservoir.synchronize_state(input.columns(0, 1));
+
+        let state_measurement = self
+            .reservoir_state_measurement
+            .measure(&self.reservoir.reservoir_state);
+        let correction = self.reservoir_state_projection.project(state_measurement);
+        self.prediction += correction;
+        &self.prediction
+    }
+
+    pub fn predict(&mut self, kickstarter: DVectorSlice<T>, predict_steps: usize) -> DMatrix<T> {
+        let mut result = DMatrix::zeros(self.baseline.dimension(), predict_steps);
+        if predict_steps == 0 {
+            return result;
+        }
+
+        let prediction = self.step(kickstarter);
+        result.column_mut(0).copy_from(prediction);
+        let mut current = result.column(0).clone_owned();
+        for step in 1..predict_steps {
+            let prediction = self.step(current.column(0));
+            result.column_mut(step).copy_from(prediction);
+            current.copy_from(&result.column(step));
+        }
+        result
+    }
+}
+
+impl<T, I, E, M, P, K> Clone for ResidualReservoirComputer<T, I, E, M, P, K>
+where
+    T: ReservoirValue + Clone,
+    I: ReservoirInputProjection<T> + Clone,
+    E: ReservoirTimeEvolution<T> + Clone,
+    M: ReservoirStateMeasurement<T> + Clone,
+    P: ReservoirStateProjection<T> + Clone,
+    K: KnowledgeBasedModel<T> + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            reservoir: self.reservoir.clone(),
+            reservoir_state_measurement: self.reservoir_state_measurement.clone(),
+            reservoir_state_projection: self.reservoir_state_projection.clone(),
+            baseline: self.baseline.clone(),
+            prediction: self.prediction.clone(),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{ReservoirError, ReservoirValue};
+
+// Validated hyperparameters, invalid values are rejected on construction instead of showing up
+// as NaN predictions later on.
+
+// Finite and positive, may be larger than one.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct SpectralRadius<T: ReservoirValue>(T);
+
+// In (0, 1], one gives the non leaky network.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct LeakRate<T: ReservoirValue>(T);
+
+// Ridge regression beta, finite and not negative.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Regularization<T: ReservoirValue>(T);
+
+fn invalid<T: ReservoirValue>(name: &'static str, value: T, range: &str) -> ReservoirError {
+    ReservoirError::InvalidHyperparameter(format!("{name} {value} is not in {range}"))
+}
+
+impl<T: ReservoirValue> SpectralRadius<T> {
+    pub fn new(value: T) -> Result<Self, ReservoirError> {
+        if num_traits::Float::is_finite(value) && value > T::zero() {
+            Ok(Self(value))
+        } else {
+            Err(invalid("Spectral radius", value, "(0, inf)"))
+        }
+    }
+
+    pub fn get(self) -> T {
+        self.0
+    }
+}
+
+impl<T: ReservoirValue> LeakRate<T> {
+    pub fn new(value: T) -> Result<Self, ReservoirError> {
+        if value > T::zero() && value <= T::one() {
+            Ok(Self(value))
+        } else {
+            Err(invalid("Leak rate", value, "(0, 1]"))
+        }
+    }
+
+    pub fn get(self) -> T {
+        self.0
+    }
+}
+
+impl<T: ReservoirValue> Regularization<T> {
+    pub fn new(value: T) -> Result<Self, ReservoirError> {
+        if num_traits::Float::is_finite(value) && value >= T::zero() {
+            Ok(Self(value))
+        } else {
+            Err(invalid("Regularization", value, "[0, inf)"))
+        }
+    }
+
+    pub fn get(self) -> T {
+        self.0
+    }
+}
+
+macro_rules! impl_conversions {
+    ($name:ident) => {
+        impl<T: ReservoirValue> TryFrom<f64> for $name<T> {
+            type Error = ReservoirError;
+
+            fn try_from(value: f64) -> Result<Self, Self::Error> {
+                Self::new(T::from_f64(value).unwrap())
+            }
+        }
+
+        impl<T: ReservoirValue> Display for $name<T> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+    };
+}
+
+impl_conversions!(SpectralRadius);
+impl_conversions!(LeakRate);
+impl_conversions!(Regularization);
+
+#[cfg(test)]
+mod tests {
+    use super::{LeakRate, Regularization, SpectralRadius};
+
+    #[test]
+    fn hyperparameter_validation() {
+        assert_eq!(SpectralRadius::new(1.2).unwrap().get(), 1.2);
+        assert!(SpectralRadius::new(0.).is_err());
+        assert!(SpectralRadius::new(-0.9).is_err());
+        assert!(SpectralRadius::<f64>::try_from(f64::NAN).is_err());
+        assert!(LeakRate::new(1.).is_ok());
+        assert!(LeakRate::new(1.1).is_err());
+        assert!(LeakRate::new(0.).is_err());
+        assert!(Regularization::new(0.).is_ok());
+        assert!(Regularization::new(-1e-6).is_err());
+        assert!(Regularization::<f32>::try_from(f64::INFINITY).is_err());
+    }
+}
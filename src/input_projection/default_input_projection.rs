@@ -1,27 +1,38 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use nalgebra::{
     ClosedAdd, ClosedMul, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut,
 };
 use rand::{
     distributions::{uniform::SampleUniform, Distribution, Uniform},
-    thread_rng,
+    rngs::StdRng,
+    thread_rng, Rng, SeedableRng,
 };
 
 use super::ReservoirInputProjection;
-use crate::ReservoirValue;
+use crate::{generation_recipe::GenerationRecipe, ReservoirError, ReservoirValue};
 
 #[derive(Clone, Debug)]
 pub struct DefaultInputProjection<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> {
-    w_in: DMatrix<T>,
+    w_in: Arc<DMatrix<T>>,
     result: DVector<T>,
+    recipe: Option<GenerationRecipe>,
 }
 
 impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> DefaultInputProjection<T> {
     pub fn new_random(input_dim: usize, output_dim: usize, input_strength: T) -> Self {
+        Self::new_random_seeded(input_dim, output_dim, input_strength, thread_rng().gen())
+    }
+
+    pub fn new_random_seeded(
+        input_dim: usize,
+        output_dim: usize,
+        input_strength: T,
+        seed: u64,
+    ) -> Self {
         let mut w_in = DMatrix::zeros(output_dim, input_dim);
 
-        let mut rnd = thread_rng();
+        let mut rnd = StdRng::seed_from_u64(seed);
         let choice_distribution = Uniform::new(0, input_dim);
         let value_distribution = Uniform::new(-T::one(), T::one());
 
@@ -32,19 +43,78 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> DefaultInputProj
         }
 
         Self {
-            w_in,
+            w_in: Arc::new(w_in),
             result: DVector::zeros(output_dim),
+            recipe: Some(GenerationRecipe::DefaultInputProjection {
+                input_dimension: input_dim,
+                output_dimension: output_dim,
+                input_strength: input_strength.to_f64().unwrap(),
+                seed,
+            }),
+        }
+    }
+
+    // Every entry is +-`weight` with probability `density` each sign equally likely and zero
+    // otherwise, for hardware with ternary input couplings.
+    pub fn new_random_ternary_seeded(
+        input_dim: usize,
+        output_dim: usize,
+        weight: T,
+        density: f64,
+        seed: u64,
+    ) -> Self {
+        assert!((0.0..=1.0).contains(&density));
+        let mut rnd = StdRng::seed_from_u64(seed);
+        let w_in = DMatrix::from_fn(output_dim, input_dim, |_, _| {
+            if !rnd.gen_bool(density) {
+                T::zero()
+            } else if rnd.gen_bool(0.5) {
+                weight
+            } else {
+                -weight
+            }
+        });
+        Self::new_with_matrix(w_in)
+    }
+
+    pub fn from_recipe(recipe: &GenerationRecipe) -> Result<Self, ReservoirError> {
+        match recipe {
+            GenerationRecipe::DefaultInputProjection {
+                input_dimension,
+                output_dimension,
+                input_strength,
+                seed,
+            } => Ok(Self::new_random_seeded(
+                *input_dimension,
+                *output_dimension,
+                T::from_f64(*input_strength).unwrap(),
+                *seed,
+            )),
+            _ => Err(ReservoirError::InvalidRecipe(format!(
+                "not a default input projection recipe: {recipe}"
+            ))),
         }
     }
 
+    // None if the projection was constructed from a given matrix.
+    pub fn recipe(&self) -> Option<&GenerationRecipe> {
+        self.recipe.as_ref()
+    }
+
     pub fn new_with_matrix(matrix: DMatrix<T>) -> Self {
         let output_dim = matrix.nrows();
         Self {
-            w_in: matrix,
+            w_in: Arc::new(matrix),
             result: DVector::zeros(output_dim),
+            recipe: None,
         }
     }
 
+    // Clones share the weight matrix.
+    pub fn w_in(&self) -> &DMatrix<T> {
+        &self.w_in
+    }
+
     fn impl_project(w_in: &DMatrix<T>, input: DMatrixSlice<T>, mut result: DVectorSliceMut<T>) {
         assert_eq!(input.ncols(), 1);
         w_in.mul_to(&input, &mut result)
@@ -72,7 +142,7 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> ReservoirInputPr
     }
 
     fn embeddings(&self) -> usize {
-        1
+        0
     }
 
     fn required_input_columns(&self) -> usize {
@@ -129,4 +199,24 @@ mod tests {
         assert_eq!(projection_results.column(1).as_slice(), &[-11., -14.]);
         assert_eq!(projection_results.column(2).as_slice(), &[3., 6.]);
     }
+
+    // Like the embedding projections without delayed columns, a single input column and zero
+    // embeddings.
+    #[test]
+    fn default_input_projection_has_no_embeddings() {
+        let input_projection = DefaultInputProjection::new_with_matrix(DMatrix::<f64>::zeros(4, 3));
+        assert_eq!(input_projection.embeddings(), 0);
+        assert_eq!(input_projection.required_input_columns(), 1);
+    }
+
+    #[test]
+    fn default_input_projection_clone_shares_weights() {
+        let mut input_projection = DefaultInputProjection::<f64>::new_random(3, 10, 1.0);
+        let clone = input_projection.clone();
+        assert!(std::ptr::eq(input_projection.w_in(), clone.w_in()));
+
+        let data = DMatrix::from_vec(3, 1, vec![0., 1., 2.]);
+        let projection_result = input_projection.project(data.columns(0, 1)).clone();
+        assert_eq!(clone.project_many(data.columns(0, 1)), projection_result);
+    }
 }
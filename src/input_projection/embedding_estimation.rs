@@ -0,0 +1,234 @@
+use nalgebra::{ClosedAdd, ClosedMul, DMatrix, DMatrixSlice};
+use rand::distributions::uniform::SampleUniform;
+
+use super::InputProjectionWithEmbedding;
+use crate::ReservoirValue;
+
+// Histogram bins per channel for the mutual information.
+const MUTUAL_INFORMATION_BINS: usize = 16;
+// Half width of the moving average over delays that keeps histogram noise from producing
+// spurious minima.
+const MUTUAL_INFORMATION_SMOOTHING: usize = 2;
+// Kennel's criteria: a neighbor is false if the next delay coordinate grows its distance by
+// more than this factor ...
+const FALSE_NEIGHBOR_DISTANCE_RATIO: f64 = 15.;
+// ... or moves it further apart than this multiple of the size of the attractor.
+const FALSE_NEIGHBOR_ATTRACTOR_RATIO: f64 = 2.;
+// The dimension is the first one with less false neighbors than this fraction.
+const FALSE_NEIGHBOR_FRACTION: f64 = 0.05;
+// Points whose nearest neighbor is searched, evenly spread over the data.
+const MAX_REFERENCE_POINTS: usize = 500;
+
+// Delay embedding parameters estimated from training data, columns are time steps. The delay is
+// the first local minimum of the (smoothed) average mutual information between x(t) and x(t + delay), the
+// dimension the smallest one for which (almost) no false nearest neighbors remain. All channels
+// are embedded with the same delays.
+#[derive(Clone, Debug)]
+pub struct EmbeddingEstimate {
+    system_dimension: usize,
+    delay: usize,
+    dimension: usize,
+    mutual_information: Vec<f64>,
+    false_neighbor_fractions: Vec<f64>,
+}
+
+impl EmbeddingEstimate {
+    pub fn from_data<T: ReservoirValue>(
+        data: DMatrixSlice<T>,
+        max_delay: usize,
+        max_dimension: usize,
+    ) -> Self {
+        assert!(max_delay > 0 && max_dimension > 0);
+        assert!(
+            data.ncols() > 2 * max_delay * max_dimension,
+            "Too few time steps for delays up to {max_delay} and {max_dimension} dimensions."
+        );
+        let data = data.map(|value| value.to_f64().unwrap());
+
+        let mutual_information: Vec<_> = (0..=max_delay)
+            .map(|delay| average_mutual_information(&data, delay))
+            .collect();
+        let smoothed = smoothed(&mutual_information[1..]);
+        let delay = (0..smoothed.len() - 1)
+            .find(|index| smoothed[index + 1] > smoothed[*index])
+            .unwrap_or_else(|| argmin(&smoothed))
+            + 1;
+
+        let false_neighbor_fractions: Vec<_> = (1..=max_dimension)
+            .map(|dimension| false_neighbor_fraction(&data, delay, dimension))
+            .collect();
+        let dimension = false_neighbor_fractions
+            .iter()
+            .position(|fraction| *fraction < FALSE_NEIGHBOR_FRACTION)
+            .unwrap_or_else(|| argmin(&false_neighbor_fractions))
+            + 1;
+
+        Self {
+            system_dimension: data.nrows(),
+            delay,
+            dimension,
+            mutual_information,
+            false_neighbor_fractions,
+        }
+    }
+
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    // Number of embedded columns including the current one.
+    pub fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    // Indexed by the delay, starting at 0.
+    pub fn mutual_information(&self) -> &[f64] {
+        &self.mutual_information
+    }
+
+    // Indexed by the dimension minus one.
+    pub fn false_neighbor_fractions(&self) -> &[f64] {
+        &self.false_neighbor_fractions
+    }
+
+    pub fn input_projection<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul>(
+        &self,
+        output_dim: usize,
+        seed: u64,
+    ) -> InputProjectionWithEmbedding<T> {
+        InputProjectionWithEmbedding::new_random_seeded(
+            self.system_dimension,
+            output_dim,
+            self.dimension - 1,
+            self.delay,
+            seed,
+        )
+    }
+}
+
+// Mean over the channels of the mutual information in nats between a channel and itself
+// `delay` steps later, from histograms over the range of the channel.
+fn average_mutual_information(data: &DMatrix<f64>, delay: usize) -> f64 {
+    let samples = data.ncols() - delay;
+    let mut total = 0.;
+    for row in data.row_iter() {
+        let (min, max) = row
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                (min.min(*value), max.max(*value))
+            });
+        let width = (max - min).max(f64::MIN_POSITIVE);
+        let bin = |value: f64| {
+            (((value - min) / width * MUTUAL_INFORMATION_BINS as f64) as usize)
+                .min(MUTUAL_INFORMATION_BINS - 1)
+        };
+
+        let mut joint = DMatrix::<f64>::zeros(MUTUAL_INFORMATION_BINS, MUTUAL_INFORMATION_BINS);
+        for t in 0..samples {
+            joint[(bin(row[t]), bin(row[t + delay]))] += 1.;
+        }
+        joint /= samples as f64;
+        let now = joint.column_sum();
+        let later = joint.row_sum();
+        for i in 0..MUTUAL_INFORMATION_BINS {
+            for j in 0..MUTUAL_INFORMATION_BINS {
+                let p = joint[(i, j)];
+                if p > 0. {
+                    total += p * (p / (now[i] * later[j])).ln();
+                }
+            }
+        }
+    }
+    total / data.nrows() as f64
+}
+
+// Fraction of nearest neighbors in the `dimension` dimensional delay embedding that separate
+// when the next delay coordinate is added. Neighbors closer in time than `delay` are skipped.
+fn false_neighbor_fraction(data: &DMatrix<f64>, delay: usize, dimension: usize) -> f64 {
+    // Point t uses the columns t, t - delay, ..., t - dimension * delay, the last one being
+    // the additional coordinate.
+    let first = dimension * delay;
+    let points = data.ncols() - first;
+    let column = |point: usize, lag: usize| data.column(first + point - lag * delay);
+    let distance = |a: usize, b: usize| {
+        (0..dimension)
+            .map(|lag| (column(a, lag) - column(b, lag)).norm_squared())
+            .sum::<f64>()
+    };
+    let mean = data.column_mean();
+    let attractor_size = (data
+        .column_iter()
+        .map(|column| (column - &mean).norm_squared())
+        .sum::<f64>()
+        / data.ncols() as f64)
+        .sqrt();
+
+    let stride = points.div_ceil(MAX_REFERENCE_POINTS);
+    let mut references = 0;
+    let mut false_neighbors = 0;
+    for reference in (0..points).step_by(stride) {
+        let nearest = (0..points)
+            .filter(|other| other.abs_diff(reference) > delay)
+            .map(|other| (other, distance(reference, other)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((neighbor, squared_distance)) = nearest else {
+            continue;
+        };
+        let extra = (column(reference, dimension) - column(neighbor, dimension)).norm();
+        let distance = squared_distance.sqrt();
+        references += 1;
+        if extra > FALSE_NEIGHBOR_DISTANCE_RATIO * distance
+            || (squared_distance + extra * extra).sqrt()
+                > FALSE_NEIGHBOR_ATTRACTOR_RATIO * attractor_size
+        {
+            false_neighbors += 1;
+        }
+    }
+    false_neighbors as f64 / references.max(1) as f64
+}
+
+fn smoothed(values: &[f64]) -> Vec<f64> {
+    (0..values.len())
+        .map(|index| {
+            let start = index.saturating_sub(MUTUAL_INFORMATION_SMOOTHING);
+            let end = (index + MUTUAL_INFORMATION_SMOOTHING + 1).min(values.len());
+            values[start..end].iter().sum::<f64>() / (end - start) as f64
+        })
+        .collect()
+}
+
+fn argmin(values: &[f64]) -> usize {
+    (0..values.len())
+        .min_by(|a, b| values[*a].total_cmp(&values[*b]))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::EmbeddingEstimate;
+    use crate::input_projection::ReservoirInputProjection;
+
+    #[test]
+    fn sine_needs_two_dimensions_and_a_quarter_period_delay() {
+        // Period of 2 pi / 0.05, about 126 steps.
+        let mut rng = StdRng::seed_from_u64(7);
+        let data = DMatrix::from_fn(1, 2000, |_, j| {
+            (j as f64 * 0.05).sin() + rng.gen_range(-0.01..0.01)
+        });
+        let estimate = EmbeddingEstimate::from_data(data.columns(0, 2000), 50, 3);
+        assert!(
+            (20..=40).contains(&estimate.delay()),
+            "{}",
+            estimate.delay()
+        );
+        assert_eq!(estimate.dimension(), 2);
+        assert!(estimate.false_neighbor_fractions()[0] > 0.1);
+
+        let projection = estimate.input_projection::<f64>(50, 1);
+        assert_eq!(projection.input_dimension(), 1);
+        assert_eq!(projection.required_input_columns(), estimate.delay() + 1);
+    }
+}
@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    seq::index::sample,
+    thread_rng, Rng, SeedableRng,
+};
+
+use super::ReservoirInputProjection;
+use crate::ReservoirValue;
+
+// Structured random input weights for very large reservoirs, without storing W_in:
+// W_in = input_strength / sqrt(m) S H D2 H D1 with random signs D1, random unit variance
+// weights D2, the m x m Walsh-Hadamard matrix H and S selecting output_dimension of its m rows,
+// m the next power of two of both dimensions. A projection costs O(m log m) and only the
+// diagonals and the selected rows are stored. The entries are close to normal with standard
+// deviation input_strength, a single H D would only give the 2^input_dimension sign patterns
+// of the first Hadamard columns and identical weights for most nodes.
+#[derive(Clone, Debug)]
+pub struct HadamardInputProjection<T: ReservoirValue> {
+    input_dimension: usize,
+    scale: T,
+    input_signs: Arc<Vec<T>>,
+    mixing_weights: Arc<Vec<T>>,
+    selected_rows: Arc<Vec<usize>>,
+    buffer: Vec<T>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue> HadamardInputProjection<T> {
+    pub fn new_random(input_dimension: usize, output_dimension: usize, input_strength: T) -> Self {
+        Self::new_random_seeded(
+            input_dimension,
+            output_dimension,
+            input_strength,
+            thread_rng().gen(),
+        )
+    }
+
+    pub fn new_random_seeded(
+        input_dimension: usize,
+        output_dimension: usize,
+        input_strength: T,
+        seed: u64,
+    ) -> Self {
+        let transform_size = input_dimension.max(output_dimension).next_power_of_two();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let input_signs = (0..transform_size)
+            .map(|_| if rng.gen() { T::one() } else { -T::one() })
+            .collect();
+        let unit_variance = Uniform::new_inclusive(-3f64.sqrt(), 3f64.sqrt());
+        let mixing_weights = (0..transform_size)
+            .map(|_| T::from_f64(unit_variance.sample(&mut rng)).unwrap())
+            .collect();
+        let selected_rows = sample(&mut rng, transform_size, output_dimension).into_vec();
+
+        Self {
+            input_dimension,
+            scale: input_strength / num_traits::Float::sqrt(T::from_usize(transform_size).unwrap()),
+            input_signs: Arc::new(input_signs),
+            mixing_weights: Arc::new(mixing_weights),
+            selected_rows: Arc::new(selected_rows),
+            buffer: vec![T::zero(); transform_size],
+            result: DVector::zeros(output_dimension),
+        }
+    }
+
+    pub fn transform_size(&self) -> usize {
+        self.input_signs.len()
+    }
+
+    // The implied W_in, only meant for inspection of small projections.
+    pub fn to_matrix(&self) -> DMatrix<T> {
+        let mut matrix = DMatrix::zeros(self.selected_rows.len(), self.input_dimension);
+        let mut unit = DMatrix::zeros(self.input_dimension, 1);
+        for column in 0..self.input_dimension {
+            unit[column] = T::one();
+            self.project_many_into(unit.columns(0, 1), matrix.columns_mut(column, 1));
+            unit[column] = T::zero();
+        }
+        matrix
+    }
+
+    fn impl_project(
+        &self,
+        buffer: &mut [T],
+        input: impl Iterator<Item = T>,
+        mut target: DVectorSliceMut<T>,
+    ) {
+        buffer.fill(T::zero());
+        for ((value, input), sign) in buffer.iter_mut().zip(input).zip(self.input_signs.iter()) {
+            *value = input * *sign;
+        }
+        walsh_hadamard_transform(buffer);
+        for (value, weight) in buffer.iter_mut().zip(self.mixing_weights.iter()) {
+            *value *= *weight;
+        }
+        walsh_hadamard_transform(buffer);
+        for (target, row) in target.iter_mut().zip(self.selected_rows.iter()) {
+            *target = buffer[*row] * self.scale;
+        }
+    }
+}
+
+// Unnormalized, in place, the length has to be a power of two.
+fn walsh_hadamard_transform<T: ReservoirValue>(values: &mut [T]) {
+    let mut half = 1;
+    while half < values.len() {
+        for start in (0..values.len()).step_by(2 * half) {
+            for index in start..start + half {
+                let (a, b) = (values[index], values[index + half]);
+                values[index] = a + b;
+                values[index + half] = a - b;
+            }
+        }
+        half *= 2;
+    }
+}
+
+impl<T: ReservoirValue> ReservoirInputProjection<T> for HadamardInputProjection<T> {
+    fn output_dimensions(&self) -> usize {
+        self.selected_rows.len()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.input_dimension
+    }
+
+    fn embeddings(&self) -> usize {
+        0
+    }
+
+    fn required_input_columns(&self) -> usize {
+        1
+    }
+
+    fn project(&mut self, input: DMatrixSlice<T>) -> &DVector<T> {
+        assert_eq!(input.shape(), (self.input_dimension, 1));
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let mut result = std::mem::replace(&mut self.result, DVector::zeros(0));
+        self.impl_project(&mut buffer, input.iter().copied(), result.column_mut(0));
+        self.buffer = buffer;
+        self.result = result;
+        &self.result
+    }
+
+    fn project_into(&mut self, input: DMatrixSlice<T>, target: DVectorSliceMut<T>) {
+        assert_eq!(input.shape(), (self.input_dimension, 1));
+        assert_eq!(self.output_dimensions(), target.nrows());
+        let mut buffer = std::mem::take(&mut self.buffer);
+        self.impl_project(&mut buffer, input.iter().copied(), target);
+        self.buffer = buffer;
+    }
+
+    fn project_many(&self, inputs: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut result = DMatrix::zeros(self.output_dimensions(), inputs.ncols());
+        self.project_many_into(inputs, result.columns_mut(0, inputs.ncols()));
+        result
+    }
+
+    fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
+        self.project_many_into_with_workspace(inputs, targets, &mut DMatrix::zeros(0, 0));
+    }
+
+    // Reuses `workspace` as the transform buffer.
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        mut targets: DMatrixSliceMut<T>,
+        workspace: &mut DMatrix<T>,
+    ) {
+        assert_eq!(inputs.nrows(), self.input_dimension);
+        assert_eq!(inputs.ncols(), targets.ncols());
+        if workspace.shape() != (self.transform_size(), 1) {
+            *workspace = DMatrix::zeros(self.transform_size(), 1);
+        }
+        for (input, target) in inputs.column_iter().zip(targets.column_iter_mut()) {
+            self.impl_project(workspace.as_mut_slice(), input.iter().copied(), target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::HadamardInputProjection;
+    use crate::input_projection::ReservoirInputProjection;
+
+    #[test]
+    fn matches_the_implied_matrix() {
+        let mut projection = HadamardInputProjection::<f64>::new_random_seeded(3, 500, 0.5, 7);
+        assert_eq!(projection.transform_size(), 512);
+        let w_in = projection.to_matrix();
+
+        let inputs = DMatrix::from_fn(3, 4, |i, j| ((i * 4 + j) as f64).sin());
+        let projected = projection.project_many(inputs.columns(0, 4));
+        assert!((&projected - &w_in * &inputs).amax() < 1e-12);
+        let single = projection.project(inputs.columns(2, 1)).clone();
+        assert!((single - projected.column(2)).amax() < 1e-12);
+
+        // Close to normal weights with the input strength as standard deviation and no
+        // repeated input weights.
+        let standard_deviation = (w_in.norm_squared() / w_in.len() as f64).sqrt();
+        assert!((standard_deviation - 0.5).abs() < 0.05);
+        assert!(w_in.mean().abs() < 0.05);
+        let distinct_rows = w_in
+            .row_iter()
+            .filter(|row| {
+                w_in.row_iter()
+                    .filter(|other| (*other - *row).amax() < 1e-12)
+                    .count()
+                    == 1
+            })
+            .count();
+        assert_eq!(distinct_rows, 500);
+    }
+}
@@ -12,52 +12,116 @@ pub struct IdentityProjectionWithEmbedding<T: ReservoirValue + ClosedAdd + Close
     input_dimensions: usize,
     embeddings: usize,
     stride: usize,
+    embedded_channels: Vec<usize>,
+    quadratic_features: bool,
     result: DVector<T>,
 }
 
 impl<T: ReservoirValue + ClosedAdd + ClosedMul> IdentityProjectionWithEmbedding<T> {
     pub fn new(system_dim: usize, embeddings: usize, stride: usize) -> Self {
+        Self::new_with_embedded_channels(
+            system_dim,
+            embeddings,
+            stride,
+            &(0..system_dim).collect::<Vec<_>>(),
+        )
+    }
+
+    // Only `embedded_channels` are delay embedded, the remaining channels enter at lag 0 only.
+    pub fn new_with_embedded_channels(
+        system_dim: usize,
+        embeddings: usize,
+        stride: usize,
+        embedded_channels: &[usize],
+    ) -> Self {
         if embeddings > 0 {
             assert_ne!(stride, 0);
         }
+        assert!(embedded_channels
+            .iter()
+            .all(|channel| *channel < system_dim));
+        assert!(
+            embedded_channels.windows(2).all(|w| w[0] < w[1]),
+            "Channels must be increasing."
+        );
         Self {
             input_dimensions: system_dim,
             embeddings,
             stride,
-            result: DVector::zeros(system_dim * (embeddings + 1)),
+            embedded_channels: embedded_channels.to_vec(),
+            quadratic_features: false,
+            result: DVector::zeros(embedded_channels.len() * embeddings + system_dim),
         }
     }
 
-    fn impl_project(input: DMatrixSlice<T>, stride: usize, mut result: DVectorSliceMut<T>) {
-        let system_dimension = input.nrows();
-        let mut iter = input.column_iter();
-        let mut counter = 0;
-        while let Some(column_slice) = iter.next() {
-            let mut temp_slice = result.rows_mut(counter * system_dimension, system_dimension);
-            temp_slice.copy_from(&column_slice);
+    pub fn embedded_channels(&self) -> &[usize] {
+        &self.embedded_channels
+    }
 
-            if stride > 1 {
-                iter.nth(stride - 2);
+    // Appends the products x_i * x_j with i <= j of the current input after the linear features.
+    pub fn with_quadratic_features(mut self) -> Self {
+        self.quadratic_features = true;
+        let linear_dimension =
+            self.embedded_channels.len() * self.embeddings + self.input_dimensions;
+        self.result = DVector::zeros(
+            linear_dimension + self.input_dimensions * (self.input_dimensions + 1) / 2,
+        );
+        self
+    }
+
+    pub fn quadratic_features(&self) -> bool {
+        self.quadratic_features
+    }
+
+    fn impl_project(
+        input: DMatrixSlice<T>,
+        column: usize,
+        embeddings: usize,
+        stride: usize,
+        embedded_channels: &[usize],
+        quadratic_features: bool,
+        mut result: DVectorSliceMut<T>,
+    ) {
+        let channels = embedded_channels.len();
+        for e in 0..embeddings {
+            for (k, channel) in embedded_channels.iter().enumerate() {
+                result[e * channels + k] = input[(*channel, column + e * stride)];
+            }
+        }
+        result
+            .rows_mut(embeddings * channels, input.nrows())
+            .copy_from(&input.column(column + embeddings * stride));
+
+        if quadratic_features {
+            let current = input.column(column + embeddings * stride);
+            let mut row = embeddings * channels + input.nrows();
+            for i in 0..current.nrows() {
+                for j in i..current.nrows() {
+                    result[row] = current[i] * current[j];
+                    row += 1;
+                }
             }
-            counter += 1;
         }
     }
 
-    fn impl_project_many(input: DMatrixSlice<T>, stride: usize, mut result: DMatrixSliceMut<T>) {
-        let input_dim = input.nrows();
-        let embeddings = result.nrows() / input.nrows() - 1;
+    fn impl_project_many(
+        input: DMatrixSlice<T>,
+        embeddings: usize,
+        stride: usize,
+        embedded_channels: &[usize],
+        quadratic_features: bool,
+        mut result: DMatrixSliceMut<T>,
+    ) {
         for i in 0..result.ncols() {
-            result
-                .column_mut(i)
-                .rows_mut(0, input_dim)
-                .copy_from(&input.column(i));
-
-            for e in 1..=embeddings {
-                result
-                    .column_mut(i)
-                    .rows_mut(e * input_dim, input_dim)
-                    .copy_from(&input.column(i + e * stride));
-            }
+            Self::impl_project(
+                input,
+                i,
+                embeddings,
+                stride,
+                embedded_channels,
+                quadratic_features,
+                result.column_mut(i),
+            );
         }
     }
 }
@@ -84,14 +148,30 @@ impl<T: ReservoirValue + ClosedAdd + ClosedMul> ReservoirInputProjection<T>
     fn project(&mut self, input: DMatrixSlice<T>) -> &DVector<T> {
         assert_eq!(input.ncols(), self.required_input_columns());
         let target_slice = self.result.column_mut(0);
-        Self::impl_project(input, self.stride, target_slice);
+        Self::impl_project(
+            input,
+            0,
+            self.embeddings,
+            self.stride,
+            &self.embedded_channels,
+            self.quadratic_features,
+            target_slice,
+        );
         &self.result
     }
 
     fn project_into(&mut self, input: DMatrixSlice<T>, target: DVectorSliceMut<T>) {
         assert_eq!(input.ncols(), self.required_input_columns());
         assert_eq!(target.nrows(), self.output_dimensions());
-        Self::impl_project(input, self.stride, target);
+        Self::impl_project(
+            input,
+            0,
+            self.embeddings,
+            self.stride,
+            &self.embedded_channels,
+            self.quadratic_features,
+            target,
+        );
     }
 
     fn project_many(&self, inputs: DMatrixSlice<T>) -> DMatrix<T> {
@@ -99,12 +179,26 @@ impl<T: ReservoirValue + ClosedAdd + ClosedMul> ReservoirInputProjection<T>
             self.output_dimensions(),
             1 + inputs.ncols() - self.required_input_columns(),
         );
-        Self::impl_project_many(inputs, self.stride, result.columns_mut(0, result.ncols()));
+        Self::impl_project_many(
+            inputs,
+            self.embeddings,
+            self.stride,
+            &self.embedded_channels,
+            self.quadratic_features,
+            result.columns_mut(0, result.ncols()),
+        );
         result
     }
 
     fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
-        Self::impl_project_many(inputs, self.stride, targets);
+        Self::impl_project_many(
+            inputs,
+            self.embeddings,
+            self.stride,
+            &self.embedded_channels,
+            self.quadratic_features,
+            targets,
+        );
     }
 }
 
@@ -239,4 +333,42 @@ mod tests {
 
         identity_projection.project(input_matrix.columns(0, 2));
     }
+
+    #[test]
+    fn identity_input_embedded_channels() {
+        let mut identity_projection =
+            IdentityProjectionWithEmbedding::<f64>::new_with_embedded_channels(3, 2, 1, &[0, 2]);
+        assert_eq!(identity_projection.output_dimensions(), 7);
+
+        let data = vec![1., 2., 3., 4., 5., 6., 7., 8., 9., 10., 11., 12.];
+        let input_matrix = DMatrix::from_vec(3, 4, data);
+
+        let project_result = identity_projection.project(input_matrix.columns(0, 3));
+        assert_eq!(project_result.as_slice(), &[1., 3., 4., 6., 7., 8., 9.]);
+
+        let project_many = identity_projection.project_many(input_matrix.columns(0, 4));
+        assert_eq!(
+            project_many.column(1).as_slice(),
+            &[4., 6., 7., 9., 10., 11., 12.]
+        );
+    }
+
+    #[test]
+    fn identity_input_quadratic_features() {
+        let mut identity_projection =
+            IdentityProjectionWithEmbedding::<f64>::new(2, 1, 1).with_quadratic_features();
+        assert_eq!(identity_projection.output_dimensions(), 7);
+
+        let data = vec![1., 2., 3., 4., 5., 6.];
+        let input_matrix = DMatrix::from_vec(2, 3, data);
+
+        let project_result = identity_projection.project(input_matrix.columns(0, 2));
+        assert_eq!(project_result.as_slice(), &[1., 2., 3., 4., 9., 12., 16.]);
+
+        let project_many = identity_projection.project_many(input_matrix.columns(0, 3));
+        assert_eq!(
+            project_many.column(1).as_slice(),
+            &[3., 4., 5., 6., 25., 30., 36.]
+        );
+    }
 }
@@ -1,24 +1,26 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
 use nalgebra::{
     ClosedAdd, ClosedMul, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut,
 };
 use rand::{
     distributions::{uniform::SampleUniform, Distribution, Uniform},
-    thread_rng,
+    rngs::StdRng,
+    thread_rng, Rng, SeedableRng,
 };
 
 use super::ReservoirInputProjection;
-use crate::ReservoirValue;
+use crate::{generation_recipe::GenerationRecipe, ReservoirError, ReservoirValue};
 
 #[derive(Clone, Debug)]
 pub struct InputProjectionWithEmbedding<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> {
-    w_in: DMatrix<T>,
+    w_in: Arc<DMatrix<T>>,
     input_dimensions: usize,
-    embeddings: usize,
-    stride: usize,
+    column_offsets: Vec<usize>,
+    embedded_channels: Vec<usize>,
     temporary: DVector<T>,
     result: DVector<T>,
+    recipe: Option<GenerationRecipe>,
 }
 
 impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> InputProjectionWithEmbedding<T> {
@@ -29,11 +31,96 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> InputProjectionW
         stride: usize,
     ) -> Self {
         assert_ne!(stride, 0);
+        Self::new_random_with_offsets(
+            system_dim,
+            output_dim,
+            Self::uniform_column_offsets(embeddings, stride),
+            (0..system_dim).collect(),
+            thread_rng().gen(),
+        )
+    }
+
+    pub fn new_random_seeded(
+        system_dim: usize,
+        output_dim: usize,
+        embeddings: usize,
+        stride: usize,
+        seed: u64,
+    ) -> Self {
+        assert_ne!(stride, 0);
+        Self::new_random_with_offsets(
+            system_dim,
+            output_dim,
+            Self::uniform_column_offsets(embeddings, stride),
+            (0..system_dim).collect(),
+            seed,
+        )
+    }
+
+    // `lags` are the distances of the embedded columns to the current column, e.g. [1, 2, 4, 8].
+    pub fn new_random_with_lags(system_dim: usize, output_dim: usize, lags: &[usize]) -> Self {
+        Self::new_random_with_offsets(
+            system_dim,
+            output_dim,
+            Self::lag_column_offsets(lags),
+            (0..system_dim).collect(),
+            thread_rng().gen(),
+        )
+    }
+
+    // Only `embedded_channels` are delay embedded, the remaining channels enter at lag 0 only.
+    pub fn new_random_with_embedded_channels(
+        system_dim: usize,
+        output_dim: usize,
+        lags: &[usize],
+        embedded_channels: &[usize],
+    ) -> Self {
+        Self::new_random_with_offsets(
+            system_dim,
+            output_dim,
+            Self::lag_column_offsets(lags),
+            Self::checked_channels(system_dim, embedded_channels),
+            thread_rng().gen(),
+        )
+    }
+
+    pub fn from_recipe(recipe: &GenerationRecipe) -> Result<Self, ReservoirError> {
+        match recipe {
+            GenerationRecipe::InputProjectionWithEmbedding {
+                system_dimension,
+                output_dimension,
+                column_offsets,
+                embedded_channels,
+                seed,
+            } => Ok(Self::new_random_with_offsets(
+                *system_dimension,
+                *output_dimension,
+                column_offsets.clone(),
+                Self::checked_channels(*system_dimension, embedded_channels),
+                *seed,
+            )),
+            _ => Err(ReservoirError::InvalidRecipe(format!(
+                "not an input projection with embedding recipe: {recipe}"
+            ))),
+        }
+    }
+
+    // None if the projection was constructed from a given matrix.
+    pub fn recipe(&self) -> Option<&GenerationRecipe> {
+        self.recipe.as_ref()
+    }
 
-        let input_dim = system_dim * (1 + embeddings);
+    fn new_random_with_offsets(
+        system_dim: usize,
+        output_dim: usize,
+        column_offsets: Vec<usize>,
+        embedded_channels: Vec<usize>,
+        seed: u64,
+    ) -> Self {
+        let input_dim = embedded_channels.len() * (column_offsets.len() - 1) + system_dim;
         let mut w_in = DMatrix::zeros(output_dim, input_dim);
 
-        let mut rnd = thread_rng();
+        let mut rnd = StdRng::seed_from_u64(seed);
         let choice_distribution = Uniform::new(0, input_dim);
         let value_distribution = Uniform::new(-T::one(), T::one());
 
@@ -44,66 +131,173 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> InputProjectionW
         }
 
         Self {
-            w_in,
+            w_in: Arc::new(w_in),
             input_dimensions: system_dim,
-            embeddings,
-            stride,
+            recipe: Some(GenerationRecipe::InputProjectionWithEmbedding {
+                system_dimension: system_dim,
+                output_dimension: output_dim,
+                column_offsets: column_offsets.clone(),
+                embedded_channels: embedded_channels.clone(),
+                seed,
+            }),
+            column_offsets,
+            embedded_channels,
             temporary: DVector::zeros(input_dim),
             result: DVector::zeros(output_dim),
         }
     }
 
     pub fn new_with_matrix(matrix: DMatrix<T>, embeddings: usize, stride: usize) -> Self {
-        let input_dimensions = matrix.ncols() / (1 + embeddings);
+        let column_offsets = Self::uniform_column_offsets(embeddings, stride);
+        assert_eq!(matrix.ncols() % column_offsets.len(), 0);
+        let input_dimensions = matrix.ncols() / column_offsets.len();
+        Self::new_with_matrix_and_offsets(matrix, column_offsets, (0..input_dimensions).collect())
+    }
+
+    pub fn new_with_matrix_and_lags(matrix: DMatrix<T>, lags: &[usize]) -> Self {
+        let column_offsets = Self::lag_column_offsets(lags);
+        assert_eq!(matrix.ncols() % column_offsets.len(), 0);
+        let input_dimensions = matrix.ncols() / column_offsets.len();
+        Self::new_with_matrix_and_offsets(matrix, column_offsets, (0..input_dimensions).collect())
+    }
+
+    pub fn new_with_matrix_and_embedded_channels(
+        matrix: DMatrix<T>,
+        lags: &[usize],
+        embedded_channels: &[usize],
+    ) -> Self {
+        let column_offsets = Self::lag_column_offsets(lags);
+        assert!(
+            matrix.ncols() >= embedded_channels.len() * lags.len(),
+            "The matrix has {} columns, fewer than the {} embedded columns.",
+            matrix.ncols(),
+            embedded_channels.len() * lags.len()
+        );
+        let input_dimensions = matrix.ncols() - embedded_channels.len() * lags.len();
+        let embedded_channels = Self::checked_channels(input_dimensions, embedded_channels);
+        Self::new_with_matrix_and_offsets(matrix, column_offsets, embedded_channels)
+    }
+
+    fn new_with_matrix_and_offsets(
+        matrix: DMatrix<T>,
+        column_offsets: Vec<usize>,
+        embedded_channels: Vec<usize>,
+    ) -> Self {
+        let embedded_dimensions = embedded_channels.len() * (column_offsets.len() - 1);
+        let input_dimensions = matrix.ncols() - embedded_dimensions;
         let output_dimensions = matrix.nrows();
 
         Self {
             temporary: DVector::zeros(matrix.ncols()),
-            w_in: matrix,
+            w_in: Arc::new(matrix),
             input_dimensions,
-            embeddings,
-            stride,
+            column_offsets,
+            embedded_channels,
             result: DVector::zeros(output_dimensions),
+            recipe: None,
         }
     }
 
+    pub fn w_in(&self) -> &DMatrix<T> {
+        &self.w_in
+    }
+
+    fn checked_channels(system_dim: usize, channels: &[usize]) -> Vec<usize> {
+        assert!(channels.iter().all(|channel| *channel < system_dim));
+        assert!(
+            channels.windows(2).all(|w| w[0] < w[1]),
+            "Channels must be increasing."
+        );
+        channels.to_vec()
+    }
+
+    fn uniform_column_offsets(embeddings: usize, stride: usize) -> Vec<usize> {
+        (0..=embeddings).map(|e| e * stride).collect()
+    }
+
+    fn lag_column_offsets(lags: &[usize]) -> Vec<usize> {
+        assert!(lags.iter().all(|lag| *lag > 0));
+        assert!(
+            lags.windows(2).all(|w| w[0] < w[1]),
+            "Lags must be increasing."
+        );
+        let max_lag = lags.last().copied().unwrap_or(0);
+        lags.iter()
+            .rev()
+            .map(|lag| max_lag - lag)
+            .chain(std::iter::once(max_lag))
+            .collect()
+    }
+
+    // Positions of the embedded columns within the required input columns, oldest first.
+    pub fn column_offsets(&self) -> &[usize] {
+        &self.column_offsets
+    }
+
+    pub fn embedded_channels(&self) -> &[usize] {
+        &self.embedded_channels
+    }
+
+    fn gather(
+        input: DMatrixSlice<T>,
+        column: usize,
+        column_offsets: &[usize],
+        embedded_channels: &[usize],
+        mut temporary: DVectorSliceMut<T>,
+    ) {
+        let channels = embedded_channels.len();
+        let (current, embedded) = column_offsets.split_last().unwrap();
+        for (e, offset) in embedded.iter().enumerate() {
+            for (k, channel) in embedded_channels.iter().enumerate() {
+                temporary[e * channels + k] = input[(*channel, column + offset)];
+            }
+        }
+        temporary
+            .rows_mut(embedded.len() * channels, input.nrows())
+            .copy_from(&input.column(column + current));
+    }
+
     fn impl_project(
         w_in: &DMatrix<T>,
         input: DMatrixSlice<T>,
-        embeddings: usize,
-        stride: usize,
+        column_offsets: &[usize],
+        embedded_channels: &[usize],
         mut temporary: DVectorSliceMut<T>,
         mut result: DVectorSliceMut<T>,
     ) {
-        let input_dim = input.nrows();
-        temporary.rows_mut(0, input_dim).copy_from(&input.column(0));
-
-        for e in 1..=embeddings {
-            temporary
-                .rows_mut(e * input_dim, input_dim)
-                .copy_from(&input.column(e * stride));
-        }
+        Self::gather(
+            input,
+            0,
+            column_offsets,
+            embedded_channels,
+            temporary.rows_mut(0, w_in.ncols()),
+        );
         w_in.mul_to(&temporary, &mut result);
     }
 
+    // Gathers every embedded column into `workspace` and projects it with a matrix vector
+    // product. A block matrix product would allocate packing buffers on every call.
     fn impl_project_many(
         w_in: &DMatrix<T>,
         input: DMatrixSlice<T>,
-        embeddings: usize,
-        stride: usize,
-        mut temporary: DVectorSliceMut<T>,
+        column_offsets: &[usize],
+        embedded_channels: &[usize],
+        workspace: &mut DMatrix<T>,
         mut result: DMatrixSliceMut<T>,
     ) {
-        let input_dim = input.nrows();
-        for i in 0..result.ncols() {
-            temporary.rows_mut(0, input_dim).copy_from(&input.column(i));
+        if workspace.nrows() != w_in.ncols() || workspace.ncols() == 0 {
+            *workspace = DMatrix::zeros(w_in.ncols(), 1);
+        }
 
-            for e in 1..=embeddings {
-                temporary
-                    .rows_mut(e * input_dim, input_dim)
-                    .copy_from(&input.column(i + e * stride));
-            }
-            w_in.mul_to(&temporary, &mut result.column_mut(i));
+        for i in 0..result.ncols() {
+            Self::gather(
+                input,
+                i,
+                column_offsets,
+                embedded_channels,
+                workspace.column_mut(0),
+            );
+            w_in.mul_to(&workspace.column(0), &mut result.column_mut(i));
         }
     }
 }
@@ -120,11 +314,11 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> ReservoirInputPr
     }
 
     fn embeddings(&self) -> usize {
-        self.embeddings
+        self.column_offsets.len() - 1
     }
 
     fn required_input_columns(&self) -> usize {
-        1 + self.embeddings * self.stride
+        1 + self.column_offsets.last().unwrap()
     }
 
     fn project(&mut self, input: DMatrixSlice<T>) -> &DVector<T> {
@@ -134,8 +328,8 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> ReservoirInputPr
         Self::impl_project(
             &self.w_in,
             input,
-            self.embeddings,
-            self.stride,
+            &self.column_offsets,
+            &self.embedded_channels,
             temp_slice,
             target_slice,
         );
@@ -150,8 +344,8 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> ReservoirInputPr
         Self::impl_project(
             &self.w_in,
             input,
-            self.embeddings,
-            self.stride,
+            &self.column_offsets,
+            &self.embedded_channels,
             temp_slice,
             target,
         );
@@ -162,26 +356,33 @@ impl<T: ReservoirValue + SampleUniform + ClosedAdd + ClosedMul> ReservoirInputPr
             self.output_dimensions(),
             1 + inputs.ncols() - self.required_input_columns(),
         );
-        let mut new_temp = DVector::zeros(self.temporary.nrows());
-        Self::impl_project_many(
-            &self.w_in,
-            inputs,
-            self.embeddings,
-            self.stride,
-            new_temp.column_mut(0),
-            result.columns_mut(0, result.ncols()),
-        );
+        let columns = result.ncols();
+        self.project_many_into(inputs, result.columns_mut(0, columns));
         result
     }
 
     fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
-        let mut new_temp = DVector::zeros(self.temporary.nrows());
+        self.project_many_into_with_workspace(inputs, targets, &mut DMatrix::zeros(0, 0));
+    }
+
+    // Reuses `workspace` for the gathered embedding vectors.
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        targets: DMatrixSliceMut<T>,
+        workspace: &mut DMatrix<T>,
+    ) {
+        assert_eq!(targets.nrows(), self.output_dimensions());
+        assert_eq!(
+            targets.ncols(),
+            1 + inputs.ncols() - self.required_input_columns()
+        );
         Self::impl_project_many(
             &self.w_in,
             inputs,
-            self.embeddings,
-            self.stride,
-            new_temp.column_mut(0),
+            &self.column_offsets,
+            &self.embedded_channels,
+            workspace,
             targets,
         );
     }
@@ -389,4 +590,95 @@ mod tests {
             result.column(2).as_slice()
         );
     }
+
+    #[test]
+    fn input_projection_with_lags() {
+        let input_projection_matrix = DMatrix::from_fn(2, 4, |i, j| (i * 4 + j + 1) as f64);
+        //1 2 3 4
+        //5 6 7 8
+        let mut input_projection = InputProjectionWithEmbedding::new_with_matrix_and_lags(
+            input_projection_matrix,
+            &[1, 3, 4],
+        );
+        assert_eq!(input_projection.input_dimension(), 1);
+        assert_eq!(input_projection.embeddings(), 3);
+        assert_eq!(input_projection.required_input_columns(), 5);
+        assert_eq!(input_projection.column_offsets(), &[0, 1, 3, 4]);
+
+        let input_matrix = DMatrix::from_vec(1, 6, vec![1., 2., 3., 4., 5., 6.]);
+        // Embedded vectors: [1, 2, 4, 5] and [2, 3, 5, 6]
+        let project_result = input_projection.project(input_matrix.columns(0, 5));
+        assert_eq!(project_result.as_slice(), &[37., 85.]);
+
+        let project_many = input_projection.project_many(input_matrix.columns(0, 6));
+        assert_eq!(project_many.ncols(), 2);
+        assert_eq!(project_many.column(0).as_slice(), &[37., 85.]);
+        assert_eq!(project_many.column(1).as_slice(), &[47., 111.]);
+    }
+
+    #[test]
+    fn input_projection_with_embedded_channels() {
+        let input_projection_matrix = DMatrix::from_fn(1, 4, |_, j| (j + 1) as f64);
+        //1 2 3 4
+        let mut input_projection =
+            InputProjectionWithEmbedding::new_with_matrix_and_embedded_channels(
+                input_projection_matrix,
+                &[1, 2],
+                &[1],
+            );
+        assert_eq!(input_projection.input_dimension(), 2);
+        assert_eq!(input_projection.embeddings(), 2);
+        assert_eq!(input_projection.required_input_columns(), 3);
+
+        let input_matrix = DMatrix::from_vec(2, 4, vec![1., 2., 3., 4., 5., 6., 7., 8.]);
+        // 1 3 5 7
+        // 2 4 6 8
+        // Embedded vectors: [2, 4, 5, 6] and [4, 6, 7, 8]
+        let project_result = input_projection.project(input_matrix.columns(0, 3));
+        assert_eq!(project_result.as_slice(), &[49.]);
+
+        let project_many = input_projection.project_many(input_matrix.columns(0, 4));
+        assert_eq!(project_many.ncols(), 2);
+        assert_eq!(project_many.column(0).as_slice(), &[49.]);
+        assert_eq!(project_many.column(1).as_slice(), &[69.]);
+    }
+
+    #[test]
+    #[should_panic(expected = "fewer than the 4 embedded columns")]
+    fn too_narrow_matrix_for_the_embedded_channels_is_rejected() {
+        InputProjectionWithEmbedding::new_with_matrix_and_embedded_channels(
+            DMatrix::<f64>::zeros(2, 3),
+            &[1, 2],
+            &[0, 1],
+        );
+    }
+
+    #[test]
+    fn input_projection_many_blocks_with_workspace() {
+        let input_projection_matrix = DMatrix::from_fn(3, 4, |i, j| (i + 2 * j) as f64 - 3.);
+        let mut input_projection =
+            InputProjectionWithEmbedding::new_with_matrix(input_projection_matrix, 1, 2);
+
+        let input_matrix = DMatrix::from_fn(2, 150, |i, j| ((i + 1) * j) as f64 * 0.1);
+        let mut expected = DMatrix::zeros(3, 148);
+        for i in 0..148 {
+            let project_result = input_projection.project(input_matrix.columns(i, 3));
+            expected.column_mut(i).copy_from(project_result);
+        }
+
+        assert_eq!(
+            input_projection.project_many(input_matrix.columns(0, 150)),
+            expected
+        );
+
+        let mut workspace = DMatrix::zeros(0, 0);
+        let mut targets = DMatrix::zeros(3, 148);
+        input_projection.project_many_into_with_workspace(
+            input_matrix.columns(0, 150),
+            targets.columns_mut(0, 148),
+            &mut workspace,
+        );
+        assert_eq!(targets, expected);
+        assert_eq!(workspace.nrows(), 4);
+    }
 }
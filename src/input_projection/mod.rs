@@ -5,12 +5,18 @@ use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut}
 use crate::ReservoirValue;
 
 pub mod default_input_projection;
+pub mod embedding_estimation;
+pub mod hadamard_input_projection;
 pub mod identity_projection_with_embedding;
 pub mod input_projection_with_embedding;
+pub mod task_embedding_input_projection;
 
 pub use default_input_projection::DefaultInputProjection;
+pub use embedding_estimation::EmbeddingEstimate;
+pub use hadamard_input_projection::HadamardInputProjection;
 pub use identity_projection_with_embedding::IdentityProjectionWithEmbedding;
 pub use input_projection_with_embedding::InputProjectionWithEmbedding;
+pub use task_embedding_input_projection::TaskEmbeddingInputProjection;
 
 pub trait ReservoirInputProjection<T: ReservoirValue>: Debug + Send + Sync {
     fn output_dimensions(&self) -> usize;
@@ -28,6 +34,17 @@ pub trait ReservoirInputProjection<T: ReservoirValue>: Debug + Send + Sync {
     fn project_many(&self, inputs: DMatrixSlice<T>) -> DMatrix<T>;
 
     fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>);
+
+    // Same projection with `workspace` as scratch space for callers that must not allocate, it
+    // is resized on the first call. Projections that allocate internally should override it.
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        targets: DMatrixSliceMut<T>,
+        _workspace: &mut DMatrix<T>,
+    ) {
+        self.project_many_into(inputs, targets);
+    }
 }
 
 impl<T: ReservoirValue, I: ReservoirInputProjection<T>> ReservoirInputProjection<T> for Box<I> {
@@ -62,6 +79,15 @@ impl<T: ReservoirValue, I: ReservoirInputProjection<T>> ReservoirInputProjection
     fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
         (**self).project_many_into(inputs, targets);
     }
+
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        targets: DMatrixSliceMut<T>,
+        workspace: &mut DMatrix<T>,
+    ) {
+        (**self).project_many_into_with_workspace(inputs, targets, workspace);
+    }
 }
 
 impl<T: ReservoirValue> ReservoirInputProjection<T> for Box<dyn ReservoirInputProjection<T>> {
@@ -96,4 +122,13 @@ impl<T: ReservoirValue> ReservoirInputProjection<T> for Box<dyn ReservoirInputPr
     fn project_many_into(&self, inputs: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
         (**self).project_many_into(inputs, targets);
     }
+
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        targets: DMatrixSliceMut<T>,
+        workspace: &mut DMatrix<T>,
+    ) {
+        (**self).project_many_into_with_workspace(inputs, targets, workspace);
+    }
 }
@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    SeedableRng,
+};
+
+use super::ReservoirInputProjection;
+use crate::ReservoirValue;
+
+// Adds the constant input B e to the wrapped projection, e is a small task embedding and B a
+// fixed matrix. With reservoir and readout frozen, a related task is adapted by fitting only e,
+// see `ReservoirComputer::adapt_task_embedding`. The base task is trained with e = 0.
+#[derive(Clone, Debug)]
+pub struct TaskEmbeddingInputProjection<T: ReservoirValue, I: ReservoirInputProjection<T>> {
+    projection: I,
+    embedding_matrix: Arc<DMatrix<T>>,
+    embedding: DVector<T>,
+    // B e
+    bias: DVector<T>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue, I: ReservoirInputProjection<T>> TaskEmbeddingInputProjection<T, I> {
+    // B has entries uniform in [-strength, strength].
+    pub fn new_random_seeded(
+        projection: I,
+        embedding_dimension: usize,
+        strength: T,
+        seed: u64,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let strength = strength.to_f64().unwrap();
+        let distribution = Uniform::new_inclusive(-strength, strength);
+        let matrix = DMatrix::from_fn(
+            projection.output_dimensions(),
+            embedding_dimension,
+            |_, _| T::from_f64(distribution.sample(&mut rng)).unwrap(),
+        );
+        Self::new_with_matrix(projection, matrix)
+    }
+
+    pub fn new_with_matrix(projection: I, embedding_matrix: DMatrix<T>) -> Self {
+        assert_eq!(embedding_matrix.nrows(), projection.output_dimensions());
+        Self {
+            bias: DVector::zeros(embedding_matrix.nrows()),
+            result: DVector::zeros(embedding_matrix.nrows()),
+            embedding: DVector::zeros(embedding_matrix.ncols()),
+            embedding_matrix: Arc::new(embedding_matrix),
+            projection,
+        }
+    }
+
+    pub fn projection(&self) -> &I {
+        &self.projection
+    }
+
+    pub fn embedding_matrix(&self) -> &DMatrix<T> {
+        &self.embedding_matrix
+    }
+
+    pub fn embedding_dimension(&self) -> usize {
+        self.embedding.nrows()
+    }
+
+    pub fn embedding(&self) -> &DVector<T> {
+        &self.embedding
+    }
+
+    pub fn set_embedding(&mut self, embedding: DVector<T>) {
+        assert_eq!(embedding.nrows(), self.embedding.nrows());
+        self.bias = self.embedding_matrix.as_ref() * &embedding;
+        self.embedding = embedding;
+    }
+}
+
+impl<T: ReservoirValue, I: ReservoirInputProjection<T>> ReservoirInputProjection<T>
+    for TaskEmbeddingInputProjection<T, I>
+{
+    fn output_dimensions(&self) -> usize {
+        self.projection.output_dimensions()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.projection.input_dimension()
+    }
+
+    fn embeddings(&self) -> usize {
+        self.projection.embeddings()
+    }
+
+    fn required_input_columns(&self) -> usize {
+        self.projection.required_input_columns()
+    }
+
+    fn project(&mut self, input: DMatrixSlice<T>) -> &DVector<T> {
+        self.result.copy_from(self.projection.project(input));
+        self.result += &self.bias;
+        &self.result
+    }
+
+    fn project_into(&mut self, input: DMatrixSlice<T>, mut target: DVectorSliceMut<T>) {
+        self.projection
+            .project_into(input, target.rows_mut(0, target.nrows()));
+        target += &self.bias;
+    }
+
+    fn project_many(&self, inputs: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut projected = self.projection.project_many(inputs);
+        for mut column in projected.column_iter_mut() {
+            column += &self.bias;
+        }
+        projected
+    }
+
+    fn project_many_into(&self, inputs: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        let columns = targets.ncols();
+        self.projection
+            .project_many_into(inputs, targets.columns_mut(0, columns));
+        for mut column in targets.column_iter_mut() {
+            column += &self.bias;
+        }
+    }
+
+    fn project_many_into_with_workspace(
+        &self,
+        inputs: DMatrixSlice<T>,
+        mut targets: DMatrixSliceMut<T>,
+        workspace: &mut DMatrix<T>,
+    ) {
+        let columns = targets.ncols();
+        self.projection.project_many_into_with_workspace(
+            inputs,
+            targets.columns_mut(0, columns),
+            workspace,
+        );
+        for mut column in targets.column_iter_mut() {
+            column += &self.bias;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::TaskEmbeddingInputProjection;
+    use crate::input_projection::{DefaultInputProjection, ReservoirInputProjection};
+
+    #[test]
+    fn adds_the_embedding_as_constant_input() {
+        let base = DefaultInputProjection::new_random_seeded(2, 10, 1., 3);
+        let mut projection =
+            TaskEmbeddingInputProjection::new_random_seeded(base.clone(), 3, 0.5, 4);
+        let inputs = DMatrix::from_fn(2, 4, |i, j| (i + j) as f64 * 0.1);
+        assert_eq!(
+            projection.project_many(inputs.columns(0, 4)),
+            base.project_many(inputs.columns(0, 4))
+        );
+
+        let embedding = DVector::from_vec(vec![1., -2., 0.5]);
+        projection.set_embedding(embedding.clone());
+        let bias = projection.embedding_matrix() * &embedding;
+        let projected = projection.project_many(inputs.columns(0, 4));
+        for (column, expected) in projected
+            .column_iter()
+            .zip(base.project_many(inputs.columns(0, 4)).column_iter())
+        {
+            assert!((column - expected - &bias).amax() < 1e-12);
+        }
+        let single = projection.project(inputs.columns(1, 1)).clone();
+        assert!((single - projected.column(1)).amax() < 1e-12);
+    }
+}
@@ -1,4 +1,6 @@
-//! It is recommended to use f64s since f32s are numerically not stable enough.
+//! f64 is the default precision. f32 halves the memory of states and weights: the ridge
+//! regression then accumulates its Gram matrix in f64 and linear readouts use compensated sums,
+//! see `compensated`.
 
 use std::fmt::{Debug, Display};
 
@@ -6,15 +8,44 @@ use nalgebra::{RealField, Scalar};
 use num_traits::Float;
 
 pub mod activation_function;
+pub mod baseline;
+pub mod benchmark;
+pub mod change_point;
+pub mod compensated;
 pub mod controlled_reservoir;
 pub mod controlled_time_evolution;
+pub mod data;
 pub mod echo_state_network;
+pub mod error;
+pub mod external_recording;
+pub mod external_time_evolution;
+mod fft;
+#[cfg(feature = "fine-tuning")]
+pub mod fine_tuning;
+pub mod fit_predict;
+pub mod generation_recipe;
+pub mod hybrid;
+pub mod hyperparameter;
 pub mod input_projection;
+pub mod metrics;
+pub mod model_registry;
 pub mod output_projection;
+pub mod prelude;
+pub mod preprocessing;
+pub mod profile;
+#[cfg(feature = "protocol")]
+pub mod protocol;
+mod random;
 pub mod reservoir;
+pub mod state_matrix;
 pub mod state_measurement;
 pub mod time_evolution;
+pub mod tuning;
 
+pub use error::ReservoirError;
+pub use fit_predict::{fit_predict, FitPredictConfig};
+pub use generation_recipe::{AdjacencyTransform, GenerationRecipe, NetworkGenerator};
+pub use hyperparameter::{LeakRate, Regularization, SpectralRadius};
 pub use reservoir::{Reservoir, ReservoirComputer, ReservoirComputerDynamics, ReservoirDynamics};
 
 #[cfg(not(feature = "lapack"))]
@@ -0,0 +1,231 @@
+use nalgebra::{Complex, ComplexField, DMatrixSlice, DVector};
+
+use crate::{fft::fft, ReservoirValue};
+
+// Root mean squared error normalized by the standard deviation of `truth` around its mean
+// over time, columns are time steps.
+pub fn normalized_root_mean_squared_error<T: ReservoirValue>(
+    prediction: DMatrixSlice<T>,
+    truth: DMatrixSlice<T>,
+) -> T {
+    assert_eq!(prediction.shape(), truth.shape());
+    let squared_error = (prediction - truth).norm_squared();
+    num_traits::Float::sqrt(squared_error / truth_variance(truth))
+}
+
+// Normalized error of every single time step, with the same normalization as
+// `normalized_root_mean_squared_error`.
+pub fn normalized_error_per_step<T: ReservoirValue>(
+    prediction: DMatrixSlice<T>,
+    truth: DMatrixSlice<T>,
+) -> DVector<T> {
+    assert_eq!(prediction.shape(), truth.shape());
+    let variance = truth_variance(truth) / T::from_usize(truth.ncols()).unwrap();
+    DVector::from_iterator(
+        truth.ncols(),
+        prediction
+            .column_iter()
+            .zip(truth.column_iter())
+            .map(|(p, t)| num_traits::Float::sqrt((p - t).norm_squared() / variance)),
+    )
+}
+
+// Number of steps before the normalized error of a single step first exceeds `threshold`.
+pub fn valid_time<T: ReservoirValue>(
+    prediction: DMatrixSlice<T>,
+    truth: DMatrixSlice<T>,
+    threshold: T,
+) -> usize {
+    let errors = normalized_error_per_step(prediction, truth);
+    errors
+        .iter()
+        .position(|error| error.is_nan() || *error > threshold)
+        .unwrap_or(errors.nrows())
+}
+
+// Dominant oscillation of prediction and truth per dimension, from the Hann windowed spectra of
+// the rows with their means removed. Frequencies are in cycles per unit time for samples
+// `sample_interval` apart, refined between bins by a parabola through the peak magnitudes.
+// Amplitudes are estimated at the peaks, phases are compared at the peak bin of the truth.
+#[derive(Clone, Debug)]
+pub struct SpectralComparison<T: ReservoirValue> {
+    truth_frequency: DVector<T>,
+    prediction_frequency: DVector<T>,
+    truth_amplitude: DVector<T>,
+    prediction_amplitude: DVector<T>,
+    phase_error: DVector<T>,
+}
+
+impl<T: ReservoirValue> SpectralComparison<T> {
+    pub fn new(prediction: DMatrixSlice<T>, truth: DMatrixSlice<T>, sample_interval: T) -> Self {
+        assert_eq!(prediction.shape(), truth.shape());
+        assert!(
+            truth.ncols() >= 4,
+            "At least 4 time steps are needed for a spectrum."
+        );
+        assert!(sample_interval > T::zero());
+        let dimensions = truth.nrows();
+        let resolution = 1. / (truth.ncols() as f64 * sample_interval.to_f64().unwrap());
+        let mut comparison = Self {
+            truth_frequency: DVector::zeros(dimensions),
+            prediction_frequency: DVector::zeros(dimensions),
+            truth_amplitude: DVector::zeros(dimensions),
+            prediction_amplitude: DVector::zeros(dimensions),
+            phase_error: DVector::zeros(dimensions),
+        };
+        for row in 0..dimensions {
+            let truth_spectrum = windowed_spectrum(truth.row(row).iter().copied());
+            let prediction_spectrum = windowed_spectrum(prediction.row(row).iter().copied());
+            let (truth_peak, truth_bin, truth_amplitude) = dominant_peak(&truth_spectrum);
+            let (_, prediction_bin, prediction_amplitude) = dominant_peak(&prediction_spectrum);
+
+            let phase_error =
+                prediction_spectrum[truth_peak].argument() - truth_spectrum[truth_peak].argument();
+            comparison.truth_frequency[row] = T::from_f64(truth_bin * resolution).unwrap();
+            comparison.prediction_frequency[row] =
+                T::from_f64(prediction_bin * resolution).unwrap();
+            comparison.truth_amplitude[row] = T::from_f64(truth_amplitude).unwrap();
+            comparison.prediction_amplitude[row] = T::from_f64(prediction_amplitude).unwrap();
+            comparison.phase_error[row] = T::from_f64(wrap_phase(phase_error)).unwrap();
+        }
+        comparison
+    }
+
+    pub fn truth_frequency(&self) -> &DVector<T> {
+        &self.truth_frequency
+    }
+
+    pub fn prediction_frequency(&self) -> &DVector<T> {
+        &self.prediction_frequency
+    }
+
+    // Prediction minus truth.
+    pub fn frequency_drift(&self) -> DVector<T> {
+        &self.prediction_frequency - &self.truth_frequency
+    }
+
+    pub fn truth_amplitude(&self) -> &DVector<T> {
+        &self.truth_amplitude
+    }
+
+    pub fn prediction_amplitude(&self) -> &DVector<T> {
+        &self.prediction_amplitude
+    }
+
+    // Relative to the amplitude of the truth, prediction minus truth.
+    pub fn amplitude_error(&self) -> DVector<T> {
+        (&self.prediction_amplitude - &self.truth_amplitude).component_div(&self.truth_amplitude)
+    }
+
+    // Prediction minus truth in radians, wrapped to (-pi, pi].
+    pub fn phase_error(&self) -> &DVector<T> {
+        &self.phase_error
+    }
+}
+
+// One sided spectrum (bins 0..=n/2) of the Hann windowed signal without its mean.
+fn windowed_spectrum<T: ReservoirValue>(
+    signal: impl ExactSizeIterator<Item = T>,
+) -> Vec<Complex<f64>> {
+    let n = signal.len();
+    let mut values: Vec<_> = signal
+        .map(|value| Complex::new(value.to_f64().unwrap(), 0.))
+        .collect();
+    let mean = values.iter().map(|value| value.re).sum::<f64>() / n as f64;
+    for (index, value) in values.iter_mut().enumerate() {
+        let window = 0.5 - 0.5 * (std::f64::consts::TAU * index as f64 / n as f64).cos();
+        value.re = (value.re - mean) * window;
+    }
+    fft(&mut values, false);
+    // Rescaled such that a sinusoid on a bin has its amplitude as peak magnitude.
+    let scale = 4. / n as f64;
+    values.truncate(n / 2 + 1);
+    for value in values.iter_mut() {
+        *value *= scale;
+    }
+    values
+}
+
+// Bin of the largest magnitude (without the constant bin), its refined fractional position and
+// the magnitude.
+fn dominant_peak(spectrum: &[Complex<f64>]) -> (usize, f64, f64) {
+    let magnitudes: Vec<_> = spectrum.iter().map(|value| value.modulus()).collect();
+    let peak = (1..magnitudes.len())
+        .max_by(|a, b| magnitudes[*a].total_cmp(&magnitudes[*b]))
+        .unwrap();
+    let mut position = peak as f64;
+    if peak + 1 < magnitudes.len() {
+        let (left, center, right) = (magnitudes[peak - 1], magnitudes[peak], magnitudes[peak + 1]);
+        let curvature = left - 2. * center + right;
+        if curvature < 0. {
+            position += 0.5 * (left - right) / curvature;
+        }
+    }
+    (peak, position, magnitudes[peak])
+}
+
+fn wrap_phase(phase: f64) -> f64 {
+    let wrapped = phase.rem_euclid(std::f64::consts::TAU);
+    if wrapped > std::f64::consts::PI {
+        wrapped - std::f64::consts::TAU
+    } else {
+        wrapped
+    }
+}
+
+// Sum over all columns of the squared distance to the mean column.
+pub(crate) fn truth_variance<T: ReservoirValue>(truth: DMatrixSlice<T>) -> T {
+    assert!(truth.ncols() > 0);
+    let mean = truth.column_mean();
+    let variance = truth
+        .column_iter()
+        .map(|column| (column - &mean).norm_squared())
+        .fold(T::zero(), |acc, value| acc + value);
+    assert!(variance > T::zero(), "The true data has no variance.");
+    variance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalized_root_mean_squared_error, valid_time, SpectralComparison};
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn nrmse_and_valid_time() {
+        let truth = DMatrix::from_vec(1, 4, vec![1., -1., 1., -1.]);
+        let prediction = DMatrix::from_vec(1, 4, vec![1., -1., 1.5, 1.]);
+        let nrmse =
+            normalized_root_mean_squared_error(prediction.columns(0, 4), truth.columns(0, 4));
+        assert!((nrmse - (4.25f64 / 4.).sqrt()).abs() < 1e-12);
+        assert_eq!(
+            valid_time(prediction.columns(0, 4), truth.columns(0, 4), 0.4),
+            2
+        );
+        assert_eq!(valid_time(truth.columns(0, 4), truth.columns(0, 4), 0.4), 4);
+    }
+
+    #[test]
+    fn spectral_comparison_of_sinusoids() {
+        // 8 periods in 256 samples, every frequency lies on a bin.
+        let signal = |amplitude: f64, cycles: f64, phase: f64| {
+            DMatrix::from_fn(1, 256, move |_, j| {
+                amplitude * (std::f64::consts::TAU * cycles * j as f64 / 256. + phase).sin()
+            })
+        };
+        let truth = signal(1., 8., 0.);
+        let prediction = signal(0.8, 8., 0.3);
+        let comparison =
+            SpectralComparison::new(prediction.columns(0, 256), truth.columns(0, 256), 0.5);
+        assert!((comparison.truth_frequency()[0] - 8. / 128.).abs() < 1e-9);
+        assert!(comparison.frequency_drift()[0].abs() < 1e-9);
+        assert!((comparison.truth_amplitude()[0] - 1.).abs() < 1e-9);
+        assert!((comparison.amplitude_error()[0] + 0.2).abs() < 1e-9);
+        assert!((comparison.phase_error()[0] - 0.3).abs() < 1e-9);
+
+        let drifted = signal(1., 8.4, 0.);
+        let comparison =
+            SpectralComparison::new(drifted.columns(0, 256), truth.columns(0, 256), 0.5);
+        let drift = comparison.frequency_drift()[0] * 128.;
+        assert!((drift - 0.4).abs() < 0.05, "{drift}");
+    }
+}
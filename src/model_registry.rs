@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::reservoir::TrainingReport;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::{ReservoirComputer, ReservoirError, ReservoirValue};
+
+// Saved models on disk, every registered model gets the next version of its id. The payload is
+// whatever the service rebuilds the model from, e.g. the generation recipes and the readout
+// weights, the registry only keeps it next to the metadata. Tags such as `production` point to
+// one version of an id and can be moved back to roll back a bad retraining.
+//
+// Layout: `<root>/<id>/<version>.model` holds the payload and `<root>/<id>/<version>.meta` the
+// metadata as `key=value` lines, a version exists once its metadata is written.
+#[derive(Clone, Debug)]
+pub struct ModelRegistry {
+    root: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelMetadata {
+    version: u32,
+    input_dimension: usize,
+    output_dimension: usize,
+    reservoir_size: usize,
+    description: String,
+    metrics: BTreeMap<String, f64>,
+    tags: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ModelSelector {
+    Latest,
+    Version(u32),
+    Tag(String),
+}
+
+impl ModelMetadata {
+    pub fn new(input_dimension: usize, output_dimension: usize, reservoir_size: usize) -> Self {
+        Self {
+            version: 0,
+            input_dimension,
+            output_dimension,
+            reservoir_size,
+            description: String::new(),
+            metrics: BTreeMap::new(),
+            tags: BTreeSet::new(),
+        }
+    }
+
+    // Dimensions of `reservoir_computer`, the output dimension is the one of the readout.
+    pub fn describe<T, I, E, M, P>(reservoir_computer: &ReservoirComputer<T, I, E, M, P>) -> Self
+    where
+        T: ReservoirValue,
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        Self::new(
+            reservoir_computer
+                .state_input_projection()
+                .input_dimension(),
+            reservoir_computer.state_projection().output_dimension(),
+            reservoir_computer.state().nrows(),
+        )
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        assert!(
+            !description.contains('\n'),
+            "The description has to fit on one line."
+        );
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_metric(mut self, name: &str, value: f64) -> Self {
+        assert!(
+            is_valid_name(name),
+            "Metric names may only contain letters, digits, '-' and '_'."
+        );
+        self.metrics.insert(name.to_string(), value);
+        self
+    }
+
+    // `training_rmse` averaged over the output dimensions.
+    pub fn with_training_report<T: ReservoirValue>(self, report: &TrainingReport<T>) -> Self {
+        let rmse = report.root_mean_squared_error();
+        self.with_metric("training_rmse", rmse.mean().to_f64().unwrap())
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        assert!(
+            is_valid_name(tag),
+            "Tags may only contain letters, digits, '-' and '_'."
+        );
+        self.tags.insert(tag.to_string());
+        self
+    }
+
+    // Assigned by the registry, 0 before the model is registered.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn input_dimension(&self) -> usize {
+        self.input_dimension
+    }
+
+    pub fn output_dimension(&self) -> usize {
+        self.output_dimension
+    }
+
+    pub fn reservoir_size(&self) -> usize {
+        self.reservoir_size
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics.get(name).copied()
+    }
+
+    pub fn metrics(&self) -> &BTreeMap<String, f64> {
+        &self.metrics
+    }
+
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "version={}\ninput_dimension={}\noutput_dimension={}\nreservoir_size={}\ndescription={}\ntags={}\n",
+            self.version,
+            self.input_dimension,
+            self.output_dimension,
+            self.reservoir_size,
+            self.description,
+            self.tags.iter().cloned().collect::<Vec<_>>().join(",")
+        );
+        for (name, value) in &self.metrics {
+            text.push_str(&format!("metric.{name}={value}\n"));
+        }
+        text
+    }
+
+    fn from_text(text: &str) -> Result<Self, ReservoirError> {
+        let invalid = |line: &str| ReservoirError::InvalidData(format!("invalid metadata {line}"));
+        let mut fields = BTreeMap::new();
+        let mut metadata = Self::new(0, 0, 0);
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            if let Some(name) = key.strip_prefix("metric.") {
+                let value = value.parse().map_err(|_| invalid(line))?;
+                metadata.metrics.insert(name.to_string(), value);
+            } else {
+                fields.insert(key, value);
+            }
+        }
+        let field = |key: &str| {
+            fields
+                .get(key)
+                .copied()
+                .ok_or_else(|| ReservoirError::InvalidData(format!("missing metadata {key}")))
+        };
+        let number = |key: &str| -> Result<usize, ReservoirError> {
+            field(key)?.parse().map_err(|_| invalid(key))
+        };
+        metadata.version = field("version")?.parse().map_err(|_| invalid("version"))?;
+        metadata.input_dimension = number("input_dimension")?;
+        metadata.output_dimension = number("output_dimension")?;
+        metadata.reservoir_size = number("reservoir_size")?;
+        metadata.description = field("description")?.to_string();
+        metadata.tags = field("tags")?
+            .split(',')
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        Ok(metadata)
+    }
+}
+
+impl ModelRegistry {
+    // Creates the root directory if it does not exist yet.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, ReservoirError> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(&root).map_err(|error| io_error(&root, error))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Ids of all models with at least one version.
+    pub fn ids(&self) -> Result<Vec<String>, ReservoirError> {
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&self.root).map_err(|error| io_error(&self.root, error))? {
+            let entry = entry.map_err(|error| io_error(&self.root, error))?;
+            let id = entry.file_name().to_string_lossy().into_owned();
+            if is_valid_name(&id) && !self.version_numbers(&id)?.is_empty() {
+                ids.push(id);
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    // Stores `payload` as the next version of `id` and returns its version. The tags of the
+    // metadata move from older versions to the new one.
+    pub fn register(
+        &self,
+        id: &str,
+        metadata: ModelMetadata,
+        payload: &[u8],
+    ) -> Result<u32, ReservoirError> {
+        check_name(id)?;
+        let directory = self.root.join(id);
+        fs::create_dir_all(&directory).map_err(|error| io_error(&directory, error))?;
+        let version = self.version_numbers(id)?.last().map_or(1, |last| last + 1);
+        let model_path = directory.join(format!("{version}.model"));
+        fs::write(&model_path, payload).map_err(|error| io_error(&model_path, error))?;
+        for tag in &metadata.tags {
+            self.remove_tag(id, tag)?;
+        }
+        self.write_metadata(
+            id,
+            &ModelMetadata {
+                version,
+                ..metadata
+            },
+        )?;
+        Ok(version)
+    }
+
+    // All versions of `id`, oldest first.
+    pub fn versions(&self, id: &str) -> Result<Vec<ModelMetadata>, ReservoirError> {
+        check_name(id)?;
+        self.version_numbers(id)?
+            .into_iter()
+            .map(|version| self.read_metadata(id, version))
+            .collect()
+    }
+
+    pub fn metadata(
+        &self,
+        id: &str,
+        selector: &ModelSelector,
+    ) -> Result<ModelMetadata, ReservoirError> {
+        check_name(id)?;
+        let versions = self.versions(id)?;
+        let selected = match selector {
+            ModelSelector::Latest => versions.last(),
+            ModelSelector::Version(version) => versions
+                .iter()
+                .find(|metadata| metadata.version == *version),
+            ModelSelector::Tag(tag) => versions.iter().find(|metadata| metadata.has_tag(tag)),
+        };
+        selected.cloned().ok_or_else(|| {
+            ReservoirError::InvalidData(format!("no model {id} matches {selector:?}"))
+        })
+    }
+
+    pub fn load(
+        &self,
+        id: &str,
+        selector: &ModelSelector,
+    ) -> Result<(ModelMetadata, Vec<u8>), ReservoirError> {
+        let metadata = self.metadata(id, selector)?;
+        let model_path = self
+            .root
+            .join(id)
+            .join(format!("{}.model", metadata.version));
+        let payload = fs::read(&model_path).map_err(|error| io_error(&model_path, error))?;
+        Ok((metadata, payload))
+    }
+
+    // Points `tag` at `version`, away from the version it pointed at before.
+    pub fn tag(&self, id: &str, version: u32, tag: &str) -> Result<(), ReservoirError> {
+        check_name(tag)?;
+        let mut metadata = self.metadata(id, &ModelSelector::Version(version))?;
+        self.remove_tag(id, tag)?;
+        metadata.tags.insert(tag.to_string());
+        self.write_metadata(id, &metadata)
+    }
+
+    // Moves `tag` to the version before the one it points at and returns that version.
+    pub fn rollback(&self, id: &str, tag: &str) -> Result<u32, ReservoirError> {
+        let current = self.metadata(id, &ModelSelector::Tag(tag.to_string()))?;
+        let previous = self
+            .version_numbers(id)?
+            .into_iter()
+            .rfind(|version| *version < current.version)
+            .ok_or_else(|| {
+                ReservoirError::InvalidData(format!(
+                    "no version of {id} before {} to roll back to",
+                    current.version
+                ))
+            })?;
+        self.tag(id, previous, tag)?;
+        Ok(previous)
+    }
+
+    fn remove_tag(&self, id: &str, tag: &str) -> Result<(), ReservoirError> {
+        for mut metadata in self.versions(id)? {
+            if metadata.tags.remove(tag) {
+                self.write_metadata(id, &metadata)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn version_numbers(&self, id: &str) -> Result<Vec<u32>, ReservoirError> {
+        let directory = self.root.join(id);
+        if !directory.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(&directory).map_err(|error| io_error(&directory, error))? {
+            let entry = entry.map_err(|error| io_error(&directory, error))?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            if let Some(version) = file_name
+                .strip_suffix(".meta")
+                .and_then(|version| version.parse().ok())
+            {
+                versions.push(version);
+            }
+        }
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    fn read_metadata(&self, id: &str, version: u32) -> Result<ModelMetadata, ReservoirError> {
+        let path = self.root.join(id).join(format!("{version}.meta"));
+        let text = fs::read_to_string(&path).map_err(|error| io_error(&path, error))?;
+        ModelMetadata::from_text(&text)
+    }
+
+    fn write_metadata(&self, id: &str, metadata: &ModelMetadata) -> Result<(), ReservoirError> {
+        let path = self
+            .root
+            .join(id)
+            .join(format!("{}.meta", metadata.version));
+        fs::write(&path, metadata.to_text()).map_err(|error| io_error(&path, error))
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn check_name(name: &str) -> Result<(), ReservoirError> {
+    if is_valid_name(name) {
+        Ok(())
+    } else {
+        Err(ReservoirError::InvalidData(format!(
+            "{name:?} may only contain letters, digits, '-' and '_'"
+        )))
+    }
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> ReservoirError {
+    ReservoirError::InvalidData(format!("{}: {error}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ModelMetadata, ModelRegistry, ModelSelector};
+
+    #[test]
+    fn register_tag_and_roll_back() {
+        let root = std::env::temp_dir().join(format!("rescomp_registry_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let registry = ModelRegistry::open(&root).unwrap();
+
+        let first = ModelMetadata::new(3, 3, 100)
+            .with_description("nightly lorenz")
+            .with_metric("validation_nrmse", 0.1)
+            .with_tag("production");
+        assert_eq!(registry.register("lorenz", first, b"first").unwrap(), 1);
+        let second = ModelMetadata::new(3, 3, 100)
+            .with_metric("validation_nrmse", 0.4)
+            .with_tag("production");
+        assert_eq!(registry.register("lorenz", second, b"second").unwrap(), 2);
+        assert_eq!(registry.ids().unwrap(), vec!["lorenz".to_string()]);
+
+        let production = ModelSelector::Tag("production".to_string());
+        let (metadata, payload) = registry.load("lorenz", &production).unwrap();
+        assert_eq!(
+            (metadata.version(), payload.as_slice()),
+            (2, &b"second"[..])
+        );
+        assert!(!registry.versions("lorenz").unwrap()[0].has_tag("production"));
+
+        assert_eq!(registry.rollback("lorenz", "production").unwrap(), 1);
+        let (metadata, payload) = registry.load("lorenz", &production).unwrap();
+        assert_eq!(payload, b"first");
+        assert_eq!(metadata.description(), "nightly lorenz");
+        assert_eq!(metadata.metric("validation_nrmse"), Some(0.1));
+        assert_eq!(metadata.reservoir_size(), 100);
+        assert!(registry.rollback("lorenz", "production").is_err());
+
+        let (latest, _) = registry.load("lorenz", &ModelSelector::Latest).unwrap();
+        assert_eq!(latest.version(), 2);
+        assert!(registry.load("lorenz", &ModelSelector::Version(3)).is_err());
+        assert!(registry
+            .register("../lorenz", ModelMetadata::new(1, 1, 1), b"")
+            .is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt::Debug;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// Applies y <- scale * y + offset per output dimension after the inner projection, e.g. to
+// undo a normalization of the training data or to remove a systematic bias without refitting
+// the inner readout. The transformed output is what gets fed back in closed loop.
+#[derive(Clone, Debug)]
+pub struct AffineOutputTransform<T: ReservoirValue, P: ReservoirStateProjection<T>> {
+    projection: P,
+    scale: DVector<T>,
+    offset: DVector<T>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue, P: ReservoirStateProjection<T>> AffineOutputTransform<T, P> {
+    pub fn new(projection: P, scale: DVector<T>, offset: DVector<T>) -> Self {
+        assert_eq!(scale.nrows(), projection.output_dimension());
+        assert_eq!(offset.nrows(), projection.output_dimension());
+        Self {
+            result: DVector::zeros(projection.output_dimension()),
+            projection,
+            scale,
+            offset,
+        }
+    }
+
+    // Least squares fit of scale and offset per output dimension, mapping the predictions of
+    // `projection` on `measured_states` to `target_states`. Constant predictions keep a scale
+    // of one and only get shifted.
+    pub fn fit(
+        projection: P,
+        measured_states: DMatrixSlice<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        assert!(measured_states.ncols() > 0);
+        assert_eq!(measured_states.ncols(), target_states.ncols());
+        let predictions = projection.project_many(measured_states);
+        let samples = T::from_usize(predictions.ncols()).unwrap();
+
+        let mut scale = DVector::from_element(predictions.nrows(), T::one());
+        let mut offset = DVector::zeros(predictions.nrows());
+        for (index, (prediction, target)) in predictions
+            .row_iter()
+            .zip(target_states.row_iter())
+            .enumerate()
+        {
+            let prediction_mean = prediction.sum() / samples;
+            let target_mean = target.sum() / samples;
+            let mut covariance = T::zero();
+            let mut variance = T::zero();
+            for (p, t) in prediction.iter().zip(target.iter()) {
+                covariance += (*p - prediction_mean) * (*t - target_mean);
+                variance += (*p - prediction_mean) * (*p - prediction_mean);
+            }
+            if variance > T::zero() {
+                scale[index] = covariance / variance;
+            }
+            offset[index] = target_mean - scale[index] * prediction_mean;
+        }
+        Self::new(projection, scale, offset)
+    }
+
+    pub fn scale(&self) -> &DVector<T> {
+        &self.scale
+    }
+
+    pub fn offset(&self) -> &DVector<T> {
+        &self.offset
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.projection
+    }
+
+    pub fn into_inner(self) -> P {
+        self.projection
+    }
+
+    fn transform_many(&self, mut targets: DMatrixSliceMut<T>) {
+        for mut column in targets.column_iter_mut() {
+            column.component_mul_assign(&self.scale);
+            column += &self.offset;
+        }
+    }
+}
+
+impl<T: ReservoirValue, P: ReservoirStateProjection<T>> ReservoirStateProjection<T>
+    for AffineOutputTransform<T, P>
+{
+    fn output_dimension(&self) -> usize {
+        self.projection.output_dimension()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.projection.input_dimension()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let prediction = self.projection.project(state);
+        self.result.copy_from(prediction);
+        self.result.component_mul_assign(&self.scale);
+        self.result += &self.offset;
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        self.projection
+            .project_into(state, target.rows_mut(0, target.nrows()));
+        target.component_mul_assign(&self.scale);
+        target += &self.offset;
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = self.projection.project_many(states);
+        self.transform_many(targets.columns_mut(0, targets.ncols()));
+        targets
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        self.projection
+            .project_many_into(states, targets.columns_mut(0, targets.ncols()));
+        self.transform_many(targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AffineOutputTransform;
+    use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn affine_output_transform_fit() {
+        let projection = LinearStateProjection::new_with_matrix(DMatrix::<f64>::identity(2, 2));
+        let states = DMatrix::from_fn(2, 20, |i, j| (i + 1) as f64 * (j as f64 * 0.3).sin());
+        let targets = DMatrix::from_fn(2, 20, |i, j| {
+            if i == 0 {
+                2. * states[(0, j)] + 3.
+            } else {
+                -0.5 * states[(1, j)] - 1.
+            }
+        });
+
+        let mut transform =
+            AffineOutputTransform::fit(projection, states.columns(0, 20), targets.columns(0, 20));
+        assert!((transform.scale() - DVector::from_vec(vec![2., -0.5])).amax() < 1e-12);
+        assert!((transform.offset() - DVector::from_vec(vec![3., -1.])).amax() < 1e-12);
+
+        assert!((transform.project_many(states.columns(0, 20)) - &targets).amax() < 1e-12);
+        let state = states.column(4).clone_owned();
+        let prediction = transform.project(&state).clone();
+        assert!((prediction - targets.column(4)).amax() < 1e-12);
+        let mut target = DVector::zeros(2);
+        transform.project_into(&state, target.rows_mut(0, 2));
+        assert!((target - targets.column(4)).amax() < 1e-12);
+    }
+}
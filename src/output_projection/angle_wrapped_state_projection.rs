@@ -0,0 +1,135 @@
+use std::fmt::Debug;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// Treats the selected output dimensions as angles and wraps them to [-pi, pi) after the inner
+// projection. Phase variables that drift out of range in closed loop would otherwise be fed
+// back as inputs the reservoir never saw during training.
+#[derive(Clone, Debug)]
+pub struct AngleWrappedStateProjection<T: ReservoirValue, P: ReservoirStateProjection<T>> {
+    projection: P,
+    angle_dimensions: Vec<usize>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue, P: ReservoirStateProjection<T>> AngleWrappedStateProjection<T, P> {
+    pub fn new(projection: P, angle_dimensions: Vec<usize>) -> Self {
+        assert!(angle_dimensions
+            .iter()
+            .all(|dimension| *dimension < projection.output_dimension()));
+        Self {
+            result: DVector::zeros(projection.output_dimension()),
+            projection,
+            angle_dimensions,
+        }
+    }
+
+    pub fn angle_dimensions(&self) -> &[usize] {
+        &self.angle_dimensions
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.projection
+    }
+
+    pub fn into_inner(self) -> P {
+        self.projection
+    }
+
+    fn wrap_many(&self, mut targets: DMatrixSliceMut<T>) {
+        for column in targets.column_iter_mut() {
+            wrap_dimensions(&self.angle_dimensions, column);
+        }
+    }
+}
+
+pub fn wrap_angle<T: ReservoirValue>(value: T) -> T {
+    let pi = T::pi();
+    let two_pi = T::two_pi();
+    value - two_pi * num_traits::Float::floor((value + pi) / two_pi)
+}
+
+// Wraps the given rows of `data` in place, e.g. to bring training targets into the same range
+// as the wrapped predictions.
+pub fn wrap_angles<T: ReservoirValue>(angle_dimensions: &[usize], mut data: DMatrixSliceMut<T>) {
+    for column in data.column_iter_mut() {
+        wrap_dimensions(angle_dimensions, column);
+    }
+}
+
+fn wrap_dimensions<T: ReservoirValue>(angle_dimensions: &[usize], mut target: DVectorSliceMut<T>) {
+    for dimension in angle_dimensions {
+        target[*dimension] = wrap_angle(target[*dimension]);
+    }
+}
+
+impl<T: ReservoirValue, P: ReservoirStateProjection<T>> ReservoirStateProjection<T>
+    for AngleWrappedStateProjection<T, P>
+{
+    fn output_dimension(&self) -> usize {
+        self.projection.output_dimension()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.projection.input_dimension()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let prediction = self.projection.project(state);
+        self.result.copy_from(prediction);
+        wrap_dimensions(
+            &self.angle_dimensions,
+            self.result.rows_mut(0, self.result.nrows()),
+        );
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        self.projection
+            .project_into(state, target.rows_mut(0, target.nrows()));
+        wrap_dimensions(&self.angle_dimensions, target);
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = self.projection.project_many(states);
+        self.wrap_many(targets.columns_mut(0, targets.ncols()));
+        targets
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        self.projection
+            .project_many_into(states, targets.columns_mut(0, targets.ncols()));
+        self.wrap_many(targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wrap_angle, AngleWrappedStateProjection};
+    use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+    use nalgebra::{DMatrix, DVector};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn angle_wrapping() {
+        assert!((wrap_angle(3. * PI / 2.) + PI / 2.).abs() < 1e-12);
+        assert!((wrap_angle(-5. * PI / 2.) + PI / 2.).abs() < 1e-12);
+        assert_eq!(wrap_angle(PI), -PI);
+        assert_eq!(wrap_angle(0.5), 0.5);
+
+        let projection = LinearStateProjection::new_with_matrix(DMatrix::<f64>::identity(2, 2));
+        let mut wrapped = AngleWrappedStateProjection::new(projection, vec![1]);
+        let state = DVector::from_vec(vec![4., 2. * PI + 1.]);
+        let prediction = wrapped.project(&state).clone();
+        assert_eq!(prediction[0], 4.);
+        assert!((prediction[1] - 1.).abs() < 1e-12);
+
+        let states = DMatrix::from_vec(2, 2, vec![4., 2. * PI + 1., -4., -PI - 0.5]);
+        let projected = wrapped.project_many(states.columns(0, 2));
+        assert_eq!(projected[(0, 1)], -4.);
+        assert!((projected[(1, 1)] - (PI - 0.5)).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::Debug;
+
+use nalgebra::{
+    ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector,
+    DVectorSlice, DVectorSliceMut,
+};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+pub trait StateKernel<T: ReservoirValue>: Debug {
+    fn evaluate(&self, a: DVectorSlice<T>, b: DVectorSlice<T>) -> T;
+}
+
+#[derive(Clone, Debug)]
+pub struct RbfKernel<T: ReservoirValue> {
+    gamma: T,
+}
+
+impl<T: ReservoirValue> RbfKernel<T> {
+    // k(a, b) = exp(-gamma |a - b|^2)
+    pub fn new(gamma: T) -> Self {
+        assert!(gamma > T::zero());
+        Self { gamma }
+    }
+}
+
+impl<T: ReservoirValue> StateKernel<T> for RbfKernel<T> {
+    fn evaluate(&self, a: DVectorSlice<T>, b: DVectorSlice<T>) -> T {
+        let squared_distance = a
+            .iter()
+            .zip(b.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + (*x - *y) * (*x - *y));
+        num_traits::Float::exp(-self.gamma * squared_distance)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PolynomialKernel<T: ReservoirValue> {
+    scale: T,
+    offset: T,
+    degree: i32,
+}
+
+impl<T: ReservoirValue> PolynomialKernel<T> {
+    // k(a, b) = (scale <a, b> + offset)^degree
+    pub fn new(scale: T, offset: T, degree: i32) -> Self {
+        assert!(degree > 0);
+        Self {
+            scale,
+            offset,
+            degree,
+        }
+    }
+}
+
+impl<T: ReservoirValue> StateKernel<T> for PolynomialKernel<T> {
+    fn evaluate(&self, a: DVectorSlice<T>, b: DVectorSlice<T>) -> T {
+        let dot = a
+            .iter()
+            .zip(b.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y);
+        num_traits::Float::powi(self.scale * dot + self.offset, self.degree)
+    }
+}
+
+// Kernel ridge regression restricted to a subset of the training states (Nyström / subset of
+// regressors), predictions are weights * [k(z_1, x), ..., k(z_m, x)].
+#[derive(Clone, Debug)]
+pub struct KernelStateProjection<
+    T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul,
+    K: StateKernel<T>,
+> {
+    kernel: K,
+    landmarks: DMatrix<T>,
+    weights: DMatrix<T>,
+    kernel_vector: DVector<T>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul, K: StateKernel<T>>
+    KernelStateProjection<T, K>
+{
+    // Uses `landmarks` evenly spaced columns of `measured_states` as Nyström landmarks.
+    pub fn via_nystroem_ridge_regression(
+        kernel: K,
+        beta: T,
+        landmarks: usize,
+        measured_states: &DMatrix<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        assert_eq!(measured_states.ncols(), target_states.ncols());
+        assert!(landmarks > 0 && landmarks <= measured_states.ncols());
+
+        let landmark_states =
+            DMatrix::from_fn(measured_states.nrows(), landmarks, |row, column| {
+                measured_states[(row, column * measured_states.ncols() / landmarks)]
+            });
+
+        let k_nm = Self::kernel_matrix(
+            &kernel,
+            measured_states.columns(0, measured_states.ncols()),
+            landmark_states.columns(0, landmarks),
+        );
+        let k_mm = Self::kernel_matrix(
+            &kernel,
+            landmark_states.columns(0, landmarks),
+            landmark_states.columns(0, landmarks),
+        );
+
+        let lhs = k_nm.transpose() * &k_nm + k_mm * beta;
+        let rhs = k_nm.transpose() * target_states.transpose();
+        let lu = nalgebra::LU::new(lhs);
+        let weights = lu.solve(&rhs).unwrap().transpose();
+
+        Self {
+            kernel,
+            landmarks: landmark_states,
+            kernel_vector: DVector::zeros(landmarks),
+            result: DVector::zeros(target_states.nrows()),
+            weights,
+        }
+    }
+
+    pub fn kernel(&self) -> &K {
+        &self.kernel
+    }
+
+    pub fn landmarks(&self) -> &DMatrix<T> {
+        &self.landmarks
+    }
+
+    pub fn weights(&self) -> &DMatrix<T> {
+        &self.weights
+    }
+
+    // Entry (i, j) is k(a_i, b_j).
+    fn kernel_matrix(kernel: &K, a: DMatrixSlice<T>, b: DMatrixSlice<T>) -> DMatrix<T> {
+        DMatrix::from_fn(a.ncols(), b.ncols(), |i, j| {
+            kernel.evaluate(a.column(i), b.column(j))
+        })
+    }
+
+    fn impl_kernel_vector(
+        kernel: &K,
+        landmarks: &DMatrix<T>,
+        state: DVectorSlice<T>,
+        mut target: DVectorSliceMut<T>,
+    ) {
+        for index in 0..landmarks.ncols() {
+            target[index] = kernel.evaluate(landmarks.column(index), state);
+        }
+    }
+}
+
+impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul, K: StateKernel<T>>
+    ReservoirStateProjection<T> for KernelStateProjection<T, K>
+{
+    fn output_dimension(&self) -> usize {
+        self.result.nrows()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.landmarks.nrows()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        Self::impl_kernel_vector(
+            &self.kernel,
+            &self.landmarks,
+            state.column(0),
+            self.kernel_vector.column_mut(0),
+        );
+        self.weights.mul_to(&self.kernel_vector, &mut self.result);
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        let mut kernel_vector = DVector::zeros(self.landmarks.ncols());
+        Self::impl_kernel_vector(
+            &self.kernel,
+            &self.landmarks,
+            state.column(0),
+            kernel_vector.column_mut(0),
+        );
+        self.weights.mul_to(&kernel_vector, &mut target);
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = DMatrix::zeros(self.output_dimension(), states.ncols());
+        self.project_many_into(states, targets.columns_mut(0, states.ncols()));
+        targets
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        assert_eq!(states.ncols(), targets.ncols());
+        let kernel_vectors = Self::kernel_matrix(
+            &self.kernel,
+            self.landmarks.columns(0, self.landmarks.ncols()),
+            states,
+        );
+        self.weights.mul_to(&kernel_vectors, &mut targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KernelStateProjection, PolynomialKernel, RbfKernel};
+    use crate::output_projection::ReservoirStateProjection;
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn rbf_kernel_fits_nonlinear_readout() {
+        let states = DMatrix::from_fn(1, 200, |_, j| -3. + 6. * j as f64 / 199.);
+        let targets = states.map(f64::sin);
+
+        let mut projection = KernelStateProjection::via_nystroem_ridge_regression(
+            RbfKernel::new(1.0),
+            1e-8,
+            40,
+            &states,
+            targets.columns(0, 200),
+        );
+        assert_eq!(projection.input_dimension(), 1);
+        assert_eq!(projection.output_dimension(), 1);
+
+        let predictions = projection.project_many(states.columns(0, 200));
+        assert!((predictions - &targets).amax() < 1e-3);
+
+        let state = DVector::from_vec(vec![1.234]);
+        assert!((projection.project(&state)[0] - 1.234_f64.sin()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn polynomial_kernel_fits_quadratic() {
+        let states = DMatrix::from_fn(2, 50, |i, j| (j as f64 * 0.1 + i as f64).cos());
+        let targets = DMatrix::from_fn(1, 50, |_, j| {
+            let (a, b) = (states[(0, j)], states[(1, j)]);
+            a * b + 2. * a * a - b
+        });
+
+        let projection = KernelStateProjection::via_nystroem_ridge_regression(
+            PolynomialKernel::new(1., 1., 2),
+            1e-10,
+            25,
+            &states,
+            targets.columns(0, 50),
+        );
+        let predictions = projection.project_many(states.columns(0, 50));
+        assert!((predictions - &targets).amax() < 1e-5);
+    }
+}
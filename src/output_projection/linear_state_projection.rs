@@ -1,45 +1,107 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
 
-use crate::ReservoirValue;
+use crate::{
+    compensated::{compensated_dot, cross_f64, gram_f64, is_reduced_precision},
+    hyperparameter::Regularization,
+    state_matrix::StateMatrix,
+    ReservoirValue,
+};
 use nalgebra::{
     ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector,
     DVectorSliceMut,
 };
 
+use num_traits::Float;
+
 use super::ReservoirStateProjection;
 
 #[derive(Clone, Debug)]
 pub struct LinearStateProjection<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> {
-    w_out: DMatrix<T>,
+    w_out: Arc<DMatrix<T>>,
+    posterior: Option<Arc<RidgePosterior<T>>>,
     result: DVector<T>,
+    standard_deviation: DVector<T>,
+    compensated: bool,
+}
+
+// Posterior of Bayesian ridge regression, the covariance of the readout weights of output o is
+// noise_variance[o] * (X X^T + beta I)^-1 = noise_variance[o] * F^T F.
+#[derive(Clone, Debug)]
+pub struct RidgePosterior<T: ReservoirValue> {
+    covariance_factor: DMatrix<T>,
+    noise_variance: DVector<T>,
+}
+
+impl<T: ReservoirValue> RidgePosterior<T> {
+    pub fn covariance_factor(&self) -> &DMatrix<T> {
+        &self.covariance_factor
+    }
+
+    pub fn noise_variance(&self) -> &DVector<T> {
+        &self.noise_variance
+    }
 }
 
 impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> LinearStateProjection<T> {
-    pub fn via_ridge_regression_nalgebra(
+    pub fn new_with_matrix(w_out: DMatrix<T>) -> Self {
+        Self::from_w_out(w_out)
+    }
+
+    fn from_w_out(w_out: DMatrix<T>) -> Self {
+        Self {
+            posterior: None,
+            result: DVector::zeros(w_out.nrows()),
+            standard_deviation: DVector::zeros(w_out.nrows()),
+            compensated: is_reduced_precision::<T>(),
+            w_out: Arc::new(w_out),
+        }
+    }
+
+    pub fn via_ridge_regression_nalgebra<S: StateMatrix<T> + ?Sized>(
         beta: T,
-        measured_states: &DMatrix<T>,
+        measured_states: &S,
         target_states: DMatrixSlice<T>,
     ) -> Self {
+        Regularization::new(beta).unwrap_or_else(|error| panic!("{error}"));
+        if is_reduced_precision::<T>() {
+            return Self::via_ridge_regression_f64_accumulation(
+                beta,
+                measured_states,
+                target_states,
+            );
+        }
         let dimension_measured_state = measured_states.nrows();
 
-        let measured_states_transpose = measured_states.transpose();
-        let target_state_transpose = target_states.transpose();
-
-        let rhs = measured_states * target_state_transpose;
+        let rhs = measured_states.cross(target_states);
         let lhs = if beta == T::zero() {
-            measured_states * &measured_states_transpose
+            measured_states.gram()
         } else {
             let mut reg_matrix = DMatrix::zeros(dimension_measured_state, dimension_measured_state);
             reg_matrix.fill_diagonal(beta);
-            measured_states * &measured_states_transpose + reg_matrix
+            measured_states.gram() + reg_matrix
         };
         let lu = nalgebra::LU::new(lhs);
         let w_out = lu.solve(&rhs).unwrap().transpose();
 
-        Self {
-            w_out,
-            result: DVector::zeros(target_states.nrows()),
+        Self::from_w_out(w_out)
+    }
+
+    // Accumulates X Xᵀ and X Yᵀ and solves in f64, the weights are rounded to T at the end.
+    // `via_ridge_regression_nalgebra` uses it for f32 states, where the Gram matrix of long
+    // trainings loses most of its digits otherwise.
+    pub fn via_ridge_regression_f64_accumulation<S: StateMatrix<T> + ?Sized>(
+        beta: T,
+        measured_states: &S,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        Regularization::new(beta).unwrap_or_else(|error| panic!("{error}"));
+        let mut lhs = gram_f64(measured_states);
+        for index in 0..lhs.nrows() {
+            lhs[(index, index)] += beta.to_f64().unwrap();
         }
+        let rhs = cross_f64(measured_states, target_states);
+        let w_out = nalgebra::LU::new(lhs).solve(&rhs).unwrap().transpose();
+        Self::from_w_out(w_out.map(|weight| T::from_f64(weight).unwrap()))
     }
 
     pub fn via_tikhonov_regularization_nalgebra(
@@ -58,24 +120,174 @@ impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> LinearStateProjec
         let lu = nalgebra::LU::new(lhs);
         let w_out = lu.solve(&rhs).unwrap().transpose();
 
-        Self {
-            w_out,
-            result: DVector::zeros(target_states.nrows()),
+        Self::from_w_out(w_out)
+    }
+
+    // One ridge regression per group of output dimensions, each with its own beta. The Gram
+    // matrix X X^T = Q L Q^T is decomposed once, so every group only costs a product with
+    // Q (L + beta I)^-1 Q^T. Every output dimension has to be in exactly one group.
+    pub fn via_grouped_ridge_regression<S: StateMatrix<T> + ?Sized>(
+        groups: &[(Vec<usize>, T)],
+        measured_states: &S,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        assert_eq!(measured_states.ncols(), target_states.ncols());
+        let mut assigned = vec![false; target_states.nrows()];
+        for (dimensions, beta) in groups {
+            Regularization::new(*beta).unwrap_or_else(|error| panic!("{error}"));
+            for dimension in dimensions {
+                assert!(
+                    !std::mem::replace(&mut assigned[*dimension], true),
+                    "Output dimension {dimension} is in more than one group."
+                );
+            }
+        }
+        assert!(
+            assigned.iter().all(|assigned| *assigned),
+            "Every output dimension needs a group."
+        );
+
+        let eigen = nalgebra::SymmetricEigen::new(measured_states.gram());
+        let projected_rhs = eigen.eigenvectors.transpose() * measured_states.cross(target_states);
+
+        let mut w_out = DMatrix::zeros(target_states.nrows(), measured_states.nrows());
+        for (dimensions, beta) in groups {
+            for dimension in dimensions {
+                let mut column = projected_rhs.column(*dimension).clone_owned();
+                column.component_div_assign(&eigen.eigenvalues.add_scalar(*beta));
+                w_out
+                    .row_mut(*dimension)
+                    .tr_copy_from(&(&eigen.eigenvectors * column));
+            }
         }
+
+        Self::from_w_out(w_out)
     }
 
-    fn impl_project(w_out: &DMatrix<T>, state: &DVector<T>, mut result: DVectorSliceMut<T>) {
-        w_out.mul_to(state, &mut result);
+    // Same weights as the ridge regression, additionally keeps the posterior so that
+    // `project_with_standard_deviation` can report the predictive uncertainty.
+    pub fn via_bayesian_ridge_regression(
+        beta: T,
+        measured_states: &DMatrix<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        assert!(
+            beta > T::zero(),
+            "Bayesian ridge regression needs beta > 0."
+        );
+        assert_eq!(measured_states.ncols(), target_states.ncols());
+        let dimension_measured_state = measured_states.nrows();
+
+        let lhs = measured_states * measured_states.transpose()
+            + DMatrix::from_diagonal_element(
+                dimension_measured_state,
+                dimension_measured_state,
+                beta,
+            );
+        let rhs = measured_states * target_states.transpose();
+        let cholesky =
+            nalgebra::Cholesky::new(lhs).expect("Ridge matrix is not positive definite.");
+        let w_out = cholesky.solve(&rhs).transpose();
+
+        // With X X^T + beta I = L L^T the inverse is F^T F with F = L^-1.
+        let covariance_factor = cholesky
+            .l()
+            .solve_lower_triangular(&DMatrix::identity(
+                dimension_measured_state,
+                dimension_measured_state,
+            ))
+            .unwrap();
+
+        let residuals = &w_out * measured_states - target_states;
+        let samples = T::from_usize(measured_states.ncols()).unwrap();
+        let noise_variance = DVector::from_iterator(
+            residuals.nrows(),
+            residuals.row_iter().map(|row| row.norm_squared() / samples),
+        );
+
+        let mut projection = Self::from_w_out(w_out);
+        projection.posterior = Some(Arc::new(RidgePosterior {
+            covariance_factor,
+            noise_variance,
+        }));
+        projection
+    }
+
+    pub fn w_out(&self) -> &DMatrix<T> {
+        &self.w_out
+    }
+
+    pub fn posterior(&self) -> Option<&RidgePosterior<T>> {
+        self.posterior.as_deref()
+    }
+
+    // Compensated sums instead of the plain matrix product in every projection, on by default
+    // for f32 and other types less precise than f64.
+    pub fn with_compensated_summation(mut self, compensated: bool) -> Self {
+        self.compensated = compensated;
+        self
+    }
+
+    pub fn compensated_summation(&self) -> bool {
+        self.compensated
+    }
+
+    // Prediction and per output predictive standard deviation
+    // sqrt(noise_variance * (1 + s^T (X X^T + beta I)^-1 s)).
+    pub fn project_with_standard_deviation(
+        &mut self,
+        state: &DVector<T>,
+    ) -> (&DVector<T>, &DVector<T>) {
+        let posterior = self
+            .posterior
+            .as_ref()
+            .expect("The projection was not trained via Bayesian ridge regression.");
+        let weight_variance = (&posterior.covariance_factor * state).norm_squared();
+        for (standard_deviation, noise_variance) in self
+            .standard_deviation
+            .iter_mut()
+            .zip(posterior.noise_variance.iter())
+        {
+            *standard_deviation = Float::sqrt(*noise_variance * (T::one() + weight_variance));
+        }
+
+        let vec_slice_mut = DVectorSliceMut::from(self.result.as_mut_slice());
+        Self::impl_project(&self.w_out, self.compensated, state, vec_slice_mut);
+        (&self.result, &self.standard_deviation)
+    }
+
+    fn impl_project(
+        w_out: &DMatrix<T>,
+        compensated: bool,
+        state: &DVector<T>,
+        mut result: DVectorSliceMut<T>,
+    ) {
+        if !compensated {
+            w_out.mul_to(state, &mut result);
+            return;
+        }
+        for (value, row) in result.iter_mut().zip(w_out.row_iter()) {
+            *value = compensated_dot(row.iter().copied(), state.iter().copied());
+        }
     }
 
     fn impl_project_many(
         w_out: &DMatrix<T>,
+        compensated: bool,
         states: DMatrixSlice<T>,
         mut targets: DMatrixSliceMut<T>,
     ) {
         assert_eq!(w_out.nrows(), targets.nrows());
         assert_eq!(states.ncols(), targets.ncols());
-        w_out.mul_to(&states, &mut targets);
+        if !compensated {
+            w_out.mul_to(&states, &mut targets);
+            return;
+        }
+        for (state, mut target) in states.column_iter().zip(targets.column_iter_mut()) {
+            for (value, row) in target.iter_mut().zip(w_out.row_iter()) {
+                *value = compensated_dot(row.iter().copied(), state.iter().copied());
+            }
+        }
     }
 }
 
@@ -117,10 +329,7 @@ impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> LinearStateProjec
         let lu = nalgebra::LU::new(lhs);
         let w_out = lu.solve(&rhs).unwrap().transpose();
 
-        Self {
-            w_out,
-            result: DVector::zeros(target_states.nrows()),
-        }
+        Self::from_w_out(w_out)
     }
 }
 
@@ -137,21 +346,148 @@ impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> ReservoirStatePro
 
     fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
         let vec_slice_mut = DVectorSliceMut::from(self.result.as_mut_slice());
-        Self::impl_project(&self.w_out, state, vec_slice_mut);
+        Self::impl_project(&self.w_out, self.compensated, state, vec_slice_mut);
         &self.result
     }
 
     fn project_into(&self, state: &DVector<T>, target: DVectorSliceMut<T>) {
-        Self::impl_project(&self.w_out, state, target);
+        Self::impl_project(&self.w_out, self.compensated, state, target);
     }
 
     fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
         let mut targets = DMatrix::zeros(self.w_out.nrows(), states.ncols());
-        Self::impl_project_many(&self.w_out, states, targets.columns_mut(0, targets.ncols()));
+        Self::impl_project_many(
+            &self.w_out,
+            self.compensated,
+            states,
+            targets.columns_mut(0, targets.ncols()),
+        );
         targets
     }
 
     fn project_many_into(&self, states: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
-        Self::impl_project_many(&self.w_out, states, targets)
+        Self::impl_project_many(&self.w_out, self.compensated, states, targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinearStateProjection;
+    use crate::output_projection::ReservoirStateProjection;
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn grouped_ridge_matches_separate_regressions() {
+        let states =
+            DMatrix::<f64>::from_fn(4, 30, |i, j| ((i + 1) as f64 * j as f64 * 0.37).sin());
+        let targets = DMatrix::from_fn(3, 30, |i, j| ((i + 2) as f64 * j as f64 * 0.21).cos());
+
+        let grouped = LinearStateProjection::via_grouped_ridge_regression(
+            &[(vec![0, 2], 1e-3), (vec![1], 0.5)],
+            &states,
+            targets.columns(0, 30),
+        );
+        let small = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-3,
+            &states,
+            targets.columns(0, 30),
+        );
+        let large = LinearStateProjection::via_ridge_regression_nalgebra(
+            0.5,
+            &states,
+            targets.columns(0, 30),
+        );
+
+        for (dimension, expected) in [(0, &small), (1, &large), (2, &small)] {
+            assert!(
+                (grouped.w_out().row(dimension) - expected.w_out().row(dimension)).amax() < 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn f32_ridge_regression_accumulates_in_f64() {
+        // Large offsets make X Xᵀ badly conditioned, the signal sits in small deviations.
+        let samples = 5000;
+        let states = DMatrix::<f64>::from_fn(3, samples, |i, j| match i {
+            0 => 1.,
+            1 => 100. + (j as f64 * 0.013).sin(),
+            _ => 100. + (j as f64 * 0.029).cos(),
+        });
+        let targets = DMatrix::from_fn(1, samples, |_, j| {
+            0.5 * states[(1, j)] - 2. * states[(2, j)] + 150.
+        });
+        let reference = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-6,
+            &states,
+            targets.columns(0, samples),
+        );
+        assert!(!reference.compensated_summation());
+
+        let states_f32 = states.map(|value| value as f32);
+        let targets_f32 = targets.map(|value| value as f32);
+        let mut projection = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-6,
+            &states_f32,
+            targets_f32.columns(0, samples),
+        );
+        assert!(projection.compensated_summation());
+        let naive_lhs =
+            &states_f32 * states_f32.transpose() + DMatrix::from_diagonal_element(3, 3, 1e-6);
+        let naive_w_out = nalgebra::LU::new(naive_lhs)
+            .solve(&(&states_f32 * targets_f32.transpose()))
+            .unwrap()
+            .transpose();
+
+        let error =
+            |w_out: DMatrix<f64>| (w_out * &states - &targets).norm() / (samples as f64).sqrt();
+        let accumulated_error = error(projection.w_out().map(f64::from));
+        let naive_error = error(naive_w_out.map(f64::from));
+        assert!(accumulated_error < 1e-3);
+        assert!(accumulated_error * 10. < naive_error);
+
+        let state = DVector::from_column_slice(&[1f32, 100.5, 99.5]);
+        let expected = reference.w_out() * state.map(f64::from);
+        assert!((f64::from(projection.project(&state)[0]) - expected[0]).abs() < 1e-2);
+    }
+
+    #[test]
+    fn bayesian_ridge_standard_deviation() {
+        // Features [x, 1], targets 2x + 1 with an alternating error of +-0.1.
+        let samples = 200;
+        let states = DMatrix::from_fn(2, samples, |i, j| {
+            if i == 0 {
+                j as f64 / samples as f64
+            } else {
+                1.
+            }
+        });
+        let targets = DMatrix::from_fn(1, samples, |_, j| {
+            2. * states[(0, j)] + 1. + if j % 2 == 0 { 0.1 } else { -0.1 }
+        });
+
+        let ridge = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-6,
+            &states,
+            targets.columns(0, samples),
+        );
+        let mut bayesian = LinearStateProjection::via_bayesian_ridge_regression(
+            1e-6,
+            &states,
+            targets.columns(0, samples),
+        );
+        assert!(ridge.posterior().is_none());
+        assert!((ridge.w_out() - bayesian.w_out()).norm() < 1e-8);
+
+        let inside = DVector::from_vec(vec![0.5, 1.]);
+        let (prediction, standard_deviation) = bayesian.project_with_standard_deviation(&inside);
+        assert!((prediction[0] - 2.).abs() < 0.05);
+        let inside_deviation = standard_deviation[0];
+        assert!((inside_deviation - 0.1).abs() < 0.005);
+
+        // The weight uncertainty grows away from the training data.
+        let outside = DVector::from_vec(vec![20., 1.]);
+        let (_, standard_deviation) = bayesian.project_with_standard_deviation(&outside);
+        assert!(standard_deviation[0] > inside_deviation * 1.5);
     }
 }
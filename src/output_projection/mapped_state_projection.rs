@@ -0,0 +1,130 @@
+use std::fmt::Debug;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// Runs `map` on every predicted output vector after the inner projection, e.g. to clamp to a
+// physical range or to renormalize onto a simplex. The mapped output is what gets fed back in
+// closed loop.
+#[derive(Clone)]
+pub struct MappedStateProjection<T, P, F>
+where
+    T: ReservoirValue,
+    P: ReservoirStateProjection<T>,
+    F: Fn(DVectorSliceMut<T>),
+{
+    projection: P,
+    map: F,
+    result: DVector<T>,
+}
+
+impl<T, P, F> Debug for MappedStateProjection<T, P, F>
+where
+    T: ReservoirValue,
+    P: ReservoirStateProjection<T>,
+    F: Fn(DVectorSliceMut<T>),
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MappedStateProjection{{ {:?} }}", self.projection)
+    }
+}
+
+impl<T, P, F> MappedStateProjection<T, P, F>
+where
+    T: ReservoirValue,
+    P: ReservoirStateProjection<T>,
+    F: Fn(DVectorSliceMut<T>),
+{
+    pub fn new(projection: P, map: F) -> Self {
+        Self {
+            result: DVector::zeros(projection.output_dimension()),
+            projection,
+            map,
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.projection
+    }
+
+    pub fn into_inner(self) -> P {
+        self.projection
+    }
+
+    fn map_many(&self, mut targets: DMatrixSliceMut<T>) {
+        for column in 0..targets.ncols() {
+            (self.map)(targets.column_mut(column));
+        }
+    }
+}
+
+impl<T, P, F> ReservoirStateProjection<T> for MappedStateProjection<T, P, F>
+where
+    T: ReservoirValue,
+    P: ReservoirStateProjection<T>,
+    F: Fn(DVectorSliceMut<T>),
+{
+    fn output_dimension(&self) -> usize {
+        self.projection.output_dimension()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.projection.input_dimension()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let prediction = self.projection.project(state);
+        self.result.copy_from(prediction);
+        (self.map)(self.result.rows_mut(0, self.result.nrows()));
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        self.projection
+            .project_into(state, target.rows_mut(0, target.nrows()));
+        (self.map)(target);
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = self.projection.project_many(states);
+        self.map_many(targets.columns_mut(0, targets.ncols()));
+        targets
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        self.projection
+            .project_many_into(states, targets.columns_mut(0, targets.ncols()));
+        self.map_many(targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MappedStateProjection;
+    use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+    use nalgebra::{DMatrix, DVector, DVectorSliceMut};
+
+    #[test]
+    fn mapped_projection_clamps_outputs() {
+        let projection = LinearStateProjection::new_with_matrix(DMatrix::<f64>::identity(2, 2));
+        let mut mapped =
+            MappedStateProjection::new(projection, |mut output: DVectorSliceMut<f64>| {
+                output.apply(|value| *value = value.clamp(-1., 1.))
+            });
+
+        let state = DVector::from_vec(vec![3., -0.5]);
+        assert_eq!(*mapped.project(&state), DVector::from_vec(vec![1., -0.5]));
+        let mut target = DVector::zeros(2);
+        mapped.project_into(&state, target.rows_mut(0, 2));
+        assert_eq!(target, DVector::from_vec(vec![1., -0.5]));
+
+        let states = DMatrix::from_vec(2, 2, vec![3., -0.5, -4., 0.25]);
+        let expected = DMatrix::from_vec(2, 2, vec![1., -0.5, -1., 0.25]);
+        assert_eq!(mapped.project_many(states.columns(0, 2)), expected);
+        let mut targets = DMatrix::zeros(2, 2);
+        mapped.project_many_into(states.columns(0, 2), targets.columns_mut(0, 2));
+        assert_eq!(targets, expected);
+    }
+}
@@ -3,8 +3,27 @@ use std::fmt::Debug;
 use crate::ReservoirValue;
 use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSliceMut};
 
+pub mod affine_output_transform;
+pub mod angle_wrapped_state_projection;
+pub mod kernel_state_projection;
 pub mod linear_state_projection;
-pub use linear_state_projection::LinearStateProjection;
+pub mod mapped_state_projection;
+pub mod online_training;
+pub mod quantile_state_projection;
+pub mod sparse_linear_state_projection;
+pub use affine_output_transform::AffineOutputTransform;
+pub use angle_wrapped_state_projection::{wrap_angle, wrap_angles, AngleWrappedStateProjection};
+pub use kernel_state_projection::{
+    KernelStateProjection, PolynomialKernel, RbfKernel, StateKernel,
+};
+pub use linear_state_projection::{LinearStateProjection, RidgePosterior};
+pub use mapped_state_projection::MappedStateProjection;
+pub use online_training::{
+    LeastMeanSquares, OnlineReadoutTrainer, OnlineTrainingReport, RecursiveLeastSquares,
+    ReportingTrainer,
+};
+pub use quantile_state_projection::QuantileStateProjection;
+pub use sparse_linear_state_projection::SparseLinearStateProjection;
 
 pub trait ReservoirStateProjection<T: ReservoirValue>: Debug {
     fn output_dimension(&self) -> usize;
@@ -0,0 +1,356 @@
+use nalgebra::{DMatrix, DVector, DVectorSlice};
+
+use super::LinearStateProjection;
+use crate::ReservoirValue;
+
+// Adapts a linear readout sample by sample instead of by one regression over all recorded
+// states, e.g. to follow a deployment or for FORCE learning, where the trainer is updated in
+// closed loop with the reservoir driven by its own predictions.
+pub trait OnlineReadoutTrainer<T: ReservoirValue> {
+    fn w_out(&self) -> &DMatrix<T>;
+
+    // Returns the a priori error, `target` minus the prediction before the update.
+    fn update(&mut self, measured_state: DVectorSlice<T>, target: DVectorSlice<T>) -> DVector<T>;
+
+    // Number of updates over which old samples are forgotten, infinite without forgetting.
+    fn effective_memory(&self) -> T {
+        <T as num_traits::Float>::infinity()
+    }
+
+    fn projection(&self) -> LinearStateProjection<T> {
+        LinearStateProjection::new_with_matrix(self.w_out().clone())
+    }
+}
+
+// Recursive least squares, after n updates the readout equals the ridge regression with
+// parameter `beta` on the n samples seen. P is the inverse of the regularized state
+// correlation matrix. With a forgetting factor λ < 1 the samples are weighted by λ^age, so
+// the readout tracks slowly changing systems. Forgetting without enough excitation lets P grow
+// without bound (windup), so P can be reset to the initial I / beta whenever its trace leaves
+// [min_trace, max_trace]; a lower bound keeps the trainer from freezing.
+#[derive(Clone, Debug)]
+pub struct RecursiveLeastSquares<T: ReservoirValue> {
+    w_out: DMatrix<T>,
+    inverse_correlation: DMatrix<T>,
+    gain: DVector<T>,
+    beta: T,
+    forgetting_factor: T,
+    min_trace: T,
+    max_trace: T,
+    resets: usize,
+}
+
+impl<T: ReservoirValue> RecursiveLeastSquares<T> {
+    pub fn new(state_dimension: usize, output_dimension: usize, beta: T) -> Self {
+        Self::new_with_w_out(DMatrix::zeros(output_dimension, state_dimension), beta)
+    }
+
+    // Starts from a trained readout, `beta` sets how strongly it is trusted.
+    pub fn new_with_w_out(w_out: DMatrix<T>, beta: T) -> Self {
+        assert!(beta > T::zero());
+        let state_dimension = w_out.ncols();
+        Self {
+            inverse_correlation: DMatrix::identity(state_dimension, state_dimension) / beta,
+            gain: DVector::zeros(state_dimension),
+            w_out,
+            beta,
+            forgetting_factor: T::one(),
+            min_trace: T::zero(),
+            max_trace: <T as num_traits::Float>::infinity(),
+            resets: 0,
+        }
+    }
+
+    // λ in (0, 1], 1 keeps all samples.
+    pub fn with_forgetting_factor(mut self, forgetting_factor: T) -> Self {
+        assert!(
+            forgetting_factor > T::zero() && forgetting_factor <= T::one(),
+            "The forgetting factor must be in (0, 1]."
+        );
+        self.forgetting_factor = forgetting_factor;
+        self
+    }
+
+    // Bounds on the trace of P outside of which P is reset to I / beta.
+    pub fn with_covariance_reset(mut self, min_trace: T, max_trace: T) -> Self {
+        assert!(min_trace >= T::zero() && min_trace < max_trace);
+        self.min_trace = min_trace;
+        self.max_trace = max_trace;
+        self
+    }
+
+    pub fn forgetting_factor(&self) -> T {
+        self.forgetting_factor
+    }
+
+    pub fn covariance_reset_bounds(&self) -> (T, T) {
+        (self.min_trace, self.max_trace)
+    }
+
+    // Number of covariance resets so far.
+    pub fn resets(&self) -> usize {
+        self.resets
+    }
+
+    pub fn inverse_correlation(&self) -> &DMatrix<T> {
+        &self.inverse_correlation
+    }
+
+    pub fn reset_covariance(&mut self) {
+        let state_dimension = self.w_out.ncols();
+        self.inverse_correlation = DMatrix::identity(state_dimension, state_dimension) / self.beta;
+        self.resets += 1;
+    }
+}
+
+impl<T: ReservoirValue> OnlineReadoutTrainer<T> for RecursiveLeastSquares<T> {
+    fn w_out(&self) -> &DMatrix<T> {
+        &self.w_out
+    }
+
+    fn update(&mut self, measured_state: DVectorSlice<T>, target: DVectorSlice<T>) -> DVector<T> {
+        let error = target - &self.w_out * measured_state;
+        // k = P x / (λ + xᵀ P x), P is symmetric.
+        self.gain.gemv(
+            T::one(),
+            &self.inverse_correlation,
+            &measured_state,
+            T::zero(),
+        );
+        let denominator = self.forgetting_factor + measured_state.dot(&self.gain);
+        self.gain /= denominator;
+        self.w_out.ger(T::one(), &error, &self.gain, T::one());
+        // (P - k (P x)ᵀ) / λ = (P - k kᵀ (λ + xᵀ P x)) / λ
+        self.inverse_correlation
+            .ger(-denominator, &self.gain, &self.gain, T::one());
+        if self.forgetting_factor < T::one() {
+            self.inverse_correlation /= self.forgetting_factor;
+        }
+        let trace = self.inverse_correlation.trace();
+        if trace > self.max_trace || trace < self.min_trace {
+            self.reset_covariance();
+        }
+        error
+    }
+
+    // 1 / (1 - λ)
+    fn effective_memory(&self) -> T {
+        if self.forgetting_factor < T::one() {
+            T::one() / (T::one() - self.forgetting_factor)
+        } else {
+            <T as num_traits::Float>::infinity()
+        }
+    }
+}
+
+// Least mean squares, a stochastic gradient step on the squared error per update.
+#[derive(Clone, Debug)]
+pub struct LeastMeanSquares<T: ReservoirValue> {
+    w_out: DMatrix<T>,
+    learning_rate: T,
+}
+
+impl<T: ReservoirValue> LeastMeanSquares<T> {
+    pub fn new(state_dimension: usize, output_dimension: usize, learning_rate: T) -> Self {
+        Self::new_with_w_out(
+            DMatrix::zeros(output_dimension, state_dimension),
+            learning_rate,
+        )
+    }
+
+    pub fn new_with_w_out(w_out: DMatrix<T>, learning_rate: T) -> Self {
+        assert!(learning_rate > T::zero());
+        Self {
+            w_out,
+            learning_rate,
+        }
+    }
+
+    pub fn learning_rate(&self) -> T {
+        self.learning_rate
+    }
+}
+
+impl<T: ReservoirValue> OnlineReadoutTrainer<T> for LeastMeanSquares<T> {
+    fn w_out(&self) -> &DMatrix<T> {
+        &self.w_out
+    }
+
+    fn update(&mut self, measured_state: DVectorSlice<T>, target: DVectorSlice<T>) -> DVector<T> {
+        let error = target - &self.w_out * measured_state;
+        self.w_out
+            .ger(self.learning_rate, &error, &measured_state, T::one());
+        error
+    }
+}
+
+// Convergence summary over the updates since the previous report.
+#[derive(Clone, Debug)]
+pub struct OnlineTrainingReport<T: ReservoirValue> {
+    // Total number of updates so far.
+    pub updates: usize,
+    // Mean squared a priori error per output over the reported updates.
+    pub running_error: T,
+    // Frobenius norm of the readout change since the previous report.
+    pub weight_change_norm: T,
+    pub effective_memory: T,
+}
+
+// Wraps a trainer and hands an `OnlineTrainingReport` to `callback` every `interval` updates.
+pub struct ReportingTrainer<T: ReservoirValue, R: OnlineReadoutTrainer<T>, F> {
+    trainer: R,
+    interval: usize,
+    callback: F,
+    updates: usize,
+    squared_error: T,
+    reported_w_out: DMatrix<T>,
+}
+
+impl<T, R, F> ReportingTrainer<T, R, F>
+where
+    T: ReservoirValue,
+    R: OnlineReadoutTrainer<T>,
+    F: FnMut(&OnlineTrainingReport<T>),
+{
+    pub fn new(trainer: R, interval: usize, callback: F) -> Self {
+        assert!(interval > 0);
+        Self {
+            reported_w_out: trainer.w_out().clone(),
+            trainer,
+            interval,
+            callback,
+            updates: 0,
+            squared_error: T::zero(),
+        }
+    }
+
+    pub fn trainer(&self) -> &R {
+        &self.trainer
+    }
+
+    pub fn into_trainer(self) -> R {
+        self.trainer
+    }
+}
+
+impl<T, R, F> OnlineReadoutTrainer<T> for ReportingTrainer<T, R, F>
+where
+    T: ReservoirValue,
+    R: OnlineReadoutTrainer<T>,
+    F: FnMut(&OnlineTrainingReport<T>),
+{
+    fn w_out(&self) -> &DMatrix<T> {
+        self.trainer.w_out()
+    }
+
+    fn update(&mut self, measured_state: DVectorSlice<T>, target: DVectorSlice<T>) -> DVector<T> {
+        let error = self.trainer.update(measured_state, target);
+        self.updates += 1;
+        self.squared_error += error.norm_squared() / T::from_usize(error.nrows()).unwrap();
+        if self.updates.is_multiple_of(self.interval) {
+            let w_out = self.trainer.w_out();
+            let report = OnlineTrainingReport {
+                updates: self.updates,
+                running_error: self.squared_error / T::from_usize(self.interval).unwrap(),
+                weight_change_norm: (w_out - &self.reported_w_out).norm(),
+                effective_memory: self.trainer.effective_memory(),
+            };
+            self.reported_w_out.copy_from(w_out);
+            self.squared_error = T::zero();
+            (self.callback)(&report);
+        }
+        error
+    }
+
+    fn effective_memory(&self) -> T {
+        self.trainer.effective_memory()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::{LeastMeanSquares, OnlineReadoutTrainer, RecursiveLeastSquares, ReportingTrainer};
+    use crate::output_projection::LinearStateProjection;
+
+    fn samples() -> (DMatrix<f64>, DMatrix<f64>) {
+        let states = DMatrix::from_fn(4, 300, |i, j| ((i + 2) as f64 * j as f64 * 0.37).sin());
+        let w_out = DMatrix::from_row_slice(2, 4, &[0.5, -1., 0.25, 2., 1., 0., -0.5, 0.3]);
+        let targets = &w_out * &states;
+        (states, targets)
+    }
+
+    #[test]
+    fn recursive_least_squares_matches_ridge_regression() {
+        let (states, targets) = samples();
+        let mut rls = RecursiveLeastSquares::new(4, 2, 1e-3);
+        for (state, target) in states.column_iter().zip(targets.column_iter()) {
+            rls.update(state, target);
+        }
+        let ridge = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-3,
+            &states,
+            targets.columns(0, 300),
+        );
+        assert!((rls.w_out() - ridge.w_out()).amax() < 1e-8);
+        assert!(rls.effective_memory().is_infinite());
+    }
+
+    #[test]
+    fn reports_every_interval() {
+        let (states, targets) = samples();
+        let mut reports = vec![];
+        let mut lms = ReportingTrainer::new(LeastMeanSquares::new(4, 2, 0.2), 50, |report| {
+            reports.push(report.clone())
+        });
+        for (state, target) in states.column_iter().zip(targets.column_iter()) {
+            lms.update(state, target);
+        }
+        let error = (lms.w_out() * DVector::from_column_slice(states.column(7).as_slice())
+            - targets.column(7))
+        .amax();
+        assert!(error < 0.1, "{error}");
+
+        assert_eq!(reports.len(), 6);
+        assert_eq!(reports[2].updates, 150);
+        assert!(reports[5].running_error < 0.1 * reports[0].running_error);
+        assert!(reports[5].weight_change_norm < reports[0].weight_change_norm);
+    }
+
+    #[test]
+    fn forgetting_factor_tracks_a_changing_readout() {
+        let (states, targets) = samples();
+        let changed = DMatrix::from_row_slice(2, 4, &[-0.5, 1., 0.25, 1., 0., 1., 0.5, 0.3]);
+        let changed_targets = &changed * &states;
+        let tracking_error = |rls: &mut RecursiveLeastSquares<f64>| {
+            for (state, target) in states.column_iter().zip(targets.column_iter()) {
+                rls.update(state, target);
+            }
+            for (state, target) in states.column_iter().zip(changed_targets.column_iter()) {
+                rls.update(state, target);
+            }
+            (rls.w_out() - &changed).amax()
+        };
+        let mut forgetting =
+            RecursiveLeastSquares::<f64>::new(4, 2, 1e-3).with_forgetting_factor(0.95);
+        assert!((forgetting.effective_memory() - 20.).abs() < 1e-9);
+        let mut remembering = RecursiveLeastSquares::new(4, 2, 1e-3);
+        assert!(tracking_error(&mut forgetting) < 1e-6);
+        assert!(tracking_error(&mut remembering) > 0.1);
+    }
+
+    #[test]
+    fn covariance_is_reset_without_excitation() {
+        let mut rls = RecursiveLeastSquares::new(4, 2, 1.)
+            .with_forgetting_factor(0.9)
+            .with_covariance_reset(1e-6, 100.);
+        let (zero_state, target) = (DVector::zeros(4), DVector::zeros(2));
+        for _ in 0..100 {
+            rls.update(zero_state.column(0), target.column(0));
+            assert!(rls.inverse_correlation().trace() <= 100.);
+        }
+        // Starting at trace 4, P grows by 1 / 0.9 per update and crosses 100 after 31.
+        assert_eq!(rls.resets(), 3);
+    }
+}
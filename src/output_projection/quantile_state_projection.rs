@@ -0,0 +1,202 @@
+use std::fmt::Debug;
+
+use nalgebra::{
+    ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector,
+    DVectorSliceMut,
+};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// One linear readout per quantile, all of them acting on the same measured state. `project`
+// returns the quantile closest to the median, which is also what gets fed back in closed loop.
+#[derive(Clone, Debug)]
+pub struct QuantileStateProjection<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> {
+    quantiles: Vec<T>,
+    w_out: Vec<DMatrix<T>>,
+    feedback_quantile: usize,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> QuantileStateProjection<T> {
+    // Minimizes the ridge regularized pinball loss with iteratively reweighted least squares.
+    pub fn via_iteratively_reweighted_least_squares(
+        quantiles: &[T],
+        beta: T,
+        iterations: usize,
+        measured_states: &DMatrix<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        assert!(!quantiles.is_empty());
+        assert!(quantiles.iter().all(|q| *q > T::zero() && *q < T::one()));
+        assert_eq!(measured_states.ncols(), target_states.ncols());
+
+        let w_out = quantiles
+            .iter()
+            .map(|quantile| {
+                Self::fit_quantile(*quantile, beta, iterations, measured_states, target_states)
+            })
+            .collect();
+
+        let half = T::one() / (T::one() + T::one());
+        let feedback_quantile = quantiles
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                num_traits::Float::abs(**a - half)
+                    .partial_cmp(&num_traits::Float::abs(**b - half))
+                    .unwrap()
+            })
+            .map(|(index, _)| index)
+            .unwrap();
+
+        Self {
+            quantiles: quantiles.to_vec(),
+            w_out,
+            feedback_quantile,
+            result: DVector::zeros(target_states.nrows()),
+        }
+    }
+
+    fn fit_quantile(
+        quantile: T,
+        beta: T,
+        iterations: usize,
+        measured_states: &DMatrix<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> DMatrix<T> {
+        let feature_dimension = measured_states.nrows();
+        let samples = measured_states.ncols();
+        let epsilon = T::from_f64(1e-12).unwrap();
+        let regularization =
+            DMatrix::from_diagonal_element(feature_dimension, feature_dimension, beta);
+
+        let mut w_out = DMatrix::zeros(target_states.nrows(), feature_dimension);
+        for (output, target) in target_states.row_iter().enumerate() {
+            let mut sample_weights = DVector::from_element(samples, T::one());
+            let mut coefficients = DVector::zeros(feature_dimension);
+            for _ in 0..iterations.max(1) {
+                let mut weighted_states = measured_states.clone();
+                for (mut column, weight) in
+                    weighted_states.column_iter_mut().zip(sample_weights.iter())
+                {
+                    column *= *weight;
+                }
+                let lhs = &weighted_states * measured_states.transpose() + &regularization;
+                let rhs = &weighted_states * target.transpose();
+                coefficients = nalgebra::LU::new(lhs).solve(&rhs).unwrap();
+
+                let residuals = target.transpose() - measured_states.transpose() * &coefficients;
+                // Residuals below a fraction of the mean residual are clamped, otherwise a few
+                // interpolated samples dominate the fit. The weights are rescaled to mean one
+                // so that beta keeps the same meaning as in the first, unweighted iteration.
+                let floor = num_traits::Float::max(
+                    residuals.abs().mean() * T::from_f64(1e-2).unwrap(),
+                    epsilon,
+                );
+                for (weight, residual) in sample_weights.iter_mut().zip(residuals.iter()) {
+                    let slope = if *residual >= T::zero() {
+                        quantile
+                    } else {
+                        T::one() - quantile
+                    };
+                    *weight =
+                        slope / num_traits::Float::max(num_traits::Float::abs(*residual), floor);
+                }
+                let mean_weight = sample_weights.mean();
+                sample_weights /= mean_weight;
+            }
+            w_out.row_mut(output).copy_from(&coefficients.transpose());
+        }
+        w_out
+    }
+
+    pub fn quantiles(&self) -> &[T] {
+        &self.quantiles
+    }
+
+    pub fn feedback_quantile(&self) -> T {
+        self.quantiles[self.feedback_quantile]
+    }
+
+    pub fn w_out(&self, quantile_index: usize) -> &DMatrix<T> {
+        &self.w_out[quantile_index]
+    }
+
+    // Returns one column per quantile.
+    pub fn project_quantiles(&self, state: &DVector<T>) -> DMatrix<T> {
+        let mut result = DMatrix::zeros(self.output_dimension(), self.quantiles.len());
+        for (index, w_out) in self.w_out.iter().enumerate() {
+            w_out.mul_to(state, &mut result.column_mut(index));
+        }
+        result
+    }
+}
+
+impl<T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul> ReservoirStateProjection<T>
+    for QuantileStateProjection<T>
+{
+    fn output_dimension(&self) -> usize {
+        self.result.nrows()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.w_out[0].ncols()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        self.w_out[self.feedback_quantile].mul_to(state, &mut self.result);
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        self.w_out[self.feedback_quantile].mul_to(state, &mut target);
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        &self.w_out[self.feedback_quantile] * states
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        self.w_out[self.feedback_quantile].mul_to(&states, &mut targets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QuantileStateProjection;
+    use crate::output_projection::ReservoirStateProjection;
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn quantile_readout_brackets_noisy_targets() {
+        // Features [x, 1], targets x plus a deterministic spread in [-1, 1).
+        let samples = 400;
+        let states = DMatrix::from_fn(2, samples, |i, j| {
+            if i == 0 {
+                j as f64 / samples as f64
+            } else {
+                1.
+            }
+        });
+        let targets = DMatrix::from_fn(1, samples, |_, j| {
+            states[(0, j)] + ((j * 37) % 100) as f64 / 50. - 1.
+        });
+
+        let mut projection = QuantileStateProjection::via_iteratively_reweighted_least_squares(
+            &[0.1, 0.5, 0.9],
+            1e-8,
+            50,
+            &states,
+            targets.columns(0, samples),
+        );
+        assert_eq!(projection.feedback_quantile(), 0.5);
+
+        let state = DVector::from_vec(vec![0.5, 1.]);
+        let bands = projection.project_quantiles(&state);
+        assert!((bands[(0, 0)] - (0.5 - 0.8)).abs() < 0.05);
+        assert!((bands[(0, 1)] - 0.5).abs() < 0.05);
+        assert!((bands[(0, 2)] - (0.5 + 0.8)).abs() < 0.05);
+        assert_eq!(projection.project(&state)[0], bands[(0, 1)]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSlice, DVectorSliceMut};
+use nalgebra_sparse::{CooMatrix, CsrMatrix};
+
+use super::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// Linear readout with W_out stored as CSR, a projection costs O(nnz) instead of
+// O(output_dimension * input_dimension). Meant for readouts that are sparse after pruning or
+// L1 training and high dimensional measured states.
+#[derive(Clone, Debug)]
+pub struct SparseLinearStateProjection<T: ReservoirValue> {
+    w_out: Arc<CsrMatrix<T>>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue> SparseLinearStateProjection<T> {
+    pub fn new_with_matrix(w_out: CsrMatrix<T>) -> Self {
+        Self {
+            result: DVector::zeros(w_out.nrows()),
+            w_out: Arc::new(w_out),
+        }
+    }
+
+    // Keeps the weights of a dense W_out with an absolute value above `threshold`.
+    pub fn pruned(w_out: &DMatrix<T>, threshold: T) -> Self {
+        assert!(threshold >= T::zero());
+        let mut coo = CooMatrix::new(w_out.nrows(), w_out.ncols());
+        for (column, weights) in w_out.column_iter().enumerate() {
+            for (row, weight) in weights.iter().enumerate() {
+                if num_traits::Float::abs(*weight) > threshold {
+                    coo.push(row, column, *weight);
+                }
+            }
+        }
+        Self::new_with_matrix(CsrMatrix::from(&coo))
+    }
+
+    pub fn w_out(&self) -> &CsrMatrix<T> {
+        &self.w_out
+    }
+
+    pub fn nnz(&self) -> usize {
+        self.w_out.nnz()
+    }
+
+    fn impl_project(w_out: &CsrMatrix<T>, state: DVectorSlice<T>, mut target: DVectorSliceMut<T>) {
+        assert_eq!(state.nrows(), w_out.ncols());
+        assert_eq!(target.nrows(), w_out.nrows());
+        for (value, row) in target.iter_mut().zip(w_out.row_iter()) {
+            *value = T::zero();
+            for (column, weight) in row.col_indices().iter().zip(row.values()) {
+                *value += *weight * state[*column];
+            }
+        }
+    }
+}
+
+impl<T: ReservoirValue> ReservoirStateProjection<T> for SparseLinearStateProjection<T> {
+    fn output_dimension(&self) -> usize {
+        self.w_out.nrows()
+    }
+
+    fn input_dimension(&self) -> usize {
+        self.w_out.ncols()
+    }
+
+    fn project(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let vec_slice_mut = DVectorSliceMut::from(self.result.as_mut_slice());
+        Self::impl_project(&self.w_out, state.column(0), vec_slice_mut);
+        &self.result
+    }
+
+    fn project_into(&self, state: &DVector<T>, target: DVectorSliceMut<T>) {
+        Self::impl_project(&self.w_out, state.column(0), target);
+    }
+
+    fn project_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = DMatrix::zeros(self.w_out.nrows(), states.ncols());
+        self.project_many_into(states, targets.columns_mut(0, states.ncols()));
+        targets
+    }
+
+    fn project_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        assert_eq!(states.ncols(), targets.ncols());
+        for (state, target) in states.column_iter().zip(targets.column_iter_mut()) {
+            Self::impl_project(&self.w_out, state.column(0), target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::SparseLinearStateProjection;
+    use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+
+    #[test]
+    fn matches_the_pruned_dense_readout() {
+        let w_out = DMatrix::from_fn(3, 40, |i, j| {
+            let weight = ((i * 40 + j) as f64 * 0.7).sin();
+            if weight.abs() > 0.3 {
+                weight
+            } else {
+                weight * 1e-3
+            }
+        });
+        let mut sparse = SparseLinearStateProjection::pruned(&w_out, 0.01);
+        let pruned = w_out.map(|weight| if weight.abs() > 0.01 { weight } else { 0. });
+        assert_eq!(sparse.nnz(), pruned.iter().filter(|w| **w != 0.).count());
+        assert!(sparse.nnz() < w_out.len());
+        let dense = LinearStateProjection::new_with_matrix(pruned);
+
+        let states = DMatrix::from_fn(40, 5, |i, j| ((i + 3 * j) as f64 * 0.3).cos());
+        let expected = dense.project_many(states.columns(0, 5));
+        assert!((sparse.project_many(states.columns(0, 5)) - &expected).amax() < 1e-12);
+
+        let state: DVector<f64> = states.column(3).clone_owned();
+        assert!((sparse.project(&state) - expected.column(3)).amax() < 1e-12);
+        assert_eq!(
+            (sparse.input_dimension(), sparse.output_dimension()),
+            (40, 3)
+        );
+    }
+}
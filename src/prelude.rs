@@ -0,0 +1,20 @@
+// The items most setups combine, `use rescomp::prelude::*;` replaces the separate imports.
+pub use crate::activation_function::{
+    ActivationFunctionWrapper, ActiviationFunction, BiasedActivationFunction, Relu, Tanh,
+};
+pub use crate::echo_state_network::{
+    EchoStateNetworkBuilder, SparseDiscreteEchoStateNetwork, SparseLeakyIntegratorEchoStateNetwork,
+};
+pub use crate::fit_predict::{fit_predict, FitPredictConfig};
+pub use crate::hyperparameter::{LeakRate, Regularization, SpectralRadius};
+pub use crate::input_projection::{
+    DefaultInputProjection, InputProjectionWithEmbedding, ReservoirInputProjection,
+};
+pub use crate::metrics::{normalized_root_mean_squared_error, valid_time};
+pub use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+pub use crate::reservoir::{training::ReservoirTraining, TrainingReport};
+pub use crate::state_measurement::{
+    ConstantExtensionStateMeasurement, DefaultStateMeasurement, ReservoirStateMeasurement,
+};
+pub use crate::time_evolution::ReservoirTimeEvolution;
+pub use crate::{Reservoir, ReservoirComputer, ReservoirError, ReservoirValue};
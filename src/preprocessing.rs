@@ -0,0 +1,501 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::random::standard_normal;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::{ReservoirComputer, ReservoirValue};
+
+// Delay embedding of `data`, columns are time steps. Column j of the result belongs to time
+// t = j + max(lags) and stacks data.column(t - lag) for every lag in the given order, so the
+// result has nrows * lags.len() rows and ncols - max(lags) columns. Include lag 0 to keep the
+// current value.
+pub fn delay_embed<T: ReservoirValue>(data: DMatrixSlice<T>, lags: &[usize]) -> DMatrix<T> {
+    assert!(!lags.is_empty());
+    let offset = embedding_offset(lags);
+    assert!(
+        data.ncols() > offset,
+        "Embedding with lag {offset} needs more than {offset} columns."
+    );
+    let dimension = data.nrows();
+    let columns = data.ncols() - offset;
+    let mut embedded = DMatrix::zeros(dimension * lags.len(), columns);
+    for (block, lag) in lags.iter().enumerate() {
+        embedded
+            .slice_mut((block * dimension, 0), (dimension, columns))
+            .copy_from(&data.columns(offset - lag, columns));
+    }
+    embedded
+}
+
+// Number of leading columns lost by the embedding, the largest lag.
+pub fn embedding_offset(lags: &[usize]) -> usize {
+    lags.iter().copied().max().unwrap_or(0)
+}
+
+// The columns of `data` matching the columns of `delay_embed(data, lags)`, e.g. the targets of
+// a readout trained on the embedding.
+pub fn align_to_embedding<'a, T: ReservoirValue>(
+    data: &'a DMatrix<T>,
+    lags: &[usize],
+) -> DMatrixSlice<'a, T> {
+    let offset = embedding_offset(lags);
+    assert!(data.ncols() > offset);
+    data.columns(offset, data.ncols() - offset)
+}
+
+// Inverse of `delay_embed`, recovers the original `dimension` x (ncols + max(lags)) series.
+// Every column of the original series has to appear in some lag block.
+pub fn delay_unembed<T: ReservoirValue>(
+    embedded: DMatrixSlice<T>,
+    dimension: usize,
+    lags: &[usize],
+) -> DMatrix<T> {
+    assert_eq!(embedded.nrows(), dimension * lags.len());
+    let offset = embedding_offset(lags);
+    let columns = embedded.ncols();
+    let mut data = DMatrix::zeros(dimension, columns + offset);
+    let mut covered = vec![false; columns + offset];
+    for (block, lag) in lags.iter().enumerate() {
+        data.columns_mut(offset - lag, columns)
+            .copy_from(&embedded.slice((block * dimension, 0), (dimension, columns)));
+        covered[offset - lag..offset - lag + columns].fill(true);
+    }
+    assert!(
+        covered.iter().all(|&covered| covered),
+        "The lags do not cover every column of the original series."
+    );
+    data
+}
+
+// Centered moving average over 2 * half_window + 1 columns, the window shrinks at the edges.
+pub fn moving_average<T: ReservoirValue>(data: DMatrixSlice<T>, half_window: usize) -> DMatrix<T> {
+    let columns = data.ncols();
+    DMatrix::from_fn(data.nrows(), columns, |row, column| {
+        let start = column.saturating_sub(half_window);
+        let end = (column + half_window + 1).min(columns);
+        let sum = (start..end).fold(T::zero(), |acc, t| acc + data[(row, t)]);
+        sum / T::from_usize(end - start).unwrap()
+    })
+}
+
+// Savitzky-Golay smoothing, every column is replaced by the value of a least squares
+// polynomial of `polynomial_order` through the 2 * half_window + 1 surrounding columns.
+pub fn savitzky_golay<T: ReservoirValue>(
+    data: DMatrixSlice<T>,
+    half_window: usize,
+    polynomial_order: usize,
+) -> DMatrix<T> {
+    savitzky_golay_derivative(data, half_window, polynomial_order, 0, T::one())
+}
+
+// Derivative of order `derivative` of the local Savitzky-Golay polynomial, `dt` is the time
+// between two columns. The first and last half_window columns are evaluated on the polynomial
+// of the first and last full window instead of padding the data.
+pub fn savitzky_golay_derivative<T: ReservoirValue>(
+    data: DMatrixSlice<T>,
+    half_window: usize,
+    polynomial_order: usize,
+    derivative: usize,
+    dt: T,
+) -> DMatrix<T> {
+    let window = 2 * half_window + 1;
+    assert!(
+        polynomial_order < window,
+        "A polynomial of order {polynomial_order} is not determined by {window} points."
+    );
+    assert!(derivative <= polynomial_order);
+    assert!(dt > T::zero());
+    assert!(
+        data.ncols() >= window,
+        "The data is shorter than the window of {window} columns."
+    );
+
+    // Row k of weights evaluates the fit through the window at window position k.
+    let weights = savitzky_golay_weights::<T>(half_window, polynomial_order, derivative)
+        / num_traits::Float::powi(dt, derivative as i32);
+    let columns = data.ncols();
+    DMatrix::from_fn(data.nrows(), columns, |row, column| {
+        let (start, position) = if column < half_window {
+            (0, column)
+        } else if column + half_window >= columns {
+            (columns - window, column + window - columns)
+        } else {
+            (column - half_window, half_window)
+        };
+        (0..window).fold(T::zero(), |acc, k| {
+            acc + weights[(position, k)] * data[(row, start + k)]
+        })
+    })
+}
+
+fn savitzky_golay_weights<T: ReservoirValue>(
+    half_window: usize,
+    polynomial_order: usize,
+    derivative: usize,
+) -> DMatrix<T> {
+    let window = 2 * half_window + 1;
+    let offset = |k: usize| T::from_usize(k).unwrap() - T::from_usize(half_window).unwrap();
+    let vandermonde = DMatrix::from_fn(window, polynomial_order + 1, |k, power| {
+        num_traits::Float::powi(offset(k), power as i32)
+    });
+    let vandermonde_transpose = vandermonde.transpose();
+    // Maps the window values to the polynomial coefficients.
+    let fit = (&vandermonde_transpose * &vandermonde)
+        .try_inverse()
+        .expect("The Savitzky-Golay normal equations are singular.")
+        * vandermonde_transpose;
+    let evaluation = DMatrix::from_fn(window, polynomial_order + 1, |k, power| {
+        if power < derivative {
+            T::zero()
+        } else {
+            let factor = ((power - derivative + 1)..=power)
+                .fold(T::one(), |acc, factor| acc * T::from_usize(factor).unwrap());
+            factor * num_traits::Float::powi(offset(k), (power - derivative) as i32)
+        }
+    });
+    evaluation * fit
+}
+
+// Linear trend intercept + slope * t per row, t is the column index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinearTrend<T: ReservoirValue> {
+    intercept: DVector<T>,
+    slope: DVector<T>,
+}
+
+impl<T: ReservoirValue> LinearTrend<T> {
+    pub fn new(intercept: DVector<T>, slope: DVector<T>) -> Self {
+        assert_eq!(intercept.nrows(), slope.nrows());
+        Self { intercept, slope }
+    }
+
+    // Least squares line through every row of `data`.
+    pub fn fit(data: DMatrixSlice<T>) -> Self {
+        assert!(data.ncols() > 1, "A trend needs at least two columns.");
+        let samples = T::from_usize(data.ncols()).unwrap();
+        let time_mean = T::from_usize(data.ncols() - 1).unwrap() / T::from_f64(2.).unwrap();
+        let time_variance = (0..data.ncols()).fold(T::zero(), |acc, t| {
+            let centered = T::from_usize(t).unwrap() - time_mean;
+            acc + centered * centered
+        });
+        let mut intercept = DVector::zeros(data.nrows());
+        let mut slope = DVector::zeros(data.nrows());
+        for (row, values) in data.row_iter().enumerate() {
+            let mean = values.sum() / samples;
+            let covariance = values
+                .iter()
+                .enumerate()
+                .fold(T::zero(), |acc, (t, value)| {
+                    acc + (T::from_usize(t).unwrap() - time_mean) * (*value - mean)
+                });
+            slope[row] = covariance / time_variance;
+            intercept[row] = mean - slope[row] * time_mean;
+        }
+        Self { intercept, slope }
+    }
+
+    pub fn intercept(&self) -> &DVector<T> {
+        &self.intercept
+    }
+
+    pub fn slope(&self) -> &DVector<T> {
+        &self.slope
+    }
+
+    // `start` is the time of the first column of `data`.
+    pub fn remove(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        self.apply(data, start, -T::one())
+    }
+
+    pub fn reapply(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        self.apply(data, start, T::one())
+    }
+
+    fn apply(&self, data: DMatrixSlice<T>, start: usize, sign: T) -> DMatrix<T> {
+        assert_eq!(data.nrows(), self.slope.nrows());
+        DMatrix::from_fn(data.nrows(), data.ncols(), |row, column| {
+            let time = T::from_usize(start + column).unwrap();
+            data[(row, column)] + sign * (self.intercept[row] + self.slope[row] * time)
+        })
+    }
+}
+
+// Seasonal component of a known period, one column per phase. Column t belongs to phase
+// t % period.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SeasonalComponent<T: ReservoirValue> {
+    profile: DMatrix<T>,
+}
+
+impl<T: ReservoirValue> SeasonalComponent<T> {
+    pub fn new(profile: DMatrix<T>) -> Self {
+        assert!(profile.ncols() > 0);
+        Self { profile }
+    }
+
+    // Mean of every phase minus the overall mean, so the component sums to zero over a period.
+    pub fn fit(data: DMatrixSlice<T>, period: usize) -> Self {
+        assert!(period > 0);
+        assert!(
+            data.ncols() >= period,
+            "Fitting a period of {period} needs at least one full period of data."
+        );
+        let mut profile = DMatrix::zeros(data.nrows(), period);
+        let mut counts = vec![0usize; period];
+        for (t, column) in data.column_iter().enumerate() {
+            let mut phase = profile.column_mut(t % period);
+            phase += column;
+            counts[t % period] += 1;
+        }
+        for (phase, count) in counts.iter().enumerate() {
+            let mut column = profile.column_mut(phase);
+            column /= T::from_usize(*count).unwrap();
+        }
+        let mean = profile.column_mean();
+        for mut column in profile.column_iter_mut() {
+            column -= &mean;
+        }
+        Self { profile }
+    }
+
+    pub fn period(&self) -> usize {
+        self.profile.ncols()
+    }
+
+    pub fn profile(&self) -> &DMatrix<T> {
+        &self.profile
+    }
+
+    pub fn remove(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        self.apply(data, start, -T::one())
+    }
+
+    pub fn reapply(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        self.apply(data, start, T::one())
+    }
+
+    fn apply(&self, data: DMatrixSlice<T>, start: usize, sign: T) -> DMatrix<T> {
+        assert_eq!(data.nrows(), self.profile.nrows());
+        DMatrix::from_fn(data.nrows(), data.ncols(), |row, column| {
+            data[(row, column)] + sign * self.profile[(row, (start + column) % self.period())]
+        })
+    }
+}
+
+// Trend plus optional season. The reservoir computer is trained on the removed data, its closed
+// loop predictions are mapped back with `reapply` or directly via `predict_retrended`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrendDecomposition<T: ReservoirValue> {
+    trend: LinearTrend<T>,
+    seasonal: Option<SeasonalComponent<T>>,
+}
+
+impl<T: ReservoirValue> TrendDecomposition<T> {
+    // The season is fitted on the detrended data.
+    pub fn fit(data: DMatrixSlice<T>, period: Option<usize>) -> Self {
+        let trend = LinearTrend::fit(data);
+        let seasonal = period.map(|period| {
+            let detrended = trend.remove(data, 0);
+            SeasonalComponent::fit(detrended.columns(0, detrended.ncols()), period)
+        });
+        Self { trend, seasonal }
+    }
+
+    pub fn trend(&self) -> &LinearTrend<T> {
+        &self.trend
+    }
+
+    pub fn seasonal(&self) -> Option<&SeasonalComponent<T>> {
+        self.seasonal.as_ref()
+    }
+
+    pub fn remove(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        let detrended = self.trend.remove(data, start);
+        match &self.seasonal {
+            Some(seasonal) => seasonal.remove(detrended.columns(0, detrended.ncols()), start),
+            None => detrended,
+        }
+    }
+
+    pub fn reapply(&self, data: DMatrixSlice<T>, start: usize) -> DMatrix<T> {
+        let retrended = self.trend.reapply(data, start);
+        match &self.seasonal {
+            Some(seasonal) => seasonal.reapply(retrended.columns(0, retrended.ncols()), start),
+            None => retrended,
+        }
+    }
+
+    // `synchronize_and_predict` on the raw `input` starting at time `start`. The input is
+    // decomposed, the closed loop runs on the removed data and the predictions, which continue
+    // right after the input, get the trend and season of their own times back.
+    pub fn predict_retrended<I, E, M, P>(
+        &self,
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+        input: DMatrixSlice<T>,
+        start: usize,
+        sync_steps: usize,
+        predict_steps: usize,
+    ) -> DMatrix<T>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        let removed = self.remove(input, start);
+        let predictions = reservoir_computer.synchronize_and_predict(
+            removed.columns(0, removed.ncols()),
+            sync_steps,
+            predict_steps,
+        );
+        self.reapply(predictions.columns(0, predict_steps), start + input.ncols())
+    }
+}
+
+// Noisy copies of a clean trajectory for training, e.g. when only one trajectory is available.
+// Each row gets white gaussian noise with variance var(row) / signal_to_noise, the ratio is in
+// power and not in decibels.
+#[derive(Clone, Debug)]
+pub struct NoiseAugmentation<T: ReservoirValue> {
+    copies: usize,
+    signal_to_noise: T,
+    seed: u64,
+    keep_clean: bool,
+}
+
+impl<T: ReservoirValue> NoiseAugmentation<T> {
+    pub fn new(copies: usize, signal_to_noise: T, seed: u64) -> Self {
+        assert!(signal_to_noise > T::zero());
+        Self {
+            copies,
+            signal_to_noise,
+            seed,
+            keep_clean: true,
+        }
+    }
+
+    // By default the clean trajectory comes first in the augmented set.
+    pub fn keep_clean(&mut self, keep_clean: bool) -> &mut Self {
+        self.keep_clean = keep_clean;
+        self
+    }
+
+    pub fn augment(&self, data: DMatrixSlice<T>) -> Vec<DMatrix<T>> {
+        assert!(data.ncols() > 1);
+        let samples = T::from_usize(data.ncols()).unwrap();
+        let noise_scale: Vec<T> = data
+            .row_iter()
+            .map(|row| {
+                let mean = row.sum() / samples;
+                let variance = row.iter().fold(T::zero(), |acc, value| {
+                    acc + (*value - mean) * (*value - mean)
+                }) / samples;
+                num_traits::Float::sqrt(variance / self.signal_to_noise)
+            })
+            .collect();
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut trajectories = Vec::with_capacity(self.copies + usize::from(self.keep_clean));
+        if self.keep_clean {
+            trajectories.push(data.clone_owned());
+        }
+        for _ in 0..self.copies {
+            let mut noisy = data.clone_owned();
+            for mut column in noisy.column_iter_mut() {
+                for (value, scale) in column.iter_mut().zip(noise_scale.iter()) {
+                    *value += *scale * T::from_f64(standard_normal(&mut rng)).unwrap();
+                }
+            }
+            trajectories.push(noisy);
+        }
+        trajectories
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        align_to_embedding, delay_embed, delay_unembed, moving_average, savitzky_golay,
+        savitzky_golay_derivative, NoiseAugmentation, TrendDecomposition,
+    };
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn delay_embedding_round_trip() {
+        let data = DMatrix::from_fn(2, 6, |i, j| (10 * i + j) as f64);
+        let lags = [0, 2];
+        let embedded = delay_embed(data.columns(0, 6), &lags);
+        assert_eq!(embedded.shape(), (4, 4));
+        // Time 3: current value on top, the value two steps back below.
+        assert_eq!(
+            embedded.column(1).as_slice(),
+            &[data[(0, 3)], data[(1, 3)], data[(0, 1)], data[(1, 1)]]
+        );
+        let targets = align_to_embedding(&data, &lags);
+        assert_eq!(targets.column(0), data.column(2));
+        assert_eq!(delay_unembed(embedded.columns(0, 4), 2, &lags), data);
+    }
+
+    #[test]
+    fn savitzky_golay_is_exact_for_polynomials() {
+        // 1 + 2t - 0.5t^2 sampled with dt = 0.1
+        let dt = 0.1;
+        let data = DMatrix::from_fn(1, 20, |_, j| {
+            let t = j as f64 * dt;
+            1. + 2. * t - 0.5 * t * t
+        });
+        let smoothed = savitzky_golay(data.columns(0, 20), 3, 2);
+        assert!((&smoothed - &data).amax() < 1e-10);
+        let derivative = savitzky_golay_derivative(data.columns(0, 20), 3, 2, 1, dt);
+        for j in 0..20 {
+            assert!((derivative[j] - (2. - j as f64 * dt)).abs() < 1e-9);
+        }
+        let second = savitzky_golay_derivative(data.columns(0, 20), 3, 2, 2, dt);
+        assert!(second.iter().all(|value| (value + 1.).abs() < 1e-8));
+
+        let averaged = moving_average(data.columns(0, 3), 1);
+        assert!((averaged[1] - data.columns(0, 3).mean()).abs() < 1e-12);
+        assert!((averaged[0] - (data[0] + data[1]) / 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn trend_decomposition_round_trip() {
+        let data = DMatrix::from_fn(2, 48, |i, j| {
+            let season = [1., -2., 0.5, 0.5][j % 4];
+            (i + 1) as f64 * (3. + 0.25 * j as f64) + season
+        });
+        let decomposition = TrendDecomposition::fit(data.columns(0, 48), Some(4));
+        let removed = decomposition.remove(data.columns(0, 48), 0);
+        assert!(removed.amax() < 0.2);
+        assert!((decomposition.reapply(removed.columns(0, 48), 0) - &data).amax() < 1e-10);
+
+        // Later times get the continued trend and the season of their own phase.
+        let future = DMatrix::zeros(2, 3);
+        let extrapolated = decomposition.reapply(future.columns(0, 3), 49);
+        let expected = [
+            3. + 0.25 * 49. - 2.,
+            3. + 0.25 * 50. + 0.5,
+            3. + 0.25 * 51. + 0.5,
+        ];
+        for (column, value) in expected.iter().enumerate() {
+            assert!((extrapolated[(0, column)] - value).abs() < 0.2);
+        }
+    }
+
+    #[test]
+    fn noise_augmentation_matches_signal_to_noise() {
+        let data = DMatrix::from_fn(1, 4000, |_, j| (j as f64 * 0.05).sin());
+        let trajectories = NoiseAugmentation::new(2, 25., 3).augment(data.columns(0, 4000));
+        assert_eq!(trajectories.len(), 3);
+        assert_eq!(trajectories[0], data);
+        assert_ne!(trajectories[1], trajectories[2]);
+        // The signal variance is about 0.5.
+        let noise_variance = (&trajectories[1] - &data).norm_squared() / 4000.;
+        assert!((noise_variance - 0.5 / 25.).abs() < 0.002);
+        let again = NoiseAugmentation::new(2, 25., 3).augment(data.columns(0, 4000));
+        assert_eq!(again, trajectories);
+    }
+}
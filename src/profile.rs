@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+#[cfg(feature = "profile")]
+use std::{cell::RefCell, time::Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileComponent {
+    InputProjection,
+    SparseMatrixVector,
+    Activation,
+    Measurement,
+    Readout,
+}
+
+// Accumulated time spent in each component of the prediction loop.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InferenceProfile {
+    pub input_projection: Duration,
+    pub sparse_matrix_vector: Duration,
+    pub activation: Duration,
+    pub measurement: Duration,
+    pub readout: Duration,
+}
+
+impl InferenceProfile {
+    pub fn get(&self, component: ProfileComponent) -> Duration {
+        match component {
+            ProfileComponent::InputProjection => self.input_projection,
+            ProfileComponent::SparseMatrixVector => self.sparse_matrix_vector,
+            ProfileComponent::Activation => self.activation,
+            ProfileComponent::Measurement => self.measurement,
+            ProfileComponent::Readout => self.readout,
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.input_projection
+            + self.sparse_matrix_vector
+            + self.activation
+            + self.measurement
+            + self.readout
+    }
+
+    #[cfg(feature = "profile")]
+    fn add(&mut self, component: ProfileComponent, duration: Duration) {
+        match component {
+            ProfileComponent::InputProjection => self.input_projection += duration,
+            ProfileComponent::SparseMatrixVector => self.sparse_matrix_vector += duration,
+            ProfileComponent::Activation => self.activation += duration,
+            ProfileComponent::Measurement => self.measurement += duration,
+            ProfileComponent::Readout => self.readout += duration,
+        }
+    }
+}
+
+#[cfg(feature = "profile")]
+thread_local! {
+    static PROFILE: RefCell<InferenceProfile> = RefCell::new(InferenceProfile::default());
+}
+
+// Timings are collected per thread.
+#[cfg(feature = "profile")]
+pub fn reset_profile() {
+    PROFILE.with(|profile| *profile.borrow_mut() = InferenceProfile::default());
+}
+
+#[cfg(feature = "profile")]
+pub fn take_profile() -> InferenceProfile {
+    PROFILE.with(|profile| profile.replace(InferenceProfile::default()))
+}
+
+// Without the `profile` feature this is a no-op.
+pub(crate) struct ProfileTimer {
+    #[cfg(feature = "profile")]
+    start: Instant,
+}
+
+impl ProfileTimer {
+    #[inline]
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(feature = "profile")]
+            start: Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "profile")]
+    #[inline]
+    pub(crate) fn stop(self, component: ProfileComponent) {
+        let elapsed = self.start.elapsed();
+        PROFILE.with(|profile| profile.borrow_mut().add(component, elapsed));
+    }
+
+    #[cfg(not(feature = "profile"))]
+    #[inline]
+    pub(crate) fn stop(self, _component: ProfileComponent) {}
+}
+
+#[cfg(all(test, feature = "profile"))]
+mod tests {
+    use super::{reset_profile, take_profile, ProfileComponent, ProfileTimer};
+
+    #[test]
+    fn test_profile_accumulates_per_component() {
+        reset_profile();
+        let timer = ProfileTimer::start();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        timer.stop(ProfileComponent::Readout);
+
+        let profile = take_profile();
+        assert!(profile.readout >= std::time::Duration::from_millis(2));
+        assert_eq!(profile.input_projection, std::time::Duration::ZERO);
+        assert_eq!(profile.total(), profile.get(ProfileComponent::Readout));
+        assert_eq!(take_profile().total(), std::time::Duration::ZERO);
+    }
+}
@@ -0,0 +1,191 @@
+use nalgebra::{DMatrix, DMatrixSlice};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::{ReservoirComputer, ReservoirError, ReservoirValue};
+
+// Wire format of prediction requests and responses, so that a trained model can be put behind
+// any server framework with the same semantics everywhere.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireFormat {
+    Json,
+    MessagePack,
+}
+
+// Column major, every column is one time step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WireMatrix {
+    pub nrows: usize,
+    pub ncols: usize,
+    pub data: Vec<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PredictionRequest {
+    pub model_id: String,
+    pub kickstarter: WireMatrix,
+    pub horizon: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PredictionResponse {
+    pub model_id: String,
+    pub prediction: WireMatrix,
+}
+
+impl WireMatrix {
+    pub fn from_matrix<T: ReservoirValue>(matrix: DMatrixSlice<T>) -> Self {
+        Self {
+            nrows: matrix.nrows(),
+            ncols: matrix.ncols(),
+            data: matrix.iter().map(|value| value.to_f64().unwrap()).collect(),
+        }
+    }
+
+    pub fn to_matrix<T: ReservoirValue>(&self) -> Result<DMatrix<T>, ReservoirError> {
+        if self.data.len() != self.nrows * self.ncols {
+            return Err(ReservoirError::Protocol(format!(
+                "{} values for a {}x{} matrix",
+                self.data.len(),
+                self.nrows,
+                self.ncols
+            )));
+        }
+        Ok(DMatrix::from_iterator(
+            self.nrows,
+            self.ncols,
+            self.data.iter().map(|value| T::from_f64(*value).unwrap()),
+        ))
+    }
+}
+
+impl PredictionRequest {
+    pub fn encode(&self, format: WireFormat) -> Result<Vec<u8>, ReservoirError> {
+        encode(self, format)
+    }
+
+    pub fn decode(bytes: &[u8], format: WireFormat) -> Result<Self, ReservoirError> {
+        decode(bytes, format)
+    }
+
+    // Predicts with `predict_from_recent`, the kickstarter may hold more columns than the
+    // reservoir computer needs.
+    pub fn respond<T, I, E, M, P>(
+        &self,
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+    ) -> Result<PredictionResponse, ReservoirError>
+    where
+        T: ReservoirValue,
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        let kickstarter = self.kickstarter.to_matrix::<T>()?;
+        let input_dimension = reservoir_computer
+            .state_input_projection()
+            .input_dimension();
+        if kickstarter.nrows() != input_dimension {
+            return Err(ReservoirError::DimensionMismatch {
+                component: "prediction request kickstarter",
+                expected: input_dimension,
+                actual: kickstarter.nrows(),
+            });
+        }
+        if kickstarter.ncols() < reservoir_computer.kickstarter_len() {
+            return Err(ReservoirError::Protocol(format!(
+                "the kickstarter needs at least {} columns",
+                reservoir_computer.kickstarter_len()
+            )));
+        }
+
+        let prediction = reservoir_computer
+            .predict_from_recent(kickstarter.columns(0, kickstarter.ncols()), self.horizon);
+        Ok(PredictionResponse {
+            model_id: self.model_id.clone(),
+            prediction: WireMatrix::from_matrix(prediction.columns(0, prediction.ncols())),
+        })
+    }
+}
+
+impl PredictionResponse {
+    pub fn encode(&self, format: WireFormat) -> Result<Vec<u8>, ReservoirError> {
+        encode(self, format)
+    }
+
+    pub fn decode(bytes: &[u8], format: WireFormat) -> Result<Self, ReservoirError> {
+        decode(bytes, format)
+    }
+}
+
+fn encode<V: Serialize>(value: &V, format: WireFormat) -> Result<Vec<u8>, ReservoirError> {
+    match format {
+        WireFormat::Json => {
+            serde_json::to_vec(value).map_err(|error| ReservoirError::Protocol(error.to_string()))
+        }
+        WireFormat::MessagePack => rmp_serde::to_vec_named(value)
+            .map_err(|error| ReservoirError::Protocol(error.to_string())),
+    }
+}
+
+fn decode<V: DeserializeOwned>(bytes: &[u8], format: WireFormat) -> Result<V, ReservoirError> {
+    match format {
+        WireFormat::Json => serde_json::from_slice(bytes)
+            .map_err(|error| ReservoirError::Protocol(error.to_string())),
+        WireFormat::MessagePack => rmp_serde::from_slice(bytes)
+            .map_err(|error| ReservoirError::Protocol(error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::{PredictionRequest, WireFormat, WireMatrix};
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        input_projection::DefaultInputProjection, output_projection::LinearStateProjection,
+        state_measurement::DefaultStateMeasurement, Reservoir, ReservoirComputer, ReservoirError,
+    };
+
+    #[test]
+    fn request_round_trip_and_response() {
+        let kickstarter = DMatrix::from_vec(2, 2, vec![0.1, 0.2, 0.3, 0.4]);
+        let request = PredictionRequest {
+            model_id: "sine".to_string(),
+            kickstarter: WireMatrix::from_matrix(kickstarter.columns(0, 2)),
+            horizon: 3,
+        };
+        for format in [WireFormat::Json, WireFormat::MessagePack] {
+            let bytes = request.encode(format).unwrap();
+            assert_eq!(PredictionRequest::decode(&bytes, format).unwrap(), request);
+        }
+        assert!(matches!(
+            PredictionRequest::decode(b"{", WireFormat::Json),
+            Err(ReservoirError::Protocol(_))
+        ));
+
+        let esn = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, 1)
+            .build_sparse_discrete_network(Tanh);
+        let reservoir = Reservoir::new(
+            DefaultInputProjection::new_with_matrix(DMatrix::from_element(10, 2, 0.1)),
+            esn,
+        );
+        let mut reservoir_computer = ReservoirComputer::new(
+            reservoir,
+            DefaultStateMeasurement::new(10),
+            LinearStateProjection::new_with_matrix(DMatrix::from_element(2, 10, 0.1)),
+        );
+        let mut forked = reservoir_computer.fork();
+        let response = request.respond(&mut reservoir_computer).unwrap();
+        assert_eq!(response.model_id, "sine");
+        assert_eq!(
+            response.prediction.to_matrix::<f64>().unwrap(),
+            forked.predict_from_recent(kickstarter.columns(0, 2), 3)
+        );
+    }
+}
@@ -0,0 +1,8 @@
+use rand::Rng;
+
+// Box-Muller transform.
+pub(crate) fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let uniform: f64 = 1. - rng.gen::<f64>();
+    let angle: f64 = rng.gen::<f64>() * std::f64::consts::TAU;
+    (-2. * uniform.ln()).sqrt() * angle.cos()
+}
@@ -0,0 +1,156 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::Stream;
+use nalgebra::DVector;
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::AllocationFreeTimeEvolution;
+use crate::ReservoirValue;
+
+use super::FrozenReservoirComputer;
+
+// Drives the reservoir with every incoming sample in open loop and yields the one step ahead
+// prediction made after it, i.e. the n-th output predicts the (n + 1)-th input.
+#[derive(Debug)]
+pub struct AsyncPredictionStream<S, T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    samples: S,
+    model: FrozenReservoirComputer<T, I, E, M, P>,
+}
+
+impl<S, T, I, E, M, P> AsyncPredictionStream<S, T, I, E, M, P>
+where
+    S: Stream<Item = DVector<T>> + Unpin,
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn new(samples: S, model: FrozenReservoirComputer<T, I, E, M, P>) -> Self {
+        Self { samples, model }
+    }
+
+    pub fn model(&self) -> &FrozenReservoirComputer<T, I, E, M, P> {
+        &self.model
+    }
+
+    pub fn into_inner(self) -> (S, FrozenReservoirComputer<T, I, E, M, P>) {
+        (self.samples, self.model)
+    }
+}
+
+// No field is ever pinned, only the sample stream is polled through a fresh pin.
+impl<S, T, I, E, M, P> Unpin for AsyncPredictionStream<S, T, I, E, M, P>
+where
+    S: Unpin,
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+}
+
+impl<S, T, I, E, M, P> Stream for AsyncPredictionStream<S, T, I, E, M, P>
+where
+    S: Stream<Item = DVector<T>> + Unpin,
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    type Item = DVector<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.samples).poll_next(cx) {
+            Poll::Ready(Some(sample)) => {
+                Poll::Ready(Some(this.model.step(sample.column(0)).clone()))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.samples.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use futures_core::Stream;
+    use nalgebra::{DMatrix, DVector};
+
+    use super::AsyncPredictionStream;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        input_projection::DefaultInputProjection, output_projection::LinearStateProjection,
+        state_measurement::DefaultStateMeasurement, Reservoir, ReservoirComputer,
+    };
+
+    struct Samples(std::vec::IntoIter<DVector<f64>>);
+
+    impl Stream for Samples {
+        type Item = DVector<f64>;
+
+        fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            Poll::Ready(self.get_mut().0.next())
+        }
+    }
+
+    #[test]
+    fn async_stream_matches_open_loop_steps() {
+        let esn = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, 1)
+            .build_sparse_discrete_network(Tanh);
+        let reservoir = Reservoir::new(
+            DefaultInputProjection::new_with_matrix(DMatrix::from_fn(10, 2, |i, j| {
+                (i + j) as f64 * 0.1
+            })),
+            esn,
+        );
+        let frozen = ReservoirComputer::new(
+            reservoir,
+            DefaultStateMeasurement::new(10),
+            LinearStateProjection::new_with_matrix(DMatrix::from_element(2, 10, 0.1)),
+        )
+        .freeze();
+        let mut expected_model = frozen.clone();
+
+        let samples: Vec<_> = (0..5)
+            .map(|t| DVector::from_vec(vec![(t as f64).sin(), (t as f64).cos()]))
+            .collect();
+        let mut stream = AsyncPredictionStream::new(Samples(samples.clone().into_iter()), frozen);
+
+        let mut context = Context::from_waker(Waker::noop());
+        for sample in samples {
+            let expected = expected_model.step(sample.column(0)).clone();
+            match Pin::new(&mut stream).poll_next(&mut context) {
+                Poll::Ready(Some(prediction)) => assert_eq!(prediction, expected),
+                _ => panic!("Expected a prediction."),
+            }
+        }
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut context),
+            Poll::Ready(None)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use nalgebra::DVector;
+
+use crate::error::{check_dimension, ReservoirError};
+use crate::ReservoirValue;
+
+const MAGIC: &[u8; 4] = b"RCCK";
+const FORMAT_VERSION: u32 = 1;
+// Magic, format version and four lengths.
+const HEADER_LEN: usize = 40;
+
+// Synchronized state of a reservoir computer, so that a forecasting service can resume after a
+// restart without synchronizing on the raw history again. The model signature is the response
+// of the trained components to fixed probe inputs, restoring onto a model with other weights
+// fails instead of silently continuing from a meaningless state.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "protocol", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReservoirCheckpoint {
+    format_version: u32,
+    input_dimension: usize,
+    output_dimension: usize,
+    state: Vec<f64>,
+    model_signature: Vec<f64>,
+}
+
+impl ReservoirCheckpoint {
+    pub(crate) fn new<T: ReservoirValue>(
+        input_dimension: usize,
+        output_dimension: usize,
+        state: &DVector<T>,
+        model_signature: Vec<f64>,
+    ) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            input_dimension,
+            output_dimension,
+            state: state.iter().map(|value| value.to_f64().unwrap()).collect(),
+            model_signature,
+        }
+    }
+
+    pub fn format_version(&self) -> u32 {
+        self.format_version
+    }
+
+    pub fn input_dimension(&self) -> usize {
+        self.input_dimension
+    }
+
+    pub fn output_dimension(&self) -> usize {
+        self.output_dimension
+    }
+
+    pub fn state<T: ReservoirValue>(&self) -> DVector<T> {
+        DVector::from_iterator(
+            self.state.len(),
+            self.state.iter().map(|value| T::from_f64(*value).unwrap()),
+        )
+    }
+
+    pub fn model_signature(&self) -> &[f64] {
+        &self.model_signature
+    }
+
+    // Fails unless the checkpoint was taken from a model with the given dimensions and
+    // signature. The signatures are compared with a tolerance in the precision of `T`.
+    pub(crate) fn check_model<T: ReservoirValue>(
+        &self,
+        input_dimension: usize,
+        output_dimension: usize,
+        state_dimension: usize,
+        model_signature: &[f64],
+    ) -> Result<(), ReservoirError> {
+        if self.format_version != FORMAT_VERSION {
+            return Err(ReservoirError::InvalidData(format!(
+                "checkpoint format version {} is not supported",
+                self.format_version
+            )));
+        }
+        check_dimension(
+            "checkpoint input dimension",
+            input_dimension,
+            self.input_dimension,
+        )?;
+        check_dimension(
+            "checkpoint output dimension",
+            output_dimension,
+            self.output_dimension,
+        )?;
+        check_dimension(
+            "checkpoint state dimension",
+            state_dimension,
+            self.state.len(),
+        )?;
+        let tolerance = <T as num_traits::Float>::epsilon().to_f64().unwrap().sqrt();
+        let matches = self.model_signature.len() == model_signature.len()
+            && self
+                .model_signature
+                .iter()
+                .zip(model_signature)
+                .all(|(a, b)| (a - b).abs() <= tolerance * (1. + a.abs().max(b.abs())));
+        if matches {
+            Ok(())
+        } else {
+            Err(ReservoirError::InvalidData(
+                "the checkpoint was taken from a different model".to_string(),
+            ))
+        }
+    }
+
+    // Little endian: magic, format version, dimensions, lengths and then the values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(HEADER_LEN + 8 * (self.state.len() + self.model_signature.len()));
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.format_version.to_le_bytes());
+        for length in [
+            self.input_dimension,
+            self.output_dimension,
+            self.state.len(),
+            self.model_signature.len(),
+        ] {
+            bytes.extend_from_slice(&(length as u64).to_le_bytes());
+        }
+        for value in self.state.iter().chain(&self.model_signature) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReservoirError> {
+        let truncated = || ReservoirError::InvalidData("truncated checkpoint".to_string());
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+            return Err(ReservoirError::InvalidData(
+                "not a reservoir checkpoint".to_string(),
+            ));
+        }
+        let format_version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if format_version != FORMAT_VERSION {
+            return Err(ReservoirError::InvalidData(format!(
+                "checkpoint format version {format_version} is not supported"
+            )));
+        }
+        let header: Vec<usize> = bytes[8..HEADER_LEN]
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize)
+            .collect();
+        let (state_len, signature_len) = (header[2], header[3]);
+        let value_count = state_len.checked_add(signature_len).ok_or_else(truncated)?;
+        if Some(bytes.len() - HEADER_LEN) != value_count.checked_mul(8) {
+            return Err(truncated());
+        }
+        let mut values = bytes[HEADER_LEN..]
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()));
+        let state = values.by_ref().take(state_len).collect();
+        let model_signature = values.collect();
+        Ok(Self {
+            format_version,
+            input_dimension: header[0],
+            output_dimension: header[1],
+            state,
+            model_signature,
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReservoirError> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|error| ReservoirError::InvalidData(format!("{}: {error}", path.display())))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReservoirError> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|error| ReservoirError::InvalidData(format!("{}: {error}", path.display())))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::ReservoirCheckpoint;
+
+    #[test]
+    fn bytes_round_trip() {
+        let state = DVector::from_vec(vec![0.25, -1.5, 3.]);
+        let checkpoint = ReservoirCheckpoint::new(2, 2, &state, vec![0.5, 7.]);
+        let bytes = checkpoint.to_bytes();
+        assert_eq!(ReservoirCheckpoint::from_bytes(&bytes).unwrap(), checkpoint);
+        assert_eq!(checkpoint.state::<f64>(), state);
+        assert!(ReservoirCheckpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(ReservoirCheckpoint::from_bytes(&bytes[1..]).is_err());
+    }
+}
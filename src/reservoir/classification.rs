@@ -0,0 +1,295 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    input_projection::ReservoirInputProjection,
+    output_projection::{LinearStateProjection, ReservoirStateProjection},
+    state_measurement::ReservoirStateMeasurement,
+    time_evolution::ReservoirTimeEvolution,
+    Reservoir, ReservoirComputer, ReservoirValue,
+};
+
+type LinearReservoirComputer<T, I, E, M> = ReservoirComputer<T, I, E, M, LinearStateProjection<T>>;
+
+// Labeled sequences for classification: every sequence drives the reservoir from the zero state
+// and the readout maps the measured final state to one score per class, trained on one-hot
+// targets. Class weights count the samples of a class that many times in the regression, which
+// keeps rare classes from being ignored in imbalanced datasets.
+#[derive(Clone, Debug)]
+pub struct SequenceClassification<T: ReservoirValue> {
+    classes: usize,
+    sequences: Vec<DMatrix<T>>,
+    labels: Vec<usize>,
+    class_weights: Vec<T>,
+}
+
+impl<T: ReservoirValue> SequenceClassification<T> {
+    pub fn new(classes: usize) -> Self {
+        assert!(classes > 1, "At least two classes are needed.");
+        Self {
+            classes,
+            sequences: vec![],
+            labels: vec![],
+            class_weights: vec![T::one(); classes],
+        }
+    }
+
+    pub fn add_sequence(&mut self, sequence: DMatrix<T>, label: usize) -> &mut Self {
+        assert!(label < self.classes, "Label {label} is not a class.");
+        assert!(sequence.ncols() > 0);
+        if let Some(first) = self.sequences.first() {
+            assert_eq!(sequence.nrows(), first.nrows());
+        }
+        self.sequences.push(sequence);
+        self.labels.push(label);
+        self
+    }
+
+    pub fn classes(&self) -> usize {
+        self.classes
+    }
+
+    pub fn sequences(&self) -> &[DMatrix<T>] {
+        &self.sequences
+    }
+
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    pub fn class_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.classes];
+        for label in &self.labels {
+            counts[*label] += 1;
+        }
+        counts
+    }
+
+    pub fn set_class_weights(&mut self, weights: &[T]) -> &mut Self {
+        assert_eq!(weights.len(), self.classes);
+        assert!(
+            weights
+                .iter()
+                .all(|weight| num_traits::Float::is_finite(*weight) && *weight > T::zero()),
+            "Class weights must be positive and finite."
+        );
+        self.class_weights = weights.to_vec();
+        self
+    }
+
+    // Weights inversely proportional to the class frequencies, samples / (classes * count), so
+    // every class contributes equally. Classes without samples keep weight one.
+    pub fn balance_class_weights(&mut self) -> &mut Self {
+        let samples = T::from_usize(self.labels.len()).unwrap();
+        let classes = T::from_usize(self.classes).unwrap();
+        self.class_weights = self
+            .class_counts()
+            .into_iter()
+            .map(|count| match count {
+                0 => T::one(),
+                count => samples / (classes * T::from_usize(count).unwrap()),
+            })
+            .collect();
+        self
+    }
+
+    pub fn class_weights(&self) -> &[T] {
+        &self.class_weights
+    }
+
+    // Splits into training and validation sets with the same class proportions: of every class
+    // round(validation_fraction * count) randomly drawn sequences go to validation. Both keep
+    // the class weights.
+    pub fn stratified_split(&self, validation_fraction: f64, seed: u64) -> (Self, Self) {
+        assert!((0. ..1.).contains(&validation_fraction));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut training = Self {
+            classes: self.classes,
+            sequences: vec![],
+            labels: vec![],
+            class_weights: self.class_weights.clone(),
+        };
+        let mut validation = training.clone();
+        for class in 0..self.classes {
+            let mut members: Vec<_> = (0..self.labels.len())
+                .filter(|index| self.labels[*index] == class)
+                .collect();
+            members.shuffle(&mut rng);
+            let validation_count = (validation_fraction * members.len() as f64).round() as usize;
+            for (position, index) in members.into_iter().enumerate() {
+                let target = if position < validation_count {
+                    &mut validation
+                } else {
+                    &mut training
+                };
+                target.add_sequence(self.sequences[index].clone(), class);
+            }
+        }
+        (training, validation)
+    }
+
+    pub fn train_via_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        reservoir: Reservoir<T, I, E>,
+        measurement: M,
+    ) -> LinearReservoirComputer<T, I, E, M>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert!(
+            !self.sequences.is_empty(),
+            "No training data has been added."
+        );
+        let mut reservoir_computer = ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: LinearStateProjection::new_with_matrix(DMatrix::zeros(
+                self.classes,
+                0,
+            )),
+        };
+        let mut features = DMatrix::zeros(
+            reservoir_computer
+                .reservoir_state_measurement
+                .output_dimension(),
+            self.sequences.len(),
+        );
+        let mut targets = DMatrix::zeros(self.classes, self.sequences.len());
+        for (sample, (sequence, label)) in self.sequences.iter().zip(&self.labels).enumerate() {
+            let scale = num_traits::Float::sqrt(self.class_weights[*label]);
+            let measured =
+                reservoir_computer.final_measured_state(sequence.columns(0, sequence.ncols()));
+            features.column_mut(sample).copy_from(&(measured * scale));
+            targets[(*label, sample)] = scale;
+        }
+        reservoir_computer.reservoir_state_projection =
+            LinearStateProjection::via_ridge_regression_nalgebra(
+                beta,
+                &features,
+                targets.columns(0, targets.ncols()),
+            );
+        reservoir_computer
+    }
+
+    // Fraction of correctly classified sequences per class, NaN for classes without samples.
+    pub fn recall_per_class<I, E, M, P>(
+        &self,
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+    ) -> Vec<f64>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        let mut correct = vec![0; self.classes];
+        for (sequence, label) in self.sequences.iter().zip(&self.labels) {
+            if reservoir_computer.classify(sequence.columns(0, sequence.ncols())) == *label {
+                correct[*label] += 1;
+            }
+        }
+        correct
+            .into_iter()
+            .zip(self.class_counts())
+            .map(|(correct, count)| correct as f64 / count as f64)
+            .collect()
+    }
+
+    // Mean of the per class recalls over the classes with samples, unlike the plain accuracy
+    // not dominated by the frequent classes.
+    pub fn balanced_accuracy<I, E, M, P>(
+        &self,
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+    ) -> f64
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        let recalls: Vec<_> = self
+            .recall_per_class(reservoir_computer)
+            .into_iter()
+            .filter(|recall| !recall.is_nan())
+            .collect();
+        recalls.iter().sum::<f64>() / recalls.len() as f64
+    }
+}
+
+impl<T, I, E, M, P> ReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    // Readout of the final state after driving the reservoir with `sequence` from the zero
+    // state, one score per class for a classifier trained with `SequenceClassification`.
+    pub fn class_scores(&mut self, sequence: DMatrixSlice<T>) -> DVector<T> {
+        let measured = self.final_measured_state(sequence);
+        self.reservoir_state_projection
+            .project_many(measured.columns(0, 1))
+            .column(0)
+            .clone_owned()
+    }
+
+    pub fn classify(&mut self, sequence: DMatrixSlice<T>) -> usize {
+        self.class_scores(sequence).imax()
+    }
+
+    fn final_measured_state(&mut self, sequence: DMatrixSlice<T>) -> DMatrix<T> {
+        assert!(
+            sequence.ncols() >= self.kickstarter_len(),
+            "Sequences need at least {} columns to fill the input window.",
+            self.kickstarter_len()
+        );
+        self.reservoir.reservoir_state.fill(T::zero());
+        let state = self.reservoir.record_states(sequence, sequence.ncols() - 1);
+        self.reservoir_state_measurement
+            .measure_many(state.columns(0, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::SequenceClassification;
+
+    #[test]
+    fn stratified_split_and_balanced_weights() {
+        let mut classification = SequenceClassification::<f64>::new(3);
+        for index in 0..100 {
+            let label = match index % 20 {
+                0 => 2,
+                1..=4 => 1,
+                _ => 0,
+            };
+            classification.add_sequence(DMatrix::from_element(1, 3, index as f64), label);
+        }
+        assert_eq!(classification.class_counts(), vec![75, 20, 5]);
+
+        classification.balance_class_weights();
+        let weights = classification.class_weights();
+        assert!((weights[0] - 100. / 225.).abs() < 1e-12);
+        assert!((weights[2] - 100. / 15.).abs() < 1e-12);
+
+        let (training, validation) = classification.stratified_split(0.2, 3);
+        assert_eq!(training.class_counts(), vec![60, 16, 4]);
+        assert_eq!(validation.class_counts(), vec![15, 4, 1]);
+        assert_eq!(validation.class_weights(), weights);
+        // Every sequence ends up in exactly one of the sets.
+        let mut firsts: Vec<_> = training
+            .sequences()
+            .iter()
+            .chain(validation.sequences())
+            .map(|sequence| sequence[0] as usize)
+            .collect();
+        firsts.sort();
+        assert_eq!(firsts, (0..100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,151 @@
+use nalgebra::{DMatrix, DMatrixSlice};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::ReservoirValue;
+
+use super::ReservoirComputer;
+
+// Split conformal intervals for closed loop forecasts. For every output and every step of the
+// horizon the absolute residuals on a held-out calibration segment are collected, the interval
+// half width is their ceil((n + 1) * coverage)-th smallest value.
+#[derive(Clone, Debug)]
+pub struct ConformalCalibration<T: ReservoirValue> {
+    coverage: T,
+    half_widths: DMatrix<T>,
+}
+
+impl<T: ReservoirValue> ConformalCalibration<T> {
+    // `residuals` holds one (output x horizon) matrix of prediction minus truth per forecast.
+    pub fn from_residuals(residuals: &[DMatrix<T>], coverage: T) -> Self {
+        assert!(!residuals.is_empty());
+        assert!(coverage > T::zero() && coverage < T::one());
+        let (output_dimension, horizon) = residuals[0].shape();
+        assert!(residuals
+            .iter()
+            .all(|residual| residual.shape() == (output_dimension, horizon)));
+
+        let samples = residuals.len();
+        let rank = num_traits::Float::ceil(T::from_usize(samples + 1).unwrap() * coverage)
+            .to_usize()
+            .unwrap();
+
+        let mut scores = Vec::with_capacity(samples);
+        let half_widths = DMatrix::from_fn(output_dimension, horizon, |output, step| {
+            scores.clear();
+            scores.extend(
+                residuals
+                    .iter()
+                    .map(|residual| num_traits::Float::abs(residual[(output, step)])),
+            );
+            scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            // Too few calibration forecasts for the requested coverage.
+            if rank > samples {
+                T::infinity()
+            } else {
+                scores[rank - 1]
+            }
+        });
+
+        Self {
+            coverage,
+            half_widths,
+        }
+    }
+
+    // Runs a closed loop forecast of `horizon` steps from every `stride`-th position of the
+    // calibration data. Before each forecast the state is reset and synchronized on the
+    // preceding `sync_steps` columns.
+    pub fn calibrate<I, E, M, P>(
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+        calibration_data: DMatrixSlice<T>,
+        sync_steps: usize,
+        horizon: usize,
+        stride: usize,
+        coverage: T,
+    ) -> Self
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        assert!(stride > 0);
+        let kickstarter_len = reservoir_computer.kickstarter_len();
+        let warmup = sync_steps.max(1) + kickstarter_len - 1;
+        assert!(
+            calibration_data.ncols() >= warmup + horizon,
+            "The calibration data needs at least {} columns.",
+            warmup + horizon
+        );
+
+        let mut residuals = Vec::new();
+        let mut start = warmup;
+        while start + horizon <= calibration_data.ncols() {
+            let prediction = reservoir_computer.resynchronize_and_predict(
+                calibration_data,
+                start,
+                sync_steps,
+                horizon,
+            );
+            residuals.push(prediction - calibration_data.columns(start, horizon));
+            start += stride;
+        }
+
+        Self::from_residuals(&residuals, coverage)
+    }
+
+    pub fn coverage(&self) -> T {
+        self.coverage
+    }
+
+    pub fn horizon(&self) -> usize {
+        self.half_widths.ncols()
+    }
+
+    // Interval half width per output (rows) and forecast step (columns).
+    pub fn half_widths(&self) -> &DMatrix<T> {
+        &self.half_widths
+    }
+
+    // Wraps a forecast starting at step 0 into (lower, upper) bounds.
+    pub fn intervals(&self, predictions: DMatrixSlice<T>) -> (DMatrix<T>, DMatrix<T>) {
+        assert_eq!(predictions.nrows(), self.half_widths.nrows());
+        assert!(
+            predictions.ncols() <= self.horizon(),
+            "Only {} steps have been calibrated.",
+            self.horizon()
+        );
+        let half_widths = self.half_widths.columns(0, predictions.ncols());
+        (predictions - half_widths, predictions + half_widths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConformalCalibration;
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn conformal_half_widths_per_horizon() {
+        // Forecast k has residual k at step 0 and -2k at step 1.
+        let residuals: Vec<_> = (1..=19)
+            .map(|k| DMatrix::from_vec(1, 2, vec![k as f64, -2. * k as f64]))
+            .collect();
+
+        let calibration = ConformalCalibration::from_residuals(&residuals, 0.9);
+        // ceil(20 * 0.9) = 18
+        assert_eq!(calibration.half_widths()[(0, 0)], 18.);
+        assert_eq!(calibration.half_widths()[(0, 1)], 36.);
+
+        let predictions = DMatrix::from_vec(1, 2, vec![1., 2.]);
+        let (lower, upper) = calibration.intervals(predictions.columns(0, 2));
+        assert_eq!(lower, DMatrix::from_vec(1, 2, vec![-17., -34.]));
+        assert_eq!(upper, DMatrix::from_vec(1, 2, vec![19., 38.]));
+
+        let calibration = ConformalCalibration::from_residuals(&residuals[..5], 0.9);
+        assert!(calibration.half_widths()[(0, 0)].is_infinite());
+    }
+}
@@ -7,6 +7,7 @@ use crate::time_evolution::ReservoirTimeEvolution;
 use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
 
 use super::ReservoirDynamics;
+use crate::error::{check_dimension, ReservoirError};
 use crate::ReservoirValue;
 
 #[derive(Debug)]
@@ -36,6 +37,21 @@ where
         }
     }
 
+    pub fn try_new(
+        reservoir_input_projection: I,
+        reservoir_time_evolution: E,
+    ) -> Result<Self, ReservoirError> {
+        let reservoir_dimension = reservoir_time_evolution.output_dimension();
+        check_dimension(
+            "time evolution input / output",
+            reservoir_dimension,
+            reservoir_time_evolution.input_dimension(),
+        )?;
+        let reservoir_dynamics =
+            ReservoirDynamics::try_new(reservoir_input_projection, reservoir_time_evolution)?;
+        Ok(reservoir_dynamics.into_reservoir(DVector::zeros(reservoir_dimension)))
+    }
+
     pub fn into_parts(self) -> (DVector<T>, I, E) {
         let (i, e) = self.reservoir_dynamics.into_parts();
         (self.reservoir_state, i, e)
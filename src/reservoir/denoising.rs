@@ -0,0 +1,156 @@
+use nalgebra::{DMatrix, DMatrixSlice};
+
+use crate::{
+    input_projection::ReservoirInputProjection,
+    output_projection::{LinearStateProjection, ReservoirStateProjection},
+    state_measurement::ReservoirStateMeasurement,
+    time_evolution::ReservoirTimeEvolution,
+    Reservoir, ReservoirComputer, ReservoirValue,
+};
+
+use super::TrainingReport;
+
+type LinearReservoirComputer<T, I, E, M> = ReservoirComputer<T, I, E, M, LinearStateProjection<T>>;
+
+// Training for signal reconstruction: the reservoir is driven by a corrupted stream and the
+// readout maps the state after input column t to the clean column t (the same time step, not
+// the next one), so the reservoir computer acts as a nonlinear smoother.
+pub struct DenoisingTraining<T: ReservoirValue> {
+    noisy: Vec<DMatrix<T>>,
+    clean: Vec<DMatrix<T>>,
+    sync_steps: usize,
+}
+
+impl<T: ReservoirValue> DenoisingTraining<T> {
+    // The first `sync_steps` columns of every pair only synchronize the reservoir.
+    pub fn new(sync_steps: usize) -> Self {
+        Self {
+            noisy: vec![],
+            clean: vec![],
+            sync_steps,
+        }
+    }
+
+    pub fn add_data(&mut self, noisy: DMatrix<T>, clean: DMatrix<T>) -> &mut Self {
+        assert_eq!(noisy.ncols(), clean.ncols());
+        assert!(
+            noisy.ncols() > self.sync_steps,
+            "The data has no column left to reconstruct after {} synchronization steps.",
+            self.sync_steps
+        );
+        if let Some(first) = self.noisy.first() {
+            assert_eq!(noisy.nrows(), first.nrows());
+            assert_eq!(clean.nrows(), self.clean[0].nrows());
+        }
+        self.noisy.push(noisy);
+        self.clean.push(clean);
+        self
+    }
+
+    // Every pair starts from the initial reservoir state, the reservoir keeps the state of the
+    // last pair. The report holds the in-sample reconstructions.
+    pub fn train_via_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        measurement: M,
+    ) -> (LinearReservoirComputer<T, I, E, M>, TrainingReport<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert!(!self.noisy.is_empty(), "No training data has been added.");
+        let samples: usize = self
+            .noisy
+            .iter()
+            .map(|noisy| noisy.ncols() - self.sync_steps)
+            .sum();
+        let initial_state = reservoir.reservoir_state.clone();
+        let mut recorded_states = DMatrix::zeros(initial_state.nrows(), samples);
+        let mut targets = DMatrix::zeros(self.clean[0].nrows(), samples);
+        let mut start = 0;
+        for (noisy, clean) in self.noisy.iter().zip(self.clean.iter()) {
+            let columns = noisy.ncols() - self.sync_steps;
+            reservoir.reservoir_state.copy_from(&initial_state);
+            reservoir.record_states_into(
+                noisy.columns(0, noisy.ncols()),
+                self.sync_steps,
+                recorded_states.columns_mut(start, columns),
+            );
+            targets
+                .columns_mut(start, columns)
+                .copy_from(&clean.columns(self.sync_steps, columns));
+            start += columns;
+        }
+
+        let measured_states =
+            measurement.measure_many(recorded_states.columns(0, recorded_states.ncols()));
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &measured_states,
+            targets.columns(0, targets.ncols()),
+        );
+        let report = TrainingReport::from_projection(
+            &linear_fit,
+            measured_states.columns(0, measured_states.ncols()),
+            targets.columns(0, targets.ncols()),
+        );
+        (
+            ReservoirComputer {
+                reservoir,
+                reservoir_state_measurement: measurement,
+                reservoir_state_projection: linear_fit,
+            },
+            report,
+        )
+    }
+}
+
+impl<T, I, E, M, P> ReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    // Reconstruction of a corrupted stream by a reservoir computer trained with
+    // `DenoisingTraining`. The state is reset to zero first, column k of the result estimates
+    // the clean column sync_steps + k.
+    pub fn denoise(&mut self, noisy: DMatrixSlice<T>, sync_steps: usize) -> DMatrix<T> {
+        assert!(
+            sync_steps + 1 >= self.kickstarter_len(),
+            "At least {} synchronization steps are needed to fill the input window.",
+            self.kickstarter_len() - 1
+        );
+        assert!(
+            noisy.ncols() > sync_steps,
+            "The data has no column left to reconstruct after {sync_steps} synchronization steps."
+        );
+        self.reservoir.reservoir_state.fill(T::zero());
+        let states = self.reservoir.record_states(noisy, sync_steps);
+        let measured_states = self
+            .reservoir_state_measurement
+            .measure_many(states.columns(0, states.ncols()));
+        self.reservoir_state_projection
+            .project_many(measured_states.columns(0, measured_states.ncols()))
+    }
+
+    // Reconstructions of `noisy` next to the matching columns of `clean`.
+    pub fn denoising_report(
+        &mut self,
+        noisy: DMatrixSlice<T>,
+        clean: DMatrixSlice<T>,
+        sync_steps: usize,
+    ) -> TrainingReport<T> {
+        assert_eq!(noisy.ncols(), clean.ncols());
+        let reconstructions = self.denoise(noisy, sync_steps);
+        TrainingReport::new(
+            reconstructions,
+            clean
+                .columns(sync_steps, clean.ncols() - sync_steps)
+                .clone_owned(),
+        )
+    }
+}
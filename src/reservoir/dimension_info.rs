@@ -0,0 +1,81 @@
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::ReservoirValue;
+
+use super::{ReservoirComputer, ReservoirComputerDynamics};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DimensionReport {
+    pub input_dimension: usize,
+    pub embeddings: usize,
+    pub reservoir_dimension: usize,
+    pub measurement_dimension: usize,
+    pub output_dimension: usize,
+    // Number of input columns a kickstarter for `synchronize_and_predict` must have.
+    pub kickstarter_columns: usize,
+}
+
+pub trait DimensionInfo {
+    fn dimension_report(&self) -> DimensionReport;
+}
+
+fn report<T, I, E, M, P>(
+    input_projection: &I,
+    time_evolution: &E,
+    measurement: &M,
+    projection: &P,
+) -> DimensionReport
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    DimensionReport {
+        input_dimension: input_projection.input_dimension(),
+        embeddings: input_projection.embeddings(),
+        reservoir_dimension: time_evolution.output_dimension(),
+        measurement_dimension: measurement.output_dimension(),
+        output_dimension: projection.output_dimension(),
+        kickstarter_columns: input_projection.required_input_columns(),
+    }
+}
+
+impl<T, I, E, M, P> DimensionInfo for ReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    fn dimension_report(&self) -> DimensionReport {
+        report(
+            self.reservoir.input_projection(),
+            self.reservoir.time_evolution(),
+            &self.reservoir_state_measurement,
+            &self.reservoir_state_projection,
+        )
+    }
+}
+
+impl<T, I, E, M, P> DimensionInfo for ReservoirComputerDynamics<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    fn dimension_report(&self) -> DimensionReport {
+        report(
+            self.reservoir_dynamics.input_projection(),
+            self.reservoir_dynamics.time_evolution(),
+            &self.reservoir_state_measurement,
+            &self.reservoir_state_projection,
+        )
+    }
+}
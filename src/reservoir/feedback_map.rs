@@ -0,0 +1,120 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use nalgebra::{DVector, DVectorSliceMut};
+
+use crate::ReservoirValue;
+
+type FeedbackFunction<T> = dyn Fn(&DVector<T>, DVectorSliceMut<T>) + Send + Sync;
+
+// Maps a prediction to the input column fed back in closed loop, so the readout may predict
+// auxiliary outputs the reservoir does not get as input or order its outputs differently.
+#[derive(Clone)]
+pub enum FeedbackMap<T: ReservoirValue> {
+    Identity,
+    // Input row i is prediction row indices[i].
+    Select(Vec<usize>),
+    Custom {
+        input_dimension: usize,
+        map: Arc<FeedbackFunction<T>>,
+    },
+}
+
+impl<T: ReservoirValue> FeedbackMap<T> {
+    pub fn select(indices: &[usize]) -> Self {
+        Self::Select(indices.to_vec())
+    }
+
+    pub fn custom<F>(input_dimension: usize, map: F) -> Self
+    where
+        F: Fn(&DVector<T>, DVectorSliceMut<T>) + Send + Sync + 'static,
+    {
+        Self::Custom {
+            input_dimension,
+            map: Arc::new(map),
+        }
+    }
+
+    // Dimension of the fed back input for predictions of `output_dimension`.
+    pub fn input_dimension(&self, output_dimension: usize) -> usize {
+        match self {
+            FeedbackMap::Identity => output_dimension,
+            FeedbackMap::Select(indices) => indices.len(),
+            FeedbackMap::Custom {
+                input_dimension, ..
+            } => *input_dimension,
+        }
+    }
+
+    pub fn apply(&self, prediction: &DVector<T>, mut input: DVectorSliceMut<T>) {
+        assert_eq!(input.nrows(), self.input_dimension(prediction.nrows()));
+        match self {
+            FeedbackMap::Identity => input.copy_from(prediction),
+            FeedbackMap::Select(indices) => {
+                for (row, index) in indices.iter().enumerate() {
+                    input[row] = prediction[*index];
+                }
+            }
+            FeedbackMap::Custom { map, .. } => map(prediction, input),
+        }
+    }
+}
+
+impl<T: ReservoirValue> Debug for FeedbackMap<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedbackMap::Identity => write!(f, "Identity"),
+            FeedbackMap::Select(indices) => f.debug_tuple("Select").field(indices).finish(),
+            FeedbackMap::Custom {
+                input_dimension, ..
+            } => f
+                .debug_struct("Custom")
+                .field("input_dimension", input_dimension)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::FeedbackMap;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        input_projection::DefaultInputProjection, output_projection::LinearStateProjection,
+        state_measurement::DefaultStateMeasurement, Reservoir, ReservoirComputer,
+    };
+
+    #[test]
+    fn selected_feedback_matches_model_without_auxiliary_output() {
+        let w_in = DMatrix::from_fn(10, 2, |i, j| ((i + 3 * j) as f64 * 0.37).sin());
+        let w_out = DMatrix::from_fn(3, 10, |i, j| ((2 * i + j) as f64 * 0.53).cos() * 0.2);
+        let feedback = FeedbackMap::select(&[0, 2]);
+        let new_model = |w_out: DMatrix<f64>, feedback: &FeedbackMap<f64>| {
+            let esn = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, 4)
+                .build_sparse_discrete_network(Tanh);
+            ReservoirComputer::try_new_with_feedback(
+                Reservoir::new(DefaultInputProjection::new_with_matrix(w_in.clone()), esn),
+                DefaultStateMeasurement::new(10),
+                LinearStateProjection::new_with_matrix(w_out),
+                feedback,
+            )
+            .unwrap()
+        };
+
+        let kickstarter = DMatrix::from_vec(2, 1, vec![0.5, -0.3]);
+        let mut auxiliary = new_model(w_out.clone(), &feedback);
+        let predictions = auxiliary.synchronize_and_predict_with_feedback(
+            kickstarter.columns(0, 1),
+            20,
+            &feedback,
+        );
+        assert_eq!(predictions.shape(), (3, 20));
+
+        let mut reduced = new_model(w_out.select_rows(&[0, 2]), &FeedbackMap::Identity);
+        let expected = reduced.synchronize_and_predict(kickstarter.columns(0, 1), 0, 20);
+        assert!((predictions.select_rows(&[0, 2]) - expected).amax() < 1e-12);
+        assert_eq!(auxiliary.state(), reduced.state());
+    }
+}
@@ -0,0 +1,400 @@
+use std::time::{Duration, Instant};
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSlice};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::{AllocationFreeTimeEvolution, ReservoirTimeEvolution};
+use crate::ReservoirValue;
+
+use super::ReservoirComputer;
+
+// Inference only model for real time threads. The components can no longer be accessed
+// mutably and every buffer is allocated up front. The time evolution must be an
+// `AllocationFreeTimeEvolution`; a step then does not allocate as long as the `_into` methods of
+// the other components do not either, which holds for the default input projection and
+// measurement and the linear readout.
+#[derive(Clone, Debug)]
+pub struct FrozenReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    input_projection: I,
+    time_evolution: E,
+    measurement: M,
+    projection: P,
+    buffers: StepBuffers<T>,
+    skip_late_readout: bool,
+    deadline_statistics: DeadlineStatistics,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadlineOutcome {
+    Met,
+    // The deadline had passed after the time evolution, the prediction was not updated.
+    ReadoutSkipped,
+    Missed,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepTiming {
+    pub elapsed: Duration,
+    pub outcome: DeadlineOutcome,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeadlineStatistics {
+    pub steps: usize,
+    // Includes the steps with a skipped readout.
+    pub missed: usize,
+    pub skipped_readouts: usize,
+    pub total_elapsed: Duration,
+    pub max_elapsed: Duration,
+}
+
+impl DeadlineStatistics {
+    pub fn mean_elapsed(&self) -> Duration {
+        if self.steps == 0 {
+            Duration::ZERO
+        } else {
+            self.total_elapsed / self.steps as u32
+        }
+    }
+
+    fn record(&mut self, timing: StepTiming) {
+        self.steps += 1;
+        self.total_elapsed += timing.elapsed;
+        self.max_elapsed = self.max_elapsed.max(timing.elapsed);
+        match timing.outcome {
+            DeadlineOutcome::Met => {}
+            DeadlineOutcome::ReadoutSkipped => {
+                self.missed += 1;
+                self.skipped_readouts += 1;
+            }
+            DeadlineOutcome::Missed => self.missed += 1,
+        }
+    }
+}
+
+impl<T, I, E, M, P> FrozenReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: AllocationFreeTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn new(reservoir_computer: ReservoirComputer<T, I, E, M, P>) -> Self {
+        let (state, input_projection, time_evolution) = reservoir_computer.reservoir.into_parts();
+        let measurement = reservoir_computer.reservoir_state_measurement;
+        let projection = reservoir_computer.reservoir_state_projection;
+        Self {
+            buffers: StepBuffers::new(state, &input_projection, &measurement, &projection),
+            skip_late_readout: false,
+            deadline_statistics: DeadlineStatistics::default(),
+            input_projection,
+            time_evolution,
+            measurement,
+            projection,
+        }
+    }
+
+    pub fn state(&self) -> &DVector<T> {
+        &self.buffers.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut DVector<T> {
+        &mut self.buffers.state
+    }
+
+    pub fn input_projection(&self) -> &I {
+        &self.input_projection
+    }
+
+    pub fn time_evolution(&self) -> &E {
+        &self.time_evolution
+    }
+
+    pub fn state_measurement(&self) -> &M {
+        &self.measurement
+    }
+
+    pub fn state_projection(&self) -> &P {
+        &self.projection
+    }
+
+    // The last prediction, zero before the first step.
+    pub fn prediction(&self) -> &DVector<T> {
+        &self.buffers.prediction
+    }
+
+    // Fills the whole input window, steps once and returns the first prediction, just like
+    // `synchronize_and_predict` with the same kickstarter.
+    pub fn kickstart(&mut self, kickstarter: DMatrixSlice<T>) -> &DVector<T> {
+        self.buffers.kickstart(kickstarter);
+        self.advance();
+        &self.buffers.prediction
+    }
+
+    // Open loop step driven by an observed input.
+    pub fn step(&mut self, input: DVectorSlice<T>) -> &DVector<T> {
+        self.buffers.push_input(input);
+        self.advance();
+        &self.buffers.prediction
+    }
+
+    // If set, `step_with_deadline` leaves the previous prediction in place instead of measuring
+    // and projecting once the deadline has passed after the time evolution.
+    pub fn skip_late_readout(&mut self, skip: bool) -> &mut Self {
+        self.skip_late_readout = skip;
+        self
+    }
+
+    // Open loop step that checks the elapsed time against `deadline`, the timing is also added
+    // to the deadline statistics.
+    pub fn step_with_deadline(&mut self, input: DVectorSlice<T>, deadline: Instant) -> StepTiming {
+        self.step_with_deadline_and_clock(input, deadline, Instant::now)
+    }
+
+    // Like `step_with_deadline` with the time read from `now`, e.g. the clock of a simulated
+    // control loop.
+    pub fn step_with_deadline_and_clock(
+        &mut self,
+        input: DVectorSlice<T>,
+        deadline: Instant,
+        mut now: impl FnMut() -> Instant,
+    ) -> StepTiming {
+        let start = now();
+        self.buffers.push_input(input);
+        self.evolve();
+
+        let outcome = if self.skip_late_readout && now() > deadline {
+            DeadlineOutcome::ReadoutSkipped
+        } else {
+            self.readout();
+            if now() > deadline {
+                DeadlineOutcome::Missed
+            } else {
+                DeadlineOutcome::Met
+            }
+        };
+        let timing = StepTiming {
+            elapsed: now().saturating_duration_since(start),
+            outcome,
+        };
+        self.deadline_statistics.record(timing);
+        timing
+    }
+
+    pub fn deadline_statistics(&self) -> &DeadlineStatistics {
+        &self.deadline_statistics
+    }
+
+    pub fn reset_deadline_statistics(&mut self) {
+        self.deadline_statistics = DeadlineStatistics::default();
+    }
+
+    // Closed loop step, the last prediction is the next input.
+    pub fn predict_step(&mut self) -> &DVector<T> {
+        self.buffers.push_prediction();
+        self.advance();
+        &self.buffers.prediction
+    }
+
+    // Writes the last prediction and `result.ncols() - 1` further closed loop predictions.
+    pub fn predict_into(&mut self, mut result: DMatrixSliceMut<T>) {
+        for step in 0..result.ncols() {
+            if step > 0 {
+                self.predict_step();
+            }
+            result.column_mut(step).copy_from(&self.buffers.prediction);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.evolve();
+        self.readout();
+    }
+
+    fn evolve(&mut self) {
+        self.buffers
+            .evolve(&self.input_projection, &self.time_evolution);
+    }
+
+    fn readout(&mut self) {
+        self.buffers.readout(&self.measurement, &self.projection);
+    }
+}
+
+// State, input window and scratch buffers of a model that steps one input at a time, shared by
+// `FrozenReservoirComputer` and `ReservoirSession`. Neither the steps nor the readout allocate
+// as long as the components do not.
+#[derive(Clone, Debug)]
+pub(super) struct StepBuffers<T: ReservoirValue> {
+    pub(super) state: DVector<T>,
+    // The most recent inputs, the newest one in the last column.
+    input_window: DMatrix<T>,
+    projected_input: DVector<T>,
+    projection_workspace: DMatrix<T>,
+    evolution_buffer: DVector<T>,
+    measured_state: DVector<T>,
+    pub(super) prediction: DVector<T>,
+}
+
+impl<T: ReservoirValue> StepBuffers<T> {
+    pub(super) fn new(
+        state: DVector<T>,
+        input_projection: &impl ReservoirInputProjection<T>,
+        measurement: &impl ReservoirStateMeasurement<T>,
+        projection: &impl ReservoirStateProjection<T>,
+    ) -> Self {
+        Self {
+            input_window: DMatrix::zeros(
+                input_projection.input_dimension(),
+                input_projection.required_input_columns(),
+            ),
+            projected_input: DVector::zeros(input_projection.output_dimensions()),
+            projection_workspace: DMatrix::zeros(0, 0),
+            evolution_buffer: DVector::zeros(state.nrows()),
+            measured_state: DVector::zeros(measurement.output_dimension()),
+            prediction: DVector::zeros(projection.output_dimension()),
+            state,
+        }
+    }
+
+    // Back to `state` with an empty input window.
+    pub(super) fn reset(&mut self, state: &DVector<T>) {
+        self.state.copy_from(state);
+        self.input_window.fill(T::zero());
+        self.prediction.fill(T::zero());
+    }
+
+    // Replaces the whole input window.
+    pub(super) fn kickstart(&mut self, kickstarter: DMatrixSlice<T>) {
+        assert_eq!(kickstarter.shape(), self.input_window.shape());
+        self.input_window.copy_from(&kickstarter);
+    }
+
+    pub(super) fn push_input(&mut self, input: DVectorSlice<T>) {
+        self.shift_input_window();
+        let newest = self.input_window.ncols() - 1;
+        self.input_window.column_mut(newest).copy_from(&input);
+    }
+
+    // The last prediction becomes the newest input.
+    pub(super) fn push_prediction(&mut self) {
+        self.shift_input_window();
+        let newest = self.input_window.ncols() - 1;
+        self.input_window
+            .column_mut(newest)
+            .copy_from(&self.prediction);
+    }
+
+    fn shift_input_window(&mut self) {
+        for column in 1..self.input_window.ncols() {
+            self.input_window.swap_columns(column - 1, column);
+        }
+    }
+
+    pub(super) fn evolve(
+        &mut self,
+        input_projection: &impl ReservoirInputProjection<T>,
+        time_evolution: &impl ReservoirTimeEvolution<T>,
+    ) {
+        input_projection.project_many_into_with_workspace(
+            self.input_window.columns(0, self.input_window.ncols()),
+            self.projected_input.columns_mut(0, 1),
+            &mut self.projection_workspace,
+        );
+        time_evolution.time_evolution_with_buffer(
+            &mut self.state,
+            self.projected_input.column(0),
+            &mut self.evolution_buffer,
+        );
+    }
+
+    pub(super) fn readout(
+        &mut self,
+        measurement: &impl ReservoirStateMeasurement<T>,
+        projection: &impl ReservoirStateProjection<T>,
+    ) {
+        measurement.measure_into(&self.state, self.measured_state.column_mut(0));
+        projection.project_into(&self.measured_state, self.prediction.column_mut(0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use nalgebra::{DMatrix, DVector};
+
+    use super::DeadlineOutcome;
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder,
+        input_projection::DefaultInputProjection, output_projection::LinearStateProjection,
+        state_measurement::DefaultStateMeasurement, Reservoir, ReservoirComputer,
+    };
+
+    // Every reading of the clock advances it by one millisecond, so a step sees the deadline at
+    // 1 ms and is timed at 2 ms.
+    fn stepped_clock(start: Instant) -> impl FnMut() -> Instant {
+        let mut readings = 0;
+        move || {
+            readings += 1;
+            start + Duration::from_millis(readings - 1)
+        }
+    }
+
+    #[test]
+    fn step_with_deadline_skips_late_readout() {
+        let esn = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, 1)
+            .build_sparse_discrete_network(Tanh);
+        let reservoir = Reservoir::new(
+            DefaultInputProjection::new_with_matrix(DMatrix::from_element(10, 2, 0.5)),
+            esn,
+        );
+        let mut frozen = ReservoirComputer::new(
+            reservoir,
+            DefaultStateMeasurement::new(10),
+            LinearStateProjection::new_with_matrix(DMatrix::from_element(2, 10, 0.1)),
+        )
+        .freeze();
+        let start = Instant::now();
+        let millis = Duration::from_millis;
+        let input = DVector::from_vec(vec![1., 0.5]);
+
+        let timing = frozen.step_with_deadline_and_clock(
+            input.column(0),
+            start + millis(2),
+            stepped_clock(start),
+        );
+        assert_eq!(timing.outcome, DeadlineOutcome::Met);
+        assert_eq!(timing.elapsed, millis(2));
+        let timing =
+            frozen.step_with_deadline_and_clock(input.column(0), start, stepped_clock(start));
+        assert_eq!(timing.outcome, DeadlineOutcome::Missed);
+        let prediction = frozen.prediction().clone();
+
+        frozen.skip_late_readout(true);
+        let state = frozen.state().clone();
+        let timing =
+            frozen.step_with_deadline_and_clock(input.column(0), start, stepped_clock(start));
+        assert_eq!(timing.outcome, DeadlineOutcome::ReadoutSkipped);
+        assert_eq!(timing.elapsed, millis(2));
+        assert_ne!(*frozen.state(), state);
+        assert_eq!(*frozen.prediction(), prediction);
+
+        let statistics = frozen.deadline_statistics();
+        assert_eq!(statistics.steps, 3);
+        assert_eq!(statistics.missed, 2);
+        assert_eq!(statistics.skipped_readouts, 1);
+        assert_eq!(statistics.max_elapsed, millis(2));
+        assert_eq!(statistics.total_elapsed, millis(6));
+    }
+}
@@ -0,0 +1,129 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::metrics::truth_variance;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::ReservoirValue;
+
+use super::ReservoirComputer;
+
+// Forecast error as a function of the horizon. From every origin a single closed loop forecast
+// up to the largest horizon is run and the normalized error of the step `horizon` steps ahead
+// is read off, the normalization is the variance of the whole evaluation data so that the
+// errors of different origins are comparable.
+#[derive(Clone, Debug)]
+pub struct HorizonErrorCurve<T: ReservoirValue> {
+    horizons: Vec<usize>,
+    origins: Vec<usize>,
+    // One row per origin, one column per horizon.
+    errors: DMatrix<T>,
+}
+
+impl<T: ReservoirValue> HorizonErrorCurve<T> {
+    // `horizons` count from one, horizon 1 is the first predicted column `data.column(origin)`.
+    // Before each forecast the state is reset and synchronized on the `sync_steps` columns
+    // preceding the origin.
+    pub fn evaluate<I, E, M, P>(
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+        data: DMatrixSlice<T>,
+        origins: &[usize],
+        sync_steps: usize,
+        horizons: &[usize],
+    ) -> Self
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        assert!(!origins.is_empty() && !horizons.is_empty());
+        assert!(horizons.iter().all(|&horizon| horizon > 0));
+        let max_horizon = *horizons.iter().max().unwrap();
+        assert!(
+            origins
+                .iter()
+                .all(|&origin| origin + max_horizon <= data.ncols()),
+            "Every origin needs {max_horizon} columns of data after it."
+        );
+
+        let variance = truth_variance(data) / T::from_usize(data.ncols()).unwrap();
+        let mut errors = DMatrix::zeros(origins.len(), horizons.len());
+        for (row, &origin) in origins.iter().enumerate() {
+            let prediction =
+                reservoir_computer.resynchronize_and_predict(data, origin, sync_steps, max_horizon);
+            for (column, &horizon) in horizons.iter().enumerate() {
+                let step = origin + horizon - 1;
+                let squared_error =
+                    (prediction.column(horizon - 1) - data.column(step)).norm_squared();
+                errors[(row, column)] = num_traits::Float::sqrt(squared_error / variance);
+            }
+        }
+
+        Self {
+            horizons: horizons.to_vec(),
+            origins: origins.to_vec(),
+            errors,
+        }
+    }
+
+    // Origins every `stride` columns, starting as early as the synchronization allows.
+    pub fn evaluate_strided<I, E, M, P>(
+        reservoir_computer: &mut ReservoirComputer<T, I, E, M, P>,
+        data: DMatrixSlice<T>,
+        stride: usize,
+        sync_steps: usize,
+        horizons: &[usize],
+    ) -> Self
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    {
+        assert!(stride > 0);
+        let warmup = sync_steps.max(1) + reservoir_computer.kickstarter_len() - 1;
+        let max_horizon = horizons.iter().copied().max().unwrap_or(0);
+        let origins: Vec<usize> = (warmup..)
+            .step_by(stride)
+            .take_while(|origin| origin + max_horizon <= data.ncols())
+            .collect();
+        Self::evaluate(reservoir_computer, data, &origins, sync_steps, horizons)
+    }
+
+    pub fn horizons(&self) -> &[usize] {
+        &self.horizons
+    }
+
+    pub fn origins(&self) -> &[usize] {
+        &self.origins
+    }
+
+    pub fn errors(&self) -> &DMatrix<T> {
+        &self.errors
+    }
+
+    // Error per horizon averaged over all origins.
+    pub fn mean_errors(&self) -> DVector<T> {
+        self.errors.row_mean().transpose()
+    }
+
+    // Error per horizon, the median over all origins is less sensitive to single diverging
+    // forecasts than the mean.
+    pub fn median_errors(&self) -> DVector<T> {
+        DVector::from_iterator(
+            self.horizons.len(),
+            self.errors.column_iter().map(|column| {
+                let mut values: Vec<T> = column.iter().copied().collect();
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let middle = values.len() / 2;
+                if values.len().is_multiple_of(2) {
+                    (values[middle - 1] + values[middle]) / T::from_f64(2.).unwrap()
+                } else {
+                    values[middle]
+                }
+            }),
+        )
+    }
+}
@@ -0,0 +1,76 @@
+use nalgebra::{DMatrix, DVector};
+
+use crate::ReservoirValue;
+
+// Derivatives of one prediction step at the current state: `state` is the derivative of the
+// next state by the current one with the input held fixed, `input` the one by the newest input
+// column and `readout` the one of the prediction made from the current state. In closed loop
+// the prediction is the newest input, see `closed_loop`.
+#[derive(Clone, Debug)]
+pub struct ReservoirJacobian<T: ReservoirValue> {
+    state: DMatrix<T>,
+    input: DMatrix<T>,
+    readout: DMatrix<T>,
+}
+
+impl<T: ReservoirValue> ReservoirJacobian<T> {
+    pub fn new(state: DMatrix<T>, input: DMatrix<T>, readout: DMatrix<T>) -> Self {
+        assert!(state.is_square());
+        assert_eq!(input.nrows(), state.nrows());
+        assert_eq!(readout.ncols(), state.ncols());
+        assert_eq!(input.ncols(), readout.nrows());
+        Self {
+            state,
+            input,
+            readout,
+        }
+    }
+
+    pub fn state(&self) -> &DMatrix<T> {
+        &self.state
+    }
+
+    pub fn input(&self) -> &DMatrix<T> {
+        &self.input
+    }
+
+    pub fn readout(&self) -> &DMatrix<T> {
+        &self.readout
+    }
+
+    // Derivative of the next state by the current one when the prediction is fed back.
+    pub fn closed_loop(&self) -> DMatrix<T> {
+        &self.state + &self.input * &self.readout
+    }
+}
+
+// Jacobian of `map` at `point` by central differences, `map` writes its value at the first
+// argument into the second one, a vector of `output_dimension` entries.
+pub(crate) fn central_differences<T, F>(
+    point: &DVector<T>,
+    output_dimension: usize,
+    mut map: F,
+) -> DMatrix<T>
+where
+    T: ReservoirValue,
+    F: FnMut(&DVector<T>, &mut DVector<T>),
+{
+    let relative_step = num_traits::Float::cbrt(<T as num_traits::Float>::epsilon());
+    let mut jacobian = DMatrix::zeros(output_dimension, point.nrows());
+    let mut shifted = point.clone();
+    let mut forward = DVector::zeros(output_dimension);
+    let mut backward = DVector::zeros(output_dimension);
+    for column in 0..point.nrows() {
+        let step =
+            relative_step * num_traits::Float::max(T::one(), num_traits::Float::abs(point[column]));
+        shifted[column] = point[column] + step;
+        map(&shifted, &mut forward);
+        shifted[column] = point[column] - step;
+        map(&shifted, &mut backward);
+        shifted[column] = point[column];
+        jacobian
+            .column_mut(column)
+            .copy_from(&((&forward - &backward) / (step + step)));
+    }
+    jacobian
+}
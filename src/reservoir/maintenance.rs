@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+
+use nalgebra::DVectorSlice;
+
+use crate::ReservoirValue;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaintenanceAction {
+    Keep,
+    // Synchronize the reservoir on the recent observations and continue from them.
+    Resync,
+    // Fit the readout to the recent observations by ridge regression, then resync.
+    RetrainReadout,
+}
+
+// Decides after every observation of a long running prediction stream how to maintain it, see
+// `PredictionStream::observe`.
+pub trait MaintenancePolicy<T: ReservoirValue> {
+    // `residual` is the observation minus its prediction.
+    fn decide(&mut self, residual: DVectorSlice<T>) -> MaintenanceAction;
+
+    // Number of recent observations kept to resync and retrain with.
+    fn memory(&self) -> usize;
+
+    // Regularization of the readout retraining.
+    fn retraining_beta(&self) -> T;
+}
+
+// Acts once the mean residual norm over the last `window` observations exceeds `threshold`:
+// a loss of synchronization is fixed by resyncing, so the first `max_resyncs` times in a row
+// the stream is resynced and only if the error persists the readout is retrained. The window
+// starts over after every action.
+#[derive(Clone, Debug)]
+pub struct SlidingWindowPolicy<T: ReservoirValue> {
+    window: usize,
+    threshold: T,
+    max_resyncs: usize,
+    memory: usize,
+    beta: T,
+    residual_norms: VecDeque<T>,
+    resyncs: usize,
+}
+
+impl<T: ReservoirValue> SlidingWindowPolicy<T> {
+    pub fn new(window: usize, threshold: T, max_resyncs: usize, memory: usize, beta: T) -> Self {
+        assert!(window > 0);
+        assert!(threshold > T::zero() && beta > T::zero());
+        Self {
+            window,
+            threshold,
+            max_resyncs,
+            memory,
+            beta,
+            residual_norms: VecDeque::with_capacity(window + 1),
+            resyncs: 0,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    pub fn threshold(&self) -> T {
+        self.threshold
+    }
+
+    // None until the window is full.
+    pub fn mean_residual_norm(&self) -> Option<T> {
+        (self.residual_norms.len() == self.window).then(|| {
+            self.residual_norms
+                .iter()
+                .fold(T::zero(), |sum, norm| sum + *norm)
+                / T::from_usize(self.window).unwrap()
+        })
+    }
+}
+
+impl<T: ReservoirValue> MaintenancePolicy<T> for SlidingWindowPolicy<T> {
+    fn decide(&mut self, residual: DVectorSlice<T>) -> MaintenanceAction {
+        self.residual_norms.push_back(residual.norm());
+        if self.residual_norms.len() > self.window {
+            self.residual_norms.pop_front();
+        }
+        match self.mean_residual_norm() {
+            None => MaintenanceAction::Keep,
+            Some(mean) if mean <= self.threshold => {
+                self.resyncs = 0;
+                MaintenanceAction::Keep
+            }
+            Some(_) => {
+                self.residual_norms.clear();
+                if self.resyncs < self.max_resyncs {
+                    self.resyncs += 1;
+                    MaintenanceAction::Resync
+                } else {
+                    self.resyncs = 0;
+                    MaintenanceAction::RetrainReadout
+                }
+            }
+        }
+    }
+
+    fn memory(&self) -> usize {
+        self.memory
+    }
+
+    fn retraining_beta(&self) -> T {
+        self.beta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DVector;
+
+    use super::{MaintenanceAction, MaintenancePolicy, SlidingWindowPolicy};
+
+    #[test]
+    fn resyncs_before_retraining() {
+        let mut policy = SlidingWindowPolicy::new(3, 0.5, 2, 100, 1e-6);
+        let small = DVector::from_element(1, 0.1);
+        let large = DVector::from_element(1, 1.);
+        let mut decide = |residual: &DVector<f64>| policy.decide(residual.column(0));
+
+        // A single large residual is averaged out.
+        assert_eq!(decide(&large), MaintenanceAction::Keep);
+        assert_eq!(decide(&small), MaintenanceAction::Keep);
+        assert_eq!(decide(&small), MaintenanceAction::Keep);
+
+        let actions: Vec<_> = (0..9).map(|_| decide(&large)).collect();
+        assert_eq!(
+            actions,
+            [
+                MaintenanceAction::Keep,
+                MaintenanceAction::Resync,
+                MaintenanceAction::Keep,
+                MaintenanceAction::Keep,
+                MaintenanceAction::Resync,
+                MaintenanceAction::Keep,
+                MaintenanceAction::Keep,
+                MaintenanceAction::RetrainReadout,
+                MaintenanceAction::Keep,
+            ]
+        );
+    }
+}
@@ -1,10 +1,44 @@
+#[cfg(feature = "async")]
+pub mod async_prediction_stream;
+pub mod checkpoint;
+pub mod classification;
+pub mod conformal;
 pub mod core_reservoir;
+pub mod denoising;
+pub mod dimension_info;
+pub mod feedback_map;
+pub mod frozen_reservoir_computer;
+pub mod horizon_evaluation;
+pub mod jacobian;
+pub mod maintenance;
+pub mod prediction_stream;
 pub mod reservoir_computer;
 pub mod reservoir_computer_dynamics;
 pub mod reservoir_dynamics;
+pub mod shared_reservoir_model;
+pub mod task_embedding;
 pub mod training;
+pub mod training_report;
 
+#[cfg(feature = "async")]
+pub use async_prediction_stream::AsyncPredictionStream;
+pub use checkpoint::ReservoirCheckpoint;
+pub use classification::SequenceClassification;
+pub use conformal::ConformalCalibration;
 pub use core_reservoir::Reservoir;
+pub use denoising::DenoisingTraining;
+pub use dimension_info::{DimensionInfo, DimensionReport};
+pub use feedback_map::FeedbackMap;
+pub use frozen_reservoir_computer::{
+    DeadlineOutcome, DeadlineStatistics, FrozenReservoirComputer, StepTiming,
+};
+pub use horizon_evaluation::HorizonErrorCurve;
+pub use jacobian::ReservoirJacobian;
+pub use maintenance::{MaintenanceAction, MaintenancePolicy, SlidingWindowPolicy};
+pub use prediction_stream::PredictionStream;
 pub use reservoir_computer::ReservoirComputer;
 pub use reservoir_computer_dynamics::ReservoirComputerDynamics;
-pub use reservoir_dynamics::ReservoirDynamics;
+pub use reservoir_dynamics::{ReservoirDynamics, StepCallback};
+pub use shared_reservoir_model::{ReservoirSession, SharedReservoirModel};
+pub use task_embedding::TaskEmbeddingReport;
+pub use training_report::TrainingReport;
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::{LinearStateProjection, ReservoirStateProjection};
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+
+use super::{MaintenanceAction, MaintenancePolicy, ReservoirComputer};
+use crate::ReservoirValue;
+
+// Autonomous prediction as an endless iterator, the last `required_input_columns` outputs are
+// kept in a ring buffer and fed back as input.
+#[derive(Debug)]
+pub struct PredictionStream<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    reservoir_computer: ReservoirComputer<T, I, E, M, P>,
+    history: DMatrix<T>,
+    oldest: usize,
+    window: DMatrix<T>,
+    // Whether the newest history column is a prediction, not an observation.
+    predicted: bool,
+    observations: VecDeque<DVector<T>>,
+}
+
+impl<T, I, E, M, P> PredictionStream<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn new(
+        mut reservoir_computer: ReservoirComputer<T, I, E, M, P>,
+        kickstarter: DMatrixSlice<T>,
+    ) -> Self {
+        let input_columns = reservoir_computer.kickstarter_len();
+        assert_eq!(kickstarter.ncols(), input_columns);
+        reservoir_computer.reservoir.synchronize_state(kickstarter);
+
+        Self {
+            reservoir_computer,
+            history: kickstarter.clone_owned(),
+            oldest: 0,
+            window: DMatrix::zeros(kickstarter.nrows(), input_columns),
+            predicted: false,
+            observations: VecDeque::new(),
+        }
+    }
+
+    pub fn reservoir_computer(&self) -> &ReservoirComputer<T, I, E, M, P> {
+        &self.reservoir_computer
+    }
+
+    pub fn into_inner(self) -> ReservoirComputer<T, I, E, M, P> {
+        self.reservoir_computer
+    }
+}
+
+impl<T, I, E, M, P> Iterator for PredictionStream<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    type Item = DVector<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let reservoir_computer = &mut self.reservoir_computer;
+        let state_measurement = reservoir_computer
+            .reservoir_state_measurement
+            .measure(&reservoir_computer.reservoir.reservoir_state);
+        let prediction = reservoir_computer
+            .reservoir_state_projection
+            .project(state_measurement)
+            .clone();
+
+        let input_columns = self.history.ncols();
+        self.history.column_mut(self.oldest).copy_from(&prediction);
+        self.oldest = (self.oldest + 1) % input_columns;
+        for column in 0..input_columns {
+            self.window
+                .column_mut(column)
+                .copy_from(&self.history.column((self.oldest + column) % input_columns));
+        }
+        reservoir_computer
+            .reservoir
+            .synchronize_state(self.window.columns(0, input_columns));
+        self.predicted = true;
+
+        Some(prediction)
+    }
+}
+
+impl<T, I, E, M> PredictionStream<T, I, E, M, LinearStateProjection<T>>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+{
+    // Hands the residual of the latest prediction, `observation` being its true value, to
+    // `policy` and carries out the maintenance it decides on, returning the action performed.
+    // The last `policy.memory()` observations are kept: resyncing drives the reservoir from the
+    // zero state with them and continues the prediction after the latest one, retraining fits
+    // the readout to the one step predictions of them, the first quarter only synchronizing.
+    // Until enough observations are kept the action degrades to what is possible.
+    pub fn observe<Q: MaintenancePolicy<T>>(
+        &mut self,
+        observation: DVector<T>,
+        policy: &mut Q,
+    ) -> MaintenanceAction {
+        assert!(self.predicted, "There is no prediction to compare with.");
+        let input_columns = self.history.ncols();
+        let memory = policy.memory();
+        assert!(
+            memory > input_columns,
+            "The policy has to keep more than {input_columns} observations."
+        );
+        let latest = (self.oldest + input_columns - 1) % input_columns;
+        let action = policy.decide((&observation - self.history.column(latest)).column(0));
+        self.observations.push_back(observation);
+        while self.observations.len() > memory {
+            self.observations.pop_front();
+        }
+
+        let action = match action {
+            MaintenanceAction::RetrainReadout if self.observations.len() == memory => action,
+            MaintenanceAction::Keep => return action,
+            _ if self.observations.len() >= input_columns => MaintenanceAction::Resync,
+            _ => return MaintenanceAction::Keep,
+        };
+        let observations = DMatrix::from_columns(self.observations.make_contiguous());
+        let columns = observations.ncols();
+        let reservoir_computer = &mut self.reservoir_computer;
+        reservoir_computer.reservoir.reservoir_state.fill(T::zero());
+        if action == MaintenanceAction::RetrainReadout {
+            let sync_steps = (columns / 4).max(input_columns - 1);
+            let states = reservoir_computer
+                .reservoir
+                .record_states(observations.columns(0, columns - 1), sync_steps);
+            let measured_states = reservoir_computer
+                .reservoir_state_measurement
+                .measure_many(states.columns(0, states.ncols()));
+            reservoir_computer.reservoir_state_projection =
+                LinearStateProjection::via_ridge_regression_nalgebra(
+                    policy.retraining_beta(),
+                    &measured_states,
+                    observations.columns(sync_steps + 1, columns - sync_steps - 1),
+                );
+            reservoir_computer
+                .reservoir
+                .synchronize_state(observations.columns(columns - input_columns, input_columns));
+        } else {
+            reservoir_computer
+                .reservoir
+                .synchronize_state(observations.columns(0, columns));
+        }
+        self.history
+            .copy_from(&observations.columns(columns - input_columns, input_columns));
+        self.oldest = 0;
+        self.predicted = false;
+        action
+    }
+}
@@ -1,12 +1,21 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::ControlFlow};
 
 use crate::input_projection::ReservoirInputProjection;
-use crate::output_projection::ReservoirStateProjection;
+use crate::output_projection::{
+    LinearStateProjection, OnlineReadoutTrainer, QuantileStateProjection, ReservoirStateProjection,
+};
 use crate::state_measurement::ReservoirStateMeasurement;
-use crate::time_evolution::ReservoirTimeEvolution;
-use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
+use crate::time_evolution::{AllocationFreeTimeEvolution, ReservoirTimeEvolution};
+use nalgebra::{
+    ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice, DMatrixSliceMut, DVector,
+};
 
-use super::Reservoir;
+use super::{
+    jacobian::central_differences, FeedbackMap, FrozenReservoirComputer, PredictionStream,
+    Reservoir, ReservoirCheckpoint, ReservoirComputerDynamics, ReservoirJacobian,
+    SharedReservoirModel,
+};
+use crate::error::{check_dimension, ReservoirError};
 use crate::ReservoirValue;
 
 #[derive(Debug)]
@@ -31,6 +40,70 @@ where
     M: ReservoirStateMeasurement<T>,
     P: ReservoirStateProjection<T>,
 {
+    pub fn new(reservoir: Reservoir<T, I, E>, measurement: M, projection: P) -> Self {
+        match Self::try_new(reservoir, measurement, projection) {
+            Ok(reservoir_computer) => reservoir_computer,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    // The projected output is fed back as input, so it has to match the input dimension.
+    pub fn try_new(
+        reservoir: Reservoir<T, I, E>,
+        measurement: M,
+        projection: P,
+    ) -> Result<Self, ReservoirError> {
+        Self::try_new_with_feedback(reservoir, measurement, projection, &FeedbackMap::Identity)
+    }
+
+    // Like `try_new` for predictions that are fed back through `feedback`.
+    pub fn try_new_with_feedback(
+        reservoir: Reservoir<T, I, E>,
+        measurement: M,
+        projection: P,
+        feedback: &FeedbackMap<T>,
+    ) -> Result<Self, ReservoirError> {
+        check_dimension(
+            "input projection output / time evolution input",
+            reservoir.time_evolution().input_dimension(),
+            reservoir.input_projection().output_dimensions(),
+        )?;
+        check_dimension(
+            "reservoir state / time evolution output",
+            reservoir.time_evolution().output_dimension(),
+            reservoir.state().nrows(),
+        )?;
+        check_dimension(
+            "state measurement output / state projection input",
+            projection.input_dimension(),
+            measurement.output_dimension(),
+        )?;
+        check_dimension(
+            "fed back state projection output / input projection input",
+            reservoir.input_projection().input_dimension(),
+            feedback.input_dimension(projection.output_dimension()),
+        )?;
+        Ok(Self {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: projection,
+        })
+    }
+
+    pub fn split_reservoir_computer_dynamics(
+        self,
+    ) -> (DVector<T>, ReservoirComputerDynamics<T, I, E, M, P>) {
+        let (state, reservoir_dynamics) = self.reservoir.split_reservoir_dynamics();
+        (
+            state,
+            ReservoirComputerDynamics::new(
+                reservoir_dynamics,
+                self.reservoir_state_measurement,
+                self.reservoir_state_projection,
+            ),
+        )
+    }
+
     pub fn state(&self) -> &DVector<T> {
         self.reservoir.state()
     }
@@ -43,10 +116,81 @@ where
         &self.reservoir_state_measurement
     }
 
+    // E.g. to switch the context of a `ContextStateMeasurement` between predictions.
+    pub fn state_measurement_mut(&mut self) -> &mut M {
+        &mut self.reservoir_state_measurement
+    }
+
     pub fn state_projection(&self) -> &P {
         &self.reservoir_state_projection
     }
 
+    // Number of trailing input columns `predict_from_recent` uses as kickstarter.
+    pub fn kickstarter_len(&self) -> usize {
+        self.reservoir.input_projection().required_input_columns()
+    }
+
+    pub fn predict_from_recent(
+        &mut self,
+        recent_columns: DMatrixSlice<T>,
+        predict_steps: usize,
+    ) -> DMatrix<T> {
+        let kickstarter_len = self.kickstarter_len();
+        assert!(
+            recent_columns.ncols() >= kickstarter_len,
+            "At least {kickstarter_len} columns are required, got {}.",
+            recent_columns.ncols()
+        );
+        let kickstarter =
+            recent_columns.columns(recent_columns.ncols() - kickstarter_len, kickstarter_len);
+        self.synchronize_and_predict(kickstarter, 0, predict_steps)
+    }
+
+    pub fn into_prediction_stream(
+        self,
+        kickstarter: DMatrixSlice<T>,
+    ) -> PredictionStream<T, I, E, M, P> {
+        PredictionStream::new(self, kickstarter)
+    }
+
+    // Resets the state, synchronizes on the `sync_steps` columns of `data` before `origin` and
+    // predicts `predict_steps` steps in closed loop, i.e. a forecast of
+    // data.columns(origin, predict_steps).
+    pub fn resynchronize_and_predict(
+        &mut self,
+        data: DMatrixSlice<T>,
+        origin: usize,
+        sync_steps: usize,
+        predict_steps: usize,
+    ) -> DMatrix<T> {
+        let kickstarter_len = self.kickstarter_len();
+        assert!(
+            origin + 1 >= sync_steps.max(1) + kickstarter_len,
+            "Origin {origin} leaves too few columns for the synchronization."
+        );
+        self.reservoir.reservoir_state.fill(T::zero());
+        // Feeds the windows ending before the kickstarter.
+        if sync_steps > 1 {
+            self.reservoir.synchronize_state(data.columns(
+                origin + 1 - sync_steps - kickstarter_len,
+                sync_steps + kickstarter_len - 2,
+            ));
+        }
+        let kickstarter = data.columns(origin - kickstarter_len, kickstarter_len);
+        self.synchronize_and_predict(kickstarter, 0, predict_steps)
+    }
+
+    pub fn freeze(self) -> FrozenReservoirComputer<T, I, E, M, P>
+    where
+        E: AllocationFreeTimeEvolution<T>,
+    {
+        FrozenReservoirComputer::new(self)
+    }
+
+    pub fn into_shared(self) -> SharedReservoirModel<T, I, E, M, P> {
+        SharedReservoirModel::new(self)
+    }
+
     pub fn synchronize_and_predict(
         &mut self,
         input: DMatrixSlice<T>,
@@ -62,6 +206,19 @@ where
         )
     }
 
+    // Resets the timings of the current thread, predicts and returns the timings of the run.
+    #[cfg(feature = "profile")]
+    pub fn synchronize_and_predict_profiled(
+        &mut self,
+        input: DMatrixSlice<T>,
+        sync_steps: usize,
+        predict_steps: usize,
+    ) -> (DMatrix<T>, crate::profile::InferenceProfile) {
+        crate::profile::reset_profile();
+        let prediction = self.synchronize_and_predict(input, sync_steps, predict_steps);
+        (prediction, crate::profile::take_profile())
+    }
+
     pub fn synchronize_and_predict_into(
         &mut self,
         input: DMatrixSlice<T>,
@@ -78,6 +235,405 @@ where
             result,
         );
     }
+
+    // Closed loop prediction where `feedback` maps every prediction to the next input, e.g. when
+    // the readout has auxiliary outputs that are not fed back.
+    pub fn synchronize_and_predict_with_feedback(
+        &mut self,
+        kickstarter: DMatrixSlice<T>,
+        predict_steps: usize,
+        feedback: &FeedbackMap<T>,
+    ) -> DMatrix<T> {
+        let mut predictions = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            predict_steps,
+        );
+        self.reservoir
+            .reservoir_dynamics
+            .predict_with_feedback_into(
+                &mut self.reservoir.reservoir_state,
+                kickstarter,
+                &mut self.reservoir_state_measurement,
+                &mut self.reservoir_state_projection,
+                feedback,
+                predictions.columns_mut(0, predict_steps),
+            );
+        predictions
+    }
+
+    // Forecast with known exogenous drivers: the input rows `covariate_rows` are taken from
+    // `covariates`, whose column k is known at the time of prediction k, the other input rows
+    // are fed back from the predictions through `feedback`.
+    pub fn synchronize_and_predict_with_covariates(
+        &mut self,
+        kickstarter: DMatrixSlice<T>,
+        feedback: &FeedbackMap<T>,
+        covariate_rows: &[usize],
+        covariates: DMatrixSlice<T>,
+    ) -> DMatrix<T> {
+        let predict_steps = covariates.ncols();
+        let mut predictions = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            predict_steps,
+        );
+        self.reservoir
+            .reservoir_dynamics
+            .predict_with_covariates_into(
+                &mut self.reservoir.reservoir_state,
+                kickstarter,
+                &mut self.reservoir_state_measurement,
+                &mut self.reservoir_state_projection,
+                feedback,
+                covariate_rows,
+                covariates,
+                predictions.columns_mut(0, predict_steps),
+            );
+        predictions
+    }
+
+    // Closed loop prediction that hands every step, state and prediction to `callback`. Stops
+    // early once the callback breaks, the result then only holds the predictions made so far.
+    pub fn synchronize_and_predict_with_callback<F>(
+        &mut self,
+        kickstarter: DMatrixSlice<T>,
+        predict_steps: usize,
+        mut callback: F,
+    ) -> DMatrix<T>
+    where
+        F: FnMut(usize, &DVector<T>, &DVector<T>) -> ControlFlow<()>,
+    {
+        let mut predictions = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            predict_steps,
+        );
+        let completed = self
+            .reservoir
+            .reservoir_dynamics
+            .predict_with_callback_into(
+                &mut self.reservoir.reservoir_state,
+                kickstarter,
+                &mut self.reservoir_state_measurement,
+                &mut self.reservoir_state_projection,
+                &FeedbackMap::Identity,
+                &mut callback,
+                predictions.columns_mut(0, predict_steps),
+            );
+        predictions.columns(0, completed).clone_owned()
+    }
+
+    // Teacher forced one step ahead predictions over the whole series: the reservoir is driven
+    // by the true data and column k of the result predicts data column sync_steps + 1 + k, the
+    // same pairing the training uses. The state is reset to zero first, the first `sync_steps`
+    // columns only synchronize.
+    pub fn one_step_predictions(&mut self, data: DMatrixSlice<T>, sync_steps: usize) -> DMatrix<T> {
+        let kickstarter_len = self.kickstarter_len();
+        assert!(
+            sync_steps + 1 >= kickstarter_len,
+            "At least {} synchronization steps are needed to fill the input window.",
+            kickstarter_len - 1
+        );
+        assert!(
+            data.ncols() > sync_steps + 1,
+            "The data has no column left to predict after {sync_steps} synchronization steps."
+        );
+        self.reservoir.reservoir_state.fill(T::zero());
+        let states = self
+            .reservoir
+            .record_states(data.columns(0, data.ncols() - 1), sync_steps);
+        let measured_states = self
+            .reservoir_state_measurement
+            .measure_many(states.columns(0, states.ncols()));
+        self.reservoir_state_projection
+            .project_many(measured_states.columns(0, measured_states.ncols()))
+    }
+
+    // Derivatives of the step from the current state driven by `window`, the input window of
+    // that step with the newest input in the last column. In closed loop the last column is the
+    // prediction from the current state and `closed_loop` of the result is the derivative of
+    // the prediction map. The readout and input projection derivatives are central differences.
+    pub fn jacobian(&self, window: DMatrixSlice<T>) -> ReservoirJacobian<T> {
+        let input_projection = self.reservoir.input_projection();
+        assert_eq!(
+            window.shape(),
+            (
+                input_projection.input_dimension(),
+                input_projection.required_input_columns()
+            )
+        );
+        let state = self.state();
+
+        let mut measured_state =
+            DVector::zeros(self.reservoir_state_measurement.output_dimension());
+        let readout = central_differences(
+            state,
+            self.reservoir_state_projection.output_dimension(),
+            |point, prediction| {
+                self.reservoir_state_measurement
+                    .measure_into(point, measured_state.column_mut(0));
+                self.reservoir_state_projection
+                    .project_into(&measured_state, prediction.column_mut(0));
+            },
+        );
+
+        let newest = window.ncols() - 1;
+        let mut shifted_window = window.clone_owned();
+        let input_projection_jacobian = central_differences(
+            &window.column(newest).clone_owned(),
+            input_projection.output_dimensions(),
+            |point, projected_input| {
+                shifted_window.column_mut(newest).copy_from(point);
+                input_projection.project_many_into(
+                    shifted_window.columns(0, window.ncols()),
+                    projected_input.columns_mut(0, 1),
+                );
+            },
+        );
+
+        let mut projected_input = DVector::zeros(input_projection.output_dimensions());
+        input_projection.project_many_into(window, projected_input.columns_mut(0, 1));
+        let (state_jacobian, evolution_input_jacobian) = self
+            .reservoir
+            .time_evolution()
+            .jacobians(state, projected_input.column(0));
+        ReservoirJacobian::new(
+            state_jacobian,
+            evolution_input_jacobian * input_projection_jacobian,
+            readout,
+        )
+    }
+
+    // Closed loop prediction with extended Kalman style uncertainty: `initial_covariance` of the
+    // current state is propagated through the linearized steps, `process_noise` is added to the
+    // state covariance after every step. Returns the predictions of `synchronize_and_predict`
+    // and the covariance of every prediction.
+    pub fn synchronize_and_predict_with_covariance(
+        &mut self,
+        kickstarter: DMatrixSlice<T>,
+        predict_steps: usize,
+        initial_covariance: &DMatrix<T>,
+        process_noise: Option<&DMatrix<T>>,
+    ) -> (DMatrix<T>, Vec<DMatrix<T>>) {
+        let state_dimension = self.state().nrows();
+        assert_eq!(
+            initial_covariance.shape(),
+            (state_dimension, state_dimension)
+        );
+        if let Some(process_noise) = process_noise {
+            assert_eq!(process_noise.shape(), (state_dimension, state_dimension));
+        }
+        let add_process_noise = |covariance: &mut DMatrix<T>| {
+            if let Some(process_noise) = process_noise {
+                *covariance += process_noise;
+            }
+        };
+
+        let mut window = kickstarter.clone_owned();
+        let newest = window.ncols() - 1;
+        // The kickstarter is known, only the state uncertainty moves through the first step.
+        let jacobian = self.jacobian(window.columns(0, window.ncols()));
+        let mut covariance = jacobian.state() * initial_covariance * jacobian.state().transpose();
+        add_process_noise(&mut covariance);
+        self.reservoir
+            .synchronize_state(window.columns(0, window.ncols()));
+
+        let mut predictions = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            predict_steps,
+        );
+        let mut output_covariances = Vec::with_capacity(predict_steps);
+        let mut measured_state =
+            DVector::zeros(self.reservoir_state_measurement.output_dimension());
+        for step in 0..predict_steps {
+            self.reservoir_state_measurement
+                .measure_into(self.state(), measured_state.column_mut(0));
+            self.reservoir_state_projection
+                .project_into(&measured_state, predictions.column_mut(step));
+            for column in 1..window.ncols() {
+                window.swap_columns(column - 1, column);
+            }
+            window
+                .column_mut(newest)
+                .copy_from(&predictions.column(step));
+
+            let jacobian = self.jacobian(window.columns(0, window.ncols()));
+            output_covariances
+                .push(jacobian.readout() * &covariance * jacobian.readout().transpose());
+            let closed_loop = jacobian.closed_loop();
+            covariance = &closed_loop * covariance * closed_loop.transpose();
+            add_process_noise(&mut covariance);
+            self.reservoir
+                .synchronize_state(window.columns(0, window.ncols()));
+        }
+        (predictions, output_covariances)
+    }
+
+    pub fn checkpoint(&self) -> ReservoirCheckpoint {
+        ReservoirCheckpoint::new(
+            self.reservoir.input_projection().input_dimension(),
+            self.reservoir_state_projection.output_dimension(),
+            self.state(),
+            self.model_signature(),
+        )
+    }
+
+    // Continues from the checkpointed state. Fails if the checkpoint was taken from a model
+    // with other dimensions or weights, the state is left untouched then.
+    pub fn restore(&mut self, checkpoint: &ReservoirCheckpoint) -> Result<(), ReservoirError> {
+        checkpoint.check_model::<T>(
+            self.reservoir.input_projection().input_dimension(),
+            self.reservoir_state_projection.output_dimension(),
+            self.state().nrows(),
+            &self.model_signature(),
+        )?;
+        self.reservoir.reservoir_state = checkpoint.state();
+        Ok(())
+    }
+
+    // Readout of a fixed probe state and a few moments of the state after one step from the
+    // probe state with a fixed probe input window, identifies the trained components.
+    fn model_signature(&self) -> Vec<f64> {
+        let input_projection = self.reservoir.input_projection();
+        let probe = |index: usize| T::from_f64(0.5 * (index as f64 + 1.).sin()).unwrap();
+        let mut state = DVector::from_fn(self.state().nrows(), |row, _| probe(row));
+        let window = DMatrix::from_fn(
+            input_projection.input_dimension(),
+            input_projection.required_input_columns(),
+            |row, column| probe(1000 + column * input_projection.input_dimension() + row),
+        );
+
+        let mut measured_state =
+            DVector::zeros(self.reservoir_state_measurement.output_dimension());
+        self.reservoir_state_measurement
+            .measure_into(&state, measured_state.column_mut(0));
+        let mut readout = DVector::zeros(self.reservoir_state_projection.output_dimension());
+        self.reservoir_state_projection
+            .project_into(&measured_state, readout.column_mut(0));
+
+        let mut projected_input = DVector::zeros(input_projection.output_dimensions());
+        input_projection.project_many_into(
+            window.columns(0, window.ncols()),
+            projected_input.columns_mut(0, 1),
+        );
+        let mut buffer = DVector::zeros(state.nrows());
+        self.reservoir.time_evolution().time_evolution_with_buffer(
+            &mut state,
+            projected_input.column(0),
+            &mut buffer,
+        );
+        let weighted_sum = state
+            .iter()
+            .enumerate()
+            .fold(T::zero(), |sum, (row, value)| sum + probe(row) * *value);
+
+        readout
+            .iter()
+            .chain([state.sum(), weighted_sum].iter())
+            .map(|value| value.to_f64().unwrap())
+            .collect()
+    }
+}
+
+impl<T, I, E, M> ReservoirComputer<T, I, E, M, QuantileStateProjection<T>>
+where
+    T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+{
+    // Closed loop prediction driven by the feedback quantile, returns one matrix per quantile
+    // with the bands evaluated on the same reservoir states.
+    pub fn synchronize_and_predict_quantiles(
+        &mut self,
+        input: DMatrixSlice<T>,
+        predict_steps: usize,
+    ) -> Vec<DMatrix<T>> {
+        let input_columns = self.kickstarter_len();
+        assert_eq!(
+            input.ncols(),
+            input_columns,
+            "Only a single element as kickstarter is supported."
+        );
+        let output_dimension = self.reservoir_state_projection.output_dimension();
+        let mut bands = vec![
+            DMatrix::zeros(output_dimension, predict_steps);
+            self.reservoir_state_projection.quantiles().len()
+        ];
+
+        let mut window = input.clone_owned();
+        self.reservoir
+            .synchronize_state(window.columns(0, input_columns));
+        for step in 0..predict_steps {
+            let state_measurement = self
+                .reservoir_state_measurement
+                .measure(&self.reservoir.reservoir_state);
+            let quantiles = self
+                .reservoir_state_projection
+                .project_quantiles(state_measurement);
+            for (band, quantile) in bands.iter_mut().zip(quantiles.column_iter()) {
+                band.column_mut(step).copy_from(&quantile);
+            }
+            let prediction = self.reservoir_state_projection.project(state_measurement);
+
+            for column in 1..input_columns {
+                let previous = window.column(column).clone_owned();
+                window.column_mut(column - 1).copy_from(&previous);
+            }
+            window.column_mut(input_columns - 1).copy_from(prediction);
+            self.reservoir
+                .synchronize_state(window.columns(0, input_columns));
+        }
+        bands
+    }
+}
+
+impl<T, I, E, M> ReservoirComputer<T, I, E, M, LinearStateProjection<T>>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+{
+    // Teacher forced online adaptation of the readout: the reservoir is driven by `data` from
+    // its current state and after the first `sync_steps` columns every state updates `trainer`
+    // with the next data column as target. The readout is then replaced by the one of the
+    // trainer, which e.g. starts from the current `w_out`.
+    pub fn adapt_readout_online<R: OnlineReadoutTrainer<T>>(
+        &mut self,
+        data: DMatrixSlice<T>,
+        sync_steps: usize,
+        trainer: &mut R,
+    ) {
+        assert!(
+            data.ncols() > sync_steps + 1,
+            "The data has no column left to train on after {sync_steps} synchronization steps."
+        );
+        let states = self
+            .reservoir
+            .record_states(data.columns(0, data.ncols() - 1), sync_steps);
+        let measured_states = self
+            .reservoir_state_measurement
+            .measure_many(states.columns(0, states.ncols()));
+        assert_eq!(trainer.w_out().ncols(), measured_states.nrows());
+        for (step, measured_state) in measured_states.column_iter().enumerate() {
+            trainer.update(measured_state, data.column(sync_steps + 1 + step));
+        }
+        self.reservoir_state_projection = trainer.projection();
+    }
+}
+
+impl<T, I, E, M, P> ReservoirComputer<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T> + Clone,
+    E: ReservoirTimeEvolution<T> + Clone,
+    M: ReservoirStateMeasurement<T> + Clone,
+    P: ReservoirStateProjection<T> + Clone,
+{
+    // Branches off a copy with the current reservoir state. The weight matrices are shared,
+    // only the state and the working buffers are copied.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
 }
 
 impl<T, I, E, M, P> Clone for ReservoirComputer<T, I, E, M, P>
@@ -96,3 +652,161 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use nalgebra::{DMatrix, DVector};
+
+    use crate::{
+        activation_function::Tanh,
+        echo_state_network::{EchoStateNetworkBuilder, SparseDiscreteEchoStateNetwork},
+        input_projection::{DefaultInputProjection, ReservoirInputProjection},
+        output_projection::{LinearStateProjection, ReservoirStateProjection},
+        reservoir::{jacobian::central_differences, ReservoirCheckpoint},
+        state_measurement::{DefaultStateMeasurement, ReservoirStateMeasurement},
+        time_evolution::ReservoirTimeEvolution,
+        Reservoir, ReservoirComputer,
+    };
+
+    type TestModel = ReservoirComputer<
+        f64,
+        DefaultInputProjection<f64>,
+        SparseDiscreteEchoStateNetwork<f64, Tanh>,
+        DefaultStateMeasurement<f64>,
+        LinearStateProjection<f64>,
+    >;
+
+    fn seeded_model(seed: u64) -> TestModel {
+        let esn = EchoStateNetworkBuilder::<f64>::random_seeded(10, 3, seed)
+            .build_sparse_discrete_network(Tanh);
+        ReservoirComputer::new(
+            Reservoir::new(
+                DefaultInputProjection::new_with_matrix(DMatrix::from_element(10, 2, 0.3)),
+                esn,
+            ),
+            DefaultStateMeasurement::new(10),
+            LinearStateProjection::new_with_matrix(DMatrix::from_fn(2, 10, |i, j| {
+                ((i + j) as f64).sin() * 0.3
+            })),
+        )
+    }
+
+    #[test]
+    fn callback_stops_the_closed_loop() {
+        let mut model = seeded_model(4);
+        let kickstarter = DMatrix::from_vec(2, 1, vec![0.5, -0.3]);
+        let expected = model
+            .fork()
+            .synchronize_and_predict(kickstarter.columns(0, 1), 0, 30);
+
+        let mut states = Vec::new();
+        let predictions = model.synchronize_and_predict_with_callback(
+            kickstarter.columns(0, 1),
+            30,
+            |step, state, prediction| {
+                states.push(state.clone());
+                assert_eq!(*prediction, expected.column(step));
+                if step == 9 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert_eq!(predictions, expected.columns(0, 10));
+        assert_eq!(states.len(), 10);
+        assert_eq!(*model.state(), states[9]);
+    }
+
+    #[test]
+    fn restored_checkpoint_continues_the_prediction() {
+        let mut model = seeded_model(3);
+        let history = DMatrix::from_fn(2, 50, |i, j| ((i + 2 * j) as f64 * 0.1).sin());
+        model.reservoir.synchronize_state(history.columns(0, 49));
+        let checkpoint = ReservoirCheckpoint::from_bytes(&model.checkpoint().to_bytes()).unwrap();
+        let kickstarter = history.columns(49, 1);
+        let expected = model.fork().synchronize_and_predict(kickstarter, 0, 20);
+
+        let mut restarted = model.fork();
+        restarted
+            .reservoir
+            .synchronize_state(history.columns(0, 10));
+        restarted.restore(&checkpoint).unwrap();
+        assert_eq!(
+            restarted.synchronize_and_predict(kickstarter, 0, 20),
+            expected
+        );
+
+        let mut other = seeded_model(4);
+        let state = other.state().clone();
+        assert!(other.restore(&checkpoint).is_err());
+        assert_eq!(*other.state(), state);
+    }
+
+    #[test]
+    fn closed_loop_jacobian_matches_the_prediction_step() {
+        let mut model = seeded_model(3);
+        let history = DMatrix::from_fn(2, 30, |i, j| ((i + 2 * j) as f64 * 0.1).sin());
+        model.reservoir.synchronize_state(history.columns(0, 30));
+
+        // State -> prediction -> next state driven by the fed back prediction.
+        let step = |state: &DVector<f64>, next_state: &mut DVector<f64>| {
+            let measured = model
+                .reservoir_state_measurement
+                .measure_many(state.columns(0, 1));
+            let prediction = model
+                .reservoir_state_projection
+                .project_many(measured.columns(0, 1));
+            let projected = model
+                .reservoir
+                .input_projection()
+                .project_many(prediction.columns(0, 1));
+            next_state.copy_from(state);
+            model
+                .reservoir
+                .time_evolution()
+                .time_evolution(next_state, projected.column(0));
+        };
+        let expected = central_differences(model.state(), 10, step);
+
+        let measured = model
+            .reservoir_state_measurement
+            .measure_many(model.state().columns(0, 1));
+        let window = model
+            .reservoir_state_projection
+            .project_many(measured.columns(0, 1));
+        let jacobian = model.jacobian(window.columns(0, 1));
+        assert_eq!(jacobian.readout().shape(), (2, 10));
+        assert!((jacobian.closed_loop() - expected).amax() < 1e-8);
+    }
+
+    #[test]
+    fn rank_one_covariance_follows_a_perturbed_prediction() {
+        let mut model = seeded_model(3);
+        let history = DMatrix::from_fn(2, 30, |i, j| ((i + 2 * j) as f64 * 0.1).sin());
+        model.reservoir.synchronize_state(history.columns(0, 29));
+        let kickstarter = history.columns(29, 1);
+        let perturbation = DVector::from_fn(10, |i, _| 1e-6 * (i as f64 * 1.3).cos());
+
+        let mut perturbed = model.fork();
+        perturbed.reservoir.reservoir_state += &perturbation;
+        let perturbed_predictions = perturbed.synchronize_and_predict(kickstarter, 0, 15);
+        let expected = model.fork().synchronize_and_predict(kickstarter, 0, 15);
+        let (predictions, covariances) = model.synchronize_and_predict_with_covariance(
+            kickstarter,
+            15,
+            &(&perturbation * perturbation.transpose()),
+            None,
+        );
+
+        assert_eq!(predictions, expected);
+        assert_eq!(covariances.len(), 15);
+        for (step, covariance) in covariances.iter().enumerate() {
+            let deviation = perturbed_predictions.column(step) - expected.column(step);
+            let linearized = &deviation * deviation.transpose();
+            assert!((covariance - &linearized).amax() <= 1e-3 * linearized.amax());
+        }
+    }
+}
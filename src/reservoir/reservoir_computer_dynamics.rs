@@ -5,9 +5,10 @@ use crate::output_projection::ReservoirStateProjection;
 use crate::state_measurement::ReservoirStateMeasurement;
 use crate::time_evolution::ReservoirTimeEvolution;
 use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
+use rand::{rngs::StdRng, SeedableRng};
 
 use super::ReservoirDynamics;
-use crate::ReservoirValue;
+use crate::{random::standard_normal, ReservoirValue};
 
 #[derive(Debug)]
 pub struct ReservoirComputerDynamics<T, I, E, M, P>
@@ -18,9 +19,9 @@ where
     M: ReservoirStateMeasurement<T>,
     P: ReservoirStateProjection<T>,
 {
-    reservoir_dynamics: ReservoirDynamics<T, I, E>,
-    reservoir_state_measurement: M,
-    reservoir_state_projection: P,
+    pub(crate) reservoir_dynamics: ReservoirDynamics<T, I, E>,
+    pub(crate) reservoir_state_measurement: M,
+    pub(crate) reservoir_state_projection: P,
 }
 
 impl<T, I, E, M, P> ReservoirComputerDynamics<T, I, E, M, P>
@@ -31,6 +32,18 @@ where
     M: ReservoirStateMeasurement<T>,
     P: ReservoirStateProjection<T>,
 {
+    pub fn new(
+        reservoir_dynamics: ReservoirDynamics<T, I, E>,
+        measurement: M,
+        projection: P,
+    ) -> Self {
+        Self {
+            reservoir_dynamics,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: projection,
+        }
+    }
+
     pub fn into_parts(self) -> (I, E, M, P) {
         let (i, e) = self.reservoir_dynamics.into_parts();
         (
@@ -84,6 +97,191 @@ where
             result,
         );
     }
+
+    // Closed loop prediction of all batch members at once, one column of `states` per member.
+    // The time evolution gets all states as one dense right hand side, which is much faster
+    // than predicting the members one by one. Same results as `synchronize_and_predict` with
+    // the matching state and kickstarter.
+    pub fn predict_batch(
+        &self,
+        states: &mut DMatrix<T>,
+        kickstarters: &[DMatrixSlice<T>],
+        predict_steps: usize,
+    ) -> Vec<DMatrix<T>> {
+        self.predict_batch_with_perturbation(states, kickstarters, predict_steps, |_, _| {})
+    }
+
+    // Like `predict_batch`, `perturbation` gets the step and the predictions of all members,
+    // one column per member, and may change them before they are recorded and fed back.
+    pub fn predict_batch_with_perturbation<F>(
+        &self,
+        states: &mut DMatrix<T>,
+        kickstarters: &[DMatrixSlice<T>],
+        predict_steps: usize,
+        mut perturbation: F,
+    ) -> Vec<DMatrix<T>>
+    where
+        F: FnMut(usize, DMatrixSliceMut<T>),
+    {
+        assert_eq!(states.ncols(), kickstarters.len());
+        let input_projection = self.reservoir_dynamics.input_projection();
+        let input_columns = input_projection.required_input_columns();
+        let batch_size = kickstarters.len();
+
+        let mut windows: Vec<DMatrix<T>> = kickstarters
+            .iter()
+            .map(|kickstarter| {
+                assert_eq!(kickstarter.ncols(), input_columns);
+                kickstarter.clone_owned()
+            })
+            .collect();
+        let mut predictions = vec![
+            DMatrix::zeros(
+                self.reservoir_state_projection.output_dimension(),
+                predict_steps
+            );
+            batch_size
+        ];
+        let mut projected_inputs = DMatrix::zeros(input_projection.output_dimensions(), batch_size);
+        let mut measured_states = DMatrix::zeros(
+            self.reservoir_state_measurement.output_dimension(),
+            batch_size,
+        );
+        let mut outputs = DMatrix::zeros(
+            self.reservoir_state_projection.output_dimension(),
+            batch_size,
+        );
+
+        self.evolve_batch(states, &windows, &mut projected_inputs);
+        for step in 0..predict_steps {
+            self.reservoir_state_measurement.measure_many_into(
+                states.columns(0, batch_size),
+                measured_states.columns_mut(0, batch_size),
+            );
+            self.reservoir_state_projection.project_many_into(
+                measured_states.columns(0, batch_size),
+                outputs.columns_mut(0, batch_size),
+            );
+            perturbation(step, outputs.columns_mut(0, batch_size));
+
+            for (member, window) in windows.iter_mut().enumerate() {
+                predictions[member]
+                    .column_mut(step)
+                    .copy_from(&outputs.column(member));
+                for column in 1..input_columns {
+                    window.swap_columns(column - 1, column);
+                }
+                window
+                    .column_mut(input_columns - 1)
+                    .copy_from(&outputs.column(member));
+            }
+            self.evolve_batch(states, &windows, &mut projected_inputs);
+        }
+        predictions
+    }
+
+    // Monte Carlo scenarios from one state and kickstarter in a single batch: every member
+    // feeds back its prediction plus gaussian noise with the given standard deviation, so a
+    // step is one product of the coupling with all member states and one readout product.
+    pub fn predict_monte_carlo(
+        &self,
+        state: &DVector<T>,
+        kickstarter: DMatrixSlice<T>,
+        members: usize,
+        predict_steps: usize,
+        noise_standard_deviation: T,
+        seed: u64,
+    ) -> Vec<DMatrix<T>> {
+        assert!(noise_standard_deviation >= T::zero());
+        let mut states = DMatrix::from_fn(state.nrows(), members, |row, _| state[row]);
+        let kickstarters = vec![kickstarter; members];
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.predict_batch_with_perturbation(
+            &mut states,
+            &kickstarters,
+            predict_steps,
+            |_, mut outputs| {
+                for value in outputs.iter_mut() {
+                    *value +=
+                        noise_standard_deviation * T::from_f64(standard_normal(&mut rng)).unwrap();
+                }
+            },
+        )
+    }
+
+    // `predict_batch` over groups of at most `microbatch_size` members, bounds the memory of
+    // the dense state matrix for very large ensembles. The states are not changed.
+    pub fn predict_microbatched(
+        &self,
+        states: &[DVector<T>],
+        kickstarters: &[DMatrixSlice<T>],
+        predict_steps: usize,
+        microbatch_size: usize,
+    ) -> Vec<DMatrix<T>> {
+        assert_eq!(states.len(), kickstarters.len());
+        assert!(microbatch_size > 0);
+        states
+            .chunks(microbatch_size)
+            .zip(kickstarters.chunks(microbatch_size))
+            .flat_map(|(states, kickstarters)| {
+                let mut batch = DMatrix::from_columns(states);
+                self.predict_batch(&mut batch, kickstarters, predict_steps)
+            })
+            .collect()
+    }
+
+    fn evolve_batch(
+        &self,
+        states: &mut DMatrix<T>,
+        windows: &[DMatrix<T>],
+        projected_inputs: &mut DMatrix<T>,
+    ) {
+        let input_projection = self.reservoir_dynamics.input_projection();
+        let mut workspace = DMatrix::zeros(0, 0);
+        for (member, window) in windows.iter().enumerate() {
+            input_projection.project_many_into_with_workspace(
+                window.columns(0, window.ncols()),
+                projected_inputs.columns_mut(member, 1),
+                &mut workspace,
+            );
+        }
+        self.reservoir_dynamics
+            .time_evolution()
+            .time_evolution_many(states, projected_inputs.columns(0, windows.len()));
+    }
+}
+
+// Each rayon worker predicts with its own clone of the dynamics.
+#[cfg(feature = "parallel")]
+impl<T, I, E, M, P> ReservoirComputerDynamics<T, I, E, M, P>
+where
+    T: ReservoirValue + Send + Sync,
+    I: ReservoirInputProjection<T> + Clone + Send + Sync,
+    E: ReservoirTimeEvolution<T> + Clone + Send + Sync,
+    M: ReservoirStateMeasurement<T> + Clone + Send + Sync,
+    P: ReservoirStateProjection<T> + Clone + Send + Sync,
+{
+    pub fn predict_ensemble(
+        &self,
+        states: &[DVector<T>],
+        kickstarters: &[DMatrixSlice<T>],
+        predict_steps: usize,
+    ) -> Vec<DMatrix<T>> {
+        use rayon::prelude::*;
+
+        assert_eq!(states.len(), kickstarters.len());
+        states
+            .par_iter()
+            .zip(kickstarters.par_iter())
+            .map_init(
+                || self.clone(),
+                |dynamics, (state, kickstarter)| {
+                    let mut state = state.clone();
+                    dynamics.synchronize_and_predict(&mut state, *kickstarter, 0, predict_steps)
+                },
+            )
+            .collect()
+    }
 }
 
 impl<T, I, E, M, P> Clone for ReservoirComputerDynamics<T, I, E, M, P>
@@ -1,14 +1,20 @@
-use std::{cmp::Ordering, fmt::Debug, marker::PhantomData};
+use std::{fmt::Debug, marker::PhantomData, ops::ControlFlow};
 
 use crate::input_projection::ReservoirInputProjection;
 use crate::output_projection::ReservoirStateProjection;
+use crate::profile::{ProfileComponent, ProfileTimer};
 use crate::state_measurement::ReservoirStateMeasurement;
 use crate::time_evolution::ReservoirTimeEvolution;
 use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector};
 
+use crate::error::{check_dimension, ReservoirError};
 use crate::ReservoirValue;
 
-use super::Reservoir;
+use super::{FeedbackMap, Reservoir};
+
+const RECORD_STATES_BLOCK_COLUMNS: usize = 256;
+
+pub type StepCallback<'a, T> = dyn FnMut(usize, &DVector<T>, &DVector<T>) -> ControlFlow<()> + 'a;
 
 #[derive(Debug)]
 pub struct ReservoirDynamics<T, I, E>
@@ -51,6 +57,21 @@ where
         }
     }
 
+    pub fn try_new(
+        reservoir_input_projection: I,
+        reservoir_time_evolution: E,
+    ) -> Result<Self, ReservoirError> {
+        check_dimension(
+            "input projection output / time evolution input",
+            reservoir_time_evolution.input_dimension(),
+            reservoir_input_projection.output_dimensions(),
+        )?;
+        Ok(Self::new(
+            reservoir_input_projection,
+            reservoir_time_evolution,
+        ))
+    }
+
     pub fn into_parts(self) -> (I, E) {
         (
             self.reservoir_input_projection,
@@ -116,21 +137,45 @@ where
         let data_points = input.ncols();
         let required_input_columns = self.reservoir_input_projection.required_input_columns();
 
+        let first_window_start = (sync_steps + 1)
+            .checked_sub(required_input_columns)
+            .expect("At least required_input_columns - 1 synchronization steps are needed.");
+
         let synchronization_slice = input.columns(0, sync_steps);
+        // Window k ends at column sync_steps + k, the synchronization already consumed all
+        // windows ending before sync_steps.
         let train_slice = input.columns(
-            sync_steps - required_input_columns,
-            data_points - sync_steps,
+            first_window_start,
+            data_points - sync_steps + required_input_columns - 1,
         );
         self.synchronize_state(state, synchronization_slice);
 
-        for step in 0..(data_points - sync_steps - required_input_columns + 1) {
-            let current_train_slice = train_slice.columns(step, required_input_columns);
+        // The inputs of a whole block are projected at once, only the time evolution itself
+        // has to run step by step.
+        let total_steps = data_points - sync_steps;
+        let block_columns = RECORD_STATES_BLOCK_COLUMNS.min(total_steps);
+        let mut projected_inputs = DMatrix::zeros(
+            self.reservoir_input_projection.output_dimensions(),
+            block_columns,
+        );
 
-            let input_vector = self.reservoir_input_projection.project(current_train_slice);
-            self.reservoir_time_evolution
-                .time_evolution(state, input_vector.column(0));
+        let mut workspace = DMatrix::zeros(0, 0);
+        let mut block_start = 0;
+        while block_start < total_steps {
+            let columns = block_columns.min(total_steps - block_start);
+            self.reservoir_input_projection
+                .project_many_into_with_workspace(
+                    train_slice.columns(block_start, columns + required_input_columns - 1),
+                    projected_inputs.columns_mut(0, columns),
+                    &mut workspace,
+                );
 
-            result.columns_mut(step, 1).copy_from(state);
+            for column in 0..columns {
+                self.reservoir_time_evolution
+                    .time_evolution(state, projected_inputs.column(column));
+                result.columns_mut(block_start + column, 1).copy_from(state);
+            }
+            block_start += columns;
         }
     }
 
@@ -192,56 +237,245 @@ where
                 .columns_mut(0, input_columns - 1)
                 .copy_from(&input.columns(1, input_columns - 1));
 
+            let timer = ProfileTimer::start();
             let input = self.reservoir_input_projection.project(input);
+            timer.stop(ProfileComponent::InputProjection);
             self.reservoir_time_evolution
                 .time_evolution(state, input.column(0));
             for step in 0..usize::min(input_columns, predict_steps) {
+                let timer = ProfileTimer::start();
                 let state_measurement = measurement.measure(state);
+                timer.stop(ProfileComponent::Measurement);
+                let timer = ProfileTimer::start();
                 let prediction = projection.project(state_measurement);
+                timer.stop(ProfileComponent::Readout);
 
                 result.column_mut(step).copy_from(prediction);
                 overlapped_data
                     .column_mut(input_columns + step - 1)
                     .copy_from(prediction);
 
+                let timer = ProfileTimer::start();
                 let input = self
                     .reservoir_input_projection
                     .project(overlapped_data.columns(step, input_columns));
+                timer.stop(ProfileComponent::InputProjection);
                 self.reservoir_time_evolution
                     .time_evolution(state, input.column(0));
             }
 
             for step in input_columns..predict_steps {
+                let timer = ProfileTimer::start();
                 let state_measurement = measurement.measure(state);
+                timer.stop(ProfileComponent::Measurement);
+                let timer = ProfileTimer::start();
                 let prediction = projection.project(state_measurement);
+                timer.stop(ProfileComponent::Readout);
 
                 result.column_mut(step).copy_from(prediction);
 
+                let timer = ProfileTimer::start();
                 let input = self
                     .reservoir_input_projection
-                    .project(result.columns(step - input_columns, input_columns));
+                    .project(result.columns(step + 1 - input_columns, input_columns));
+                timer.stop(ProfileComponent::InputProjection);
                 self.reservoir_time_evolution
                     .time_evolution(state, input.column(0));
             }
         } else {
+            let timer = ProfileTimer::start();
             let input = self.reservoir_input_projection.project(input);
+            timer.stop(ProfileComponent::InputProjection);
             self.reservoir_time_evolution
                 .time_evolution(state, input.column(0));
             for step in 0..predict_steps {
+                let timer = ProfileTimer::start();
                 let state_measurement = measurement.measure(state);
+                timer.stop(ProfileComponent::Measurement);
+                let timer = ProfileTimer::start();
                 let prediction = projection.project(state_measurement);
+                timer.stop(ProfileComponent::Readout);
 
                 result.column_mut(step).copy_from(prediction);
 
+                let timer = ProfileTimer::start();
                 let input = self
                     .reservoir_input_projection
                     .project(result.columns(step, 1));
+                timer.stop(ProfileComponent::InputProjection);
                 self.reservoir_time_evolution
                     .time_evolution(state, input.column(0));
             }
         }
     }
 
+    // Closed loop prediction from a kickstarter where `feedback` turns each prediction into the
+    // next input column. Otherwise the same steps as `synchronize_and_predict_into`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn predict_with_feedback_into<
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    >(
+        &mut self,
+        state: &mut DVector<T>,
+        kickstarter: DMatrixSlice<T>,
+        measurement: &mut M,
+        projection: &mut P,
+        feedback: &FeedbackMap<T>,
+        result: DMatrixSliceMut<T>,
+    ) {
+        self.predict_closed_loop_into(
+            state,
+            kickstarter,
+            measurement,
+            projection,
+            feedback,
+            None,
+            None,
+            result,
+        );
+    }
+
+    // Closed loop prediction that calls `callback` with the step, the state and the prediction
+    // after every step, e.g. to log, to stream the predictions or to stop early. Returns the
+    // number of predictions made, fewer than result.ncols() if the callback broke off.
+    #[allow(clippy::too_many_arguments)]
+    pub fn predict_with_callback_into<
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    >(
+        &mut self,
+        state: &mut DVector<T>,
+        kickstarter: DMatrixSlice<T>,
+        measurement: &mut M,
+        projection: &mut P,
+        feedback: &FeedbackMap<T>,
+        callback: &mut StepCallback<'_, T>,
+        result: DMatrixSliceMut<T>,
+    ) -> usize {
+        self.predict_closed_loop_into(
+            state,
+            kickstarter,
+            measurement,
+            projection,
+            feedback,
+            None,
+            Some(callback),
+            result,
+        )
+    }
+
+    // Partial feedback: the input rows `covariate_rows` are read from `covariates`, column k
+    // holds the known values at the time of prediction k, and `feedback` fills the remaining
+    // input rows in order. Predicts covariates.ncols() steps.
+    #[allow(clippy::too_many_arguments)]
+    pub fn predict_with_covariates_into<
+        M: ReservoirStateMeasurement<T>,
+        P: ReservoirStateProjection<T>,
+    >(
+        &mut self,
+        state: &mut DVector<T>,
+        kickstarter: DMatrixSlice<T>,
+        measurement: &mut M,
+        projection: &mut P,
+        feedback: &FeedbackMap<T>,
+        covariate_rows: &[usize],
+        covariates: DMatrixSlice<T>,
+        result: DMatrixSliceMut<T>,
+    ) {
+        assert_eq!(covariates.nrows(), covariate_rows.len());
+        assert!(
+            covariates.ncols() >= result.ncols(),
+            "Known covariates for {} of {} steps.",
+            covariates.ncols(),
+            result.ncols()
+        );
+        self.predict_closed_loop_into(
+            state,
+            kickstarter,
+            measurement,
+            projection,
+            feedback,
+            Some((covariate_rows, covariates)),
+            None,
+            result,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn predict_closed_loop_into<M: ReservoirStateMeasurement<T>, P: ReservoirStateProjection<T>>(
+        &mut self,
+        state: &mut DVector<T>,
+        kickstarter: DMatrixSlice<T>,
+        measurement: &mut M,
+        projection: &mut P,
+        feedback: &FeedbackMap<T>,
+        covariates: Option<(&[usize], DMatrixSlice<T>)>,
+        mut callback: Option<&mut StepCallback<'_, T>>,
+        mut result: DMatrixSliceMut<T>,
+    ) -> usize {
+        let input_columns = self.reservoir_input_projection.required_input_columns();
+        let input_dimension = kickstarter.nrows();
+        assert_eq!(kickstarter.ncols(), input_columns);
+
+        let covariate_rows = covariates.map_or(&[][..], |(rows, _)| rows);
+        assert!(
+            covariate_rows.windows(2).all(|rows| rows[0] < rows[1])
+                && covariate_rows.iter().all(|row| *row < input_dimension),
+            "Covariate rows must be increasing input rows."
+        );
+        let fed_back_rows: Vec<usize> = (0..input_dimension)
+            .filter(|row| covariate_rows.binary_search(row).is_err())
+            .collect();
+        let mut fed_back = DVector::zeros(fed_back_rows.len());
+        assert_eq!(
+            feedback.input_dimension(projection.output_dimension()),
+            fed_back_rows.len(),
+            "The feedback map does not produce the fed back input rows."
+        );
+
+        let mut window = kickstarter.clone_owned();
+        let input = self
+            .reservoir_input_projection
+            .project(window.columns(0, input_columns));
+        self.reservoir_time_evolution
+            .time_evolution(state, input.column(0));
+        for step in 0..result.ncols() {
+            let state_measurement = measurement.measure(state);
+            let prediction = projection.project(state_measurement);
+            result.column_mut(step).copy_from(prediction);
+            if let Some(callback) = callback.as_mut() {
+                if callback(step, state, prediction).is_break() {
+                    return step + 1;
+                }
+            }
+
+            for column in 1..input_columns {
+                window.swap_columns(column - 1, column);
+            }
+            feedback.apply(prediction, fed_back.column_mut(0));
+            let mut newest = window.column_mut(input_columns - 1);
+            for (value, row) in fed_back.iter().zip(fed_back_rows.iter()) {
+                newest[*row] = *value;
+            }
+            if let Some((rows, covariates)) = covariates {
+                for (index, row) in rows.iter().enumerate() {
+                    newest[*row] = covariates[(index, step)];
+                }
+            }
+            let input = self
+                .reservoir_input_projection
+                .project(window.columns(0, input_columns));
+            self.reservoir_time_evolution
+                .time_evolution(state, input.column(0));
+        }
+        result.ncols()
+    }
+
+    // Open loop over every full input window, window k ends at input column
+    // required_input_columns - 1 + k, so embeddings and strides need no special treatment. The
+    // first `sync_steps` windows only synchronize, each later window gives the prediction of the
+    // column after it.
     pub fn predict_from_input_sequence<
         M: ReservoirStateMeasurement<T>,
         P: ReservoirStateProjection<T>,
@@ -253,7 +487,7 @@ where
         measurement: &mut M,
         projection: &mut P,
     ) -> DMatrix<T> {
-        let predict_steps = input.ncols() - self.input_projection().required_input_columns() + 1;
+        let predict_steps = self.input_windows(input).saturating_sub(sync_steps);
         let mut predictions = DMatrix::zeros(projection.output_dimension(), predict_steps);
         let slice = predictions.columns_mut(0, predict_steps);
         self.predict_from_input_sequence_into(
@@ -267,6 +501,7 @@ where
         predictions
     }
 
+    // Returns the number of predictions written to the first columns of `result`.
     pub fn predict_from_input_sequence_into<
         M: ReservoirStateMeasurement<T>,
         P: ReservoirStateProjection<T>,
@@ -278,40 +513,154 @@ where
         measurement: &mut M,
         projection: &mut P,
         mut result: DMatrixSliceMut<T>,
-    ) {
-        assert_eq!(
-            sync_steps, 0,
-            "Synchronization before prediction is not yet supported."
+    ) -> usize {
+        let input_columns = self.reservoir_input_projection.required_input_columns();
+        let windows = self.input_windows(input);
+        assert!(
+            sync_steps < windows,
+            "{} input columns leave no window to predict from after {sync_steps} synchronization steps.",
+            input.ncols()
+        );
+        let predict_steps = windows - sync_steps;
+        assert!(
+            result.ncols() >= predict_steps,
+            "The result has room for {} of {predict_steps} predictions.",
+            result.ncols()
         );
 
-        match input
-            .ncols()
-            .cmp(&self.input_projection().required_input_columns())
-        {
-            Ordering::Equal => {
-                let input_projection = self.reservoir_input_projection.project(input);
+        // Like `record_states_into` the inputs are projected blockwise.
+        let block_columns = RECORD_STATES_BLOCK_COLUMNS.min(windows);
+        let mut projected_inputs = DMatrix::zeros(
+            self.reservoir_input_projection.output_dimensions(),
+            block_columns,
+        );
+        let mut measured_state = DVector::zeros(measurement.output_dimension());
+        let mut workspace = DMatrix::zeros(0, 0);
+        let mut block_start = 0;
+        while block_start < windows {
+            let block = block_columns.min(windows - block_start);
+            self.reservoir_input_projection
+                .project_many_into_with_workspace(
+                    input.columns(block_start, block + input_columns - 1),
+                    projected_inputs.columns_mut(0, block),
+                    &mut workspace,
+                );
+            for offset in 0..block {
+                let window = block_start + offset;
                 self.reservoir_time_evolution
-                    .time_evolution(state, input_projection.column(0));
-                let state_measurement = measurement.measure(state);
-                projection.project_into(state_measurement, result.column_mut(0));
+                    .time_evolution(state, projected_inputs.column(offset));
+                if window >= sync_steps {
+                    measurement.measure_into(state, measured_state.column_mut(0));
+                    projection
+                        .project_into(&measured_state, result.column_mut(window - sync_steps));
+                }
             }
-            Ordering::Greater => {
-                let input_projection = self.input_projection().project_many(input);
+            block_start += block;
+        }
+        predict_steps
+    }
 
-                let mut reservoir_states = DMatrix::zeros(state.nrows(), input_projection.ncols());
-                for (index, input) in input_projection.column_iter().enumerate() {
-                    self.time_evolution().time_evolution(state, input);
-                    reservoir_states.column_mut(index).copy_from(state);
-                }
+    // Number of full input windows in `input`.
+    fn input_windows(&self, input: DMatrixSlice<T>) -> usize {
+        let input_columns = self.reservoir_input_projection.required_input_columns();
+        assert!(
+            input.ncols() >= input_columns,
+            "The input has {} columns, the input projection needs {input_columns}.",
+            input.ncols()
+        );
+        input.ncols() + 1 - input_columns
+    }
+}
 
-                let state_measurements =
-                    measurement.measure_many(reservoir_states.columns(0, reservoir_states.ncols()));
-                projection.project_many_into(
-                    state_measurements.columns(0, state_measurements.ncols()),
-                    result,
-                );
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::ReservoirDynamics;
+    use crate::{
+        activation_function::ActivationFunctionWrapper,
+        echo_state_network::EchoStateNetworkBuilder,
+        input_projection::{InputProjectionWithEmbedding, ReservoirInputProjection},
+        output_projection::LinearStateProjection,
+        state_measurement::DefaultStateMeasurement,
+        time_evolution::ReservoirTimeEvolution,
+    };
+
+    // Every input window is fed exactly once: the first recorded state follows the window
+    // ending at column sync_steps. Feeding the last synchronization window a second time would
+    // shift all recorded states by one step.
+    #[test]
+    fn recorded_states_follow_each_window_once() {
+        let network = EchoStateNetworkBuilder::<f64>::random(10, 3)
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+        let mut projection = InputProjectionWithEmbedding::new_random(2, 10, 3, 1);
+        let input = DMatrix::from_fn(2, 30, |i, j| ((i + 2 * j) as f64 * 0.3).sin());
+        let mut dynamics = ReservoirDynamics::new(projection.clone(), network.clone());
+        let states = dynamics.record_states(&mut DVector::zeros(10), input.columns(0, 30), 5);
+        assert_eq!(states.ncols(), 25);
+
+        let window = projection.required_input_columns();
+        let mut state = DVector::zeros(10);
+        for end in window - 1..30 {
+            let projected = projection
+                .project(input.columns(end + 1 - window, window))
+                .clone_owned();
+            network.time_evolution(&mut state, projected.column(0));
+            if end >= 5 {
+                assert!((states.column(end - 5) - &state).amax() < 1e-12, "{end}");
             }
-            _ => panic!(),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "synchronization steps")]
+    fn too_short_synchronization_is_rejected() {
+        let network = EchoStateNetworkBuilder::<f64>::random(10, 3)
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+        let projection = InputProjectionWithEmbedding::new_random(2, 10, 3, 1);
+        let input = DMatrix::zeros(2, 30);
+        ReservoirDynamics::new(projection, network).record_states(
+            &mut DVector::zeros(10),
+            input.columns(0, 30),
+            2,
+        );
+    }
+
+    // Once the kickstarter is consumed, every step is fed the window ending at the most recent
+    // prediction, exactly as in a manual closed loop.
+    #[test]
+    fn closed_loop_feeds_the_most_recent_predictions() {
+        let network = EchoStateNetworkBuilder::<f64>::random(10, 3)
+            .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+        let mut projection = InputProjectionWithEmbedding::new_random(2, 10, 2, 1);
+        let w_out = DMatrix::from_fn(2, 10, |i, j| ((i + 3 * j) as f64 * 0.7).cos());
+        let window = projection.required_input_columns();
+        let mut history = DMatrix::from_fn(2, window + 12, |i, j| ((i + j) as f64 * 0.4).sin());
+
+        let mut dynamics = ReservoirDynamics::new(projection.clone(), network.clone());
+        let mut predictions = DMatrix::zeros(2, 12);
+        dynamics.synchronize_and_predict_into(
+            &mut DVector::zeros(10),
+            history.columns(0, window),
+            0,
+            12,
+            &mut DefaultStateMeasurement::new(10),
+            &mut LinearStateProjection::new_with_matrix(w_out.clone()),
+            predictions.columns_mut(0, 12),
+        );
+
+        let mut state = DVector::zeros(10);
+        for step in 0..12 {
+            let projected = projection
+                .project(history.columns(step, window))
+                .clone_owned();
+            network.time_evolution(&mut state, projected.column(0));
+            let prediction = &w_out * &state;
+            assert!(
+                (predictions.column(step) - &prediction).amax() < 1e-12,
+                "{step}"
+            );
+            history.column_mut(window + step).copy_from(&prediction);
         }
     }
 }
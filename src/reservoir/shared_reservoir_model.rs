@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use nalgebra::{DMatrix, DMatrixSlice, DMatrixSliceMut, DVector, DVectorSlice};
+
+use crate::input_projection::ReservoirInputProjection;
+use crate::output_projection::ReservoirStateProjection;
+use crate::state_measurement::ReservoirStateMeasurement;
+use crate::time_evolution::ReservoirTimeEvolution;
+use crate::ReservoirValue;
+
+use super::{frozen_reservoir_computer::StepBuffers, ReservoirComputer};
+
+// Trained components behind an `Arc`, cheap to clone and to hand to other threads. Every
+// session keeps its own state and scratch buffers, which it passes to the components, and only
+// uses their `&self` methods, which leave the components unchanged. So any number of sessions
+// can synchronize and predict concurrently. Time features of the measurement stay at the time
+// index the reservoir computer had when it was shared.
+#[derive(Debug)]
+pub struct SharedReservoirModel<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    parts: Arc<SharedParts<T, I, E, M, P>>,
+}
+
+#[derive(Debug)]
+struct SharedParts<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    input_projection: I,
+    time_evolution: E,
+    measurement: M,
+    projection: P,
+    // Sessions start from the state the reservoir computer had when it was shared.
+    initial_state: DVector<T>,
+}
+
+#[derive(Debug)]
+pub struct ReservoirSession<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    model: SharedReservoirModel<T, I, E, M, P>,
+    buffers: StepBuffers<T>,
+}
+
+impl<T, I, E, M, P> Clone for SharedReservoirModel<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            parts: self.parts.clone(),
+        }
+    }
+}
+
+impl<T, I, E, M, P> SharedReservoirModel<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn new(reservoir_computer: ReservoirComputer<T, I, E, M, P>) -> Self {
+        let (initial_state, input_projection, time_evolution) =
+            reservoir_computer.reservoir.into_parts();
+        Self {
+            parts: Arc::new(SharedParts {
+                input_projection,
+                time_evolution,
+                measurement: reservoir_computer.reservoir_state_measurement,
+                projection: reservoir_computer.reservoir_state_projection,
+                initial_state,
+            }),
+        }
+    }
+
+    pub fn new_session(&self) -> ReservoirSession<T, I, E, M, P> {
+        let parts = &self.parts;
+        ReservoirSession {
+            buffers: StepBuffers::new(
+                parts.initial_state.clone(),
+                &parts.input_projection,
+                &parts.measurement,
+                &parts.projection,
+            ),
+            model: self.clone(),
+        }
+    }
+
+    pub fn input_projection(&self) -> &I {
+        &self.parts.input_projection
+    }
+
+    pub fn time_evolution(&self) -> &E {
+        &self.parts.time_evolution
+    }
+
+    pub fn state_measurement(&self) -> &M {
+        &self.parts.measurement
+    }
+
+    pub fn state_projection(&self) -> &P {
+        &self.parts.projection
+    }
+}
+
+impl<T, I, E, M, P> ReservoirSession<T, I, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn model(&self) -> &SharedReservoirModel<T, I, E, M, P> {
+        &self.model
+    }
+
+    pub fn state(&self) -> &DVector<T> {
+        &self.buffers.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut DVector<T> {
+        &mut self.buffers.state
+    }
+
+    pub fn prediction(&self) -> &DVector<T> {
+        &self.buffers.prediction
+    }
+
+    // Back to the initial state with an empty input window.
+    pub fn reset(&mut self) {
+        self.buffers.reset(&self.model.parts.initial_state);
+    }
+
+    // Drives the reservoir with every column of `input` in open loop. With an embedding the
+    // input window still holds the inputs of earlier calls, zeros after a reset.
+    pub fn synchronize(&mut self, input: DMatrixSlice<T>) {
+        for column in input.column_iter() {
+            self.buffers.push_input(column);
+            self.evolve();
+        }
+        self.readout();
+    }
+
+    pub fn step(&mut self, input: DVectorSlice<T>) -> &DVector<T> {
+        self.buffers.push_input(input);
+        self.evolve();
+        self.readout();
+        &self.buffers.prediction
+    }
+
+    // Same as `ReservoirComputer::synchronize_and_predict` with the given kickstarter.
+    pub fn predict(&mut self, kickstarter: DMatrixSlice<T>, predict_steps: usize) -> DMatrix<T> {
+        let mut predictions = DMatrix::zeros(self.buffers.prediction.nrows(), predict_steps);
+        self.predict_into(kickstarter, predictions.columns_mut(0, predict_steps));
+        predictions
+    }
+
+    pub fn predict_into(&mut self, kickstarter: DMatrixSlice<T>, mut result: DMatrixSliceMut<T>) {
+        self.buffers.kickstart(kickstarter);
+        self.evolve();
+        self.readout();
+        for step in 0..result.ncols() {
+            if step > 0 {
+                self.buffers.push_prediction();
+                self.evolve();
+                self.readout();
+            }
+            result.column_mut(step).copy_from(&self.buffers.prediction);
+        }
+    }
+
+    fn evolve(&mut self) {
+        let parts = &self.model.parts;
+        self.buffers
+            .evolve(&parts.input_projection, &parts.time_evolution);
+    }
+
+    fn readout(&mut self) {
+        let parts = &self.model.parts;
+        self.buffers.readout(&parts.measurement, &parts.projection);
+    }
+}
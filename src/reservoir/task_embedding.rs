@@ -0,0 +1,118 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+
+use crate::{
+    input_projection::{ReservoirInputProjection, TaskEmbeddingInputProjection},
+    output_projection::ReservoirStateProjection,
+    state_measurement::ReservoirStateMeasurement,
+    time_evolution::ReservoirTimeEvolution,
+    ReservoirComputer, ReservoirValue,
+};
+
+// Mean squared one step error before the adaptation and after every accepted step.
+#[derive(Clone, Debug)]
+pub struct TaskEmbeddingReport<T: ReservoirValue> {
+    losses: Vec<T>,
+    embedding: DVector<T>,
+}
+
+impl<T: ReservoirValue> TaskEmbeddingReport<T> {
+    pub fn losses(&self) -> &[T] {
+        &self.losses
+    }
+
+    pub fn embedding(&self) -> &DVector<T> {
+        &self.embedding
+    }
+}
+
+impl<T, I, E, M, P> ReservoirComputer<T, TaskEmbeddingInputProjection<T, I>, E, M, P>
+where
+    T: ReservoirValue,
+    I: ReservoirInputProjection<T>,
+    E: ReservoirTimeEvolution<T>,
+    M: ReservoirStateMeasurement<T>,
+    P: ReservoirStateProjection<T>,
+{
+    pub fn task_embedding(&self) -> &DVector<T> {
+        self.reservoir.input_projection().embedding()
+    }
+
+    pub fn set_task_embedding(&mut self, embedding: DVector<T>) {
+        self.reservoir
+            .reservoir_dynamics
+            .input_projection_mut()
+            .set_embedding(embedding);
+    }
+
+    // Fits only the task embedding to the one step predictions of `data`, reservoir and
+    // readout stay fixed. Levenberg-Marquardt on the residuals, the Jacobian by the embedding
+    // comes from forward differences, which costs one run over the data per embedding
+    // dimension and iteration. Keeps the best embedding.
+    pub fn adapt_task_embedding(
+        &mut self,
+        data: DMatrixSlice<T>,
+        sync_steps: usize,
+        iterations: usize,
+    ) -> TaskEmbeddingReport<T> {
+        let dimension = self.task_embedding().nrows();
+        let mut embedding = self.task_embedding().clone();
+        let mut residuals = self.embedding_residuals(&embedding, data, sync_steps);
+        let samples = T::from_usize(residuals.nrows()).unwrap();
+        let mut losses = vec![residuals.norm_squared() / samples];
+        let mut damping = T::from_f64(1e-3).unwrap();
+        let relative_step = num_traits::Float::sqrt(<T as num_traits::Float>::epsilon());
+
+        for _ in 0..iterations {
+            let mut jacobian = DMatrix::zeros(residuals.nrows(), dimension);
+            for coordinate in 0..dimension {
+                let step = relative_step
+                    * num_traits::Float::max(
+                        T::one(),
+                        num_traits::Float::abs(embedding[coordinate]),
+                    );
+                let mut shifted = embedding.clone();
+                shifted[coordinate] += step;
+                let shifted_residuals = self.embedding_residuals(&shifted, data, sync_steps);
+                jacobian
+                    .column_mut(coordinate)
+                    .copy_from(&((shifted_residuals - &residuals) / step));
+            }
+            let normal = jacobian.tr_mul(&jacobian);
+            let gradient = jacobian.tr_mul(&residuals);
+
+            let mut lhs = normal.clone();
+            for index in 0..dimension {
+                lhs[(index, index)] += damping * normal[(index, index)] + damping;
+            }
+            let Some(update) = lhs.lu().solve(&(-gradient)) else {
+                break;
+            };
+            let candidate = &embedding + update;
+            let candidate_residuals = self.embedding_residuals(&candidate, data, sync_steps);
+            let candidate_loss = candidate_residuals.norm_squared() / samples;
+            if candidate_loss < *losses.last().unwrap() {
+                embedding = candidate;
+                residuals = candidate_residuals;
+                losses.push(candidate_loss);
+                damping /= T::from_f64(3.).unwrap();
+            } else {
+                damping *= T::from_f64(4.).unwrap();
+            }
+        }
+
+        self.set_task_embedding(embedding.clone());
+        TaskEmbeddingReport { losses, embedding }
+    }
+
+    fn embedding_residuals(
+        &mut self,
+        embedding: &DVector<T>,
+        data: DMatrixSlice<T>,
+        sync_steps: usize,
+    ) -> DVector<T> {
+        self.set_task_embedding(embedding.clone());
+        let predictions = self.one_step_predictions(data, sync_steps);
+        let residuals = predictions - data.columns(sync_steps + 1, data.ncols() - sync_steps - 1);
+        DVector::from_column_slice(residuals.as_slice())
+    }
+}
@@ -1,21 +1,43 @@
-use nalgebra::{ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice};
-use num_traits::Float;
+use nalgebra::{ClosedAdd, ClosedMul, ComplexField, DMatrix, DMatrixSlice, DVector};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::{
-    input_projection::ReservoirInputProjection, output_projection::LinearStateProjection,
-    state_measurement::ReservoirStateMeasurement, time_evolution::ReservoirTimeEvolution,
-    Reservoir, ReservoirComputer, ReservoirValue,
+    hybrid::{HybridReservoirComputer, KnowledgeBasedModel, ResidualReservoirComputer},
+    input_projection::{ReservoirInputProjection, TaskEmbeddingInputProjection},
+    output_projection::{
+        KernelStateProjection, LinearStateProjection, QuantileStateProjection, StateKernel,
+    },
+    preprocessing::NoiseAugmentation,
+    state_measurement::{
+        ContextStateMeasurement, ReservoirStateMeasurement, StandardizedStateMeasurement,
+    },
+    time_evolution::ReservoirTimeEvolution,
+    Reservoir, ReservoirComputer, ReservoirError, ReservoirValue,
 };
 
+use super::TrainingReport;
+
+// Levenberg-Marquardt iterations per task embedding and round.
+const TASK_EMBEDDING_ITERATIONS: usize = 10;
+// Ridge parameter of the trainers without a beta argument.
+const DEFAULT_BETA: f64 = 1e-7;
+
+type LinearReservoirComputer<T, I, E, M> = ReservoirComputer<T, I, E, M, LinearStateProjection<T>>;
+type TaskEmbeddingReservoirComputer<T, I, E, M> =
+    LinearReservoirComputer<T, TaskEmbeddingInputProjection<T, I>, E, M>;
+
 pub struct ReservoirTraining<T>
 where
     T: ReservoirValue + ComplexField + ClosedAdd + ClosedMul,
 {
     data: Vec<DMatrix<T>>,
+    weights: Vec<T>,
     train_sync_steps: usize,
     train_steps: usize,
     prediction_sync_steps: usize,
     prediction_steps: usize,
+    dropout: Option<(T, u64)>,
+    target_rows: Option<Vec<usize>>,
 }
 
 impl<T> ReservoirTraining<T>
@@ -30,10 +52,103 @@ where
     ) -> Self {
         Self {
             data: vec![],
+            weights: vec![],
             train_sync_steps,
             train_steps,
             prediction_sync_steps,
             prediction_steps,
+            dropout: None,
+            target_rows: None,
+        }
+    }
+
+    // Zeroes each measured feature with probability `rate` before the readout is fitted, the
+    // remaining features are scaled by 1 / (1 - rate). The masks are drawn per training column
+    // from `seed`.
+    pub fn dropout(&mut self, rate: T, seed: u64) -> &mut Self {
+        assert!(rate >= T::zero() && rate < T::one());
+        self.dropout = Some((rate, seed));
+        self
+    }
+
+    // Fits the readout only for the input rows `rows`, in this order, e.g. one channel of many.
+    // In closed loop the remaining rows have to be supplied, see `covariate_rows`.
+    pub fn target_rows(&mut self, rows: &[usize]) -> &mut Self {
+        assert!(!rows.is_empty(), "At least one target row is needed.");
+        assert!(
+            rows.windows(2).all(|rows| rows[0] < rows[1]),
+            "Target rows must be increasing."
+        );
+        if let Some(data) = self.data.first() {
+            assert!(rows.iter().all(|row| *row < data.nrows()));
+        }
+        self.target_rows = Some(rows.to_vec());
+        self
+    }
+
+    // The input rows that are not predicted with `target_rows`, increasing. Passed to
+    // `ReservoirComputer::synchronize_and_predict_with_covariates` together with
+    // `FeedbackMap::Identity`, the predictions are fed back into the target rows and the
+    // covariate rows are taken from known data.
+    pub fn covariate_rows(&self) -> Vec<usize> {
+        assert!(!self.data.is_empty(), "No training data has been added.");
+        let input_dimension = self.data[0].nrows();
+        match &self.target_rows {
+            Some(rows) => (0..input_dimension)
+                .filter(|row| rows.binary_search(row).is_err())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    fn assert_all_rows_targeted(&self) {
+        assert!(
+            self.target_rows.is_none(),
+            "This training predicts all input rows, target rows are not supported."
+        );
+    }
+
+    // Scales the samples of trajectory k by the square root of its weight, which weights its
+    // squared residuals in the least squares fit.
+    fn apply_trajectory_weights(&self, features: &mut DMatrix<T>, targets: &mut DMatrix<T>) {
+        if self.weights.iter().all(|weight| *weight == T::one()) {
+            return;
+        }
+        let samples = features.ncols() / self.data.len();
+        for (trajectory, weight) in self.weights.iter().enumerate() {
+            let scale = num_traits::Float::sqrt(*weight);
+            features
+                .columns_mut(trajectory * samples, samples)
+                .scale_mut(scale);
+            targets
+                .columns_mut(trajectory * samples, samples)
+                .scale_mut(scale);
+        }
+    }
+
+    fn assert_unweighted(&self) {
+        assert!(
+            self.weights.iter().all(|weight| *weight == T::one()),
+            "This training does not support trajectory weights."
+        );
+    }
+
+    fn apply_dropout(&self, features: &mut DMatrix<T>) {
+        let (rate, seed) = match self.dropout {
+            Some(dropout) => dropout,
+            None => return,
+        };
+        let keep_probability = (T::one() - rate).to_f64().unwrap();
+        let scale = T::one() / (T::one() - rate);
+        let mut rng = StdRng::seed_from_u64(seed);
+        for mut column in features.column_iter_mut() {
+            for value in column.iter_mut() {
+                if rng.gen_bool(keep_probability) {
+                    *value *= scale;
+                } else {
+                    *value = T::zero();
+                }
+            }
         }
     }
 
@@ -45,44 +160,383 @@ where
                     + self.prediction_sync_steps
                     + self.prediction_steps
         );
+        if let Some(rows) = &self.target_rows {
+            assert!(rows.iter().all(|row| *row < data.nrows()));
+        }
         self.data.push(data);
+        self.weights.push(T::one());
+        self
+    }
+
+    // The samples of `data` count `weight` times in the regression, e.g. to emphasize the regime
+    // of interest among several trajectories.
+    pub fn add_weighted_data(&mut self, data: DMatrix<T>, weight: T) -> &mut Self {
+        self.try_add_weighted_data(data, weight)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    pub fn try_add_weighted_data(
+        &mut self,
+        data: DMatrix<T>,
+        weight: T,
+    ) -> Result<&mut Self, ReservoirError> {
+        if !(num_traits::Float::is_finite(weight) && weight > T::zero()) {
+            return Err(ReservoirError::InvalidData(format!(
+                "trajectory weight {weight} is not positive and finite"
+            )));
+        }
+        self.add_data(data);
+        *self.weights.last_mut().unwrap() = weight;
+        Ok(self)
+    }
+
+    pub fn trajectory_weights(&self) -> &[T] {
+        &self.weights
+    }
+
+    // Adds the trajectories of `augmentation` applied to `data`, each one is trained on like a
+    // separate trajectory.
+    pub fn add_augmented_data(
+        &mut self,
+        data: DMatrixSlice<T>,
+        augmentation: &NoiseAugmentation<T>,
+    ) -> &mut Self {
+        for trajectory in augmentation.augment(data) {
+            self.add_data(trajectory);
+        }
         self
     }
 
     pub fn train_via_ridge_regression<I, E, M>(
         &self,
         mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+    ) -> ReservoirComputer<T, I, E, M, LinearStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            T::from_f64(DEFAULT_BETA).unwrap(),
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
+
+    // Like `train_via_ridge_regression`, additionally returns the one step predictions of the
+    // fitted readout on the training segment.
+    pub fn train_via_ridge_regression_with_report<I, E, M>(
+        &self,
+        reservoir: Reservoir<T, I, E>,
         measurement: M,
+    ) -> (LinearReservoirComputer<T, I, E, M>, TrainingReport<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let initial_state = reservoir.reservoir_state.clone();
+        let mut reservoir_computer = self.train_via_ridge_regression(reservoir, measurement);
+
+        // Recording the training segments again from the initial state leaves the reservoir in
+        // the state of the training.
+        let reservoir = &mut reservoir_computer.reservoir;
+        reservoir.reservoir_state.copy_from(&initial_state);
+        let (features, targets) = self.measure_training_features(
+            reservoir,
+            &mut reservoir_computer.reservoir_state_measurement,
+        );
+        let report = TrainingReport::from_projection(
+            &reservoir_computer.reservoir_state_projection,
+            features.columns(0, features.ncols()),
+            targets.columns(0, targets.ncols()),
+        );
+        (reservoir_computer, report)
+    }
+
+    pub fn train_via_bayesian_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
     ) -> ReservoirComputer<T, I, E, M, LinearStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let linear_fit = LinearStateProjection::via_bayesian_ridge_regression(
+            beta,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
+
+    // Separate ridge parameters per group of target dimensions, e.g. positions and velocities.
+    pub fn train_via_grouped_ridge_regression<I, E, M>(
+        &self,
+        groups: &[(Vec<usize>, T)],
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+    ) -> ReservoirComputer<T, I, E, M, LinearStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let linear_fit = LinearStateProjection::via_grouped_ridge_regression(
+            groups,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
+
+    // Fits the feature statistics of the measurement on the recorded training states.
+    pub fn train_standardized_via_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        measurement: M,
+    ) -> ReservoirComputer<T, I, E, StandardizedStateMeasurement<T, M>, LinearStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let mut measurement = measurement;
+        let (mut features, mut targets) =
+            self.measure_training_features(&mut reservoir, &mut measurement);
+        let measurement = StandardizedStateMeasurement::fit_measured(
+            measurement,
+            features.columns(0, features.ncols()),
+        );
+        measurement.standardize_many(features.columns_mut(0, features.ncols()));
+        self.regularize_features(&mut features, &mut targets);
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
+
+    pub fn train_kernel_via_ridge_regression<I, E, M, K>(
+        &self,
+        kernel: K,
+        beta: T,
+        landmarks: usize,
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+    ) -> ReservoirComputer<T, I, E, M, KernelStateProjection<T, K>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        K: StateKernel<T>,
+    {
+        self.assert_unweighted();
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let kernel_fit = KernelStateProjection::via_nystroem_ridge_regression(
+            kernel,
+            beta,
+            landmarks,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: kernel_fit,
+        }
+    }
+
+    pub fn train_quantiles_via_iteratively_reweighted_least_squares<I, E, M>(
+        &self,
+        quantiles: &[T],
+        beta: T,
+        iterations: usize,
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+    ) -> ReservoirComputer<T, I, E, M, QuantileStateProjection<T>>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        self.assert_unweighted();
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let quantile_fit = QuantileStateProjection::via_iteratively_reweighted_least_squares(
+            quantiles,
+            beta,
+            iterations,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: quantile_fit,
+        }
+    }
+
+    // One readout for several behaviors: trajectory k of the added data is measured with
+    // context k appended, see `ContextStateMeasurement`. The prediction starts with the first
+    // context, `state_measurement_mut` selects or blends the others.
+    pub fn train_multifunction_via_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        measurement: ContextStateMeasurement<T, M>,
+    ) -> ReservoirComputer<T, I, E, ContextStateMeasurement<T, M>, LinearStateProjection<T>>
     where
         I: ReservoirInputProjection<T>,
         E: ReservoirTimeEvolution<T>,
         M: ReservoirStateMeasurement<T>,
     {
         assert_eq!(
+            measurement.contexts().len(),
             self.data.len(),
-            1,
-            "Multifunctional learning scenarios are not yet supported."
+            "Every trajectory needs its own context."
+        );
+        let mut measurement = measurement;
+        let (features, targets) = self.fit_features_with(
+            &mut reservoir,
+            &mut measurement,
+            |trajectory, _, measurement| {
+                measurement.select(trajectory);
+            },
+        );
+        measurement.select(0);
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features,
+            targets.columns(0, targets.ncols()),
         );
-        let data = &self.data[0];
 
-        let sync_train_steps = self.train_sync_steps + self.train_steps;
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
 
-        let sync_train_data = data.columns(0, sync_train_steps - 1);
-        let recorded_states = reservoir.record_states(sync_train_data, self.train_sync_steps);
-        let matching_data_states = data.columns(
-            self.train_sync_steps + 1,
-            sync_train_steps - self.train_sync_steps - 1,
+    // Trains one readout for all trajectories, each with its own task embedding, and learns the
+    // embeddings alongside. Every round fits the readout by ridge regression with the embeddings
+    // fixed, then the embeddings with the readout fixed (see
+    // `ReservoirComputer::adapt_task_embedding`). Returns the learned embeddings in the order of
+    // the trajectories, the reservoir computer is left with the first one.
+    pub fn train_task_embeddings_via_ridge_regression<I, E, M>(
+        &self,
+        beta: T,
+        reservoir: Reservoir<T, TaskEmbeddingInputProjection<T, I>, E>,
+        measurement: M,
+        initial_embeddings: Vec<DVector<T>>,
+        rounds: usize,
+    ) -> (TaskEmbeddingReservoirComputer<T, I, E, M>, Vec<DVector<T>>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert_eq!(
+            initial_embeddings.len(),
+            self.data.len(),
+            "Every trajectory needs its own task embedding."
         );
+        self.assert_all_rows_targeted();
+        assert!(rounds > 0);
+        let initial_state = reservoir.reservoir_state.clone();
+        let mut embeddings = initial_embeddings;
+        let mut reservoir_computer =
+            self.fit_task_embedding_readout(beta, reservoir, measurement, &embeddings);
+        let sync_train_steps = self.train_sync_steps + self.train_steps;
+        for _ in 1..rounds {
+            for (embedding, data) in embeddings.iter_mut().zip(self.data.iter()) {
+                reservoir_computer.set_task_embedding(embedding.clone());
+                let report = reservoir_computer.adapt_task_embedding(
+                    data.columns(0, sync_train_steps),
+                    self.train_sync_steps,
+                    TASK_EMBEDDING_ITERATIONS,
+                );
+                *embedding = report.embedding().clone();
+            }
+            let ReservoirComputer {
+                mut reservoir,
+                reservoir_state_measurement,
+                ..
+            } = reservoir_computer;
+            reservoir.reservoir_state.copy_from(&initial_state);
+            reservoir_computer = self.fit_task_embedding_readout(
+                beta,
+                reservoir,
+                reservoir_state_measurement,
+                &embeddings,
+            );
+        }
+        reservoir_computer.set_task_embedding(embeddings[0].clone());
+        reservoir_computer
+            .reservoir
+            .reservoir_state
+            .copy_from(&initial_state);
+        (reservoir_computer, embeddings)
+    }
 
-        let recorded_states =
-            measurement.measure_many(recorded_states.columns(0, recorded_states.ncols()));
+    fn fit_task_embedding_readout<I, E, M>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, TaskEmbeddingInputProjection<T, I>, E>,
+        mut measurement: M,
+        embeddings: &[DVector<T>],
+    ) -> TaskEmbeddingReservoirComputer<T, I, E, M>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let (features, targets) = self.fit_features_with(
+            &mut reservoir,
+            &mut measurement,
+            |trajectory, reservoir, _| {
+                reservoir
+                    .reservoir_dynamics
+                    .input_projection_mut()
+                    .set_embedding(embeddings[trajectory].clone());
+            },
+        );
         let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
-            Float::powi(T::one(), -7),
-            &recorded_states,
-            matching_data_states,
+            beta,
+            &features,
+            targets.columns(0, targets.ncols()),
         );
-
         ReservoirComputer {
             reservoir,
             reservoir_state_measurement: measurement,
@@ -90,60 +544,280 @@ where
         }
     }
 
+    // Features and targets of the readout fits: the measured training states with dropout and
+    // trajectory weights applied. Trainers only add their solver.
+    fn fit_features<I, E, M>(
+        &self,
+        reservoir: &mut Reservoir<T, I, E>,
+        measurement: &mut M,
+    ) -> (DMatrix<T>, DMatrix<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        self.fit_features_with(reservoir, measurement, |_, _, _| {})
+    }
+
+    // Like `fit_features`, `prepare` adjusts the reservoir and the measurement before each
+    // trajectory.
+    fn fit_features_with<I, E, M>(
+        &self,
+        reservoir: &mut Reservoir<T, I, E>,
+        measurement: &mut M,
+        prepare: impl FnMut(usize, &mut Reservoir<T, I, E>, &mut M),
+    ) -> (DMatrix<T>, DMatrix<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        let (mut features, mut targets) =
+            self.measure_training_features_with(reservoir, measurement, prepare);
+        self.regularize_features(&mut features, &mut targets);
+        (features, targets)
+    }
+
+    fn regularize_features(&self, features: &mut DMatrix<T>, targets: &mut DMatrix<T>) {
+        self.apply_dropout(features);
+        self.apply_trajectory_weights(features, targets);
+    }
+
+    fn measure_training_features<I, E, M>(
+        &self,
+        reservoir: &mut Reservoir<T, I, E>,
+        measurement: &mut M,
+    ) -> (DMatrix<T>, DMatrix<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        self.measure_training_features_with(reservoir, measurement, |_, _, _| {})
+    }
+
+    // Measured reservoir states of the training segments and the data columns they have to
+    // predict. Every trajectory starts from the initial reservoir state and with the time index at
+    // `train_sync_steps`, the features and targets of all trajectories are concatenated. The
+    // reservoir keeps the state of the last trajectory.
+    fn measure_training_features_with<I, E, M>(
+        &self,
+        reservoir: &mut Reservoir<T, I, E>,
+        measurement: &mut M,
+        mut prepare: impl FnMut(usize, &mut Reservoir<T, I, E>, &mut M),
+    ) -> (DMatrix<T>, DMatrix<T>)
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+    {
+        assert!(!self.data.is_empty(), "No training data has been added.");
+        let sync_train_steps = self.train_sync_steps + self.train_steps;
+        let samples = sync_train_steps - self.train_sync_steps - 1;
+        let initial_state = reservoir.reservoir_state.clone();
+
+        let mut recorded_states = DMatrix::zeros(initial_state.nrows(), samples);
+        let mut features =
+            DMatrix::zeros(measurement.output_dimension(), samples * self.data.len());
+        let target_rows = match &self.target_rows {
+            Some(rows) => rows.clone(),
+            None => (0..self.data[0].nrows()).collect(),
+        };
+        let mut matching_data_states = DMatrix::zeros(target_rows.len(), samples * self.data.len());
+        for (trajectory, data) in self.data.iter().enumerate() {
+            assert_eq!(data.nrows(), self.data[0].nrows());
+            prepare(trajectory, reservoir, measurement);
+            reservoir.reservoir_state.copy_from(&initial_state);
+            let sync_train_data = data.columns(0, sync_train_steps - 1);
+            reservoir.record_states_into(
+                sync_train_data,
+                self.train_sync_steps,
+                recorded_states.columns_mut(0, samples),
+            );
+            measurement.set_time_index(self.train_sync_steps);
+            measurement.measure_many_into(
+                recorded_states.columns(0, samples),
+                features.columns_mut(trajectory * samples, samples),
+            );
+            matching_data_states
+                .columns_mut(trajectory * samples, samples)
+                .copy_from(
+                    &data
+                        .columns(self.train_sync_steps + 1, samples)
+                        .select_rows(&target_rows),
+                );
+        }
+        (features, matching_data_states)
+    }
+
     pub fn train_via_tikhonov_regularization<I, E, M>(
         &self,
         tikhonov: &DMatrix<T>,
         mut reservoir: Reservoir<T, I, E>,
-        measurement: M,
+        mut measurement: M,
     ) -> ReservoirComputer<T, I, E, M, LinearStateProjection<T>>
     where
         I: ReservoirInputProjection<T>,
         E: ReservoirTimeEvolution<T>,
         M: ReservoirStateMeasurement<T>,
+    {
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let linear_fit = LinearStateProjection::via_tikhonov_regularization_nalgebra(
+            tikhonov,
+            &features,
+            targets.columns(0, targets.ncols()),
+        );
+
+        ReservoirComputer {
+            reservoir,
+            reservoir_state_measurement: measurement,
+            reservoir_state_projection: linear_fit,
+        }
+    }
+
+    pub fn train_hybrid_via_ridge_regression<I, E, M, K>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+        knowledge_based_model: K,
+        feed_model_into_reservoir: bool,
+    ) -> HybridReservoirComputer<T, I, E, M, LinearStateProjection<T>, K>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        K: KnowledgeBasedModel<T>,
     {
         assert_eq!(
             self.data.len(),
             1,
             "Multifunctional learning scenarios are not yet supported."
         );
+        self.assert_all_rows_targeted();
+        self.assert_unweighted();
+        assert_eq!(reservoir.input_projection().required_input_columns(), 1);
         let data = &self.data[0];
-
+        let system_dimension = data.nrows();
         let sync_train_steps = self.train_sync_steps + self.train_steps;
 
-        let sync_train_data = data.columns(0, sync_train_steps - 1);
-        let recorded_states = reservoir.record_states(sync_train_data, self.train_sync_steps);
-        let matching_data_states = data.columns(
-            self.train_sync_steps + 1,
-            sync_train_steps - self.train_sync_steps - 1,
+        let model_predictions = Self::knowledge_based_predictions(
+            &knowledge_based_model,
+            data.columns(0, sync_train_steps - 1),
         );
 
-        let recorded_states =
-            measurement.measure_many(recorded_states.columns(0, recorded_states.ncols()));
-        let linear_fit = LinearStateProjection::via_tikhonov_regularization_nalgebra(
-            tikhonov,
-            &recorded_states,
-            matching_data_states,
+        let reservoir_inputs = if feed_model_into_reservoir {
+            let mut reservoir_inputs = DMatrix::zeros(2 * system_dimension, sync_train_steps - 1);
+            reservoir_inputs
+                .rows_mut(0, system_dimension)
+                .copy_from(&data.columns(0, sync_train_steps - 1));
+            reservoir_inputs
+                .rows_mut(system_dimension, system_dimension)
+                .copy_from(&model_predictions);
+            reservoir_inputs
+        } else {
+            data.columns(0, sync_train_steps - 1).clone_owned()
+        };
+
+        reservoir.synchronize_state(reservoir_inputs.columns(0, self.train_sync_steps));
+        measurement.set_time_index(self.train_sync_steps);
+        let samples = self.train_steps - 1;
+        let measurement_dimension = measurement.output_dimension();
+        let mut features = DMatrix::zeros(measurement_dimension + system_dimension, samples);
+        for sample in 0..samples {
+            let column = self.train_sync_steps + sample;
+            reservoir.synchronize_state(reservoir_inputs.columns(column, 1));
+            let state_measurement = measurement.measure(reservoir.state());
+            features
+                .column_mut(sample)
+                .rows_mut(0, measurement_dimension)
+                .copy_from(state_measurement);
+            features
+                .column_mut(sample)
+                .rows_mut(measurement_dimension, system_dimension)
+                .copy_from(&model_predictions.column(column));
+        }
+        let mut targets = data
+            .columns(self.train_sync_steps + 1, samples)
+            .clone_owned();
+        self.regularize_features(&mut features, &mut targets);
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features,
+            targets.columns(0, samples),
         );
 
-        ReservoirComputer {
+        HybridReservoirComputer::new(
             reservoir,
-            reservoir_state_measurement: measurement,
-            reservoir_state_projection: linear_fit,
+            measurement,
+            linear_fit,
+            knowledge_based_model,
+            feed_model_into_reservoir,
+        )
+    }
+
+    pub fn train_residual_via_ridge_regression<I, E, M, K>(
+        &self,
+        beta: T,
+        mut reservoir: Reservoir<T, I, E>,
+        mut measurement: M,
+        baseline: K,
+    ) -> ResidualReservoirComputer<T, I, E, M, LinearStateProjection<T>, K>
+    where
+        I: ReservoirInputProjection<T>,
+        E: ReservoirTimeEvolution<T>,
+        M: ReservoirStateMeasurement<T>,
+        K: KnowledgeBasedModel<T>,
+    {
+        assert_eq!(
+            self.data.len(),
+            1,
+            "Multifunctional learning scenarios are not yet supported."
+        );
+        self.assert_all_rows_targeted();
+        self.assert_unweighted();
+        assert_eq!(reservoir.input_projection().required_input_columns(), 1);
+        let data = &self.data[0];
+        let sync_train_steps = self.train_sync_steps + self.train_steps;
+
+        let baseline_predictions =
+            Self::knowledge_based_predictions(&baseline, data.columns(0, sync_train_steps - 1));
+
+        let (features, targets) = self.fit_features(&mut reservoir, &mut measurement);
+        let samples = targets.ncols();
+        let residuals = targets - baseline_predictions.columns(self.train_sync_steps, samples);
+        let linear_fit = LinearStateProjection::via_ridge_regression_nalgebra(
+            beta,
+            &features,
+            residuals.columns(0, samples),
+        );
+
+        ResidualReservoirComputer::new(reservoir, measurement, linear_fit, baseline)
+    }
+
+    fn knowledge_based_predictions<K: KnowledgeBasedModel<T>>(
+        model: &K,
+        inputs: DMatrixSlice<T>,
+    ) -> DMatrix<T> {
+        let mut predictions = DMatrix::zeros(model.dimension(), inputs.ncols());
+        for (input, target) in inputs.column_iter().zip(predictions.column_iter_mut()) {
+            model.predict_into(input, target);
         }
+        predictions
     }
 
     pub fn get_prediction_kickstarter(
         &self,
         index: usize,
         required_elements: usize,
-    ) -> DMatrixSlice<T> {
+    ) -> DMatrixSlice<'_, T> {
         self.data[index].columns(
             self.train_sync_steps + self.train_steps - required_elements,
             required_elements,
         )
     }
 
-    pub fn get_true_future(&self, index: usize) -> DMatrixSlice<T> {
+    pub fn get_true_future(&self, index: usize) -> DMatrixSlice<'_, T> {
         let column_count = self.data[index].ncols();
         let start_offset = self.train_sync_steps + self.train_steps + self.prediction_sync_steps;
         let remaining = column_count - start_offset;
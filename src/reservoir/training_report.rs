@@ -0,0 +1,78 @@
+use nalgebra::{DMatrix, DMatrixSlice, DVector};
+
+use crate::output_projection::ReservoirStateProjection;
+use crate::ReservoirValue;
+
+// Open loop one step predictions on the training segment next to the matching targets, to
+// judge the in-sample fit without running the reservoir again.
+#[derive(Clone, Debug)]
+pub struct TrainingReport<T: ReservoirValue> {
+    predictions: DMatrix<T>,
+    targets: DMatrix<T>,
+}
+
+impl<T: ReservoirValue> TrainingReport<T> {
+    pub fn new(predictions: DMatrix<T>, targets: DMatrix<T>) -> Self {
+        assert_eq!(predictions.shape(), targets.shape());
+        Self {
+            predictions,
+            targets,
+        }
+    }
+
+    pub fn from_projection<P: ReservoirStateProjection<T>>(
+        projection: &P,
+        measured_states: DMatrixSlice<T>,
+        target_states: DMatrixSlice<T>,
+    ) -> Self {
+        Self::new(
+            projection.project_many(measured_states),
+            target_states.clone_owned(),
+        )
+    }
+
+    pub fn predictions(&self) -> &DMatrix<T> {
+        &self.predictions
+    }
+
+    pub fn targets(&self) -> &DMatrix<T> {
+        &self.targets
+    }
+
+    pub fn residuals(&self) -> DMatrix<T> {
+        &self.predictions - &self.targets
+    }
+
+    // Per output dimension.
+    pub fn root_mean_squared_error(&self) -> DVector<T> {
+        let residuals = self.residuals();
+        let samples = T::from_usize(residuals.ncols()).unwrap();
+        DVector::from_iterator(
+            residuals.nrows(),
+            residuals
+                .row_iter()
+                .map(|row| num_traits::Float::sqrt(row.norm_squared() / samples)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrainingReport;
+    use nalgebra::DMatrix;
+
+    #[test]
+    fn training_report_errors() {
+        let predictions = DMatrix::from_vec(2, 2, vec![1., 0., 3., 2.]);
+        let targets = DMatrix::from_vec(2, 2, vec![0., 0., 0., 2.]);
+        let report = TrainingReport::new(predictions, targets);
+        assert_eq!(
+            report.residuals(),
+            DMatrix::from_vec(2, 2, vec![1., 0., 3., 0.])
+        );
+        assert_eq!(
+            report.root_mean_squared_error().as_slice(),
+            &[5f64.sqrt(), 0.]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use nalgebra::{DMatrix, DMatrixSlice};
+
+use crate::ReservoirValue;
+
+// Storage of recorded (measured) states, one column per sample, as consumed by the regression
+// trainers of `LinearStateProjection`. The trainers only need X Xᵀ and X Yᵀ, which by default
+// are accumulated from consecutive column chunks, so the states never have to be in one dense
+// matrix. Backends that keep the states elsewhere (memory mapped, on a GPU) implement
+// `for_each_chunk` or override `gram` and `cross` with their own products.
+pub trait StateMatrix<T: ReservoirValue> {
+    // Dimension of a state.
+    fn nrows(&self) -> usize;
+
+    // Number of samples.
+    fn ncols(&self) -> usize;
+
+    // Visits all columns in order as consecutive chunks, `visit` gets the index of the first
+    // column of the chunk.
+    fn for_each_chunk(&self, visit: &mut dyn FnMut(usize, DMatrixSlice<T>));
+
+    // X Xᵀ
+    fn gram(&self) -> DMatrix<T> {
+        let mut gram = DMatrix::zeros(self.nrows(), self.nrows());
+        self.for_each_chunk(&mut |_, chunk| {
+            gram.gemm(T::one(), &chunk, &chunk.transpose(), T::one());
+        });
+        gram
+    }
+
+    // X Yᵀ, one column of `targets` per sample.
+    fn cross(&self, targets: DMatrixSlice<T>) -> DMatrix<T> {
+        assert_eq!(self.ncols(), targets.ncols());
+        let mut cross = DMatrix::zeros(self.nrows(), targets.nrows());
+        self.for_each_chunk(&mut |start, chunk| {
+            let targets = targets.columns(start, chunk.ncols());
+            cross.gemm(T::one(), &chunk, &targets.transpose(), T::one());
+        });
+        cross
+    }
+}
+
+impl<T: ReservoirValue> StateMatrix<T> for DMatrix<T> {
+    fn nrows(&self) -> usize {
+        self.nrows()
+    }
+
+    fn ncols(&self) -> usize {
+        self.ncols()
+    }
+
+    fn for_each_chunk(&self, visit: &mut dyn FnMut(usize, DMatrixSlice<T>)) {
+        visit(0, self.columns(0, self.ncols()));
+    }
+
+    fn gram(&self) -> DMatrix<T> {
+        self * self.transpose()
+    }
+
+    fn cross(&self, targets: DMatrixSlice<T>) -> DMatrix<T> {
+        self * targets.transpose()
+    }
+}
+
+// States recorded in separate blocks, e.g. one per training trajectory or per flush of a
+// streaming recorder, without concatenating them.
+#[derive(Clone, Debug)]
+pub struct ChunkedStateMatrix<T: ReservoirValue> {
+    nrows: usize,
+    chunks: Vec<DMatrix<T>>,
+}
+
+impl<T: ReservoirValue> ChunkedStateMatrix<T> {
+    pub fn new(nrows: usize) -> Self {
+        Self {
+            nrows,
+            chunks: vec![],
+        }
+    }
+
+    pub fn push(&mut self, chunk: DMatrix<T>) -> &mut Self {
+        assert_eq!(chunk.nrows(), self.nrows);
+        self.chunks.push(chunk);
+        self
+    }
+
+    pub fn chunks(&self) -> &[DMatrix<T>] {
+        &self.chunks
+    }
+}
+
+impl<T: ReservoirValue> StateMatrix<T> for ChunkedStateMatrix<T> {
+    fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    fn ncols(&self) -> usize {
+        self.chunks.iter().map(|chunk| chunk.ncols()).sum()
+    }
+
+    fn for_each_chunk(&self, visit: &mut dyn FnMut(usize, DMatrixSlice<T>)) {
+        let mut start = 0;
+        for chunk in &self.chunks {
+            visit(start, chunk.columns(0, chunk.ncols()));
+            start += chunk.ncols();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::DMatrix;
+
+    use super::{ChunkedStateMatrix, StateMatrix};
+    use crate::output_projection::LinearStateProjection;
+
+    #[test]
+    fn chunked_states_train_the_same_readout() {
+        let states =
+            DMatrix::<f64>::from_fn(4, 90, |i, j| ((i + 1) as f64 * j as f64 * 0.17).sin());
+        let targets = DMatrix::from_fn(2, 90, |i, j| ((i + 3) as f64 * j as f64 * 0.11).cos());
+        let mut chunked = ChunkedStateMatrix::new(4);
+        for (start, columns) in [(0, 25), (25, 40), (65, 25)] {
+            chunked.push(states.columns(start, columns).clone_owned());
+        }
+        assert_eq!(StateMatrix::ncols(&chunked), 90);
+        assert!((chunked.gram() - StateMatrix::gram(&states)).amax() < 1e-12);
+
+        let dense = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-4,
+            &states,
+            targets.columns(0, 90),
+        );
+        let from_chunks = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-4,
+            &chunked,
+            targets.columns(0, 90),
+        );
+        assert!((dense.w_out() - from_chunks.w_out()).amax() < 1e-10);
+
+        let groups = [(vec![0], 1e-4), (vec![1], 1e-2)];
+        let grouped = LinearStateProjection::via_grouped_ridge_regression(
+            &groups,
+            &chunked,
+            targets.columns(0, 90),
+        );
+        let expected = LinearStateProjection::via_grouped_ridge_regression(
+            &groups,
+            &states,
+            targets.columns(0, 90),
+        );
+        assert!((grouped.w_out() - expected.w_out()).amax() < 1e-10);
+
+        let f32_chunked = {
+            let mut f32_chunked = ChunkedStateMatrix::new(4);
+            for chunk in chunked.chunks() {
+                f32_chunked.push(chunk.map(|value| value as f32));
+            }
+            f32_chunked
+        };
+        let f32_targets = targets.map(|value| value as f32);
+        let f32_readout = LinearStateProjection::via_ridge_regression_nalgebra(
+            1e-4,
+            &f32_chunked,
+            f32_targets.columns(0, 90),
+        );
+        assert!((f32_readout.w_out().map(f64::from) - dense.w_out()).amax() < 1e-3);
+    }
+}
@@ -0,0 +1,160 @@
+use nalgebra::{
+    base::{DMatrix, DMatrixSlice, DVector},
+    DMatrixSliceMut, DVectorSliceMut,
+};
+
+use super::ReservoirStateMeasurement;
+use crate::ReservoirValue;
+
+// Appends a context vector to the wrapped measurement, which lets one readout learn several
+// behaviors (e.g. attractors), one context per behavior. In prediction the context selects a
+// learned behavior or blends between them.
+#[derive(Clone, Debug)]
+pub struct ContextStateMeasurement<T: ReservoirValue, M: ReservoirStateMeasurement<T>> {
+    measurement: M,
+    contexts: Vec<DVector<T>>,
+    context: DVector<T>,
+    result: DVector<T>,
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> ContextStateMeasurement<T, M> {
+    // Starts with the first context.
+    pub fn new(measurement: M, contexts: Vec<DVector<T>>) -> Self {
+        assert!(!contexts.is_empty(), "At least one context is needed.");
+        let context = contexts[0].clone();
+        assert!(contexts
+            .iter()
+            .all(|other| other.nrows() == context.nrows()));
+        Self {
+            result: DVector::zeros(measurement.output_dimension() + context.nrows()),
+            measurement,
+            contexts,
+            context,
+        }
+    }
+
+    // One-hot contexts, the usual choice for `count` behaviors.
+    pub fn one_hot(measurement: M, count: usize) -> Self {
+        let contexts = (0..count)
+            .map(|index| {
+                let mut context = DVector::zeros(count);
+                context[index] = T::one();
+                context
+            })
+            .collect();
+        Self::new(measurement, contexts)
+    }
+
+    pub fn measurement(&self) -> &M {
+        &self.measurement
+    }
+
+    pub fn contexts(&self) -> &[DVector<T>] {
+        &self.contexts
+    }
+
+    pub fn context(&self) -> &DVector<T> {
+        &self.context
+    }
+
+    pub fn set_context(&mut self, context: DVector<T>) {
+        assert_eq!(context.nrows(), self.context.nrows());
+        self.context = context;
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.context = self.contexts[index].clone();
+    }
+
+    // Weighted sum of the learned contexts, weights summing to one interpolate between them.
+    pub fn interpolate(&mut self, weights: &[T]) {
+        assert_eq!(weights.len(), self.contexts.len());
+        let mut context = DVector::zeros(self.context.nrows());
+        for (weight, learned) in weights.iter().zip(self.contexts.iter()) {
+            context.axpy(*weight, learned, T::one());
+        }
+        self.context = context;
+    }
+
+    fn impl_measure_many(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        assert_eq!(targets.nrows(), self.output_dimension());
+        assert_eq!(states.ncols(), targets.ncols());
+        let measured_dimension = self.measurement.output_dimension();
+        self.measurement
+            .measure_many_into(states, targets.rows_mut(0, measured_dimension));
+        for mut target in targets.column_iter_mut() {
+            target
+                .rows_mut(measured_dimension, self.context.nrows())
+                .copy_from(&self.context);
+        }
+    }
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> ReservoirStateMeasurement<T>
+    for ContextStateMeasurement<T, M>
+{
+    fn output_dimension(&self) -> usize {
+        self.measurement.output_dimension() + self.context.nrows()
+    }
+
+    fn measure(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let measured_dimension = self.measurement.output_dimension();
+        self.result
+            .rows_mut(0, measured_dimension)
+            .copy_from(self.measurement.measure(state));
+        self.result
+            .rows_mut(measured_dimension, self.context.nrows())
+            .copy_from(&self.context);
+        &self.result
+    }
+
+    fn measure_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        let measured_dimension = self.measurement.output_dimension();
+        self.measurement
+            .measure_into(state, target.rows_mut(0, measured_dimension));
+        target
+            .rows_mut(measured_dimension, self.context.nrows())
+            .copy_from(&self.context);
+    }
+
+    fn measure_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = DMatrix::zeros(self.output_dimension(), states.ncols());
+        self.impl_measure_many(states, targets.columns_mut(0, states.ncols()));
+        targets
+    }
+
+    fn measure_many_into(&self, states: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
+        self.impl_measure_many(states, targets);
+    }
+
+    fn set_time_index(&mut self, time_index: usize) {
+        self.measurement.set_time_index(time_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector};
+
+    use super::ContextStateMeasurement;
+    use crate::state_measurement::{DefaultStateMeasurement, ReservoirStateMeasurement};
+
+    #[test]
+    fn appends_selected_and_interpolated_contexts() {
+        let mut measurement = ContextStateMeasurement::one_hot(DefaultStateMeasurement::new(2), 2);
+        assert_eq!(measurement.output_dimension(), 4);
+        let state = DVector::from_vec(vec![3., 4.]);
+        assert_eq!(measurement.measure(&state).as_slice(), &[3., 4., 1., 0.]);
+
+        measurement.select(1);
+        let states = DMatrix::from_vec(2, 2, vec![1., 2., 5., 6.]);
+        let measured = measurement.measure_many(states.columns(0, 2));
+        assert_eq!(measured.column(1).as_slice(), &[5., 6., 0., 1.]);
+
+        measurement.interpolate(&[0.25, 0.75]);
+        assert_eq!(
+            measurement.measure(&state).as_slice(),
+            &[3., 4., 0.25, 0.75]
+        );
+    }
+}
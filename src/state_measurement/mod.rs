@@ -8,14 +8,23 @@ use nalgebra::{
 use crate::ReservoirValue;
 
 pub mod constant_extension_state_measurement;
+pub mod context_state_measurement;
 pub mod default_state_measurement;
 pub mod extended_lu_state_measurement;
 pub mod lu_state_measurement;
+pub mod standardized_state_measurement;
+pub mod time_feature_state_measurement;
 
 pub use constant_extension_state_measurement::ConstantExtensionStateMeasurement;
+pub use context_state_measurement::ContextStateMeasurement;
 pub use default_state_measurement::DefaultStateMeasurement;
 pub use extended_lu_state_measurement::ExtendedLuStateMeasurement;
 pub use lu_state_measurement::LuStateMeasurement;
+pub use standardized_state_measurement::StandardizedStateMeasurement;
+pub use time_feature_state_measurement::{
+    SeasonalTimeFeatures, StepCounterTimeFeature, TimeFeatureStateMeasurement, TimeFeatures,
+    TimeFeaturesWrapper,
+};
 
 pub trait ReservoirStateMeasurement<T: ReservoirValue>: Debug {
     fn output_dimension(&self) -> usize;
@@ -27,6 +36,10 @@ pub trait ReservoirStateMeasurement<T: ReservoirValue>: Debug {
     fn measure_many(&self, state: DMatrixSlice<T>) -> DMatrix<T>;
 
     fn measure_many_into(&self, states: DMatrixSlice<T>, targets: DMatrixSliceMut<T>);
+
+    // Sets the time index of the next measured state. Only measurements of features of the time
+    // use it, see `TimeFeatureStateMeasurement`.
+    fn set_time_index(&mut self, _time_index: usize) {}
 }
 
 impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> ReservoirStateMeasurement<T> for Box<M> {
@@ -49,6 +62,10 @@ impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> ReservoirStateMeasureme
     fn measure_many_into(&self, states: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
         (**self).measure_many_into(states, targets);
     }
+
+    fn set_time_index(&mut self, time_index: usize) {
+        (**self).set_time_index(time_index);
+    }
 }
 
 impl<T: ReservoirValue> ReservoirStateMeasurement<T> for Box<dyn ReservoirStateMeasurement<T>> {
@@ -71,6 +88,10 @@ impl<T: ReservoirValue> ReservoirStateMeasurement<T> for Box<dyn ReservoirStateM
     fn measure_many_into(&self, states: DMatrixSlice<T>, targets: DMatrixSliceMut<T>) {
         (**self).measure_many_into(states, targets);
     }
+
+    fn set_time_index(&mut self, time_index: usize) {
+        (**self).set_time_index(time_index);
+    }
 }
 
 /*
@@ -0,0 +1,164 @@
+use std::fmt::Debug;
+
+use nalgebra::{
+    base::{DMatrix, DMatrixSlice, DVector},
+    DMatrixSliceMut, DVectorSliceMut,
+};
+
+use super::ReservoirStateMeasurement;
+use crate::ReservoirValue;
+
+// Centers and scales every measured feature with statistics of the training states. Features
+// that are constant on the training states, e.g. a bias entry, are passed through unchanged.
+#[derive(Clone, Debug)]
+pub struct StandardizedStateMeasurement<T: ReservoirValue, M: ReservoirStateMeasurement<T>> {
+    measurement: M,
+    mean: DVector<T>,
+    scale: DVector<T>,
+    transformed_state: DVector<T>,
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> StandardizedStateMeasurement<T, M> {
+    pub fn fit(measurement: M, states: DMatrixSlice<T>) -> Self {
+        let measured_states = measurement.measure_many(states);
+        Self::fit_measured(
+            measurement,
+            measured_states.columns(0, measured_states.ncols()),
+        )
+    }
+
+    // Like `fit`, with the training states already measured by `measurement`.
+    pub fn fit_measured(measurement: M, measured_states: DMatrixSlice<T>) -> Self {
+        assert!(measured_states.ncols() > 0);
+        let samples = T::from_usize(measured_states.ncols()).unwrap();
+
+        let mut mean = DVector::zeros(measured_states.nrows());
+        let mut scale = DVector::zeros(measured_states.nrows());
+        for (row_index, row) in measured_states.row_iter().enumerate() {
+            let row_mean = row.sum() / samples;
+            let variance = row
+                .iter()
+                .map(|value| (*value - row_mean) * (*value - row_mean))
+                .fold(T::zero(), |acc, value| acc + value)
+                / samples;
+            let deviation = num_traits::Float::sqrt(variance);
+            if deviation > T::zero() {
+                mean[row_index] = row_mean;
+                scale[row_index] = T::one() / deviation;
+            } else {
+                scale[row_index] = T::one();
+            }
+        }
+        Self::new_with_statistics(measurement, mean, scale)
+    }
+
+    // `scale` is multiplied with the centered features.
+    pub fn new_with_statistics(measurement: M, mean: DVector<T>, scale: DVector<T>) -> Self {
+        assert_eq!(mean.nrows(), measurement.output_dimension());
+        assert_eq!(scale.nrows(), measurement.output_dimension());
+        Self {
+            transformed_state: DVector::zeros(measurement.output_dimension()),
+            measurement,
+            mean,
+            scale,
+        }
+    }
+
+    pub fn mean(&self) -> &DVector<T> {
+        &self.mean
+    }
+
+    pub fn scale(&self) -> &DVector<T> {
+        &self.scale
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.measurement
+    }
+
+    pub fn into_inner(self) -> M {
+        self.measurement
+    }
+
+    // Standardizes states already measured by the wrapped measurement.
+    pub fn standardize_many(&self, mut measured_states: DMatrixSliceMut<T>) {
+        for measured_state in measured_states.column_iter_mut() {
+            Self::standardize(&self.mean, &self.scale, measured_state);
+        }
+    }
+
+    fn standardize(mean: &DVector<T>, scale: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        for ((value, mean), scale) in target.iter_mut().zip(mean.iter()).zip(scale.iter()) {
+            *value = (*value - *mean) * *scale;
+        }
+    }
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>> ReservoirStateMeasurement<T>
+    for StandardizedStateMeasurement<T, M>
+{
+    fn output_dimension(&self) -> usize {
+        self.transformed_state.nrows()
+    }
+
+    fn measure(&mut self, state: &DVector<T>) -> &DVector<T> {
+        self.transformed_state
+            .copy_from(self.measurement.measure(state));
+        Self::standardize(
+            &self.mean,
+            &self.scale,
+            self.transformed_state.column_mut(0),
+        );
+        &self.transformed_state
+    }
+
+    fn measure_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        self.measurement
+            .measure_into(state, target.rows_mut(0, target.nrows()));
+        Self::standardize(&self.mean, &self.scale, target);
+    }
+
+    fn measure_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = DMatrix::zeros(self.output_dimension(), states.ncols());
+        self.measure_many_into(states, targets.columns_mut(0, states.ncols()));
+        targets
+    }
+
+    fn measure_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        self.measurement
+            .measure_many_into(states, targets.columns_mut(0, targets.ncols()));
+        self.standardize_many(targets);
+    }
+
+    fn set_time_index(&mut self, time_index: usize) {
+        self.measurement.set_time_index(time_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StandardizedStateMeasurement;
+    use crate::state_measurement::{ConstantExtensionStateMeasurement, ReservoirStateMeasurement};
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn test_standardized_measurement() {
+        let states = DMatrix::<f64>::from_vec(2, 4, vec![1., 10., 2., 20., 3., 30., 4., 40.]);
+        let mut measurement = StandardizedStateMeasurement::fit(
+            ConstantExtensionStateMeasurement::new(2),
+            states.columns(0, 4),
+        );
+        assert_eq!(measurement.mean().as_slice(), &[2.5, 25., 0.]);
+        assert_eq!(measurement.scale()[2], 1.);
+
+        let measured = measurement.measure_many(states.columns(0, 4));
+        for row in 0..2 {
+            assert!(measured.row(row).sum().abs() < 1e-12);
+            assert!((measured.row(row).norm_squared() / 4. - 1.).abs() < 1e-12);
+        }
+        assert_eq!(measured.row(2).iter().sum::<f64>(), 4.);
+
+        let state = DVector::from_vec(vec![2.5, 25.]);
+        assert_eq!(measurement.measure(&state).as_slice(), &[0., 0., 1.]);
+    }
+}
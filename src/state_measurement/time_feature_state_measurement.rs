@@ -0,0 +1,282 @@
+use std::{fmt::Debug, marker::PhantomData};
+
+use nalgebra::{
+    base::{DMatrix, DMatrixSlice, DVector},
+    DMatrixSliceMut, DVectorSliceMut,
+};
+
+use super::ReservoirStateMeasurement;
+use crate::ReservoirValue;
+
+pub trait TimeFeatures<T: ReservoirValue>: Debug {
+    fn dimension(&self) -> usize;
+
+    fn features_into(&self, time_index: usize, target: DVectorSliceMut<T>);
+}
+
+#[derive(Clone, Debug)]
+pub struct SeasonalTimeFeatures<T: ReservoirValue> {
+    periods: Vec<T>,
+}
+
+impl<T: ReservoirValue> SeasonalTimeFeatures<T> {
+    // Periods are given in time steps.
+    pub fn new(periods: Vec<T>) -> Self {
+        assert!(periods.iter().all(|p| *p > T::zero()));
+        Self { periods }
+    }
+}
+
+impl<T: ReservoirValue> TimeFeatures<T> for SeasonalTimeFeatures<T> {
+    fn dimension(&self) -> usize {
+        2 * self.periods.len()
+    }
+
+    fn features_into(&self, time_index: usize, mut target: DVectorSliceMut<T>) {
+        let time = T::from_usize(time_index).unwrap();
+        for (index, period) in self.periods.iter().enumerate() {
+            let phase = T::two_pi() * time / *period;
+            target[2 * index] = nalgebra::ComplexField::sin(phase);
+            target[2 * index + 1] = nalgebra::ComplexField::cos(phase);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct StepCounterTimeFeature<T: ReservoirValue> {
+    scale: T,
+}
+
+impl<T: ReservoirValue> StepCounterTimeFeature<T> {
+    pub fn new(scale: T) -> Self {
+        Self { scale }
+    }
+}
+
+impl<T: ReservoirValue> TimeFeatures<T> for StepCounterTimeFeature<T> {
+    fn dimension(&self) -> usize {
+        1
+    }
+
+    fn features_into(&self, time_index: usize, mut target: DVectorSliceMut<T>) {
+        target[0] = self.scale * T::from_usize(time_index).unwrap();
+    }
+}
+
+pub struct TimeFeaturesWrapper<T: ReservoirValue, F: Fn(usize, DVectorSliceMut<T>)> {
+    dimension: usize,
+    func: F,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: ReservoirValue, F: Fn(usize, DVectorSliceMut<T>)> TimeFeaturesWrapper<T, F> {
+    pub fn new(dimension: usize, f: F) -> Self {
+        Self {
+            dimension,
+            func: f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(usize, DVectorSliceMut<T>)> Debug for TimeFeaturesWrapper<T, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TimeFeaturesWrapper{{ {} }}", self.dimension)
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(usize, DVectorSliceMut<T>)> TimeFeatures<T>
+    for TimeFeaturesWrapper<T, F>
+{
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn features_into(&self, time_index: usize, target: DVectorSliceMut<T>) {
+        (self.func)(time_index, target);
+    }
+}
+
+impl<T: ReservoirValue, F: Fn(usize, DVectorSliceMut<T>) + Clone> Clone
+    for TimeFeaturesWrapper<T, F>
+{
+    fn clone(&self) -> Self {
+        Self {
+            dimension: self.dimension,
+            func: self.func.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// Appends features of the time index to the wrapped measurement. `measure` advances the time
+// index by one, `measure_into` uses the current one and the batch methods assign consecutive
+// time indices starting at the current one without advancing it. The trainers start every
+// training trajectory at `train_sync_steps`, set the time index of the first predicted step
+// with `set_time_index` before predicting.
+#[derive(Clone, Debug)]
+pub struct TimeFeatureStateMeasurement<
+    T: ReservoirValue,
+    M: ReservoirStateMeasurement<T>,
+    F: TimeFeatures<T>,
+> {
+    measurement: M,
+    features: F,
+    time_index: usize,
+    transformed_state: DVector<T>,
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>, F: TimeFeatures<T>>
+    TimeFeatureStateMeasurement<T, M, F>
+{
+    pub fn new(measurement: M, features: F) -> Self {
+        let output_dimension = measurement.output_dimension() + features.dimension();
+        Self {
+            measurement,
+            features,
+            time_index: 0,
+            transformed_state: DVector::zeros(output_dimension),
+        }
+    }
+
+    pub fn time_index(&self) -> usize {
+        self.time_index
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.measurement
+    }
+
+    pub fn time_features(&self) -> &F {
+        &self.features
+    }
+
+    pub fn into_inner(self) -> M {
+        self.measurement
+    }
+}
+
+impl<T: ReservoirValue, M: ReservoirStateMeasurement<T>, F: TimeFeatures<T>>
+    ReservoirStateMeasurement<T> for TimeFeatureStateMeasurement<T, M, F>
+{
+    fn output_dimension(&self) -> usize {
+        self.transformed_state.nrows()
+    }
+
+    fn set_time_index(&mut self, time_index: usize) {
+        self.time_index = time_index;
+    }
+
+    fn measure(&mut self, state: &DVector<T>) -> &DVector<T> {
+        let inner_dimension = self.measurement.output_dimension();
+        self.measurement
+            .measure_into(state, self.transformed_state.rows_mut(0, inner_dimension));
+        self.features.features_into(
+            self.time_index,
+            self.transformed_state
+                .rows_mut(inner_dimension, self.features.dimension()),
+        );
+        self.time_index += 1;
+        &self.transformed_state
+    }
+
+    fn measure_into(&self, state: &DVector<T>, mut target: DVectorSliceMut<T>) {
+        let inner_dimension = self.measurement.output_dimension();
+        self.measurement
+            .measure_into(state, target.rows_mut(0, inner_dimension));
+        self.features.features_into(
+            self.time_index,
+            target.rows_mut(inner_dimension, self.features.dimension()),
+        );
+    }
+
+    fn measure_many(&self, states: DMatrixSlice<T>) -> DMatrix<T> {
+        let mut targets = DMatrix::zeros(self.output_dimension(), states.ncols());
+        self.measure_many_into(states, targets.columns_mut(0, states.ncols()));
+        targets
+    }
+
+    fn measure_many_into(&self, states: DMatrixSlice<T>, mut targets: DMatrixSliceMut<T>) {
+        assert_eq!(targets.nrows(), self.output_dimension());
+        assert_eq!(targets.ncols(), states.ncols());
+        let inner_dimension = self.measurement.output_dimension();
+        self.measurement
+            .measure_many_into(states, targets.rows_mut(0, inner_dimension));
+        for (offset, mut target_column) in targets.column_iter_mut().enumerate() {
+            self.features.features_into(
+                self.time_index + offset,
+                target_column.rows_mut(inner_dimension, self.features.dimension()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SeasonalTimeFeatures, StepCounterTimeFeature, TimeFeatureStateMeasurement};
+    use crate::state_measurement::{DefaultStateMeasurement, ReservoirStateMeasurement};
+    use nalgebra::{DMatrix, DVector};
+
+    #[test]
+    fn test_step_counter_features() {
+        let mut measurement = TimeFeatureStateMeasurement::new(
+            DefaultStateMeasurement::new(2),
+            StepCounterTimeFeature::new(0.5),
+        );
+        measurement.set_time_index(2);
+
+        let state = DVector::from_vec(vec![1., 2.]);
+        assert_eq!(measurement.measure(&state).as_slice(), &[1., 2., 1.]);
+        assert_eq!(measurement.measure(&state).as_slice(), &[1., 2., 1.5]);
+        assert_eq!(measurement.time_index(), 4);
+
+        let states = DMatrix::from_vec(2, 2, vec![1., 2., 3., 4.]);
+        let measure_results = measurement.measure_many(states.columns(0, 2));
+        assert_eq!(measure_results.column(0).as_slice(), &[1., 2., 2.]);
+        assert_eq!(measure_results.column(1).as_slice(), &[3., 4., 2.5]);
+    }
+
+    #[test]
+    fn test_seasonal_features() {
+        let mut measurement = TimeFeatureStateMeasurement::new(
+            DefaultStateMeasurement::new(1),
+            SeasonalTimeFeatures::new(vec![4.0_f64]),
+        );
+        measurement.set_time_index(1);
+
+        let state = DVector::from_vec(vec![3.]);
+        let measure_result = measurement.measure(&state);
+        assert_eq!(measure_result.nrows(), 3);
+        assert_eq!(measure_result[0], 3.);
+        assert!((measure_result[1] - 1.).abs() < 1e-12);
+        assert!(measure_result[2].abs() < 1e-12);
+    }
+
+    #[test]
+    fn only_measure_advances_the_time_index() {
+        let mut measurement = TimeFeatureStateMeasurement::new(
+            DefaultStateMeasurement::new(2),
+            SeasonalTimeFeatures::new(vec![5.0_f64]),
+        );
+        measurement.set_time_index(3);
+        let states = DMatrix::from_fn(2, 4, |i, j| (i + 2 * j) as f64);
+
+        let batch = measurement.measure_many(states.columns(0, 4));
+        let mut batch_into = DMatrix::zeros(4, 4);
+        measurement.measure_many_into(states.columns(0, 4), batch_into.columns_mut(0, 4));
+        let mut first_into = DVector::zeros(4);
+        measurement.measure_into(&states.column(0).clone_owned(), first_into.column_mut(0));
+        assert_eq!(measurement.time_index(), 3);
+
+        let mut singles = DMatrix::zeros(4, 4);
+        for (column, state) in states.column_iter().enumerate() {
+            singles
+                .column_mut(column)
+                .copy_from(measurement.measure(&state.clone_owned()));
+        }
+        assert_eq!(measurement.time_index(), 7);
+        assert_eq!(batch, singles);
+        assert_eq!(batch_into, singles);
+        assert_eq!(first_into, singles.column(0));
+    }
+}
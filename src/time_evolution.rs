@@ -1,5 +1,6 @@
+use crate::reservoir::jacobian::central_differences;
 use crate::ReservoirValue;
-use nalgebra::{DVector, DVectorSlice};
+use nalgebra::{DMatrix, DMatrixSlice, DVector, DVectorSlice};
 use std::fmt::Debug;
 
 pub trait ReservoirTimeEvolution<T: ReservoirValue>: Debug {
@@ -8,6 +9,44 @@ pub trait ReservoirTimeEvolution<T: ReservoirValue>: Debug {
     fn output_dimension(&self) -> usize;
 
     fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>);
+
+    // Same step with `buffer`, of the state dimension, as scratch space for callers that must
+    // not allocate. Evolutions that allocate internally should override it.
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        _buffer: &mut DVector<T>,
+    ) {
+        self.time_evolution(state, input);
+    }
+
+    // One step for every column of `states` with the matching column of `inputs`, evolutions
+    // that can batch their matrix products should override it.
+    fn time_evolution_many(&self, states: &mut DMatrix<T>, inputs: DMatrixSlice<T>) {
+        assert_eq!(states.ncols(), inputs.ncols());
+        let mut state = DVector::zeros(states.nrows());
+        for (mut column, input) in states.column_iter_mut().zip(inputs.column_iter()) {
+            state.copy_from(&column);
+            self.time_evolution(&mut state, input);
+            column.copy_from(&state);
+        }
+    }
+
+    // Derivatives of one step by the state and by the input, at `state` driven by `input`. The
+    // default takes central differences, evolutions with a closed form should override it.
+    fn jacobians(&self, state: &DVector<T>, input: DVectorSlice<T>) -> (DMatrix<T>, DMatrix<T>) {
+        finite_difference_jacobians(self, state, input)
+    }
+}
+
+// Evolutions whose `time_evolution_with_buffer` does not allocate, required by
+// `FrozenReservoirComputer`.
+pub trait AllocationFreeTimeEvolution<T: ReservoirValue>: ReservoirTimeEvolution<T> {}
+
+impl<T: ReservoirValue, E: AllocationFreeTimeEvolution<T>> AllocationFreeTimeEvolution<T>
+    for Box<E>
+{
 }
 
 impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> ReservoirTimeEvolution<T> for Box<E> {
@@ -22,4 +61,44 @@ impl<T: ReservoirValue, E: ReservoirTimeEvolution<T>> ReservoirTimeEvolution<T>
     fn time_evolution(&self, state: &mut DVector<T>, input: DVectorSlice<T>) {
         (**self).time_evolution(state, input);
     }
+
+    fn time_evolution_with_buffer(
+        &self,
+        state: &mut DVector<T>,
+        input: DVectorSlice<T>,
+        buffer: &mut DVector<T>,
+    ) {
+        (**self).time_evolution_with_buffer(state, input, buffer);
+    }
+
+    fn time_evolution_many(&self, states: &mut DMatrix<T>, inputs: DMatrixSlice<T>) {
+        (**self).time_evolution_many(states, inputs);
+    }
+
+    fn jacobians(&self, state: &DVector<T>, input: DVectorSlice<T>) -> (DMatrix<T>, DMatrix<T>) {
+        (**self).jacobians(state, input)
+    }
+}
+
+pub(crate) fn finite_difference_jacobians<T, E>(
+    evolution: &E,
+    state: &DVector<T>,
+    input: DVectorSlice<T>,
+) -> (DMatrix<T>, DMatrix<T>)
+where
+    T: ReservoirValue,
+    E: ReservoirTimeEvolution<T> + ?Sized,
+{
+    let input = input.clone_owned();
+    let state_jacobian =
+        central_differences(state, evolution.output_dimension(), |point, next_state| {
+            next_state.copy_from(point);
+            evolution.time_evolution(next_state, input.column(0));
+        });
+    let input_jacobian =
+        central_differences(&input, evolution.output_dimension(), |point, next_state| {
+            next_state.copy_from(state);
+            evolution.time_evolution(next_state, point.column(0));
+        });
+    (state_jacobian, input_jacobian)
 }
@@ -0,0 +1,397 @@
+use std::cmp::Ordering;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    hyperparameter::{LeakRate, Regularization, SpectralRadius},
+    ReservoirError, ReservoirValue,
+};
+
+// Evaluates every candidate configuration with a user supplied error function
+// `evaluate(candidate, horizon)`, e.g. the prediction error of a freshly trained reservoir
+// computer over `horizon` steps of validation data. Early stopping stages evaluate on short
+// horizons first and drop diverging candidates before the expensive full evaluation.
+#[derive(Clone, Debug)]
+pub struct HyperparameterSweep<C> {
+    candidates: Vec<C>,
+    stages: Vec<EarlyStoppingStage>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EarlyStoppingStage {
+    pub horizon: usize,
+    // Candidates with a larger error are dropped.
+    pub max_error: f64,
+    // Fraction of the surviving candidates with the smallest errors that is kept, successive
+    // halving uses 0.5.
+    pub keep_fraction: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CandidateOutcome {
+    // Dropped in the given early stopping stage with the error of that stage.
+    Stopped { stage: usize, error: f64 },
+    Completed { error: f64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectiveDirection {
+    Minimize,
+    Maximize,
+}
+
+// A candidate that no other candidate beats in every objective at once.
+#[derive(Clone, Debug)]
+pub struct ParetoCandidate<'a, C> {
+    pub candidate: &'a C,
+    pub index: usize,
+    pub scores: Vec<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SweepResult<'a, C> {
+    candidates: &'a [C],
+    outcomes: Vec<CandidateOutcome>,
+}
+
+impl<C> HyperparameterSweep<C> {
+    pub fn new(candidates: Vec<C>) -> Self {
+        Self {
+            candidates,
+            stages: vec![],
+        }
+    }
+
+    pub fn candidates(&self) -> &[C] {
+        &self.candidates
+    }
+
+    pub fn early_stopping(&mut self, horizon: usize, max_error: f64) -> &mut Self {
+        self.early_stopping_stage(EarlyStoppingStage {
+            horizon,
+            max_error,
+            keep_fraction: 1.,
+        })
+    }
+
+    pub fn early_stopping_stage(&mut self, stage: EarlyStoppingStage) -> &mut Self {
+        assert!(stage.keep_fraction > 0. && stage.keep_fraction <= 1.);
+        if let Some(previous) = self.stages.last() {
+            assert!(
+                stage.horizon > previous.horizon,
+                "Early stopping horizons have to increase."
+            );
+        }
+        self.stages.push(stage);
+        self
+    }
+
+    // Non finite errors count as diverged and are dropped in every stage.
+    pub fn run<F: FnMut(&C, usize) -> f64>(
+        &self,
+        full_horizon: usize,
+        mut evaluate: F,
+    ) -> SweepResult<'_, C> {
+        if let Some(last) = self.stages.last() {
+            assert!(last.horizon < full_horizon);
+        }
+
+        let mut outcomes = vec![None; self.candidates.len()];
+        let mut surviving: Vec<usize> = (0..self.candidates.len()).collect();
+        for (stage_index, stage) in self.stages.iter().enumerate() {
+            let mut errors = Vec::with_capacity(surviving.len());
+            for index in surviving.drain(..) {
+                let error = evaluate(&self.candidates[index], stage.horizon);
+                if error.is_finite() && error <= stage.max_error {
+                    errors.push((index, error));
+                } else {
+                    outcomes[index] = Some(CandidateOutcome::Stopped {
+                        stage: stage_index,
+                        error,
+                    });
+                }
+            }
+
+            errors.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+            let keep = (errors.len() as f64 * stage.keep_fraction).ceil() as usize;
+            for (index, error) in errors.drain(keep..) {
+                outcomes[index] = Some(CandidateOutcome::Stopped {
+                    stage: stage_index,
+                    error,
+                });
+            }
+            surviving = errors.into_iter().map(|(index, _)| index).collect();
+            surviving.sort_unstable();
+        }
+
+        for index in surviving {
+            let error = evaluate(&self.candidates[index], full_horizon);
+            outcomes[index] = Some(CandidateOutcome::Completed { error });
+        }
+
+        SweepResult {
+            candidates: &self.candidates,
+            outcomes: outcomes.into_iter().map(Option::unwrap).collect(),
+        }
+    }
+
+    // Scores every candidate on several objectives, e.g. valid time, NRMSE, reservoir size and
+    // inference latency, and returns the non dominated candidates in candidate order. Candidates
+    // with a non finite score are never part of the front.
+    pub fn pareto_front<F: FnMut(&C) -> Vec<f64>>(
+        &self,
+        directions: &[ObjectiveDirection],
+        mut evaluate: F,
+    ) -> Vec<ParetoCandidate<'_, C>> {
+        let scores: Vec<Vec<f64>> = self
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let scores = evaluate(candidate);
+                assert_eq!(scores.len(), directions.len());
+                scores
+            })
+            .collect();
+        let finite_minimized: Vec<Vec<f64>> = scores
+            .iter()
+            .map(|scores| minimized(scores, directions))
+            .filter(|scores| scores.iter().all(|score| score.is_finite()))
+            .collect();
+
+        let mut front = vec![];
+        for (index, candidate_scores) in scores.into_iter().enumerate() {
+            if candidate_scores.iter().any(|score| !score.is_finite()) {
+                continue;
+            }
+            let candidate_minimized = minimized(&candidate_scores, directions);
+            if !finite_minimized
+                .iter()
+                .any(|other| dominates(other, &candidate_minimized))
+            {
+                front.push(ParetoCandidate {
+                    candidate: &self.candidates[index],
+                    index,
+                    scores: candidate_scores,
+                });
+            }
+        }
+        front
+    }
+}
+
+fn minimized(scores: &[f64], directions: &[ObjectiveDirection]) -> Vec<f64> {
+    scores
+        .iter()
+        .zip(directions)
+        .map(|(score, direction)| match direction {
+            ObjectiveDirection::Minimize => *score,
+            ObjectiveDirection::Maximize => -*score,
+        })
+        .collect()
+}
+
+// All objectives are minimized here.
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a <= b) && a.iter().zip(b).any(|(a, b)| a < b)
+}
+
+impl<'a, C> SweepResult<'a, C> {
+    pub fn outcomes(&self) -> &[CandidateOutcome] {
+        &self.outcomes
+    }
+
+    pub fn completed(&self) -> impl Iterator<Item = (&'a C, f64)> + '_ {
+        self.candidates
+            .iter()
+            .zip(self.outcomes.iter())
+            .filter_map(|(candidate, outcome)| match outcome {
+                CandidateOutcome::Completed { error } => Some((candidate, *error)),
+                CandidateOutcome::Stopped { .. } => None,
+            })
+    }
+
+    pub fn best(&self) -> Option<(&'a C, f64)> {
+        self.completed()
+            .filter(|(_, error)| error.is_finite())
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+    }
+}
+
+// Values of the echo state network hyperparameters to search over. They are validated once
+// here, so every candidate goes into the builders and the readout training as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EchoStateSearchSpace<T: ReservoirValue> {
+    pub spectral_radii: Vec<SpectralRadius<T>>,
+    pub leak_rates: Vec<LeakRate<T>>,
+    pub regularizations: Vec<Regularization<T>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EchoStateCandidate<T: ReservoirValue> {
+    pub spectral_radius: SpectralRadius<T>,
+    pub leak_rate: LeakRate<T>,
+    pub regularization: Regularization<T>,
+}
+
+impl<T: ReservoirValue> EchoStateSearchSpace<T> {
+    pub fn try_new(
+        spectral_radii: &[f64],
+        leak_rates: &[f64],
+        regularizations: &[f64],
+    ) -> Result<Self, ReservoirError> {
+        Ok(Self {
+            spectral_radii: collect_validated(spectral_radii)?,
+            leak_rates: collect_validated(leak_rates)?,
+            regularizations: collect_validated(regularizations)?,
+        })
+    }
+
+    // Every combination, the spectral radius varies slowest.
+    pub fn grid(&self) -> Vec<EchoStateCandidate<T>> {
+        let mut candidates = vec![];
+        for spectral_radius in &self.spectral_radii {
+            for leak_rate in &self.leak_rates {
+                for regularization in &self.regularizations {
+                    candidates.push(EchoStateCandidate {
+                        spectral_radius: *spectral_radius,
+                        leak_rate: *leak_rate,
+                        regularization: *regularization,
+                    });
+                }
+            }
+        }
+        candidates
+    }
+
+    // `count` random combinations, for spaces too large for the full grid.
+    pub fn sample(&self, count: usize, seed: u64) -> Vec<EchoStateCandidate<T>> {
+        assert!(
+            !self.spectral_radii.is_empty()
+                && !self.leak_rates.is_empty()
+                && !self.regularizations.is_empty(),
+            "Every hyperparameter needs at least one value."
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| EchoStateCandidate {
+                spectral_radius: self.spectral_radii[rng.gen_range(0..self.spectral_radii.len())],
+                leak_rate: self.leak_rates[rng.gen_range(0..self.leak_rates.len())],
+                regularization: self.regularizations[rng.gen_range(0..self.regularizations.len())],
+            })
+            .collect()
+    }
+
+    pub fn grid_sweep(&self) -> HyperparameterSweep<EchoStateCandidate<T>> {
+        HyperparameterSweep::new(self.grid())
+    }
+}
+
+fn collect_validated<V: TryFrom<f64, Error = ReservoirError>>(
+    values: &[f64],
+) -> Result<Vec<V>, ReservoirError> {
+    values.iter().map(|value| V::try_from(*value)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CandidateOutcome, EarlyStoppingStage, EchoStateSearchSpace, HyperparameterSweep,
+        ObjectiveDirection,
+    };
+    use crate::{
+        activation_function::Tanh, echo_state_network::EchoStateNetworkBuilder, ReservoirError,
+    };
+
+    #[test]
+    fn early_stopping_skips_full_evaluation() {
+        let mut sweep = HyperparameterSweep::new(vec![0.1, 0.5, 2.0, f64::NAN, 0.3]);
+        sweep
+            .early_stopping(10, 1.0)
+            .early_stopping_stage(EarlyStoppingStage {
+                horizon: 50,
+                max_error: f64::INFINITY,
+                keep_fraction: 0.5,
+            });
+
+        let mut full_evaluations = 0;
+        let result = sweep.run(200, |candidate, horizon| {
+            if horizon == 200 {
+                full_evaluations += 1;
+            }
+            candidate * horizon as f64 / 10.
+        });
+
+        assert_eq!(full_evaluations, 2);
+        assert_eq!(
+            result.outcomes()[2],
+            CandidateOutcome::Stopped {
+                stage: 0,
+                error: 2.0
+            }
+        );
+        assert!(matches!(
+            result.outcomes()[3],
+            CandidateOutcome::Stopped { stage: 0, .. }
+        ));
+        assert_eq!(
+            result.outcomes()[1],
+            CandidateOutcome::Stopped {
+                stage: 1,
+                error: 2.5
+            }
+        );
+        let (best, error) = result.best().unwrap();
+        assert_eq!(*best, 0.1);
+        assert!((error - 2.).abs() < 1e-12);
+        assert_eq!(result.completed().count(), 2);
+    }
+
+    #[test]
+    fn pareto_front_keeps_non_dominated() {
+        // (reservoir size, valid time)
+        let sweep = HyperparameterSweep::new(vec![
+            (100., 5.),
+            (200., 8.),
+            (200., 6.),
+            (400., 8.),
+            (50., f64::NAN),
+            (400., 12.),
+        ]);
+        let front = sweep.pareto_front(
+            &[ObjectiveDirection::Minimize, ObjectiveDirection::Maximize],
+            |(size, valid_time)| vec![*size, *valid_time],
+        );
+        let indices: Vec<usize> = front.iter().map(|candidate| candidate.index).collect();
+        assert_eq!(indices, vec![0, 1, 5]);
+        assert_eq!(front[1].scores, vec![200., 8.]);
+    }
+
+    #[test]
+    fn echo_state_search_space_yields_validated_candidates() {
+        assert!(matches!(
+            EchoStateSearchSpace::<f64>::try_new(&[0.9], &[1.5], &[1e-6]),
+            Err(ReservoirError::InvalidHyperparameter(_))
+        ));
+        assert!(EchoStateSearchSpace::<f64>::try_new(&[0.9], &[0.5], &[-1e-6]).is_err());
+
+        let space = EchoStateSearchSpace::<f64>::try_new(&[0.5, 0.9], &[0.3, 1.], &[1e-6]).unwrap();
+        let grid = space.grid();
+        assert_eq!(grid.len(), 4);
+        assert_eq!(grid[1].spectral_radius.get(), 0.5);
+        assert_eq!(grid[1].leak_rate.get(), 1.);
+        assert_eq!(space.sample(10, 1).len(), 10);
+        assert!(space
+            .sample(10, 1)
+            .iter()
+            .all(|candidate| grid.contains(candidate)));
+
+        let sweep = space.grid_sweep();
+        let result = sweep.run(10, |candidate, _| {
+            let mut builder = EchoStateNetworkBuilder::<f64>::random_seeded(20, 3, 1);
+            builder.spectral_radius(candidate.spectral_radius);
+            let network = builder.build_sparse_leaky_integrator_network(Tanh, candidate.leak_rate);
+            network.adjacency_matrix().values().len() as f64 * candidate.regularization.get()
+        });
+        assert_eq!(result.completed().count(), 4);
+    }
+}
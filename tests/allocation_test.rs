@@ -0,0 +1,133 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use nalgebra::{DMatrix, DVector};
+use rescomp::{
+    activation_function::Tanh,
+    echo_state_network::{EchoStateNetworkBuilder, TimeConstantDistribution},
+    input_projection::{
+        DefaultInputProjection, HadamardInputProjection, InputProjectionWithEmbedding,
+        ReservoirInputProjection,
+    },
+    output_projection::LinearStateProjection,
+    state_measurement::DefaultStateMeasurement,
+    time_evolution::AllocationFreeTimeEvolution,
+    LeakRate, Reservoir, ReservoirComputer,
+};
+
+// Counts the allocations of the current thread only, the other tests of this binary may run
+// concurrently.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout)
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(pointer, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+fn assert_frozen_steps_do_not_allocate<E: AllocationFreeTimeEvolution<f64>>(network: E) {
+    let reservoir = Reservoir::new(
+        DefaultInputProjection::new_random_seeded(2, 50, 0.5, 2),
+        network,
+    );
+    let mut frozen = ReservoirComputer::new(
+        reservoir,
+        DefaultStateMeasurement::new(50),
+        LinearStateProjection::new_with_matrix(DMatrix::from_element(2, 50, 0.01)),
+    )
+    .freeze();
+    let kickstarter = DMatrix::from_element(2, 1, 0.5);
+    let input = DVector::from_vec(vec![0.1, -0.2]);
+    frozen.kickstart(kickstarter.columns(0, 1));
+    let mut predictions = DMatrix::zeros(2, 20);
+
+    let before = allocations();
+    for _ in 0..100 {
+        frozen.step(input.column(0));
+        frozen.predict_step();
+    }
+    frozen.predict_into(predictions.columns_mut(0, 20));
+    assert_eq!(allocations() - before, 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn frozen_reservoir_computer_steps_do_not_allocate() {
+    let builder = || EchoStateNetworkBuilder::<f64>::random_seeded(50, 5, 1);
+    assert_frozen_steps_do_not_allocate(builder().build_sparse_discrete_network(Tanh));
+    assert_frozen_steps_do_not_allocate(
+        builder().build_sparse_leaky_integrator_network(Tanh, LeakRate::new(0.3).unwrap()),
+    );
+    assert_frozen_steps_do_not_allocate(builder().build_continuous_network(
+        Tanh,
+        0.1,
+        TimeConstantDistribution::Uniform { min: 0.5, max: 2. },
+    ));
+    assert_frozen_steps_do_not_allocate(builder().build_mixed_precision_network(Tanh));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn project_many_into_with_workspace_does_not_allocate_once_warmed_up() {
+    let embedded = InputProjectionWithEmbedding::<f64>::new_random_seeded(2, 50, 3, 2, 1);
+    let hadamard = HadamardInputProjection::<f64>::new_random_seeded(2, 50, 0.5, 1);
+    let inputs = DMatrix::from_fn(2, 200, |i, j| ((i + 1) * j) as f64 * 0.01);
+    let mut embedded_targets = DMatrix::zeros(50, 194);
+    let mut hadamard_targets = DMatrix::zeros(50, 200);
+    let mut embedded_workspace = DMatrix::zeros(0, 0);
+    let mut hadamard_workspace = DMatrix::zeros(0, 0);
+    let mut project = || {
+        embedded.project_many_into_with_workspace(
+            inputs.columns(0, 200),
+            embedded_targets.columns_mut(0, 194),
+            &mut embedded_workspace,
+        );
+        hadamard.project_many_into_with_workspace(
+            inputs.columns(0, 200),
+            hadamard_targets.columns_mut(0, 200),
+            &mut hadamard_workspace,
+        );
+    };
+    project();
+
+    let before = allocations();
+    for _ in 0..10 {
+        project();
+    }
+    assert_eq!(allocations() - before, 0);
+    assert_eq!(
+        embedded_targets,
+        embedded.project_many(inputs.columns(0, 200))
+    );
+    assert_eq!(
+        hadamard_targets,
+        hadamard.project_many(inputs.columns(0, 200))
+    );
+}
@@ -0,0 +1,100 @@
+use nalgebra::DMatrix;
+use rand::{distributions::Uniform, prelude::Distribution, rngs::StdRng, SeedableRng};
+use rescomp::{
+    activation_function::ActivationFunctionWrapper,
+    controlled_reservoir::{training::ControlledReservoirTraining, ControlledReservoir},
+    data::{simulate_forced, CoupledOscillators, Forcing},
+    echo_state_network::EchoStateNetworkBuilder,
+    input_projection::DefaultInputProjection,
+    state_measurement::ConstantExtensionStateMeasurement,
+    SpectralRadius,
+};
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn inverse_model_linear_system() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random_seeded(100, 4, 3);
+    esn_builder.spectral_radius(SpectralRadius::new(0.5).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+    let input_projection = DefaultInputProjection::new_random_seeded(1, 100, 0.1, 4);
+    let control_projection = DefaultInputProjection::new_random_seeded(1, 100, 0.1, 5);
+    let reservoir = ControlledReservoir::new(input_projection, control_projection, esn);
+    let measurement = ConstantExtensionStateMeasurement::<f64>::new(100);
+
+    // x_{t + 1} = 0.5 x_t + c_t
+    let steps = 1500;
+    let mut rng = StdRng::seed_from_u64(6);
+    let plus_minus_one = Uniform::new_inclusive(-1.0, 1.0);
+    let mut input = DMatrix::zeros(1, steps);
+    let mut control = DMatrix::zeros(1, steps);
+    for t in 0..(steps - 1) {
+        control[(0, t)] = plus_minus_one.sample(&mut rng);
+        input[(0, t + 1)] = 0.5 * input[(0, t)] + control[(0, t)];
+    }
+
+    let mut training = ControlledReservoirTraining::new(100, 1200);
+    training.add_data(input.clone(), control.clone());
+    let mut inverse_model =
+        training.train_inverse_model_via_ridge_regression(1e-8, reservoir, measurement);
+
+    let start = 1300;
+    inverse_model.synchronize_state(input.columns(1, start), control.columns(0, start));
+    let mut total_error = 0.0;
+    for t in start..(steps - 1) {
+        let desired = input.column(t + 1);
+        let proposed = inverse_model.control(desired)[0];
+        total_error += (proposed - control[(0, t)]).abs();
+        inverse_model.synchronize_state(input.columns(t + 1, 1), control.columns(t, 1));
+    }
+    let average_error = total_error / (steps - 1 - start) as f64;
+    println!("Average control error: {average_error}");
+    assert!(average_error < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn inverse_model_forced_coupled_oscillators() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random_seeded(200, 4, 7);
+    esn_builder.spectral_radius(SpectralRadius::new(0.5).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+    let input_projection = DefaultInputProjection::new_random_seeded(4, 200, 0.1, 7);
+    let control_projection = DefaultInputProjection::new_random_seeded(1, 200, 0.1, 8);
+    let reservoir = ControlledReservoir::new(input_projection, control_projection, esn);
+    let measurement = ConstantExtensionStateMeasurement::<f64>::new(200);
+
+    let forcing = Forcing::RandomSteps {
+        amplitude: 1.,
+        hold_steps: 1,
+        seed: 2,
+    };
+    let steps = 1500;
+    let (input, control) = simulate_forced(
+        &CoupledOscillators::default(),
+        &[0.; 4],
+        &forcing,
+        0.1,
+        steps,
+        4,
+    );
+
+    let mut training = ControlledReservoirTraining::new(100, 1200);
+    training.add_data(input.clone(), control.clone());
+    let mut inverse_model =
+        training.train_inverse_model_via_ridge_regression(1e-8, reservoir, measurement);
+
+    let start = 1300;
+    inverse_model.synchronize_state(input.columns(1, start), control.columns(0, start));
+    let mut total_error = 0.0;
+    for t in start..(steps - 1) {
+        let proposed = inverse_model.control(input.column(t + 1))[0];
+        total_error += (proposed - control[(0, t)]).abs();
+        inverse_model.synchronize_state(input.columns(t + 1, 1), control.columns(t, 1));
+    }
+    let average_error = total_error / (steps - 1 - start) as f64;
+    println!("Average control error: {average_error}");
+    assert!(average_error < 0.1);
+}
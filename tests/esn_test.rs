@@ -1,18 +1,74 @@
-use nalgebra::DMatrix;
+use nalgebra::{DMatrix, DVector};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use rescomp::{
-    activation_function::ActivationFunctionWrapper,
-    echo_state_network::EchoStateNetworkBuilder,
-    input_projection::{DefaultInputProjection, InputProjectionWithEmbedding},
-    reservoir::training::ReservoirTraining,
-    state_measurement::DefaultStateMeasurement,
-    Reservoir,
+    activation_function::{ActivationFunctionWrapper, Tanh},
+    benchmark::BenchmarkTask,
+    change_point::CusumDetector,
+    echo_state_network::{EchoStateNetworkBuilder, SparseDiscreteEchoStateNetwork},
+    external_recording::{RecordingTimeEvolution, ReplayTimeEvolution},
+    external_time_evolution::ExternalTimeEvolution,
+    fit_predict,
+    input_projection::{
+        DefaultInputProjection, InputProjectionWithEmbedding, TaskEmbeddingInputProjection,
+    },
+    output_projection::{
+        LinearStateProjection, OnlineReadoutTrainer, PolynomialKernel, RecursiveLeastSquares,
+        ReportingTrainer,
+    },
+    preprocessing::NoiseAugmentation,
+    reservoir::{
+        training::ReservoirTraining, ConformalCalibration, DenoisingTraining, DimensionInfo,
+        DimensionReport, FeedbackMap, HorizonErrorCurve, MaintenanceAction, SequenceClassification,
+        SlidingWindowPolicy,
+    },
+    state_measurement::{
+        ConstantExtensionStateMeasurement, ContextStateMeasurement, DefaultStateMeasurement,
+        SeasonalTimeFeatures, TimeFeatureStateMeasurement,
+    },
+    time_evolution::ReservoirTimeEvolution,
+    FitPredictConfig, Reservoir, ReservoirComputer, ReservoirError, SpectralRadius,
 };
 
+// Columns (sin t, cos t) at t = 0, dt, 2 dt, ...
+fn sine_cosine_data(n: usize, dt: f64) -> DMatrix<f64> {
+    DMatrix::from_fn(2, n, |i, j| {
+        let time = j as f64 * dt;
+        if i == 0 {
+            time.sin()
+        } else {
+            time.cos()
+        }
+    })
+}
+
+// Seeded sparse tanh network of degree six.
+fn tanh_network(
+    size: usize,
+    seed: u64,
+    spectral_radius: f64,
+) -> SparseDiscreteEchoStateNetwork<f64, Tanh> {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random_seeded(size, 6, seed);
+    esn_builder.spectral_radius(SpectralRadius::new(spectral_radius).unwrap());
+    esn_builder.build_sparse_discrete_network(Tanh)
+}
+
+// Training on `sine_cosine_data(n, 0.02)` without prediction synchronization.
+fn sine_cosine_training(
+    n: usize,
+    train_sync_steps: usize,
+    train_steps: usize,
+    prediction_steps: usize,
+) -> ReservoirTraining<f64> {
+    let mut rt = ReservoirTraining::new(train_sync_steps, train_steps, 0, prediction_steps);
+    rt.add_data(sine_cosine_data(n, 0.02));
+    rt
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn esn_predict_sine_cosine() {
     let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(200, 6);
-    esn_builder.spectral_radius(0.9);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
     let esn = esn_builder
         .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
 
@@ -68,11 +124,314 @@ fn esn_predict_sine_cosine() {
     );
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn fit_predict_sine_cosine() {
+    let data = sine_cosine_data(1500, 0.02);
+    let config = FitPredictConfig {
+        seed: Some(5),
+        ..Default::default()
+    };
+    let train_data = data.columns(0, 1200).clone_owned();
+    let prediction = fit_predict(&config, train_data, data.columns(1199, 1), 100);
+
+    assert_eq!(prediction.shape(), (2, 100));
+    assert!((prediction - data.columns(1200, 100)).amax() < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn frozen_reservoir_computer_matches_closed_loop_prediction() {
+    let esn = tanh_network(100, 6, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 3, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let rt = sine_cosine_training(1000, 200, 600, 200);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let kickstarter_len = reservoir_computer.kickstarter_len();
+    let kickstarter = rt.get_prediction_kickstarter(0, kickstarter_len);
+    let mut frozen = reservoir_computer.fork().freeze();
+    let prediction = reservoir_computer.synchronize_and_predict(kickstarter, 0, 50);
+
+    frozen.kickstart(kickstarter);
+    let mut frozen_prediction = DMatrix::zeros(2, 50);
+    frozen.predict_into(frozen_prediction.columns_mut(0, 50));
+    assert!((frozen_prediction - prediction).amax() < 1e-12);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn shared_reservoir_model_sessions_run_concurrently() {
+    let esn = tanh_network(100, 7, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 2, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let rt = sine_cosine_training(1000, 200, 600, 200);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let kickstarter_len = reservoir_computer.kickstarter_len();
+    let kickstarter = rt
+        .get_prediction_kickstarter(0, kickstarter_len)
+        .clone_owned();
+    let model = reservoir_computer.fork().into_shared();
+    let expected =
+        reservoir_computer.synchronize_and_predict(kickstarter.columns(0, kickstarter_len), 0, 50);
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let mut session = model.new_session();
+                let kickstarter = &kickstarter;
+                scope.spawn(move || session.predict(kickstarter.columns(0, kickstarter_len), 50))
+            })
+            .collect();
+        for handle in handles {
+            assert!((handle.join().unwrap() - &expected).amax() < 1e-12);
+        }
+    });
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_sessions_match_separate_runs() {
+    let esn = EchoStateNetworkBuilder::<f64>::random_seeded(100, 6, 9)
+        .build_mixed_precision_network(Tanh);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random_seeded(2, 100, 2, 1, 3);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let measurement = TimeFeatureStateMeasurement::new(
+        DefaultStateMeasurement::new(100),
+        SeasonalTimeFeatures::new(vec![50.]),
+    );
+
+    let train_data = sine_cosine_data(1000, 0.02);
+    let rt = sine_cosine_training(1000, 200, 600, 200);
+    let model = rt
+        .train_via_ridge_regression(reservoir, measurement)
+        .into_shared();
+
+    let inputs = [
+        train_data.columns(0, 300).clone_owned(),
+        train_data.columns(400, 300).clone_owned(),
+    ];
+    let run = |input: &DMatrix<f64>| {
+        let mut session = model.new_session();
+        let mut predictions = DMatrix::zeros(2, input.ncols());
+        for (column, mut prediction) in input.column_iter().zip(predictions.column_iter_mut()) {
+            prediction.copy_from(session.step(column));
+        }
+        predictions
+    };
+    let separate: Vec<_> = inputs.iter().map(run).collect();
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .iter()
+            .map(|input| scope.spawn(|| run(input)))
+            .collect();
+        for (handle, expected) in handles.into_iter().zip(separate.iter()) {
+            assert_eq!(&handle.join().unwrap(), expected);
+        }
+    });
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn predict_batch_matches_single_predictions() {
+    let esn = tanh_network(100, 8, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 2, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let train_data = sine_cosine_data(1000, 0.02);
+    let rt = sine_cosine_training(1000, 200, 600, 200);
+    let reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+    let kickstarter_len = reservoir_computer.kickstarter_len();
+    let (state, mut dynamics) = reservoir_computer.split_reservoir_computer_dynamics();
+
+    let origins = [100, 350, 720];
+    let kickstarters: Vec<_> = origins
+        .iter()
+        .map(|origin| train_data.columns(*origin, kickstarter_len))
+        .collect();
+    let mut states = DMatrix::from_fn(100, origins.len(), |i, j| state[i] * (j + 1) as f64 * 0.5);
+    let initial_states = states.clone();
+
+    let batch = dynamics.predict_batch(&mut states, &kickstarters, 40);
+    for (member, kickstarter) in kickstarters.iter().enumerate() {
+        let mut single_state = initial_states.column(member).clone_owned();
+        let single = dynamics.synchronize_and_predict(&mut single_state, *kickstarter, 0, 40);
+        assert!((&batch[member] - single).amax() < 1e-12);
+        assert!((states.column(member) - single_state).amax() < 1e-12);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn microbatched_and_monte_carlo_predictions() {
+    let esn = tanh_network(80, 11, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 80, 2, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let train_data = sine_cosine_data(800, 0.02);
+    let rt = sine_cosine_training(800, 200, 500, 100);
+    let reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(80));
+    let kickstarter_len = reservoir_computer.kickstarter_len();
+    let (state, dynamics) = reservoir_computer.split_reservoir_computer_dynamics();
+
+    let origins = [50, 130, 260, 390, 520];
+    let kickstarters: Vec<_> = origins
+        .iter()
+        .map(|origin| train_data.columns(*origin, kickstarter_len))
+        .collect();
+    let states: Vec<_> = (0..origins.len())
+        .map(|member| &state * (1. - 0.1 * member as f64))
+        .collect();
+    let mut batch_states = DMatrix::from_columns(&states);
+    let batch = dynamics.predict_batch(&mut batch_states, &kickstarters, 30);
+    let microbatched = dynamics.predict_microbatched(&states, &kickstarters, 30, 2);
+    assert_eq!(microbatched.len(), origins.len());
+    for (micro, full) in microbatched.iter().zip(batch.iter()) {
+        assert!((micro - full).amax() < 1e-12);
+    }
+
+    // Without noise every scenario is the unperturbed prediction, with noise the scenarios
+    // spread but stay reproducible for a seed.
+    let unperturbed = &batch[0];
+    let noiseless = dynamics.predict_monte_carlo(&states[0], kickstarters[0], 3, 30, 0., 1);
+    assert!(noiseless
+        .iter()
+        .all(|scenario| (scenario - unperturbed).amax() < 1e-12));
+    let scenarios = dynamics.predict_monte_carlo(&states[0], kickstarters[0], 20, 30, 0.01, 1);
+    let repeated = dynamics.predict_monte_carlo(&states[0], kickstarters[0], 20, 30, 0.01, 1);
+    assert_eq!(scenarios, repeated);
+    assert!((&scenarios[0] - &scenarios[1]).amax() > 1e-4);
+    let mean = scenarios
+        .iter()
+        .fold(DMatrix::zeros(2, 30), |sum, scenario| sum + scenario)
+        / 20.;
+    assert!((mean - unperturbed).amax() < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn multifunction_reservoir_with_one_readout_for_two_attractors() {
+    let esn = tanh_network(300, 21, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 300, 0.5, 22);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // A slow circle and a faster, smaller one.
+    let circle = |radius: f64, frequency: f64| radius * sine_cosine_data(2000, 0.02 * frequency);
+    let circles = [circle(1., 1.), circle(0.5, 2.)];
+    let mut rt = ReservoirTraining::new(300, 1200, 0, 200);
+    for data in &circles {
+        rt.add_data(data.clone());
+    }
+    let measurement = ContextStateMeasurement::one_hot(DefaultStateMeasurement::new(300), 2);
+    let mut reservoir_computer =
+        rt.train_multifunction_via_ridge_regression(1e-6, reservoir, measurement);
+    assert_eq!(reservoir_computer.state_measurement().context()[0], 1.);
+
+    let error = |reservoir_computer: &mut ReservoirComputer<_, _, _, _, _>, data: &DMatrix<f64>| {
+        let prediction =
+            reservoir_computer.resynchronize_and_predict(data.columns(0, 2000), 1600, 200, 100);
+        (prediction - data.columns(1600, 100)).amax()
+    };
+    for (index, data) in circles.iter().enumerate() {
+        reservoir_computer.state_measurement_mut().select(index);
+        assert!(error(&mut reservoir_computer, data) < 0.1);
+    }
+
+    // The readout weights the context channels.
+    let w_out = reservoir_computer.state_projection().w_out();
+    assert!(w_out.columns(300, 2).amax() > 0.);
+    reservoir_computer
+        .state_measurement_mut()
+        .interpolate(&[0.5, 0.5]);
+    assert_eq!(
+        reservoir_computer.state_measurement().context().as_slice(),
+        &[0.5, 0.5]
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn task_embedding_adapts_a_frozen_reservoir_computer() {
+    let esn = tanh_network(200, 31, 0.9);
+    let input_projection = TaskEmbeddingInputProjection::new_random_seeded(
+        DefaultInputProjection::new_random_seeded(2, 200, 0.5, 32),
+        2,
+        0.5,
+        33,
+    );
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // Circles of different speed and radius, the new task lies between the trained ones.
+    let circle = |radius: f64, frequency: f64| radius * sine_cosine_data(1000, 0.02 * frequency);
+    let mut rt = ReservoirTraining::new(200, 600, 0, 200);
+    rt.add_data(circle(1., 1.));
+    rt.add_data(circle(0.5, 2.));
+    let initial_embeddings = vec![
+        DVector::from_vec(vec![1., 0.]),
+        DVector::from_vec(vec![0., 1.]),
+    ];
+    let (mut reservoir_computer, embeddings) = rt.train_task_embeddings_via_ridge_regression(
+        1e-6,
+        reservoir,
+        DefaultStateMeasurement::new(200),
+        initial_embeddings,
+        2,
+    );
+    assert_eq!(embeddings.len(), 2);
+    assert_eq!(reservoir_computer.task_embedding(), &embeddings[0]);
+    let w_out = reservoir_computer.state_projection().w_out().clone();
+
+    let related = circle(0.75, 1.5);
+    reservoir_computer.set_task_embedding(DVector::zeros(2));
+    let report = reservoir_computer.adapt_task_embedding(related.columns(0, 600), 200, 10);
+    let losses = report.losses();
+    assert!(*losses.last().unwrap() < 0.1 * losses[0]);
+    assert_eq!(reservoir_computer.task_embedding(), report.embedding());
+    assert_eq!(reservoir_computer.state_projection().w_out(), &w_out);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_training_report_matches_one_step_predictions() {
+    let esn = tanh_network(100, 4, 0.9);
+    let reservoir = || {
+        let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 4);
+        Reservoir::new(input_projection, esn.clone())
+    };
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let rt = sine_cosine_training(1200, 200, 800, 200);
+    let (reported, report) =
+        rt.train_via_ridge_regression_with_report(reservoir(), reservoir_state_measurement.clone());
+    let trained = rt.train_via_ridge_regression(reservoir(), reservoir_state_measurement);
+
+    assert_eq!(
+        reported.state_projection().w_out(),
+        trained.state_projection().w_out()
+    );
+    assert_eq!(reported.state(), trained.state());
+
+    assert_eq!(report.predictions().shape(), (2, 799));
+    assert_eq!(
+        report.targets().column(0),
+        rt.get_prediction_kickstarter(0, 800).column(1)
+    );
+    assert!(report.root_mean_squared_error().amax() < 1e-2);
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn esn_predict_sine_cosine_embedded_no_stride() {
     let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(200, 6);
-    esn_builder.spectral_radius(0.9);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
     let esn = esn_builder
         .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
 
@@ -132,7 +491,7 @@ fn esn_predict_sine_cosine_embedded_no_stride() {
 #[cfg_attr(miri, ignore)]
 fn esn_predict_sine_cosine_embedded_with_stride() {
     let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(200, 6);
-    esn_builder.spectral_radius(0.9);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
     let esn = esn_builder
         .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
 
@@ -187,3 +546,780 @@ fn esn_predict_sine_cosine_embedded_with_stride() {
         total_error / 150_f64
     );
 }
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn predict_from_recent_kickstarts_with_the_trailing_columns() {
+    let esn = tanh_network(100, 91, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random_seeded(2, 100, 3, 2, 91);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let rt = sine_cosine_training(900, 200, 600, 100);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    // Three embeddings with stride two span seven input columns.
+    assert_eq!(reservoir_computer.kickstarter_len(), 7);
+    let recent_prediction = reservoir_computer
+        .clone()
+        .predict_from_recent(rt.get_prediction_kickstarter(0, 100), 10);
+    let prediction =
+        reservoir_computer.synchronize_and_predict(rt.get_prediction_kickstarter(0, 7), 0, 10);
+    assert_eq!(recent_prediction, prediction);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn prediction_stream_matches_closed_loop_prediction() {
+    let esn = tanh_network(100, 92, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random_seeded(2, 100, 3, 2, 92);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let rt = sine_cosine_training(900, 200, 600, 100);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 7);
+    let streamed_prediction: Vec<_> = reservoir_computer
+        .clone()
+        .into_prediction_stream(kickstarter)
+        .take(20)
+        .collect();
+    let prediction = reservoir_computer.synchronize_and_predict(kickstarter, 0, 20);
+    for (step, streamed) in streamed_prediction.iter().enumerate() {
+        assert_eq!(streamed, &prediction.column(step), "step {step}");
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn forked_reservoir_computer_predicts_like_the_original() {
+    let esn = tanh_network(100, 93, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 93);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let rt = sine_cosine_training(900, 200, 600, 100);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let forked_prediction = reservoir_computer
+        .fork()
+        .synchronize_and_predict(kickstarter, 0, 100);
+    let prediction = reservoir_computer.synchronize_and_predict(kickstarter, 0, 100);
+    assert_eq!(forked_prediction, prediction);
+}
+
+#[test]
+#[cfg(feature = "profile")]
+#[cfg_attr(miri, ignore)]
+fn esn_prediction_profile() {
+    let esn = tanh_network(100, 94, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 94);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let rt = sine_cosine_training(1000, 200, 500, 200);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, reservoir_state_measurement);
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let (prediction, profile) =
+        reservoir_computer.synchronize_and_predict_profiled(kickstarter, 0, 200);
+    assert_eq!(prediction.ncols(), 200);
+    assert!(profile.sparse_matrix_vector > std::time::Duration::ZERO);
+    assert!(profile.readout > std::time::Duration::ZERO);
+    assert_eq!(
+        profile.total(),
+        profile.input_projection
+            + profile.sparse_matrix_vector
+            + profile.activation
+            + profile.measurement
+            + profile.readout
+    );
+}
+
+#[test]
+fn try_new_rejects_mismatched_dimensions() {
+    let esn = EchoStateNetworkBuilder::<f64>::random(20, 3)
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+    let result = Reservoir::try_new(DefaultInputProjection::new_random(2, 10, 1.0), esn.clone());
+    assert_eq!(
+        result.unwrap_err(),
+        ReservoirError::DimensionMismatch {
+            component: "input projection output / time evolution input",
+            expected: 20,
+            actual: 10,
+        }
+    );
+
+    let reservoir =
+        Reservoir::try_new(DefaultInputProjection::new_random(2, 20, 1.0), esn).unwrap();
+    let measurement = DefaultStateMeasurement::<f64>::new(20);
+    let projection = LinearStateProjection::new_with_matrix(DMatrix::zeros(3, 20));
+    let result = ReservoirComputer::try_new(reservoir.clone(), measurement.clone(), projection);
+    assert!(matches!(
+        result,
+        Err(ReservoirError::DimensionMismatch {
+            expected: 2,
+            actual: 3,
+            ..
+        })
+    ));
+
+    let projection = LinearStateProjection::new_with_matrix(DMatrix::zeros(2, 20));
+    assert!(ReservoirComputer::try_new(reservoir, measurement, projection).is_ok());
+}
+
+#[test]
+fn dimension_report_of_an_unembedded_reservoir_computer() {
+    let esn = EchoStateNetworkBuilder::<f64>::random_seeded(20, 3, 1)
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+    let reservoir = Reservoir::new(
+        DefaultInputProjection::new_random_seeded(2, 20, 1.0, 1),
+        esn,
+    );
+    let reservoir_computer = ReservoirComputer::new(
+        reservoir,
+        DefaultStateMeasurement::<f64>::new(20),
+        LinearStateProjection::new_with_matrix(DMatrix::zeros(2, 20)),
+    );
+    assert_eq!(
+        reservoir_computer.dimension_report(),
+        DimensionReport {
+            input_dimension: 2,
+            embeddings: 0,
+            reservoir_dimension: 20,
+            measurement_dimension: 20,
+            output_dimension: 2,
+            kickstarter_columns: 1,
+        }
+    );
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+#[cfg_attr(miri, ignore)]
+fn esn_parallel_ensemble_prediction() {
+    let esn = tanh_network(100, 95, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 95);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let train_data = sine_cosine_data(1000, 0.02);
+    let rt = sine_cosine_training(1000, 200, 500, 200);
+    let reservoir_computer = rt.train_via_ridge_regression(reservoir, reservoir_state_measurement);
+    let (state, mut dynamics) = reservoir_computer.split_reservoir_computer_dynamics();
+
+    let states = vec![state.clone(); 8];
+    let kickstarters: Vec<_> = (0..8).map(|i| train_data.columns(600 + i, 1)).collect();
+    let predictions = dynamics.predict_ensemble(&states, &kickstarters, 50);
+
+    assert_eq!(predictions.len(), 8);
+    for (kickstarter, prediction) in kickstarters.iter().zip(predictions.iter()) {
+        let mut state = state.clone();
+        let expected = dynamics.synchronize_and_predict(&mut state, *kickstarter, 0, 50);
+        assert_eq!(prediction, &expected);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_training_with_dropout_is_reproducible() {
+    let esn = tanh_network(100, 96, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 96);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let mut rt = sine_cosine_training(1000, 200, 500, 200);
+    let plain =
+        rt.train_via_ridge_regression(reservoir.clone(), reservoir_state_measurement.clone());
+
+    rt.dropout(0.2, 7);
+    let first =
+        rt.train_via_ridge_regression(reservoir.clone(), reservoir_state_measurement.clone());
+    let second = rt.train_via_ridge_regression(reservoir, reservoir_state_measurement);
+
+    assert_eq!(
+        first.state_projection().w_out(),
+        second.state_projection().w_out()
+    );
+    assert_ne!(
+        first.state_projection().w_out(),
+        plain.state_projection().w_out()
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_kernel_readout_predict_sine_cosine() {
+    let esn = tanh_network(100, 1, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let rt = sine_cosine_training(1500, 300, 800, 100);
+    let mut reservoir_computer = rt.train_kernel_via_ridge_regression(
+        PolynomialKernel::new(1.0, 1.0, 1),
+        1e-8,
+        200,
+        reservoir,
+        reservoir_state_measurement,
+    );
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let true_prediction = rt.get_true_future(0);
+    let prediction = reservoir_computer.synchronize_and_predict(kickstarter, 0, 100);
+
+    let mut total_error = 0.0;
+    for (prediction, actual) in prediction.column_iter().zip(true_prediction.column_iter()) {
+        total_error += (prediction[0] - actual[0]).abs() + (prediction[1] - actual[1]).abs();
+    }
+    println!(
+        "Total Error: {total_error} Avg err: {}",
+        total_error / 100_f64
+    );
+    assert!(total_error / 100_f64 < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_quantile_readout_predict_sine_cosine() {
+    let esn = tanh_network(100, 2, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 2);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let rt = sine_cosine_training(1500, 300, 800, 100);
+    let mut reservoir_computer = rt.train_quantiles_via_iteratively_reweighted_least_squares(
+        &[0.1, 0.5, 0.9],
+        1e-8,
+        20,
+        reservoir,
+        reservoir_state_measurement,
+    );
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let true_prediction = rt.get_true_future(0);
+    let bands = reservoir_computer.synchronize_and_predict_quantiles(kickstarter, 100);
+    assert_eq!(bands.len(), 3);
+
+    let mut total_error = 0.0;
+    for (median, actual) in bands[1].column_iter().zip(true_prediction.column_iter()) {
+        total_error += (median[0] - actual[0]).abs() + (median[1] - actual[1]).abs();
+    }
+    println!(
+        "Total Error: {total_error} Avg err: {}",
+        total_error / 100_f64
+    );
+    assert!(total_error / 100_f64 < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_conformal_intervals_cover_future() {
+    let esn = tanh_network(100, 3, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 3);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    let data = sine_cosine_data(3000, 0.02);
+    let rt = sine_cosine_training(3000, 300, 800, 100);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, reservoir_state_measurement);
+    let mut held_out = reservoir_computer.fork();
+
+    let calibration = ConformalCalibration::calibrate(
+        &mut reservoir_computer,
+        data.columns(1300, 1700),
+        100,
+        50,
+        20,
+        0.9,
+    );
+    assert_eq!(calibration.horizon(), 50);
+    assert!(calibration
+        .half_widths()
+        .iter()
+        .all(|w| w.is_finite() && *w >= 0.));
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let true_future = rt.get_true_future(0).columns(0, 50).clone_owned();
+    let prediction = held_out.synchronize_and_predict(kickstarter, 0, 50);
+    let (lower, upper) = calibration.intervals(prediction.columns(0, 50));
+
+    let covered = true_future
+        .iter()
+        .zip(lower.iter().zip(upper.iter()))
+        .filter(|(actual, (lower, upper))| *lower <= *actual && *actual <= *upper)
+        .count();
+    println!("Covered {covered} of 100");
+    assert!(covered >= 60);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn horizon_error_curve_matches_single_forecasts() {
+    let esn = tanh_network(100, 5, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 2, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let data = sine_cosine_data(1400, 0.02);
+    let mut rt = ReservoirTraining::new(200, 700, 0, 100);
+    rt.add_data(data.columns(0, 1000).clone_owned());
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let test_data = data.columns(1000, 400);
+    let horizons = [1, 10, 50];
+    let curve =
+        HorizonErrorCurve::evaluate_strided(&mut reservoir_computer, test_data, 100, 50, &horizons);
+    assert_eq!(curve.origins(), &[52, 152, 252]);
+    assert_eq!(curve.errors().shape(), (3, 3));
+
+    let variance = (0..400)
+        .map(|j| (test_data.column(j) - test_data.column_mean()).norm_squared())
+        .sum::<f64>()
+        / 400.;
+    let prediction = reservoir_computer.resynchronize_and_predict(test_data, 152, 50, 50);
+    let expected = (prediction.column(9) - test_data.column(161)).norm() / variance.sqrt();
+    assert!((curve.errors()[(1, 1)] - expected).abs() < 1e-12);
+
+    let mean_errors = curve.mean_errors();
+    assert!(mean_errors[0] < 0.1);
+    assert!(mean_errors.iter().all(|error| error.is_finite()));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn training_on_noise_augmented_trajectories() {
+    let esn = tanh_network(100, 9, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 1.0, 9);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let data = sine_cosine_data(1400, 0.02);
+    let mut rt = ReservoirTraining::new(200, 700, 0, 100);
+    rt.add_augmented_data(data.columns(0, 1000), &NoiseAugmentation::new(3, 1e4, 11));
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let test_data = data.columns(1000, 400);
+    let prediction = reservoir_computer.resynchronize_and_predict(test_data, 200, 199, 100);
+    assert!((prediction - test_data.columns(200, 100)).amax() < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn esn_benchmark_tasks() {
+    let esn = tanh_network(200, 13, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(1, 200, 0.5, 13);
+    let mut reservoir = Reservoir::new(input_projection, esn);
+    let measurement = DefaultStateMeasurement::new(200);
+
+    let narma = BenchmarkTask::narma10(3000, 5);
+    let result = narma.evaluate(&mut reservoir, &measurement, 1e-8, 0.7);
+    assert!(result.test_nrmse < 0.6);
+
+    let xor = BenchmarkTask::delayed_xor(1, 3000, 5);
+    let result = xor.evaluate(&mut reservoir, &measurement, 1e-8, 0.7);
+    assert!(result.test_accuracy.unwrap() > 0.9);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn one_step_predictions_match_training_report() {
+    let esn = tanh_network(100, 21, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 2, 1);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let data = sine_cosine_data(1000, 0.02);
+    let rt = sine_cosine_training(1000, 200, 600, 100);
+    let (mut reservoir_computer, report) =
+        rt.train_via_ridge_regression_with_report(reservoir, DefaultStateMeasurement::new(100));
+
+    let predictions = reservoir_computer.one_step_predictions(data.columns(0, 800), 200);
+    assert_eq!(predictions.ncols(), 599);
+    assert!((&predictions - report.predictions()).amax() < 1e-10);
+    assert!((predictions - data.columns(201, 599)).amax() < 1e-2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn predict_from_input_sequence_with_strided_embedding() {
+    let esn = tanh_network(100, 22, 0.9);
+    let input_projection = InputProjectionWithEmbedding::<f64>::new_random(2, 100, 2, 3);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let data = sine_cosine_data(1000, 0.02);
+    let rt = sine_cosine_training(1000, 200, 600, 100);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+    let kickstarter_len = reservoir_computer.kickstarter_len();
+    assert_eq!(kickstarter_len, 7);
+
+    let one_step = reservoir_computer.one_step_predictions(data.columns(0, 800), 200);
+    let (_, dynamics) = reservoir_computer.split_reservoir_computer_dynamics();
+    let (mut reservoir_dynamics, mut measurement, mut projection) =
+        dynamics.split_reservoir_dynamics();
+    let mut state = nalgebra::DVector::zeros(100);
+    let sync_steps = 200 + 1 - kickstarter_len;
+    let predictions = reservoir_dynamics.predict_from_input_sequence(
+        &mut state,
+        data.columns(0, 799),
+        sync_steps,
+        &mut measurement,
+        &mut projection,
+    );
+    assert_eq!(predictions.ncols(), one_step.ncols());
+    assert!((predictions - one_step).amax() < 1e-10);
+
+    let mut result = DMatrix::zeros(2, 10);
+    state.fill(0.);
+    let produced = reservoir_dynamics.predict_from_input_sequence_into(
+        &mut state,
+        data.columns(0, 12),
+        2,
+        &mut measurement,
+        &mut projection,
+        result.columns_mut(0, 10),
+    );
+    assert_eq!(produced, 4);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn closed_loop_with_known_covariates() {
+    let esn = tanh_network(200, 31, 0.5);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 200, 0.2, 31);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // y(t + 1) = 0.5 y(t) + u(t) with a random driver u.
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut data = DMatrix::zeros(2, 1600);
+    for t in 0..1599 {
+        data[(1, t)] = rng.gen_range(-1.0..1.0);
+        data[(0, t + 1)] = 0.5 * data[(0, t)] + data[(1, t)];
+    }
+    let mut rt = ReservoirTraining::new(100, 1200, 0, 100);
+    rt.add_data(data.clone());
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(200));
+
+    let origin = 1400;
+    let steps = 100;
+    reservoir_computer.resynchronize_and_predict(data.columns(0, origin), origin - 1, 200, 0);
+    let predictions = reservoir_computer.synchronize_and_predict_with_covariates(
+        data.columns(origin - 1, 1),
+        &FeedbackMap::select(&[0]),
+        &[1],
+        data.slice_range(1..2, origin..origin + steps),
+    );
+    let error = (predictions.row(0) - data.slice_range(0..1, origin..origin + steps)).amax();
+    assert!(error < 0.05, "{error}");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn denoising_reconstructs_a_corrupted_signal() {
+    let esn = tanh_network(200, 41, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 200, 0.5, 42);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let clean = DMatrix::from_fn(2, 3000, |i, j| {
+        let time = j as f64 * 0.05;
+        if i == 0 {
+            time.sin()
+        } else {
+            (2. * time).cos() * 0.5
+        }
+    });
+    let mut rng = StdRng::seed_from_u64(43);
+    let noisy = clean.map(|value| value + rng.gen_range(-0.3..0.3));
+
+    let mut training = DenoisingTraining::new(100);
+    training.add_data(
+        noisy.columns(0, 2000).clone_owned(),
+        clean.columns(0, 2000).clone_owned(),
+    );
+    let (mut reservoir_computer, report) =
+        training.train_via_ridge_regression(1e-4, reservoir, DefaultStateMeasurement::new(200));
+    assert_eq!(report.predictions().ncols(), 1900);
+
+    let report = reservoir_computer.denoising_report(
+        noisy.columns(2000, 1000),
+        clean.columns(2000, 1000),
+        100,
+    );
+    let noise = (noisy.columns(2100, 900) - clean.columns(2100, 900)).map(|v| v * v);
+    let noise_rms = (noise.sum() / noise.len() as f64).sqrt();
+    let error = report.root_mean_squared_error().amax();
+    assert!(error < 0.5 * noise_rms, "{error} {noise_rms}");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn readout_for_selected_target_rows() {
+    let esn = tanh_network(200, 31, 0.5);
+    let input_projection = DefaultInputProjection::new_random_seeded(3, 200, 0.2, 31);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // y(t + 1) = 0.5 y(t) + u(t) + 0.3 v(t) with random drivers u and v, only y is predicted.
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut data = DMatrix::zeros(3, 1600);
+    for t in 0..1599 {
+        data[(0, t)] = rng.gen_range(-1.0..1.0);
+        data[(2, t)] = rng.gen_range(-1.0..1.0);
+        data[(1, t + 1)] = 0.5 * data[(1, t)] + data[(0, t)] + 0.3 * data[(2, t)];
+    }
+    let mut rt = ReservoirTraining::new(100, 1200, 0, 100);
+    rt.add_data(data.clone());
+    rt.target_rows(&[1]);
+    assert_eq!(rt.covariate_rows(), vec![0, 2]);
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(200));
+    assert_eq!(reservoir_computer.state_projection().w_out().nrows(), 1);
+
+    let origin = 1400;
+    let steps = 100;
+    let covariate_rows = rt.covariate_rows();
+    let covariates = data.columns(origin, steps).select_rows(&covariate_rows);
+    reservoir_computer.resynchronize_and_predict(data.columns(0, origin), origin - 1, 200, 0);
+    let predictions = reservoir_computer.synchronize_and_predict_with_covariates(
+        data.columns(origin - 1, 1),
+        &FeedbackMap::Identity,
+        &covariate_rows,
+        covariates.columns(0, steps),
+    );
+    let error = (predictions - data.slice_range(1..2, origin..origin + steps)).amax();
+    assert!(error < 0.05, "{error}");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn trajectory_weights_emphasize_a_regime() {
+    let esn = tanh_network(100, 51, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 0.5, 52);
+
+    // Two regimes the small reservoir cannot fit equally well at once.
+    let circle = |frequency: f64| sine_cosine_data(700, 0.05 * frequency);
+    let (slow, fast) = (circle(1.), circle(2.5));
+    let one_step_error = |weight: f64| {
+        let reservoir = Reservoir::new(input_projection.clone(), esn.clone());
+        let mut rt = ReservoirTraining::new(100, 500, 0, 100);
+        rt.add_data(slow.clone());
+        rt.add_weighted_data(fast.clone(), weight);
+        assert_eq!(rt.trajectory_weights(), &[1., weight]);
+        let mut reservoir_computer =
+            rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+        let predictions = reservoir_computer.one_step_predictions(fast.columns(0, 700), 100);
+        (predictions - fast.columns(101, 599)).norm()
+    };
+    assert!(one_step_error(100.) < 0.5 * one_step_error(1.));
+
+    let mut rt = ReservoirTraining::new(100, 500, 0, 100);
+    for weight in [0., -1., f64::NAN, f64::INFINITY] {
+        assert!(matches!(
+            rt.try_add_weighted_data(slow.clone(), weight),
+            Err(ReservoirError::InvalidData(_))
+        ));
+    }
+    assert!(rt.trajectory_weights().is_empty());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn class_weighted_sequence_classification() {
+    let esn = tanh_network(100, 61, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(1, 100, 0.5, 62);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // Frequent centered and rare shifted noise.
+    let mut rng = StdRng::seed_from_u64(63);
+    let mut classification = SequenceClassification::new(2);
+    for index in 0..110 {
+        let (label, offset) = if index % 11 == 0 { (1, 0.4) } else { (0, 0.) };
+        let sequence = DMatrix::from_fn(1, 50, |_, _| offset + rng.gen_range(-0.5..0.5));
+        classification.add_sequence(sequence, label);
+    }
+    classification.balance_class_weights();
+    assert_eq!(classification.class_weights(), &[0.55, 5.5]);
+    let (training, validation) = classification.stratified_split(0.3, 64);
+    assert_eq!(validation.class_counts(), vec![30, 3]);
+
+    let measurement = ConstantExtensionStateMeasurement::new(100);
+    let mut classifier = training.train_via_ridge_regression(1e-2, reservoir, measurement);
+    assert_eq!(classifier.state_projection().w_out().nrows(), 2);
+    let recalls = validation.recall_per_class(&mut classifier);
+    assert!(recalls.iter().all(|recall| *recall > 0.6), "{recalls:?}");
+    assert!(validation.balanced_accuracy(&mut classifier) > 0.8);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn online_readout_adaptation_reports_convergence() {
+    let esn = tanh_network(100, 61, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 0.5, 62);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    let circle = |frequency: f64| sine_cosine_data(1000, 0.05 * frequency);
+    let mut rt = ReservoirTraining::new(100, 600, 0, 100);
+    rt.add_data(circle(1.));
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    // The dynamics change, the readout follows from the trained one.
+    let drifted = circle(1.5);
+    let one_step_error = |reservoir_computer: &mut ReservoirComputer<_, _, _, _, _>| {
+        let predictions = reservoir_computer.one_step_predictions(drifted.columns(0, 1000), 100);
+        (predictions - drifted.columns(101, 899)).amax()
+    };
+    let error_before = one_step_error(&mut reservoir_computer);
+
+    let mut reports = vec![];
+    let rls = RecursiveLeastSquares::new_with_w_out(
+        reservoir_computer.state_projection().w_out().clone(),
+        1e-2,
+    );
+    let mut trainer = ReportingTrainer::new(rls, 200, |report| reports.push(report.clone()));
+    reservoir_computer.adapt_readout_online(drifted.columns(0, 1000), 100, &mut trainer);
+    assert!(trainer.effective_memory().is_infinite());
+    drop(trainer);
+
+    let error_after = one_step_error(&mut reservoir_computer);
+    assert!(
+        error_after < 0.1 * error_before,
+        "{error_before} {error_after}"
+    );
+    assert_eq!(reports.len(), 4);
+    assert_eq!(reports[3].updates, 800);
+    assert!(reports[3].running_error < reports[0].running_error);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn change_point_in_one_step_residuals() {
+    let esn = tanh_network(100, 71, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 0.5, 72);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // The frequency of the circle changes at column 600.
+    let data = DMatrix::from_fn(2, 1000, |i, j| {
+        let phase = if j < 600 {
+            j as f64 * 0.05
+        } else {
+            30. + (j - 600) as f64 * 0.08
+        };
+        if i == 0 {
+            phase.sin()
+        } else {
+            phase.cos()
+        }
+    });
+    let mut rt = ReservoirTraining::new(100, 400, 0, 100);
+    rt.add_data(data.columns(0, 600).clone_owned());
+    let mut reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+
+    let mut detector = CusumDetector::new(100, 1., 10.);
+    let change_points =
+        reservoir_computer.detect_change_points(data.columns(0, 1000), 100, &mut detector);
+    assert!(!change_points.is_empty());
+    assert!((595..=610).contains(&change_points[0]), "{change_points:?}");
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn prediction_stream_maintains_itself_after_a_regime_change() {
+    let esn = tanh_network(100, 81, 0.9);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 100, 0.5, 82);
+    let reservoir = Reservoir::new(input_projection, esn);
+
+    // The frequency of the circle changes at column 1000.
+    let data = DMatrix::from_fn(2, 3000, |i, j| {
+        let phase = if j < 1000 {
+            j as f64 * 0.05
+        } else {
+            50. + (j - 1000) as f64 * 0.08
+        };
+        if i == 0 {
+            phase.sin()
+        } else {
+            phase.cos()
+        }
+    });
+    let mut rt = ReservoirTraining::new(100, 690, 0, 10);
+    rt.add_data(data.columns(0, 800).clone_owned());
+    let reservoir_computer =
+        rt.train_via_ridge_regression(reservoir, DefaultStateMeasurement::new(100));
+    let mut stream = reservoir_computer.into_prediction_stream(data.columns(799, 1));
+
+    let mut policy = SlidingWindowPolicy::new(20, 0.1, 2, 300, 1e-8);
+    let mut actions = vec![];
+    let mut residuals = vec![];
+    for column in 800..3000 {
+        let prediction = stream.next().unwrap();
+        residuals.push((&prediction - data.column(column)).norm());
+        let action = stream.observe(data.column(column).clone_owned(), &mut policy);
+        if action != MaintenanceAction::Keep {
+            actions.push((column, action));
+        }
+    }
+    // Apart from the initial synchronization nothing is done before the change, after it
+    // resyncs do not help and the readout is retrained, after which maintenance is rare.
+    assert!(
+        actions
+            .iter()
+            .all(|(column, _)| *column < 850 || *column >= 1000),
+        "{actions:?}"
+    );
+    let retraining = actions
+        .iter()
+        .position(|(_, action)| *action == MaintenanceAction::RetrainReadout)
+        .unwrap();
+    assert!(actions[retraining].0 < 1200, "{actions:?}");
+    assert!(actions.len() - retraining <= 5, "{actions:?}");
+    let changed_error = residuals[200..350].iter().sum::<f64>();
+    let late_error = residuals[1650..1800].iter().sum::<f64>();
+    assert!(
+        late_error < 0.5 * changed_error,
+        "{changed_error} {late_error}"
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn readout_trains_offline_from_a_recorded_session() {
+    let data = sine_cosine_data(600, 0.05);
+    let mut rt = ReservoirTraining::new(50, 400, 0, 50);
+    rt.add_data(data);
+
+    // The session runs against the "hardware", here a simulated network behind a callback.
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random_seeded(50, 5, 91);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let hardware = esn_builder.build_sparse_discrete_network(Tanh);
+    let external = ExternalTimeEvolution::from_callback(50, move |state, input| {
+        let mut next = state.clone();
+        hardware.time_evolution(&mut next, input.column(0));
+        Ok(next)
+    });
+    let recorder = RecordingTimeEvolution::new(external);
+    let input_projection = DefaultInputProjection::new_random_seeded(2, 50, 0.5, 92);
+    let session = rt.train_via_ridge_regression(
+        Reservoir::new(input_projection.clone(), recorder.clone()),
+        DefaultStateMeasurement::new(50),
+    );
+    let path = std::env::temp_dir().join(format!("rescomp_session_{}", std::process::id()));
+    recorder.save(&path).unwrap();
+
+    let replay = ReplayTimeEvolution::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let offline = rt.train_via_ridge_regression(
+        Reservoir::new(input_projection, replay),
+        DefaultStateMeasurement::new(50),
+    );
+    assert_eq!(
+        offline.state_projection().w_out(),
+        session.state_projection().w_out()
+    );
+}
@@ -0,0 +1,103 @@
+use nalgebra::{DMatrix, DVectorSlice, DVectorSliceMut};
+use rescomp::{hybrid::KnowledgeBasedModelWrapper, prelude::*};
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn hybrid_predict_sine_cosine_with_imperfect_model() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(100, 6);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+    let input_projection = DefaultInputProjection::new_random(4, 100, 1.0);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let reservoir_state_measurement = DefaultStateMeasurement::<f64>::new(100);
+
+    // Rotation with a 20% wrong angular velocity.
+    let model = KnowledgeBasedModelWrapper::new(
+        2,
+        |input: DVectorSlice<f64>, mut target: DVectorSliceMut<f64>| {
+            let (sin, cos) = (0.024_f64).sin_cos();
+            target[0] = cos * input[0] + sin * input[1];
+            target[1] = -sin * input[0] + cos * input[1];
+        },
+    );
+
+    let mut data = Vec::with_capacity(4000);
+    for t in 0..(data.capacity() / 2) {
+        let time = t as f64 * 0.02;
+        data.push(time.sin());
+        data.push(time.cos());
+    }
+    let train_data = DMatrix::from_vec(2, data.len() / 2, data);
+
+    let mut rt = ReservoirTraining::new(500, 1000, 0, 500);
+    rt.add_data(train_data);
+    let mut hybrid = rt.train_hybrid_via_ridge_regression(
+        1e-8,
+        reservoir,
+        reservoir_state_measurement,
+        model,
+        true,
+    );
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let true_prediction = rt.get_true_future(0);
+    let prediction = hybrid.predict(kickstarter.column(0), 500);
+
+    let mut total_error = 0.0;
+    for (prediction, actual) in prediction.column_iter().zip(true_prediction.column_iter()) {
+        total_error += (prediction[0] - actual[0]).abs() + (prediction[1] - actual[1]).abs();
+    }
+    println!(
+        "Total Error: {total_error} Avg err: {}",
+        total_error / 500_f64
+    );
+    assert!(total_error / 500_f64 < 0.1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn residual_correction_of_persistence_forecast() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(200, 6);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+
+    let input_projection = DefaultInputProjection::new_random(2, 200, 1.0);
+    let reservoir = Reservoir::new(input_projection, esn);
+    let measurement = ConstantExtensionStateMeasurement::<f64>::new(200);
+
+    // Persistence: the next value equals the current one.
+    let baseline = KnowledgeBasedModelWrapper::new(
+        2,
+        |input: DVectorSlice<f64>, mut target: DVectorSliceMut<f64>| target.copy_from(&input),
+    );
+
+    let mut data = Vec::with_capacity(4000);
+    for t in 0..(data.capacity() / 2) {
+        let time = t as f64 * 0.02;
+        data.push(time.sin());
+        data.push(time.cos());
+    }
+    let train_data = DMatrix::from_vec(2, data.len() / 2, data);
+
+    let mut rt = ReservoirTraining::new(500, 1000, 0, 500);
+    rt.add_data(train_data);
+    let mut residual_computer =
+        rt.train_residual_via_ridge_regression(1e-8, reservoir, measurement, baseline);
+
+    let kickstarter = rt.get_prediction_kickstarter(0, 1);
+    let true_prediction = rt.get_true_future(0);
+    let prediction = residual_computer.predict(kickstarter.column(0), 100);
+
+    let mut total_error = 0.0;
+    for (prediction, actual) in prediction.column_iter().zip(true_prediction.column_iter()) {
+        total_error += (prediction[0] - actual[0]).abs() + (prediction[1] - actual[1]).abs();
+    }
+    println!(
+        "Total Error: {total_error} Avg err: {}",
+        total_error / 100_f64
+    );
+    assert!(total_error / 100_f64 < 0.1);
+}
@@ -0,0 +1,127 @@
+use nalgebra::DMatrix;
+use rescomp::{
+    activation_function::ActivationFunctionWrapper,
+    echo_state_network::EchoStateNetworkBuilder,
+    input_projection::DefaultInputProjection,
+    output_projection::ReservoirStateProjection,
+    reservoir::training::ReservoirTraining,
+    state_measurement::{
+        DefaultStateMeasurement, ReservoirStateMeasurement, SeasonalTimeFeatures,
+        TimeFeatureStateMeasurement,
+    },
+    Reservoir, SpectralRadius,
+};
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn ridge_regression_defaults_to_a_small_beta() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(50, 6);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+    let input_projection = DefaultInputProjection::new_random(2, 50, 1.0);
+    let reservoir = || Reservoir::new(input_projection.clone(), esn.clone());
+
+    let data = DMatrix::from_fn(2, 500, |i, j| {
+        let time = j as f64 * 0.02;
+        if i == 0 {
+            time.sin()
+        } else {
+            time.cos()
+        }
+    });
+    let mut rt = ReservoirTraining::new(100, 300, 0, 0);
+    rt.add_data(data);
+
+    // The readouts are compared on the same probe states. A Tikhonov matrix of sqrt(beta) I is
+    // a ridge regression with beta.
+    let probes = DMatrix::from_fn(50, 20, |i, j| ((i * 7 + j * 3) as f64 * 0.1).sin());
+    let readout = |beta: f64| {
+        let tikhonov = DMatrix::identity(50, 50) * beta.sqrt();
+        rt.train_via_tikhonov_regularization(
+            &tikhonov,
+            reservoir(),
+            DefaultStateMeasurement::new(50),
+        )
+        .state_projection()
+        .project_many(probes.columns(0, 20))
+    };
+    let (small, large) = (readout(1e-7), readout(1.));
+    let default = rt
+        .train_via_ridge_regression(reservoir(), DefaultStateMeasurement::new(50))
+        .state_projection()
+        .project_many(probes.columns(0, 20));
+    assert!((&default - &small).amax() < 1e-4 * small.amax());
+    assert!((&default - &large).amax() > 1e-2 * large.amax());
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn dropout_keeps_the_readout_close() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(50, 6);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+    let input_projection = DefaultInputProjection::new_random(2, 50, 1.0);
+    let reservoir = || Reservoir::new(input_projection.clone(), esn.clone());
+
+    let data = DMatrix::from_fn(2, 2100, |i, j| {
+        let time = j as f64 * 0.02;
+        if i == 0 {
+            time.sin()
+        } else {
+            time.cos()
+        }
+    });
+    let mut rt = ReservoirTraining::new(100, 2000, 0, 0);
+    rt.add_data(data);
+    let tikhonov = DMatrix::identity(50, 50) * 3.;
+    let w_out = |rt: &ReservoirTraining<f64>| {
+        rt.train_via_tikhonov_regularization(
+            &tikhonov,
+            reservoir(),
+            DefaultStateMeasurement::new(50),
+        )
+        .state_projection()
+        .w_out()
+        .clone()
+    };
+    let plain = w_out(&rt);
+    rt.dropout(0.002, 7);
+    // The inverted dropout only adds a small regularization on top of the Tikhonov matrix.
+    let difference = (w_out(&rt) - &plain).norm();
+    assert!(
+        difference < 0.25 * plain.norm(),
+        "{difference} {}",
+        plain.norm()
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn training_starts_the_time_features_at_the_training_segment() {
+    let mut esn_builder = EchoStateNetworkBuilder::<f64>::random(30, 6);
+    esn_builder.spectral_radius(SpectralRadius::new(0.9).unwrap());
+    let esn = esn_builder
+        .build_sparse_discrete_network(ActivationFunctionWrapper::new(|_, v: f64| v.tanh()));
+    let input_projection = DefaultInputProjection::new_random(1, 30, 1.0);
+    let reservoir = || Reservoir::new(input_projection.clone(), esn.clone());
+
+    let data = DMatrix::from_fn(1, 300, |_, j| (j as f64 * 0.3).sin());
+    let mut rt = ReservoirTraining::new(50, 200, 0, 0);
+    rt.add_data(data);
+    let measurement = TimeFeatureStateMeasurement::new(
+        DefaultStateMeasurement::new(30),
+        SeasonalTimeFeatures::new(vec![7.]),
+    );
+    let mut shifted = measurement.clone();
+    shifted.set_time_index(1000);
+
+    let w_out = |measurement| {
+        rt.train_via_ridge_regression(reservoir(), measurement)
+            .state_projection()
+            .w_out()
+            .clone()
+    };
+    assert_eq!(w_out(shifted), w_out(measurement));
+}